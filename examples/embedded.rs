@@ -0,0 +1,67 @@
+//! Pingis Pong running inside another app, which owns the window and the cameras.
+//!
+//! Run with `cargo run --example embedded`.
+
+use bevy::prelude::*;
+use bevy::render::camera::{ScalingMode, WindowOrigin};
+use pingis_pong::{PingisPongPlugin, PongSettings, ARENA_HEIGHT, ARENA_WIDTH};
+
+/// Room left around the arena for the host's own things.
+const MARGIN: f32 = 100.;
+
+/// Sprite belonging to the host, the game leaves it alone.
+struct HostBanner;
+
+fn main() {
+    App::build()
+        .insert_resource(WindowDescriptor {
+            title: "Arcade".to_string(),
+            width: ARENA_WIDTH + 2. * MARGIN,
+            height: ARENA_HEIGHT + 2. * MARGIN,
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(PingisPongPlugin(PongSettings {
+            spawn_camera: false,
+            ..Default::default()
+        }))
+        .add_startup_system(setup_host.system())
+        .add_system(pulse_banner.system())
+        .run();
+}
+
+fn setup_host(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    // The game draws in arena coordinates, frame them with a margin on every side
+    let mut cam = OrthographicCameraBundle::new_2d();
+    cam.orthographic_projection.scaling_mode = ScalingMode::None;
+    cam.orthographic_projection.left = -MARGIN;
+    cam.orthographic_projection.right = ARENA_WIDTH + MARGIN;
+    cam.orthographic_projection.top = ARENA_HEIGHT + MARGIN;
+    cam.orthographic_projection.bottom = -MARGIN;
+    cam.orthographic_projection.window_origin = WindowOrigin::BottomLeft;
+
+    commands.spawn_bundle(cam);
+    commands.spawn_bundle(UiCameraBundle::default());
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            material: materials.add(Color::rgb(0.8, 0.3, 0.1).into()),
+            sprite: Sprite::new(Vec2::new(ARENA_WIDTH, MARGIN / 2.)),
+            transform: Transform::from_xyz(ARENA_WIDTH / 2., ARENA_HEIGHT + MARGIN / 2., 0.),
+            ..Default::default()
+        })
+        .insert(HostBanner);
+}
+
+fn pulse_banner(
+    time: Res<Time>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    banners: Query<&Handle<ColorMaterial>, With<HostBanner>>,
+) {
+    let pulse = 0.8 + (time.seconds_since_startup() as f32 * 2.).sin() * 0.2;
+    for material in banners.iter() {
+        if let Some(material) = materials.get_mut(material) {
+            material.color = Color::rgb(pulse, 0.3, 0.1);
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use bevy::prelude::*;
+
+use crate::persistence::{SaveData, Settings};
+use crate::theme::Theme;
+use crate::{AppState, Ball, Paddle, Wall};
+
+pub struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Accessibility>()
+            .add_system(follow_settings.system().label("accessibility"))
+            .add_system(apply_background.system().after("accessibility"))
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(outline_sprites.system().after("accessibility"))
+                    .with_system(tint_walls.system().label("tint_walls")),
+            );
+    }
+}
+
+/// Outline of the ball and border of the paddles in high contrast, apart from the white of
+/// everything else. Both are drawn within the colliders, what shows is what bounces.
+const OUTLINE_COLOR: Color = Color::YELLOW;
+const BALL_OUTLINE_WIDTH: f32 = 4.;
+const PADDLE_BORDER_WIDTH: f32 = 3.;
+/// The ball's outline goes under it and above its glow and shadow, a paddle's border over it.
+const BALL_OUTLINE_Z: f32 = -0.01;
+const PADDLE_BORDER_Z: f32 = 0.01;
+
+/// Accessibility switches, consulted by every system they affect.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Accessibility {
+    pub high_contrast: bool,
+    pub reduced_motion: bool,
+    pub reduced_clutter: bool,
+}
+
+impl Accessibility {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Accessibility {
+            high_contrast: settings.high_contrast,
+            reduced_motion: settings.reduced_motion,
+            reduced_clutter: settings.reduced_clutter,
+        }
+    }
+}
+
+impl FromWorld for Accessibility {
+    fn from_world(world: &mut World) -> Self {
+        let save = world.get_resource::<SaveData>().unwrap();
+        Accessibility::from_settings(&save.settings)
+    }
+}
+
+fn follow_settings(save: Res<SaveData>, mut accessibility: ResMut<Accessibility>) {
+    if !save.is_changed() {
+        return;
+    }
+
+    let wanted = Accessibility::from_settings(&save.settings);
+    if wanted != *accessibility {
+        *accessibility = wanted;
+    }
+}
+
+/// High contrast blacks out the window, the host's own clear color comes back when it is
+/// switched off.
+fn apply_background(
+    accessibility: Res<Accessibility>,
+    mut clear_color: ResMut<ClearColor>,
+    mut host_color: Local<Option<Color>>,
+) {
+    if !accessibility.is_changed() {
+        return;
+    }
+
+    match (accessibility.high_contrast, *host_color) {
+        (true, None) => {
+            *host_color = Some(clear_color.0);
+            clear_color.0 = Color::BLACK;
+        }
+        (false, Some(color)) => {
+            clear_color.0 = color;
+            *host_color = None;
+        }
+        _ => {}
+    }
+}
+
+/// Walls are spawned in the theme's color, follow it when it changes.
+fn tint_walls(
+    theme: Res<Theme>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    walls: Query<&Handle<ColorMaterial>, With<Wall>>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+
+    for material in walls.iter() {
+        if let Some(material) = materials.get_mut(material) {
+            material.color = theme.wall;
+        }
+    }
+}
+
+/// Set on a ball or paddle while it is outlined, the outline sprites are its children.
+struct Outlined {
+    parts: Vec<Entity>,
+    material: Handle<ColorMaterial>,
+    /// Sprite size before the ball was drawn inset.
+    size: Vec2,
+}
+
+/// Strips along the inside of the edges of a paddle `size` large, as size and position.
+fn border_parts(size: Vec2) -> Vec<(Vec2, Vec3)> {
+    let width = PADDLE_BORDER_WIDTH.min(size.x / 2.).min(size.y / 2.);
+    let x = size.x / 2. - width / 2.;
+    let y = size.y / 2. - width / 2.;
+    vec![
+        (Vec2::new(width, size.y), Vec3::new(-x, 0., PADDLE_BORDER_Z)),
+        (Vec2::new(width, size.y), Vec3::new(x, 0., PADDLE_BORDER_Z)),
+        (Vec2::new(size.x, width), Vec3::new(0., -y, PADDLE_BORDER_Z)),
+        (Vec2::new(size.x, width), Vec3::new(0., y, PADDLE_BORDER_Z)),
+    ]
+}
+
+/// In high contrast the ball is drawn inset on a backing of the outline color as large as its
+/// collider, and the paddles get a border along the inside of their edges. For the ones just
+/// spawned and for all of them when the setting flips. An invisible ball fades its outline along.
+fn outline_sprites(
+    mut commands: Commands,
+    accessibility: Res<Accessibility>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut plain: Query<
+        (Entity, &mut Sprite, &Handle<ColorMaterial>, Option<&Ball>),
+        (Or<(With<Ball>, With<Paddle>)>, Without<Outlined>),
+    >,
+    mut outlined: Query<(Entity, &mut Sprite, &Handle<ColorMaterial>, &Outlined)>,
+) {
+    if !accessibility.high_contrast {
+        for (entity, mut sprite, _, outline) in outlined.iter_mut() {
+            sprite.size = outline.size;
+            for part in outline.parts.iter() {
+                commands.entity(*part).despawn_recursive();
+            }
+            commands.entity(entity).remove::<Outlined>();
+        }
+        return;
+    }
+
+    for (entity, mut sprite, material, ball) in plain.iter_mut() {
+        let size = sprite.size;
+        // The ball's texture gives its backing the same round shape
+        let texture = ball
+            .and_then(|_| materials.get(material))
+            .and_then(|material| material.texture.clone());
+        let outline = materials.add(ColorMaterial {
+            color: OUTLINE_COLOR,
+            texture,
+        });
+
+        let parts = if ball.is_some() {
+            sprite.size = (size - Vec2::splat(BALL_OUTLINE_WIDTH * 2.)).max(Vec2::ZERO);
+            vec![(size, Vec3::new(0., 0., BALL_OUTLINE_Z))]
+        } else {
+            border_parts(size)
+        };
+        let mut spawned = Vec::new();
+        commands.entity(entity).with_children(|parent| {
+            for (part_size, translation) in parts {
+                let part = parent.spawn_bundle(SpriteBundle {
+                    material: outline.clone(),
+                    sprite: Sprite::new(part_size),
+                    transform: Transform::from_translation(translation),
+                    ..Default::default()
+                });
+                spawned.push(part.id());
+            }
+        });
+        commands.entity(entity).insert(Outlined {
+            parts: spawned,
+            material: outline,
+            size,
+        });
+    }
+
+    for (_, _, material, outline) in outlined.iter_mut() {
+        let alpha = materials
+            .get(material)
+            .map_or(1., |material| material.color.a());
+        if let Some(outline) = materials.get_mut(&outline.material) {
+            if outline.color.a() != alpha {
+                outline.color.set_a(alpha);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paddle_border_stays_within_the_paddle() {
+        let size = Vec2::new(15., 120.);
+        for (part, translation) in border_parts(size) {
+            let half = size / 2.;
+            let (low, high) = (
+                translation.truncate() - part / 2.,
+                translation.truncate() + part / 2.,
+            );
+            assert!(
+                low.x >= -half.x && low.y >= -half.y,
+                "{:?} {:?}",
+                part,
+                translation
+            );
+            assert!(
+                high.x <= half.x && high.y <= half.y,
+                "{:?} {:?}",
+                part,
+                translation
+            );
+            assert!(translation.z > 0.);
+        }
+    }
+
+    #[test]
+    fn paddle_border_covers_the_edges() {
+        let size = Vec2::new(15., 120.);
+        let parts = border_parts(size);
+        let reaches = |point: Vec2| {
+            parts.iter().any(|(part, translation)| {
+                let offset = point - translation.truncate();
+                offset.x.abs() <= part.x / 2. && offset.y.abs() <= part.y / 2.
+            })
+        };
+
+        for point in [
+            Vec2::new(-7.5, 0.),
+            Vec2::new(7.5, 0.),
+            Vec2::new(0., -60.),
+            Vec2::new(0., 60.),
+            Vec2::new(7.5, 60.),
+        ]
+        .iter()
+        {
+            assert!(reaches(*point), "{:?}", point);
+        }
+        assert!(!reaches(Vec2::ZERO));
+    }
+
+    #[test]
+    fn thin_paddle_border_fits() {
+        for (part, _) in border_parts(Vec2::new(4., 120.)) {
+            assert!(part.x <= 4. && part.y <= 120.);
+        }
+    }
+}
@@ -0,0 +1,496 @@
+use bevy::prelude::*;
+use bevy_rapier2d::physics::{RapierConfiguration, RigidBodyHandleComponent};
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+
+use crate::gravity_zones::predicted_y;
+use crate::input::{Controller, PaddleIntent};
+use crate::match_state::MatchRules;
+use crate::rng::GameRng;
+use crate::tuning::{AiSkill, Tuning};
+use crate::{
+    paddle_spawn_x, paddle_y_range, sub_paddle_height, AppState, Ball, GameLabel, PaddleRole,
+    Paused, Player, Score, SubPaddle, ARENA_HEIGHT,
+};
+
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(ai_intent.system().label(GameLabel::Input))
+                .with_system(adapt_skill.system().after(GameLabel::GoalApply)),
+        );
+    }
+}
+
+/// Distance in pixels at which the AI moves at full speed toward its target.
+const STEER_DISTANCE: f32 = 30.;
+
+/// Per paddle memory of the computer player.
+#[derive(Debug, Default)]
+pub struct AiState {
+    /// Ball was heading toward this paddle's goal last frame.
+    approaching: bool,
+    /// Deliberately miss the current return.
+    missing: bool,
+    /// Seconds since the ball turned toward this paddle.
+    approach_seconds: f32,
+    pub skill: AiSkill,
+}
+
+impl AiState {
+    /// Ball to go after once the paddle has reacted to it, given the ball approaching now and the
+    /// seconds since the last frame. Whether to miss is decided when a ball starts approaching.
+    fn react(
+        &mut self,
+        ball: Option<(Vec2, Vec2)>,
+        delta: f32,
+        rng: &mut GameRng,
+    ) -> Option<(Vec2, Vec2)> {
+        let approaching = ball.is_some();
+        if approaching && !self.approaching {
+            self.missing = rng.f32() < self.skill.miss_chance;
+            self.approach_seconds = 0.;
+        }
+        self.approaching = approaching;
+        if approaching {
+            self.approach_seconds += delta;
+        }
+        let reacted = self.approach_seconds >= self.skill.reaction_seconds;
+        ball.filter(|_| reacted)
+    }
+
+    /// Height to meet a ball at `ball_y`, a paddle off when missing on purpose.
+    fn aim(&self, ball_y: f32, paddle_height: f32) -> f32 {
+        if self.missing {
+            ball_y + paddle_height
+        } else {
+            ball_y
+        }
+    }
+}
+
+/// Sets each computer player's skill after every goal, and for paddles just spawned. The adaptive
+/// computer plays better when behind and sloppier when ahead.
+fn adapt_skill(
+    score: Res<Score>,
+    rules: Res<MatchRules>,
+    tuning: Res<Tuning>,
+    mut states: Query<(&Player, &mut AiState, ChangeTrackers<AiState>)>,
+) {
+    let refresh = score.is_changed() || rules.is_changed() || tuning.is_changed();
+
+    for (player, mut state, trackers) in states.iter_mut() {
+        if !refresh && !trackers.is_added() {
+            continue;
+        }
+
+        let skill = if rules.adaptive_ai {
+            let lead = score.of(*player) as i32 - score.of(player.opponent()) as i32;
+            tuning.ai_skill.adapted(&tuning.ai_adaptive, lead)
+        } else {
+            tuning.ai_skill
+        };
+        if state.skill != skill {
+            state.skill = skill;
+        }
+    }
+}
+
+/// The reaction clock stands still while paused, which also keeps out the long frame after a
+/// minimized window comes back.
+fn ai_intent(
+    time: Res<Time>,
+    paused: Res<Paused>,
+    tuning: Res<Tuning>,
+    rules: Res<MatchRules>,
+    rapier_config: Res<RapierConfiguration>,
+    rigid_bodies: Res<RigidBodySet>,
+    mut rng: ResMut<GameRng>,
+    balls: Query<(&Transform, &RigidBodyHandleComponent), With<Ball>>,
+    mut paddles: Query<(
+        &Player,
+        &PaddleRole,
+        &Controller,
+        &Transform,
+        Option<&SubPaddle>,
+        &mut AiState,
+        &mut PaddleIntent,
+    )>,
+) {
+    let balls: Vec<(Vec2, Vec2)> = balls
+        .iter()
+        .filter_map(|(transform, rigid_body_component)| {
+            let rb = rigid_bodies.get(rigid_body_component.handle())?;
+            let velocity = *rb.linvel() * rapier_config.scale;
+            Some((
+                transform.translation.truncate(),
+                Vec2::new(velocity.x, velocity.y),
+            ))
+        })
+        .collect();
+
+    let sub_height = sub_paddle_height(rules.paddles_per_side, &tuning);
+    let delta = if paused.0 { 0. } else { time.delta_seconds() };
+
+    for (player, role, controller, transform, sub_paddle, mut state, mut intent) in
+        paddles.iter_mut()
+    {
+        if !controller.is_ai() {
+            continue;
+        }
+
+        let position = transform.translation.truncate();
+
+        let ball = state.react(
+            most_threatening(&balls, *player, position.x),
+            delta,
+            &mut rng,
+        );
+
+        let target_y = match ball {
+            Some((ball_position, velocity)) => {
+                // Tracking the ball is close enough on a straight path, not on a curved one
+                let ball_y = if rules.gravity_zones {
+                    predicted_y(
+                        ball_position,
+                        velocity,
+                        position.x,
+                        &tuning,
+                        rapier_config.scale,
+                    )
+                } else {
+                    ball_position.y
+                };
+                state.aim(ball_y, tuning.paddle_height)
+            }
+            None => ARENA_HEIGHT / 2.,
+        };
+
+        // Each paddle of a wall aims off center by its place in the stack so the wall keeps shape
+        let stack_offset = match sub_paddle {
+            Some(sub_paddle) => {
+                let (bottom, top) =
+                    paddle_y_range(sub_paddle.0, rules.paddles_per_side, sub_height);
+                (bottom + top) / 2. - ARENA_HEIGHT / 2.
+            }
+            None => 0.,
+        };
+        let target_y = target_y + stack_offset;
+
+        // Wait where the paddle was spawned
+        let home_x = paddle_spawn_x(*player, *role);
+
+        intent.movement = Vec2::new(steer(home_x - position.x), steer(target_y - position.y));
+        intent.rotation = 0.;
+        intent.power = false;
+        intent.catch = false;
+    }
+}
+
+/// Movement toward a target `delta` pixels away, slowing down over the last `STEER_DISTANCE`.
+fn steer(delta: f32) -> f32 {
+    (delta / STEER_DISTANCE).max(-1.).min(1.)
+}
+
+/// Position and velocity of the ball heading toward `player`'s goal that reaches `paddle_x`
+/// first, `None` while every ball moves away.
+fn most_threatening(balls: &[(Vec2, Vec2)], player: Player, paddle_x: f32) -> Option<(Vec2, Vec2)> {
+    balls
+        .iter()
+        .filter(|(_, velocity)| match player {
+            Player::Left => velocity.x < 0.,
+            Player::Right => velocity.x > 0.,
+        })
+        .map(|(position, velocity)| {
+            // Already past the paddle counts as arriving now
+            let seconds = ((paddle_x - position.x) / velocity.x).max(0.);
+            (seconds, (*position, *velocity))
+        })
+        .min_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(_, ball)| ball)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_rapier2d::rapier::dynamics::RigidBodyBuilder;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use crate::match_state::{MatchClock, MatchPhase};
+    use crate::rules::{on_goals, RuleOutcome, RulesConfig, RulesState};
+    use crate::tuning::Preset;
+    use crate::{
+        crossing_y, serve_velocity, ServeKind, ARENA_MIDDLE, ARENA_WIDTH, PADDLE_SPEED,
+        PHYSICS_SCALE,
+    };
+
+    fn mirrored((position, velocity): (Vec2, Vec2)) -> (Vec2, Vec2) {
+        (
+            Vec2::new(ARENA_WIDTH - position.x, position.y),
+            Vec2::new(-velocity.x, velocity.y),
+        )
+    }
+
+    #[test]
+    fn threat_mirrors_between_the_sides() {
+        let balls = [
+            (Vec2::new(600., 200.), Vec2::new(-10., 3.)),
+            (Vec2::new(300., 400.), Vec2::new(-4., -2.)),
+            (Vec2::new(200., 100.), Vec2::new(8., 1.)),
+        ];
+        let mirrored_balls: Vec<(Vec2, Vec2)> = balls.iter().copied().map(mirrored).collect();
+
+        let left = most_threatening(&balls, Player::Left, 50.);
+        let right = most_threatening(&mirrored_balls, Player::Right, ARENA_WIDTH - 50.);
+        assert_eq!(left, Some(balls[0]));
+        assert_eq!(right, left.map(mirrored));
+
+        // Both sides see nothing coming when every ball moves away
+        let away = [(Vec2::new(500., 300.), Vec2::new(5., 0.))];
+        assert_eq!(most_threatening(&away, Player::Left, 50.), None);
+        let away: Vec<(Vec2, Vec2)> = away.iter().copied().map(mirrored).collect();
+        assert_eq!(
+            most_threatening(&away, Player::Right, ARENA_WIDTH - 50.),
+            None
+        );
+    }
+
+    fn approach_world(paused: bool) -> (World, Entity) {
+        let mut world = World::default();
+        world.insert_resource(Time::default());
+        world.insert_resource(Paused(paused));
+        world.insert_resource(Tuning::default());
+        world.init_resource::<MatchRules>();
+        world.insert_resource(RapierConfiguration::default());
+        world.insert_resource(GameRng::new(1));
+
+        let mut bodies = RigidBodySet::new();
+        let handle = bodies.insert(RigidBodyBuilder::new_dynamic().linvel(-10., 0.).build());
+        world.insert_resource(bodies);
+        world.spawn().insert_bundle((
+            Ball(10.),
+            Transform::from_xyz(ARENA_WIDTH / 2., 300., 0.),
+            RigidBodyHandleComponent::from(handle),
+        ));
+
+        let paddle = world
+            .spawn()
+            .insert_bundle((
+                Player::Left,
+                PaddleRole::Solo,
+                Controller::Ai,
+                Transform::from_xyz(paddle_spawn_x(Player::Left, PaddleRole::Solo), 300., 0.),
+                AiState::default(),
+                PaddleIntent::default(),
+            ))
+            .id();
+        (world, paddle)
+    }
+
+    /// Runs the AI after a frame long enough to notice.
+    fn think(world: &mut World) {
+        {
+            let mut time = world.get_resource_mut::<Time>().unwrap();
+            time.update();
+            sleep(Duration::from_millis(20));
+            time.update();
+        }
+        SystemStage::single(ai_intent.system()).run(world);
+    }
+
+    #[test]
+    fn reaction_waits_while_paused() {
+        let (mut world, paddle) = approach_world(true);
+        think(&mut world);
+        think(&mut world);
+        let state = world.get::<AiState>(paddle).unwrap();
+        assert!(state.approaching);
+        assert_eq!(state.approach_seconds, 0.);
+
+        world.get_resource_mut::<Paused>().unwrap().0 = false;
+        think(&mut world);
+        let state = world.get::<AiState>(paddle).unwrap();
+        assert!(state.approach_seconds >= 0.02);
+    }
+
+    /// Frame length of the symmetry harness, the game's physics step.
+    const STEP: f32 = 1. / 60.;
+    /// Rally length after which the harness serves again without a point, a serve can go close
+    /// to straight up and down.
+    const MAX_RALLY_SECONDS: f32 = 120.;
+    /// An hour of play, a match that runs longer is stuck.
+    const MAX_MATCH_STEPS: u32 = 60 * 60 * 60;
+    const MATCHES: u64 = 200;
+
+    struct SimPaddle {
+        player: Player,
+        x: f32,
+        y: f32,
+        state: AiState,
+    }
+
+    /// Plays a seeded match between two computer players with the same settings and returns the
+    /// winner. The serves, the computer's choices, the paddle ranges and the match rules are the
+    /// game's own. The ball flies straight and bounces off the walls and the paddle fronts, taking
+    /// on part of the paddle's velocity like in the game.
+    fn simulate_match(seed: u64, tuning: &Tuning, rules: &MatchRules) -> Player {
+        let mut rng = GameRng::new(seed);
+        let config = RulesConfig::from_rules(rules);
+        let clock = MatchClock::default();
+        let mut score = Score::default();
+        let mut phase = MatchPhase::Regular;
+        let mut paddles: Vec<SimPaddle> = [Player::Left, Player::Right]
+            .iter()
+            .map(|player| SimPaddle {
+                player: *player,
+                x: paddle_spawn_x(*player, PaddleRole::Solo),
+                y: ARENA_HEIGHT / 2.,
+                state: AiState {
+                    skill: tuning.ai_skill,
+                    ..Default::default()
+                },
+            })
+            .collect();
+        let (low, high) = paddle_y_range(0, 1, tuning.paddle_height);
+        let radius = tuning.ball_size / 2.;
+        let max_speed = tuning.max_ball_speed * PHYSICS_SCALE;
+        let center = Vec2::new(ARENA_MIDDLE, ARENA_HEIGHT / 2.);
+        let serve = |rng: &mut GameRng, score: &Score| {
+            let velocity = serve_velocity(
+                rng,
+                rules,
+                tuning,
+                None,
+                ServeKind::Standard,
+                tuning.serve_speed,
+            );
+            let velocity = score.with_streak_bonus(velocity, tuning) * PHYSICS_SCALE;
+            Vec2::new(velocity.x, velocity.y)
+        };
+
+        let mut position = center;
+        let mut velocity = serve(&mut rng, &score);
+        let mut rally = 0.;
+        for _ in 0..MAX_MATCH_STEPS {
+            for paddle in paddles.iter_mut() {
+                let ball = most_threatening(&[(position, velocity)], paddle.player, paddle.x);
+                let target_y = match paddle.state.react(ball, STEP, &mut rng) {
+                    Some((ball_position, _)) => {
+                        paddle.state.aim(ball_position.y, tuning.paddle_height)
+                    }
+                    None => ARENA_HEIGHT / 2.,
+                };
+                let y = (paddle.y + steer(target_y - paddle.y) * PADDLE_SPEED * STEP)
+                    .max(low)
+                    .min(high);
+                let paddle_velocity = (y - paddle.y) / STEP;
+                paddle.y = y;
+
+                let front = match paddle.player {
+                    Player::Left => paddle.x + tuning.paddle_width / 2. + radius,
+                    Player::Right => paddle.x - tuning.paddle_width / 2. - radius,
+                };
+                let next_x = position.x + velocity.x * STEP;
+                let crosses = match paddle.player {
+                    Player::Left => position.x >= front && next_x < front,
+                    Player::Right => position.x <= front && next_x > front,
+                };
+                if crosses && (position.y - paddle.y).abs() <= tuning.paddle_height / 2. + radius {
+                    velocity.x = -velocity.x;
+                    velocity.y += tuning.paddle_carry * paddle_velocity;
+                }
+            }
+            if velocity.length() > max_speed {
+                velocity = velocity.normalize() * max_speed;
+            }
+
+            let previous = position;
+            position += velocity * STEP;
+            if position.y < radius {
+                position.y = radius;
+                velocity.y = velocity.y.abs();
+            } else if position.y > ARENA_HEIGHT - radius {
+                position.y = ARENA_HEIGHT - radius;
+                velocity.y = -velocity.y.abs();
+            }
+            rally += STEP;
+
+            let (scorer, line_x) = if position.x < 0. {
+                (Player::Right, 0.)
+            } else if position.x > ARENA_WIDTH {
+                (Player::Left, ARENA_WIDTH)
+            } else {
+                if rally > MAX_RALLY_SECONDS {
+                    position = center;
+                    velocity = serve(&mut rng, &score);
+                    rally = 0.;
+                }
+                continue;
+            };
+            // Beside a goal mouth the ball comes back off the end wall
+            if let Some(mouth) = tuning.goal_mouth {
+                let crossed_at = crossing_y(previous, position, line_x);
+                if (crossed_at - ARENA_HEIGHT / 2.).abs() > mouth / 2. {
+                    position.x = line_x * 2. - position.x;
+                    velocity.x = -velocity.x;
+                    continue;
+                }
+            }
+
+            let state = RulesState::new(&score, phase, &clock);
+            for outcome in on_goals(&config, &state, &[scorer]) {
+                match outcome {
+                    RuleOutcome::AwardPoint(player) => score.award(player),
+                    RuleOutcome::EndMatch(winner, _) => return winner,
+                    RuleOutcome::StartOvertime => phase = MatchPhase::Overtime,
+                }
+            }
+            if rules.adaptive_ai {
+                for paddle in paddles.iter_mut() {
+                    let lead =
+                        score.of(paddle.player) as i32 - score.of(paddle.player.opponent()) as i32;
+                    paddle.state.skill = tuning.ai_skill.adapted(&tuning.ai_adaptive, lead);
+                }
+            }
+            position = center;
+            velocity = serve(&mut rng, &score);
+            rally = 0.;
+        }
+        panic!("match {} never ended", seed);
+    }
+
+    #[test]
+    fn even_computers_win_half_the_matches() {
+        // Three standard deviations of the win rate over fair matches
+        let tolerance = 3. * (0.25 / MATCHES as f32).sqrt();
+        let difficulties = [
+            (Preset::Standard, false),
+            (Preset::Standard, true),
+            (Preset::Kid, false),
+            (Preset::Kid, true),
+        ];
+
+        for (preset, adaptive_ai) in difficulties.iter() {
+            let tuning = Tuning::for_preset(*preset);
+            let rules = MatchRules {
+                score_limit: Some(5),
+                adaptive_ai: *adaptive_ai,
+                ..Default::default()
+            };
+            let left_wins = (0..MATCHES)
+                .filter(|seed| simulate_match(*seed, &tuning, &rules) == Player::Left)
+                .count();
+            let rate = left_wins as f32 / MATCHES as f32;
+            assert!(
+                (rate - 0.5).abs() <= tolerance,
+                "{:?} preset, adaptive {}: the left side won {:.3}",
+                preset,
+                adaptive_ai,
+                rate
+            );
+        }
+    }
+}
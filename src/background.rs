@@ -0,0 +1,219 @@
+use std::path::Path;
+
+use bevy::asset::{FileAssetIo, LoadState};
+use bevy::prelude::*;
+
+use crate::accessibility::Accessibility;
+use crate::persistence::SaveData;
+use crate::{AppState, ARENA_HEIGHT, ARENA_MIDDLE, ARENA_WIDTH};
+
+/// A picture of the player's own behind the arena, any PNG dropped into `assets/backgrounds/`.
+/// Without one, or when it can't be loaded, the arena keeps the flat background color.
+pub struct BackgroundPlugin;
+
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ArenaBackground>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame).with_system(spawn_background.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame).with_system(update_background.system()),
+            );
+    }
+}
+
+/// Under the assets directory.
+const BACKGROUND_DIR: &str = "backgrounds";
+/// The 2d camera sees down to z = -0.1, the picture goes behind everything else there is, the
+/// pressure quads at -0.05 included. The dimming sits right in front of it.
+const BACKGROUND_Z: f32 = -0.09;
+const DIM_Z: f32 = -0.08;
+/// Steps of the dimming setting, the share of black laid over the picture.
+const DIM_STEPS: [f32; 4] = [0., 0.25, 0.5, 0.75];
+
+/// The picture asked for and where its texture stands.
+#[derive(Default)]
+struct ArenaBackground {
+    /// File in `BACKGROUND_DIR`, `None` for the flat color.
+    file: Option<String>,
+    texture: Option<Handle<Texture>>,
+    /// The sprites show the current texture, or it failed and they stay hidden.
+    settled: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BackgroundLayer {
+    Picture,
+    Dim,
+}
+
+/// PNG files in the backgrounds directory by name. A missing directory just has none.
+pub fn available() -> Vec<String> {
+    let dir = FileAssetIo::get_root_path()
+        .join("assets")
+        .join(BACKGROUND_DIR);
+    let mut files: Vec<String> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .filter(|name| name.to_ascii_lowercase().ends_with(".png"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+/// The background after `current`, the flat color comes first and again after the last picture.
+pub fn next_background(current: Option<&str>) -> Option<String> {
+    let files = available();
+    let next = match current {
+        Some(current) => files
+            .iter()
+            .position(|file| file == current)
+            .map_or(0, |index| index + 1),
+        None => 0,
+    };
+    files.get(next).cloned()
+}
+
+/// The next dimming step after `dim`, back to none after the darkest.
+pub fn next_dim(dim: f32) -> f32 {
+    DIM_STEPS
+        .iter()
+        .copied()
+        .find(|step| *step > dim + f32::EPSILON)
+        .unwrap_or(DIM_STEPS[0])
+}
+
+/// Both sprites are spawned hidden with the arena, the picture only gets its texture once loaded.
+fn spawn_background(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut background: ResMut<ArenaBackground>,
+) {
+    background.settled = false;
+
+    let hidden = Visible {
+        is_visible: false,
+        is_transparent: true,
+    };
+    for (layer, z) in [
+        (BackgroundLayer::Picture, BACKGROUND_Z),
+        (BackgroundLayer::Dim, DIM_Z),
+    ]
+    .iter()
+    {
+        let color = match layer {
+            BackgroundLayer::Picture => Color::WHITE,
+            BackgroundLayer::Dim => Color::NONE,
+        };
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: materials.add(color.into()),
+                sprite: Sprite::new(Vec2::new(ARENA_WIDTH, ARENA_HEIGHT)),
+                transform: Transform::from_xyz(ARENA_MIDDLE, ARENA_HEIGHT / 2., *z),
+                visible: hidden.clone(),
+                ..Default::default()
+            })
+            .insert(*layer);
+    }
+}
+
+/// Largest size with the picture's proportions fitting in the arena, the rest of the arena shows
+/// the flat color around it.
+fn letterbox(width: f32, height: f32) -> Vec2 {
+    let scale = (ARENA_WIDTH / width).min(ARENA_HEIGHT / height);
+    Vec2::new(width * scale, height * scale)
+}
+
+/// Picking another picture swaps the texture on the sprites already there. High contrast keeps
+/// the arena black.
+fn update_background(
+    save: Res<SaveData>,
+    accessibility: Res<Accessibility>,
+    asset_server: Res<AssetServer>,
+    textures: Res<Assets<Texture>>,
+    mut background: ResMut<ArenaBackground>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut layers: Query<(
+        &BackgroundLayer,
+        &mut Sprite,
+        &mut Visible,
+        &Handle<ColorMaterial>,
+    )>,
+) {
+    let wanted = if accessibility.high_contrast {
+        None
+    } else {
+        save.settings.background.clone()
+    };
+    if wanted != background.file {
+        background.texture = wanted
+            .as_ref()
+            .map(|file| asset_server.load(Path::new(BACKGROUND_DIR).join(file).as_path()));
+        background.file = wanted;
+        background.settled = false;
+    }
+
+    let dim = Color::rgba(0., 0., 0., save.settings.background_dim.max(0.).min(1.));
+    for (layer, _, _, material) in layers.iter_mut() {
+        if *layer != BackgroundLayer::Dim {
+            continue;
+        }
+        if let Some(material) = materials.get_mut(material) {
+            if material.color != dim {
+                material.color = dim;
+            }
+        }
+    }
+
+    if background.settled {
+        return;
+    }
+    let shown = match &background.texture {
+        None => None,
+        Some(handle) => match asset_server.get_load_state(handle) {
+            LoadState::Loaded => textures.get(handle).map(|texture| {
+                (
+                    handle.clone(),
+                    letterbox(texture.size.width as f32, texture.size.height as f32),
+                )
+            }),
+            LoadState::Failed => {
+                warn!(
+                    "Background {} could not be loaded, using the flat color",
+                    background.file.as_deref().unwrap_or_default()
+                );
+                None
+            }
+            // Keep checking, the previous picture stays hidden meanwhile
+            _ => {
+                for (_, _, mut visible, _) in layers.iter_mut() {
+                    visible.is_visible = false;
+                }
+                return;
+            }
+        },
+    };
+
+    let mut found = false;
+    for (layer, mut sprite, mut visible, material) in layers.iter_mut() {
+        found = true;
+        visible.is_visible = shown.is_some();
+        let (texture, size) = match &shown {
+            Some(shown) => shown,
+            None => continue,
+        };
+        sprite.size = *size;
+        if *layer == BackgroundLayer::Picture {
+            if let Some(material) = materials.get_mut(material) {
+                material.texture = Some(texture.clone());
+            }
+        }
+    }
+    // The sprites of a match just started are only there from the next frame
+    background.settled = found;
+}
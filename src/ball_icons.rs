@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+
+use crate::hud;
+use crate::loading::GameAssets;
+use crate::match_state::MatchRules;
+use crate::theme::Theme;
+use crate::{AppState, Ball, GameLabel, OutOfPlay};
+
+/// A small ball icon per ball of a multi-ball match, dimmed while that ball is out of play.
+pub struct BallIconsPlugin;
+
+impl Plugin for BallIconsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_enter(AppState::InGame).with_system(spawn_ball_icons.system()),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(render_ball_icons.system().after(GameLabel::GoalApply)),
+        );
+    }
+}
+
+const ICON_SIZE: f32 = 12.;
+/// Top of the icon row as a fraction of the window height.
+const ICON_TOP: f32 = 0.92;
+/// Alpha of the icon of a ball that went in and waits for its serve.
+const OUT_ALPHA: f32 = 0.25;
+
+struct BallIcon(usize);
+
+fn spawn_ball_icons(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    rules: Res<MatchRules>,
+    theme: Res<Theme>,
+    game_assets: Res<GameAssets>,
+) {
+    if rules.balls < 2 {
+        return;
+    }
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: hud::full_row(ICON_TOP),
+            material: materials.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            for index in 0..rules.balls as usize {
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(ICON_SIZE), Val::Px(ICON_SIZE)),
+                            margin: Rect {
+                                left: Val::Px(ICON_SIZE / 4.),
+                                right: Val::Px(ICON_SIZE / 4.),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                        material: materials.add(ColorMaterial {
+                            color: theme.ball_ramp[0],
+                            texture: game_assets.ball_texture.clone(),
+                        }),
+                        ..Default::default()
+                    })
+                    .insert(BallIcon(index));
+            }
+        });
+}
+
+/// Which ball an icon stands for doesn't matter, the first icons light up for the balls in play.
+fn render_ball_icons(
+    theme: Res<Theme>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    balls: Query<Option<&OutOfPlay>, With<Ball>>,
+    icons: Query<(&BallIcon, &Handle<ColorMaterial>)>,
+) {
+    let live = balls.iter().filter(|out| out.is_none()).count();
+    for (icon, material) in icons.iter() {
+        let mut color = theme.ball_ramp[0];
+        if icon.0 >= live {
+            color.set_a(OUT_ALPHA);
+        }
+        if materials
+            .get(material)
+            .map_or(false, |material| material.color != color)
+        {
+            if let Some(material) = materials.get_mut(material) {
+                material.color = color;
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use bevy::prelude::*;
+use bevy_rapier2d::physics::RigidBodyHandleComponent;
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+
+use crate::invisible::InvisibleBall;
+use crate::loading::GameAssets;
+use crate::power::PowerShot;
+use crate::quality::QualitySettings;
+use crate::theme::Theme;
+use crate::tuning::Tuning;
+use crate::{AppState, Ball, GameLabel, Score};
+
+pub struct BallVisualsPlugin;
+
+impl Plugin for BallVisualsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame).with_system(
+                tint_balls
+                    .system()
+                    .label(GameLabel::GoalPresent)
+                    .after(GameLabel::GoalApply),
+            ),
+        );
+    }
+}
+
+/// Soft sprite behind the ball, child of the ball entity.
+pub struct BallGlow;
+
+/// Soft ellipse offset down-left of the ball, child of the ball entity.
+pub struct BallShadow;
+
+/// Largest glow size relative to the ball.
+const GLOW_SCALE: f32 = 2.2;
+/// Shadow offset from the ball center at the serve speed, it grows with speed.
+const SHADOW_OFFSET: f32 = 5.;
+/// Between the pressure quads at -0.05 and the ball.
+const SHADOW_Z: f32 = -0.02;
+
+/// Own material for a ball, its glow and its shadow, tinted every frame.
+pub fn ball_materials(
+    materials: &mut Assets<ColorMaterial>,
+    game_assets: &GameAssets,
+    theme: &Theme,
+) -> (
+    Handle<ColorMaterial>,
+    Handle<ColorMaterial>,
+    Handle<ColorMaterial>,
+) {
+    let ball = materials.add(ColorMaterial {
+        color: theme.ball_ramp[0],
+        texture: game_assets.ball_texture.clone(),
+    });
+    let mut glow_color = theme.ball_glow;
+    glow_color.set_a(0.);
+    let glow = materials.add(ColorMaterial {
+        color: glow_color,
+        texture: game_assets.ball_texture.clone(),
+    });
+    let shadow = materials.add(ColorMaterial {
+        color: theme.ball_shadow,
+        texture: game_assets.ball_texture.clone(),
+    });
+
+    (ball, glow, shadow)
+}
+
+/// Glow child to spawn under each ball.
+pub fn glow_bundle(material: Handle<ColorMaterial>, ball_size: f32) -> SpriteBundle {
+    SpriteBundle {
+        material,
+        sprite: Sprite::new(Vec2::new(ball_size, ball_size)),
+        transform: Transform::from_xyz(0., 0., -0.5),
+        ..Default::default()
+    }
+}
+
+/// Shadow child to spawn under each ball.
+pub fn shadow_bundle(material: Handle<ColorMaterial>, ball_size: f32) -> SpriteBundle {
+    SpriteBundle {
+        material,
+        sprite: Sprite::new(Vec2::new(ball_size * 1.1, ball_size * 0.7)),
+        transform: Transform::from_xyz(-SHADOW_OFFSET, -SHADOW_OFFSET, SHADOW_Z),
+        ..Default::default()
+    }
+}
+
+/// Speed as `0.0..=1.0` between the serve speed and the speed cap.
+fn speed_fraction(speed: f32, tuning: &Tuning) -> f32 {
+    let range = (tuning.max_ball_speed - tuning.serve_speed).max(f32::EPSILON);
+    ((speed - tuning.serve_speed) / range).max(0.).min(1.)
+}
+
+fn ramp(colors: &[Color; 3], t: f32) -> Color {
+    let lerp = |a: Color, b: Color, t: f32| {
+        Color::rgba(
+            a.r() + (b.r() - a.r()) * t,
+            a.g() + (b.g() - a.g()) * t,
+            a.b() + (b.b() - a.b()) * t,
+            a.a() + (b.a() - a.a()) * t,
+        )
+    };
+
+    if t < 0.5 {
+        lerp(colors[0], colors[1], t * 2.)
+    } else {
+        lerp(colors[1], colors[2], (t - 0.5) * 2.)
+    }
+}
+
+/// A fresh power shot flashes the ball, a player on fire colors it, otherwise it reads the ball
+/// speed. A fast ball also glows and lifts off its shadow, which gets smaller, fainter and
+/// further away.
+fn tint_balls(
+    score: Res<Score>,
+    theme: Res<Theme>,
+    tuning: Res<Tuning>,
+    quality: Res<QualitySettings>,
+    rigid_bodies: Res<RigidBodySet>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    balls: Query<
+        (
+            &Handle<ColorMaterial>,
+            &RigidBodyHandleComponent,
+            &Transform,
+            &Children,
+            Option<&PowerShot>,
+            Option<&InvisibleBall>,
+        ),
+        With<Ball>,
+    >,
+    mut glows: Query<(&Handle<ColorMaterial>, &mut Sprite), With<BallGlow>>,
+    mut shadows: Query<
+        (&Handle<ColorMaterial>, &mut Sprite, &mut Transform),
+        (With<BallShadow>, Without<Ball>, Without<BallGlow>),
+    >,
+) {
+    for (material, rigid_body_component, transform, children, power_shot, invisible) in balls.iter()
+    {
+        let speed = match rigid_bodies.get(rigid_body_component.handle()) {
+            Some(rb) => rb.linvel().magnitude(),
+            None => continue,
+        };
+        let t = speed_fraction(speed, &tuning);
+
+        let mut color = match (power_shot, score.on_fire()) {
+            (Some(power_shot), _) if power_shot.is_flashing() => theme.power_shot,
+            (_, Some(player)) => theme.player_color(player),
+            _ => ramp(&theme.ball_ramp, t),
+        };
+        let alpha = invisible.map_or(1., |invisible| invisible.alpha());
+        color.set_a(color.a() * alpha);
+
+        if let Some(material) = materials.get_mut(material) {
+            material.color = color;
+        }
+
+        for child in children.iter() {
+            // Effects switched off by the quality settings are faded out rather than hidden, the
+            // replay hides the ball children on its own
+            if let Ok((glow_material, mut sprite)) = glows.get_mut(*child) {
+                let size = tuning.ball_size * (1. + (GLOW_SCALE - 1.) * t);
+                sprite.size = Vec2::new(size, size);
+
+                if let Some(glow_material) = materials.get_mut(glow_material) {
+                    let strength = if quality.glow { t * alpha } else { 0. };
+                    let mut glow = theme.ball_glow;
+                    glow.set_a(theme.ball_glow.a() * strength);
+                    glow_material.color = glow;
+                }
+            }
+
+            if let Ok((shadow_material, mut sprite, mut shadow_transform)) = shadows.get_mut(*child)
+            {
+                let lift = 1. - 0.25 * t;
+                sprite.size = Vec2::new(tuning.ball_size * 1.1, tuning.ball_size * 0.7) * lift;
+
+                // Keep the offset down-left on screen however the ball spins
+                let unspin = transform.rotation.inverse();
+                let offset = -SHADOW_OFFSET * (1. + t);
+                shadow_transform.translation = unspin * Vec3::new(offset, offset, SHADOW_Z);
+                shadow_transform.rotation = unspin;
+
+                if let Some(shadow_material) = materials.get_mut(shadow_material) {
+                    let strength = if quality.shadow {
+                        (1. - 0.5 * t) * alpha
+                    } else {
+                        0.
+                    };
+                    let mut shadow = theme.ball_shadow;
+                    shadow.set_a(theme.ball_shadow.a() * strength);
+                    shadow_material.color = shadow;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{PaddleRole, Player};
+
+/// Keys controlling a single paddle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerKeys {
+    #[serde(with = "key_code")]
+    pub up: KeyCode,
+    #[serde(with = "key_code")]
+    pub down: KeyCode,
+    #[serde(with = "key_code")]
+    pub left: KeyCode,
+    #[serde(with = "key_code")]
+    pub right: KeyCode,
+    #[serde(with = "key_code")]
+    pub rotate_left: KeyCode,
+    #[serde(with = "key_code")]
+    pub rotate_right: KeyCode,
+    #[serde(with = "key_code")]
+    pub power: KeyCode,
+    /// Held to catch the ball in catch and throw matches.
+    #[serde(with = "key_code")]
+    pub catch: KeyCode,
+}
+
+impl PlayerKeys {
+    pub fn all(&self) -> [KeyCode; 8] {
+        [
+            self.up,
+            self.down,
+            self.left,
+            self.right,
+            self.rotate_left,
+            self.rotate_right,
+            self.power,
+            self.catch,
+        ]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub left: PlayerKeys,
+    pub right: PlayerKeys,
+    /// Back paddles in doubles, the front paddles use the regular keys.
+    pub left_back: PlayerKeys,
+    pub right_back: PlayerKeys,
+}
+
+impl KeyBindings {
+    pub fn for_player(&self, player: &Player) -> &PlayerKeys {
+        match player {
+            Player::Left => &self.left,
+            Player::Right => &self.right,
+        }
+    }
+
+    pub fn for_paddle(&self, player: Player, role: PaddleRole) -> &PlayerKeys {
+        match (player, role) {
+            (Player::Left, PaddleRole::Back) => &self.left_back,
+            (Player::Right, PaddleRole::Back) => &self.right_back,
+            _ => self.for_player(&player),
+        }
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            left: PlayerKeys {
+                up: KeyCode::W,
+                down: KeyCode::S,
+                left: KeyCode::A,
+                right: KeyCode::D,
+                rotate_left: KeyCode::Q,
+                rotate_right: KeyCode::E,
+                power: KeyCode::Z,
+                catch: KeyCode::X,
+            },
+            right: PlayerKeys {
+                up: KeyCode::Numpad8,
+                down: KeyCode::Numpad5,
+                left: KeyCode::Numpad4,
+                right: KeyCode::Numpad6,
+                rotate_left: KeyCode::Numpad7,
+                rotate_right: KeyCode::Numpad9,
+                power: KeyCode::Numpad0,
+                catch: KeyCode::Numpad2,
+            },
+            left_back: PlayerKeys {
+                up: KeyCode::T,
+                down: KeyCode::G,
+                left: KeyCode::F,
+                right: KeyCode::H,
+                rotate_left: KeyCode::R,
+                rotate_right: KeyCode::Y,
+                power: KeyCode::V,
+                catch: KeyCode::B,
+            },
+            right_back: PlayerKeys {
+                up: KeyCode::I,
+                down: KeyCode::K,
+                left: KeyCode::J,
+                right: KeyCode::L,
+                rotate_left: KeyCode::U,
+                rotate_right: KeyCode::O,
+                power: KeyCode::M,
+                catch: KeyCode::N,
+            },
+        }
+    }
+}
+
+/// Short label for a key, used in on-screen hints.
+pub fn key_name(key: KeyCode) -> String {
+    let name = format!("{:?}", key);
+    match name.strip_prefix("Numpad") {
+        Some(rest) => format!("Num {}", rest),
+        None => name,
+    }
+}
+
+/// Keys that can be bound, a binding is saved by the key's name.
+const BINDABLE_KEYS: [KeyCode; 68] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Numpad0,
+    KeyCode::Numpad1,
+    KeyCode::Numpad2,
+    KeyCode::Numpad3,
+    KeyCode::Numpad4,
+    KeyCode::Numpad5,
+    KeyCode::Numpad6,
+    KeyCode::Numpad7,
+    KeyCode::Numpad8,
+    KeyCode::Numpad9,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Space,
+    KeyCode::Return,
+    KeyCode::Tab,
+    KeyCode::LShift,
+    KeyCode::RShift,
+    KeyCode::LControl,
+    KeyCode::RControl,
+    KeyCode::LAlt,
+    KeyCode::RAlt,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Semicolon,
+    KeyCode::Apostrophe,
+    KeyCode::LBracket,
+    KeyCode::RBracket,
+    KeyCode::Minus,
+    KeyCode::Equals,
+];
+
+/// Bevy only serializes key codes with its `serialize` feature, keys are written as their name
+/// instead.
+mod key_code {
+    use bevy::prelude::KeyCode;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::BINDABLE_KEYS;
+
+    pub fn serialize<S: Serializer>(key: &KeyCode, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:?}", key))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<KeyCode, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        BINDABLE_KEYS
+            .iter()
+            .find(|key| format!("{:?}", key) == name)
+            .copied()
+            .ok_or_else(|| D::Error::custom(format!("unknown key {}", name)))
+    }
+}
@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+
+use crate::contacts::{BallContact, Surface};
+use crate::theme::Theme;
+use crate::tuning::Tuning;
+use crate::{AppState, GameLabel, Paddle, Paused, Player, ServeEvent};
+
+pub struct BoostPlugin;
+
+impl Plugin for BoostPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(
+                    grant_receiver_boost
+                        .system()
+                        .label("grant_receiver_boost")
+                        .after(GameLabel::GoalApply),
+                )
+                .with_system(
+                    expire_receiver_boost
+                        .system()
+                        .label("expire_receiver_boost")
+                        .after(GameLabel::Contacts)
+                        .after("grant_receiver_boost"),
+                )
+                .with_system(show_boost_glow.system().after("expire_receiver_boost")),
+        );
+    }
+}
+
+/// Speeds up the receiver's paddles for a short while after a serve.
+pub struct ReceiverBoost {
+    pub multiplier: f32,
+    timer: Timer,
+}
+
+/// Glow child shown while the paddle has a `ReceiverBoost`.
+struct BoostGlow;
+
+/// Glow size relative to the paddle.
+const GLOW_SCALE: f32 = 1.6;
+
+fn grant_receiver_boost(
+    mut commands: Commands,
+    tuning: Res<Tuning>,
+    mut serves: EventReader<ServeEvent>,
+    paddles: Query<(Entity, &Player), With<Paddle>>,
+) {
+    for serve in serves.iter() {
+        for (entity, player) in paddles.iter() {
+            if *player != serve.receiver {
+                continue;
+            }
+
+            commands.entity(entity).insert(ReceiverBoost {
+                multiplier: tuning.receiver_boost,
+                timer: Timer::from_seconds(tuning.receiver_boost_seconds, false),
+            });
+        }
+    }
+}
+
+/// Runs the boost out while the game is live, a touch of the ball ends it early.
+fn expire_receiver_boost(
+    mut commands: Commands,
+    time: Res<Time>,
+    paused: Res<Paused>,
+    mut contacts: EventReader<BallContact>,
+    mut boosted: Query<(Entity, &Player, &mut ReceiverBoost)>,
+) {
+    let touched: Vec<Player> = contacts
+        .iter()
+        .filter_map(|contact| match contact.surface {
+            Surface::Paddle(player) if contact.started => Some(player),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, player, mut boost) in boosted.iter_mut() {
+        if !paused.0 {
+            boost.timer.tick(time.delta());
+        }
+
+        if boost.timer.finished() || touched.contains(player) {
+            commands.entity(entity).remove::<ReceiverBoost>();
+        }
+    }
+}
+
+fn show_boost_glow(
+    mut commands: Commands,
+    tuning: Res<Tuning>,
+    theme: Res<Theme>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    added: Query<(Entity, &Player, &Sprite), Added<ReceiverBoost>>,
+    removed: RemovedComponents<ReceiverBoost>,
+    children: Query<&Children>,
+    glows: Query<Entity, With<BoostGlow>>,
+) {
+    let clear_glow = |commands: &mut Commands, paddle: Entity| {
+        if let Ok(children) = children.get(paddle) {
+            for child in children.iter() {
+                if glows.get(*child).is_ok() {
+                    commands.entity(*child).despawn();
+                }
+            }
+        }
+    };
+
+    for paddle in removed.iter() {
+        clear_glow(&mut commands, paddle);
+    }
+
+    for (paddle, player, sprite) in added.iter() {
+        // A new serve can re-add the boost before the old one ran out
+        clear_glow(&mut commands, paddle);
+
+        let mut color = theme.player_color(*player);
+        color.set_a(0.35);
+        let width = tuning.paddle_width * GLOW_SCALE;
+
+        commands.entity(paddle).with_children(|parent| {
+            parent
+                .spawn_bundle(SpriteBundle {
+                    material: materials.add(color.into()),
+                    sprite: Sprite::new(Vec2::new(width, sprite.size.y * 1.1)),
+                    transform: Transform::from_xyz(0., 0., -0.05),
+                    ..Default::default()
+                })
+                .insert(BoostGlow);
+        });
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::callout::Callout;
+use crate::contacts::{BallContact, Surface};
+use crate::hud;
+use crate::match_state::{MatchPhase, MatchRules};
+use crate::theme::Theme;
+use crate::{AppState, Ball, GameLabel, GoalEvent, Paused, Player, ServeEvent, Wall, ARENA_MIDDLE};
+
+pub struct BounceLimitPlugin;
+
+impl Plugin for BounceLimitPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<WallBounces>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame).with_system(spawn_bounce_pips.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(
+                        count_bounces
+                            .system()
+                            .label(GameLabel::GoalDetect)
+                            .after(GameLabel::Contacts),
+                    )
+                    .with_system(
+                        reset_bounces
+                            .system()
+                            .after(GameLabel::GoalApply)
+                            .before(GameLabel::GoalPresent),
+                    )
+                    .with_system(
+                        render_bounce_pips
+                            .system()
+                            .label(GameLabel::GoalPresent)
+                            .after(GameLabel::GoalApply),
+                    ),
+            );
+    }
+}
+
+/// Top and bottom wall touches allowed in one half, the next one gives the point away.
+const BOUNCE_LIMIT: u32 = 2;
+const PIP_SIZE: f32 = 10.;
+/// Top of the pip row as a fraction of the window height.
+const PIP_TOP: f32 = 0.1;
+
+/// Wall touches of a ball since it last crossed the center line.
+#[derive(Debug, Clone, Copy)]
+struct SideBounces {
+    side: Player,
+    count: u32,
+}
+
+#[derive(Debug, Default)]
+struct WallBounces {
+    balls: HashMap<Entity, SideBounces>,
+}
+
+impl WallBounces {
+    /// Half and bounces left of the ball closest to breaking the rule.
+    fn remaining(&self) -> Option<(Player, u32)> {
+        self.balls
+            .values()
+            .max_by_key(|bounces| bounces.count)
+            .map(|bounces| (bounces.side, BOUNCE_LIMIT.saturating_sub(bounces.count)))
+    }
+
+    /// Counts for the ball in the half of `side`, starting over when it changed halves.
+    fn on_side(&mut self, ball: Entity, side: Player) -> &mut SideBounces {
+        let bounces = self
+            .balls
+            .entry(ball)
+            .or_insert(SideBounces { side, count: 0 });
+        if bounces.side != side {
+            *bounces = SideBounces { side, count: 0 };
+        }
+        bounces
+    }
+}
+
+struct BouncePip(u32);
+
+/// Holds the pips, over the half the ball is in.
+struct BouncePipRow;
+
+fn side_of(x: f32) -> Player {
+    if x < ARENA_MIDDLE {
+        Player::Left
+    } else {
+        Player::Right
+    }
+}
+
+/// A ball touching the top or bottom wall more than `BOUNCE_LIMIT` times on one side of the
+/// center line scores for the player on the other side.
+fn count_bounces(
+    paused: Res<Paused>,
+    phase: Res<MatchPhase>,
+    rules: Res<MatchRules>,
+    mut bounces: ResMut<WallBounces>,
+    mut contacts: EventReader<BallContact>,
+    mut goals: EventWriter<GoalEvent>,
+    mut callouts: EventWriter<Callout>,
+    balls: Query<(Entity, &Transform), With<Ball>>,
+) {
+    if paused.0 || phase.is_finished() || !rules.bounce_limit {
+        return;
+    }
+
+    bounces.balls.retain(|ball, _| balls.get(*ball).is_ok());
+    for (ball, transform) in balls.iter() {
+        bounces.on_side(ball, side_of(transform.translation.x));
+    }
+
+    for contact in contacts.iter() {
+        match contact.surface {
+            Surface::Wall(Wall::Top) | Surface::Wall(Wall::Bottom) if contact.started => {}
+            _ => continue,
+        }
+
+        let side = side_of(contact.position.x);
+        let ball_bounces = bounces.on_side(contact.ball, side);
+        ball_bounces.count += 1;
+        if ball_bounces.count <= BOUNCE_LIMIT {
+            continue;
+        }
+
+        ball_bounces.count = 0;
+        callouts.send(Callout("Triple bounce!".to_string()));
+        goals.send(GoalEvent {
+            scorer: side.opponent(),
+            ball: contact.ball,
+            crossing_y: None,
+        });
+    }
+}
+
+/// Goals and serves start the count over from the center spot.
+fn reset_bounces(
+    mut bounces: ResMut<WallBounces>,
+    mut goals: EventReader<GoalEvent>,
+    mut serves: EventReader<ServeEvent>,
+) {
+    if goals.iter().next().is_some() | serves.iter().next().is_some() {
+        bounces.balls.clear();
+    }
+}
+
+fn spawn_bounce_pips(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    theme: Res<Theme>,
+    rules: Res<MatchRules>,
+) {
+    if !rules.bounce_limit {
+        return;
+    }
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: hud::half_row(Player::Left, PIP_TOP),
+            material: materials.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .insert(BouncePipRow)
+        .with_children(|parent| {
+            for index in 0..BOUNCE_LIMIT {
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(PIP_SIZE), Val::Px(PIP_SIZE)),
+                            margin: Rect {
+                                left: Val::Px(PIP_SIZE / 4.),
+                                right: Val::Px(PIP_SIZE / 4.),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                        material: materials.add(theme.bounce_pip.into()),
+                        ..Default::default()
+                    })
+                    .insert(BouncePip(index));
+            }
+        });
+}
+
+/// The row moves over the half the ball is in.
+fn render_bounce_pips(
+    bounces: Res<WallBounces>,
+    theme: Res<Theme>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut rows: Query<&mut Style, With<BouncePipRow>>,
+    pips: Query<(&BouncePip, &Handle<ColorMaterial>)>,
+) {
+    if !bounces.is_changed() && !theme.is_changed() {
+        return;
+    }
+
+    let (side, remaining) = bounces.remaining().unwrap_or((Player::Left, BOUNCE_LIMIT));
+    for mut style in rows.iter_mut() {
+        style.position.left = hud::percent(hud::half_left(side));
+    }
+    for (pip, material) in pips.iter() {
+        if let Some(material) = materials.get_mut(material) {
+            material.color = if pip.0 < remaining {
+                theme.bounce_pip
+            } else {
+                theme.bounce_pip_used
+            };
+        }
+    }
+}
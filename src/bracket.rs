@@ -0,0 +1,376 @@
+use serde::{Deserialize, Serialize};
+
+/// Slots in the first round, the smallest one that fits is used and the top seeds fill the rest
+/// with byes.
+const BRACKET_SIZES: [usize; 2] = [4, 8];
+pub const MAX_BRACKET_PLAYERS: usize = 8;
+
+/// How a match was decided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    Played {
+        left_goals: u32,
+        right_goals: u32,
+    },
+    /// No opponent in the first round.
+    Bye,
+    /// The opponent withdrew.
+    Walkover,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Decided {
+    pub winner: usize,
+    pub outcome: Outcome,
+}
+
+/// Who plays on one side of a match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entrant {
+    Player(usize),
+    Bye,
+    /// Winner of a match not decided yet.
+    Pending,
+}
+
+/// One match of the bracket, the left entrant plays on the left paddle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BracketMatch {
+    pub left: Entrant,
+    pub right: Entrant,
+    pub decided: Option<Decided>,
+}
+
+/// Single elimination between players given as indices in seed order, 0 being the top seed. No
+/// Bevy in here, the tournament drives it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bracket {
+    /// Player in each first round slot, `None` for a bye.
+    slots: Vec<Option<usize>>,
+    /// Every match of every round, first round first.
+    rounds: Vec<Vec<Option<Decided>>>,
+    /// Players who quit, they lose every match they are drawn into.
+    withdrawn: Vec<usize>,
+}
+
+/// Slots of the seeds from the top of the bracket, so the top two seeds can only meet in the
+/// final: 0 3 1 2 for four slots, 0 7 3 4 1 6 2 5 for eight.
+fn seed_order(size: usize) -> Vec<usize> {
+    let mut order = vec![0];
+    while order.len() < size {
+        let count = order.len() * 2;
+        order = order
+            .iter()
+            .flat_map(|seed| vec![*seed, count - 1 - seed])
+            .collect();
+    }
+    order
+}
+
+impl Bracket {
+    /// For `count` players up to `MAX_BRACKET_PLAYERS`, byes go to the top seeds.
+    pub fn new(count: usize) -> Self {
+        let size = BRACKET_SIZES
+            .iter()
+            .copied()
+            .find(|size| *size >= count)
+            .unwrap_or(MAX_BRACKET_PLAYERS);
+        let slots = seed_order(size)
+            .into_iter()
+            .map(|seed| if seed < count { Some(seed) } else { None })
+            .collect();
+
+        let mut rounds = Vec::new();
+        let mut matches = size / 2;
+        while matches > 0 {
+            rounds.push(vec![None; matches]);
+            matches /= 2;
+        }
+
+        let mut bracket = Bracket {
+            slots,
+            rounds,
+            withdrawn: Vec::new(),
+        };
+        bracket.settle();
+        bracket
+    }
+
+    fn entrant(&self, round: usize, index: usize, side: usize) -> Entrant {
+        if round == 0 {
+            return match self.slots.get(index * 2 + side) {
+                Some(Some(player)) => Entrant::Player(*player),
+                _ => Entrant::Bye,
+            };
+        }
+        match self.rounds[round - 1][index * 2 + side] {
+            Some(decided) => Entrant::Player(decided.winner),
+            None => Entrant::Pending,
+        }
+    }
+
+    /// Decides every match that needs no play, byes and withdrawn players. Rounds are gone
+    /// through in order so a walkover carries on through the rounds after it.
+    fn settle(&mut self) {
+        for round in 0..self.rounds.len() {
+            for index in 0..self.rounds[round].len() {
+                if self.rounds[round][index].is_some() {
+                    continue;
+                }
+
+                let decided = match (self.entrant(round, index, 0), self.entrant(round, index, 1)) {
+                    (Entrant::Player(winner), Entrant::Bye)
+                    | (Entrant::Bye, Entrant::Player(winner)) => Some(Decided {
+                        winner,
+                        outcome: Outcome::Bye,
+                    }),
+                    (Entrant::Player(left), Entrant::Player(right)) => match (
+                        self.withdrawn.contains(&left),
+                        self.withdrawn.contains(&right),
+                    ) {
+                        (false, true) | (true, true) => Some(left),
+                        (true, false) => Some(right),
+                        (false, false) => None,
+                    }
+                    .map(|winner| Decided {
+                        winner,
+                        outcome: Outcome::Walkover,
+                    }),
+                    _ => None,
+                };
+                self.rounds[round][index] = decided;
+            }
+        }
+    }
+
+    /// Round and index of the next match to play, the first one with both players known.
+    fn current(&self) -> Option<(usize, usize)> {
+        (0..self.rounds.len())
+            .flat_map(|round| (0..self.rounds[round].len()).map(move |index| (round, index)))
+            .find(|(round, index)| {
+                self.rounds[*round][*index].is_none()
+                    && matches!(self.entrant(*round, *index, 0), Entrant::Player(_))
+                    && matches!(self.entrant(*round, *index, 1), Entrant::Player(_))
+            })
+    }
+
+    /// Players of the next match, left first.
+    pub fn pairing(&self) -> Option<(usize, usize)> {
+        let (round, index) = self.current()?;
+        match (self.entrant(round, index, 0), self.entrant(round, index, 1)) {
+            (Entrant::Player(left), Entrant::Player(right)) => Some((left, right)),
+            _ => None,
+        }
+    }
+
+    /// Books the next match, a draw goes to the left player.
+    pub fn record(&mut self, left_goals: u32, right_goals: u32) {
+        let (round, index) = match self.current() {
+            Some(current) => current,
+            None => return,
+        };
+        let (left, right) = match self.pairing() {
+            Some(pairing) => pairing,
+            None => return,
+        };
+
+        self.rounds[round][index] = Some(Decided {
+            winner: if right_goals > left_goals {
+                right
+            } else {
+                left
+            },
+            outcome: Outcome::Played {
+                left_goals,
+                right_goals,
+            },
+        });
+        self.settle();
+    }
+
+    /// Takes `player` out, the opponent of their next match goes through without playing.
+    pub fn withdraw(&mut self, player: usize) {
+        if !self.withdrawn.contains(&player) {
+            self.withdrawn.push(player);
+        }
+        self.settle();
+    }
+
+    pub fn champion(&self) -> Option<usize> {
+        self.rounds
+            .last()?
+            .first()
+            .copied()
+            .flatten()
+            .map(|decided| decided.winner)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.champion().is_some()
+    }
+
+    /// Every match round by round, first round first, for drawing the bracket.
+    pub fn matches(&self) -> Vec<Vec<BracketMatch>> {
+        (0..self.rounds.len())
+            .map(|round| {
+                (0..self.rounds[round].len())
+                    .map(|index| BracketMatch {
+                        left: self.entrant(round, index, 0),
+                        right: self.entrant(round, index, 1),
+                        decided: self.rounds[round][index],
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn byes(bracket: &Bracket) -> Vec<usize> {
+        bracket.matches()[0]
+            .iter()
+            .filter_map(|m| m.decided)
+            .filter(|decided| decided.outcome == Outcome::Bye)
+            .map(|decided| decided.winner)
+            .collect()
+    }
+
+    /// Plays the next match with the better seed winning.
+    fn play_favourite(bracket: &mut Bracket) -> (usize, usize) {
+        let (left, right) = bracket.pairing().expect("a match to play");
+        if left < right {
+            bracket.record(3, 1);
+        } else {
+            bracket.record(1, 3);
+        }
+        (left, right)
+    }
+
+    #[test]
+    fn seed_order_keeps_top_seeds_apart() {
+        assert_eq!(seed_order(4), vec![0, 3, 1, 2]);
+        assert_eq!(seed_order(8), vec![0, 7, 3, 4, 1, 6, 2, 5]);
+    }
+
+    #[test]
+    fn byes_go_to_the_top_seeds() {
+        assert_eq!(byes(&Bracket::new(3)), vec![0]);
+        assert_eq!(byes(&Bracket::new(5)), vec![0, 1, 2]);
+        assert_eq!(byes(&Bracket::new(6)), vec![0, 1]);
+        assert_eq!(byes(&Bracket::new(7)), vec![0]);
+        assert!(byes(&Bracket::new(4)).is_empty());
+        assert!(byes(&Bracket::new(8)).is_empty());
+    }
+
+    #[test]
+    fn first_match_skips_byes() {
+        assert_eq!(Bracket::new(3).pairing(), Some((1, 2)));
+        assert_eq!(Bracket::new(5).pairing(), Some((3, 4)));
+        assert_eq!(Bracket::new(7).pairing(), Some((3, 4)));
+    }
+
+    #[test]
+    fn top_two_seeds_only_meet_in_the_final() {
+        for count in 3..=MAX_BRACKET_PLAYERS {
+            let mut bracket = Bracket::new(count);
+            let mut last = None;
+            while !bracket.is_finished() {
+                let pairing = play_favourite(&mut bracket);
+                if let Some(previous) = last {
+                    assert_ne!(previous, (0, 1), "{} players", count);
+                }
+                last = Some(pairing);
+            }
+            assert_eq!(last, Some((0, 1)), "{} players", count);
+            assert_eq!(bracket.champion(), Some(0));
+        }
+    }
+
+    #[test]
+    fn withdrawn_player_gives_a_walkover() {
+        let mut bracket = Bracket::new(4);
+        bracket.withdraw(3);
+
+        assert_eq!(
+            bracket.matches()[0][0].decided,
+            Some(Decided {
+                winner: 0,
+                outcome: Outcome::Walkover,
+            })
+        );
+        assert_eq!(bracket.pairing(), Some((1, 2)));
+    }
+
+    #[test]
+    fn walkover_carries_into_later_rounds() {
+        let mut bracket = Bracket::new(8);
+        // The top seed quits and hands the first match to 7, who then quits before round two
+        bracket.withdraw(0);
+        assert_eq!(
+            bracket.matches()[0][0].decided,
+            Some(Decided {
+                winner: 7,
+                outcome: Outcome::Walkover,
+            })
+        );
+        bracket.withdraw(7);
+        assert_eq!(bracket.pairing(), Some((3, 4)));
+
+        bracket.record(0, 2);
+        assert_eq!(
+            bracket.matches()[1][0].decided,
+            Some(Decided {
+                winner: 4,
+                outcome: Outcome::Walkover,
+            })
+        );
+    }
+
+    #[test]
+    fn withdrawing_after_a_win_forfeits_the_final() {
+        let mut bracket = Bracket::new(4);
+        bracket.record(5, 2);
+        bracket.withdraw(0);
+        assert!(!bracket.is_finished());
+
+        assert_eq!(bracket.pairing(), Some((1, 2)));
+        bracket.record(1, 3);
+
+        assert!(bracket.is_finished());
+        assert_eq!(bracket.champion(), Some(2));
+        assert_eq!(
+            bracket.matches()[1][0]
+                .decided
+                .map(|decided| decided.outcome),
+            Some(Outcome::Walkover)
+        );
+        assert_eq!(bracket.pairing(), None);
+    }
+
+    #[test]
+    fn champion_after_the_final() {
+        let mut bracket = Bracket::new(4);
+        assert_eq!(bracket.champion(), None);
+        bracket.record(4, 1);
+        bracket.record(2, 4);
+        assert!(!bracket.is_finished());
+        assert_eq!(bracket.pairing(), Some((0, 2)));
+
+        bracket.record(0, 5);
+        assert!(bracket.is_finished());
+        assert_eq!(bracket.champion(), Some(2));
+        assert_eq!(
+            bracket.matches()[1][0]
+                .decided
+                .map(|decided| decided.outcome),
+            Some(Outcome::Played {
+                left_goals: 0,
+                right_goals: 5,
+            })
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use bevy::prelude::*;
+
+use crate::theme::Theme;
+use crate::UiFont;
+
+pub struct ButtonsPlugin;
+
+impl Plugin for ButtonsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ButtonMaterials>()
+            .add_system(highlight_buttons.system());
+    }
+}
+
+/// Button backgrounds for each interaction, built from the theme.
+pub struct ButtonMaterials {
+    normal: Handle<ColorMaterial>,
+    hovered: Handle<ColorMaterial>,
+    pressed: Handle<ColorMaterial>,
+}
+
+impl FromWorld for ButtonMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let theme = world.get_resource::<Theme>().cloned().unwrap_or_default();
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        ButtonMaterials {
+            normal: materials.add(theme.button.into()),
+            hovered: materials.add(theme.button_hovered.into()),
+            pressed: materials.add(theme.button_pressed.into()),
+        }
+    }
+}
+
+/// Button picked with the keyboard, drawn hovered while the mouse isn't on another one.
+pub struct Focused;
+
+/// Spawns a labeled button tagged with `action`, screens react to `Interaction::Clicked` on it.
+/// Buttons are laid out by their parent's flexbox rather than at fixed pixels, so hit testing
+/// follows the layout when the window is resized.
+pub fn spawn_button(
+    parent: &mut ChildBuilder,
+    font: &UiFont,
+    theme: &Theme,
+    materials: &ButtonMaterials,
+    label: &str,
+    focused: bool,
+    action: impl Component,
+) {
+    let mut button = parent.spawn_bundle(ButtonBundle {
+        style: Style {
+            min_size: Size::new(Val::Px(260.), Val::Px(48.)),
+            margin: Rect::all(Val::Px(6.)),
+            padding: Rect::all(Val::Px(8.)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        material: if focused {
+            materials.hovered.clone()
+        } else {
+            materials.normal.clone()
+        },
+        ..Default::default()
+    });
+    if focused {
+        button.insert(Focused);
+    }
+    button.insert(action).with_children(|button| {
+        button.spawn_bundle(TextBundle {
+            text: Text::with_section(
+                label.to_string(),
+                TextStyle {
+                    font: font.0.clone(),
+                    font_size: 28.0,
+                    color: theme.button_text,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        });
+    });
+}
+
+fn highlight_buttons(
+    materials: Res<ButtonMaterials>,
+    mut buttons: Query<
+        (&Interaction, Option<&Focused>, &mut Handle<ColorMaterial>),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, focused, mut material) in buttons.iter_mut() {
+        *material = match *interaction {
+            Interaction::Clicked => materials.pressed.clone(),
+            Interaction::Hovered => materials.hovered.clone(),
+            Interaction::None if focused.is_some() => materials.hovered.clone(),
+            Interaction::None => materials.normal.clone(),
+        };
+    }
+}
@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+
+use crate::hud;
+use crate::{GameLabel, UiFont};
+
+pub struct CalloutPlugin;
+
+impl Plugin for CalloutPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<Callout>()
+            .add_system(
+                spawn_callouts
+                    .system()
+                    .label("spawn_callouts")
+                    .after(GameLabel::GoalPresent),
+            )
+            .add_system(fade_callouts.system().after("spawn_callouts"));
+    }
+}
+
+/// Short announcement flashed in the middle of the arena, e.g. "Golden goal!".
+pub struct Callout(pub String);
+
+const CALLOUT_SECONDS: f32 = 1.5;
+
+struct CalloutRoot;
+
+struct CalloutText {
+    timer: Timer,
+}
+
+fn spawn_callouts(
+    mut commands: Commands,
+    mut events: EventReader<Callout>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    font: Option<Res<UiFont>>,
+    current: Query<Entity, With<CalloutRoot>>,
+) {
+    let font = match font {
+        Some(font) => font.0.clone(),
+        None => return,
+    };
+
+    // Only the latest callout of the frame is shown, and it replaces whatever is on screen
+    let callout = match events.iter().last() {
+        Some(callout) => callout,
+        None => return,
+    };
+
+    for entity in current.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: hud::full_row(0.25),
+            material: materials.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .insert(CalloutRoot)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        callout.0.clone(),
+                        TextStyle {
+                            font,
+                            font_size: 64.0,
+                            color: Color::rgb(1.0, 0.85, 0.2),
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(CalloutText {
+                    timer: Timer::from_seconds(CALLOUT_SECONDS, false),
+                });
+        });
+}
+
+fn fade_callouts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut callouts: Query<(&Parent, &mut CalloutText, &mut Text)>,
+) {
+    for (parent, mut callout, mut text) in callouts.iter_mut() {
+        callout.timer.tick(time.delta());
+
+        if callout.timer.finished() {
+            commands.entity(parent.0).despawn_recursive();
+        } else {
+            let alpha = 1.0 - callout.timer.percent();
+            text.sections[0].style.color.set_a(alpha);
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use bevy::prelude::*;
+use bevy_rapier2d::physics::{RapierConfiguration, RigidBodyHandleComponent};
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+use bevy_rapier2d::rapier::na::Vector2;
+
+use crate::contacts::{BallContact, Surface};
+use crate::input::PaddleIntent;
+use crate::match_state::{MatchPhase, MatchRules};
+use crate::physics::teleport_body;
+use crate::theme::Theme;
+use crate::tuning::Tuning;
+use crate::{AppState, Ball, GameLabel, Paused, Player};
+
+/// Catch and throw matches, holding the catch key as the ball hits the paddle holds on to it.
+pub struct CatchPlugin;
+
+impl Plugin for CatchPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(
+                    catch_ball
+                        .system()
+                        .label("catch_ball")
+                        .after(GameLabel::Contacts),
+                )
+                .with_system(
+                    hold_ball
+                        .system()
+                        .label("hold_ball")
+                        .after("catch_ball")
+                        .before("clamp_ball_speed"),
+                )
+                .with_system(show_throw_cue.system().after("hold_ball")),
+        );
+    }
+}
+
+/// Longest the ball can be held before it is thrown anyway.
+const CATCH_SECONDS: f32 = 1.5;
+/// Extra throw speed over the serve speed for a throw right after the catch, it shrinks to
+/// nothing at `CATCH_SECONDS`.
+const THROW_BONUS: f32 = 0.5;
+/// Size of the charge cue relative to the ball.
+const CUE_SCALE: f32 = 2.;
+
+/// A ball held by a paddle, kept in place in front of it until thrown.
+pub struct Caught {
+    paddle: Entity,
+    player: Player,
+    /// Ball position in the paddle's frame, physics units.
+    offset: Vector2<f32>,
+    held: f32,
+    cue: Entity,
+}
+
+/// Ring around a held ball, in the catcher's color, telling the opponent a throw is coming.
+struct ThrowCue;
+
+/// Which way a paddle of `player` faces before it is rotated.
+fn facing(player: Player) -> f32 {
+    match player {
+        Player::Left => 1.,
+        Player::Right => -1.,
+    }
+}
+
+fn catch_ball(
+    mut commands: Commands,
+    rules: Res<MatchRules>,
+    phase: Res<MatchPhase>,
+    tuning: Res<Tuning>,
+    theme: Res<Theme>,
+    rapier_config: Res<RapierConfiguration>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    mut contacts: EventReader<BallContact>,
+    intents: Query<(&PaddleIntent, &RigidBodyHandleComponent)>,
+    balls: Query<&RigidBodyHandleComponent, (With<Ball>, Without<Caught>)>,
+) {
+    if !rules.catch_and_throw || phase.is_finished() {
+        return;
+    }
+
+    let mut caught = Vec::new();
+    for contact in contacts.iter() {
+        let player = match contact.surface {
+            Surface::Paddle(player) if contact.started => player,
+            _ => continue,
+        };
+        if caught.contains(&contact.ball) {
+            continue;
+        }
+
+        let paddle_body = match intents.get(contact.other) {
+            Ok((intent, paddle_body)) if intent.catch => paddle_body,
+            _ => continue,
+        };
+        let ball_body = match balls.get(contact.ball) {
+            Ok(ball_body) => ball_body,
+            Err(_) => continue,
+        };
+        let paddle_position = match rigid_bodies.get(paddle_body.handle()) {
+            Some(rb) => *rb.position(),
+            None => continue,
+        };
+
+        if let Some(rb) = rigid_bodies.get_mut(ball_body.handle()) {
+            // Keep the ball on the face it was caught with, never behind the paddle
+            let mut offset = paddle_position.rotation.inverse_transform_vector(
+                &(rb.position().translation.vector - paddle_position.translation.vector),
+            );
+            let gap = (tuning.paddle_width + tuning.ball_size) / 2. / rapier_config.scale;
+            offset.x = facing(player) * offset.x.abs().max(gap);
+
+            rb.set_linvel(Vector2::zeros(), true);
+            rb.set_angvel(0., true);
+
+            let mut color = theme.player_color(player);
+            color.set_a(0.5);
+            let cue = commands
+                .spawn_bundle(SpriteBundle {
+                    material: materials.add(color.into()),
+                    sprite: Sprite::new(Vec2::splat(tuning.ball_size * CUE_SCALE)),
+                    transform: Transform::from_xyz(0., 0., -0.1),
+                    ..Default::default()
+                })
+                .insert(ThrowCue)
+                .id();
+            commands
+                .entity(contact.ball)
+                .push_children(&[cue])
+                .insert(Caught {
+                    paddle: contact.other,
+                    player,
+                    offset,
+                    held: 0.,
+                    cue,
+                });
+            caught.push(contact.ball);
+        }
+    }
+}
+
+/// Keeps a held ball in front of its paddle, and throws it where the paddle faces when the key
+/// is let go or time runs out. A quick throw is a fast one.
+fn hold_ball(
+    mut commands: Commands,
+    time: Res<Time>,
+    paused: Res<Paused>,
+    phase: Res<MatchPhase>,
+    tuning: Res<Tuning>,
+    rapier_config: Res<RapierConfiguration>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    intents: Query<(&PaddleIntent, &RigidBodyHandleComponent)>,
+    mut balls: Query<(
+        Entity,
+        &RigidBodyHandleComponent,
+        &mut Transform,
+        &mut Caught,
+    )>,
+) {
+    if paused.0 {
+        return;
+    }
+
+    for (entity, ball_body, mut transform, mut caught) in balls.iter_mut() {
+        caught.held += time.delta_seconds();
+
+        let paddle = intents.get(caught.paddle).ok().and_then(|(intent, body)| {
+            rigid_bodies
+                .get(body.handle())
+                .map(|rb| (intent.catch, *rb.position()))
+        });
+        let rb = match rigid_bodies.get_mut(ball_body.handle()) {
+            Some(rb) => rb,
+            None => continue,
+        };
+
+        let (holding, paddle_position) = match paddle {
+            Some(paddle) if !phase.is_finished() => paddle,
+            _ => {
+                commands.entity(caught.cue).despawn();
+                commands.entity(entity).remove::<Caught>();
+                continue;
+            }
+        };
+
+        if holding && caught.held < CATCH_SECONDS {
+            let mut position = *rb.position();
+            position.translation.vector = paddle_position.translation.vector
+                + paddle_position.rotation.transform_vector(&caught.offset);
+            teleport_body(rb, &mut transform, position, rapier_config.scale);
+            rb.set_linvel(Vector2::zeros(), true);
+            continue;
+        }
+
+        let freshness = 1. - (caught.held / CATCH_SECONDS).min(1.);
+        let speed = tuning.serve_speed * (1. + THROW_BONUS * freshness);
+        let direction = paddle_position
+            .rotation
+            .transform_vector(&Vector2::new(facing(caught.player), 0.));
+        rb.set_linvel(direction * speed, true);
+
+        commands.entity(caught.cue).despawn();
+        commands.entity(entity).remove::<Caught>();
+    }
+}
+
+/// The cue pulses and closes in on the ball as the hold runs out.
+fn show_throw_cue(
+    time: Res<Time>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    balls: Query<&Caught>,
+    mut cues: Query<(&mut Transform, &Handle<ColorMaterial>), With<ThrowCue>>,
+) {
+    let pulse = (time.seconds_since_startup() as f32 * 12.).sin() * 0.5 + 0.5;
+
+    for caught in balls.iter() {
+        if let Ok((mut transform, material)) = cues.get_mut(caught.cue) {
+            let left = 1. - (caught.held / CATCH_SECONDS).min(1.);
+            transform.scale = Vec3::splat(0.5 + 0.5 * left);
+            if let Some(material) = materials.get_mut(material) {
+                material.color.set_a(0.3 + 0.4 * pulse);
+            }
+        }
+    }
+}
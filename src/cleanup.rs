@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+
+use crate::AppState;
+
+pub struct CleanupPlugin;
+
+impl Plugin for CleanupPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        for state in [AppState::Loading, AppState::InGame].iter() {
+            app.add_system_set(
+                SystemSet::on_exit(*state).with_system(despawn_tagged(*state).system()),
+            );
+        }
+    }
+}
+
+/// Despawned together with its children when the app leaves the state. Rapier keeps the bodies
+/// and colliders of despawned entities, physics_sync takes them out of its sets. So far only the
+/// loading screen is ever left, the game stays in `AppState::InGame` until it quits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cleanup(pub AppState);
+
+fn despawn_tagged(state: AppState) -> impl FnMut(Commands, Query<(Entity, &Cleanup)>) {
+    move |mut commands: Commands, tagged: Query<(Entity, &Cleanup)>| {
+        for (entity, cleanup) in tagged.iter() {
+            if cleanup.0 == state {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_rapier2d::physics::RigidBodyHandleComponent;
+    use bevy_rapier2d::rapier::dynamics::{JointSet, RigidBodyBuilder, RigidBodySet};
+    use bevy_rapier2d::rapier::geometry::ColliderSet;
+
+    use crate::match_state::MatchPhase;
+    use crate::physics_sync::PhysicsSyncPlugin;
+
+    fn cycling_app() -> App {
+        let mut app = App::build();
+        app.add_state(AppState::Loading)
+            .init_resource::<MatchPhase>()
+            .insert_resource(RigidBodySet::new())
+            .insert_resource(ColliderSet::new())
+            .insert_resource(JointSet::new())
+            .add_plugin(CleanupPlugin)
+            .add_plugin(PhysicsSyncPlugin);
+        app.app
+    }
+
+    /// What a state spawns: an entity with a child and one with a rigid body.
+    fn spawn_scoped(world: &mut World, state: AppState) {
+        let handle = world
+            .get_resource_mut::<RigidBodySet>()
+            .unwrap()
+            .insert(RigidBodyBuilder::new_dynamic().build());
+        world
+            .spawn()
+            .insert_bundle((Cleanup(state), RigidBodyHandleComponent::from(handle)));
+        let child = world.spawn().id();
+        world.spawn().insert(Cleanup(state)).push_children(&[child]);
+    }
+
+    fn counts(world: &World) -> (u32, usize) {
+        let bodies = world.get_resource::<RigidBodySet>().unwrap().len();
+        (world.entities().len(), bodies)
+    }
+
+    #[test]
+    fn cycling_states_returns_to_the_baseline() {
+        let mut app = cycling_app();
+        app.world.spawn();
+        app.update();
+        let baseline = counts(&app.world);
+
+        let cycle = [
+            (AppState::Loading, AppState::InGame),
+            (AppState::InGame, AppState::Loading),
+            (AppState::Loading, AppState::InGame),
+            (AppState::InGame, AppState::Loading),
+        ];
+        for (state, next) in cycle.iter() {
+            spawn_scoped(&mut app.world, *state);
+            app.update();
+            assert_eq!(counts(&app.world), (baseline.0 + 3, baseline.1 + 1));
+
+            app.world
+                .get_resource_mut::<State<AppState>>()
+                .unwrap()
+                .set(*next)
+                .unwrap();
+            app.update();
+            assert_eq!(counts(&app.world), baseline, "leaving {:?}", state);
+        }
+    }
+
+    #[test]
+    fn other_states_keep_their_entities() {
+        let mut app = cycling_app();
+        app.update();
+        let loading = app.world.spawn().insert(Cleanup(AppState::Loading)).id();
+        let in_game = app.world.spawn().insert(Cleanup(AppState::InGame)).id();
+
+        app.world
+            .get_resource_mut::<State<AppState>>()
+            .unwrap()
+            .set(AppState::InGame)
+            .unwrap();
+        app.update();
+
+        assert!(app.world.get_entity(loading).is_none());
+        assert!(app.world.get_entity(in_game).is_some());
+    }
+}
@@ -0,0 +1,214 @@
+use pingis_pong::{
+    Preset, ShotClockRule, TournamentEntry, MAX_BALLS, MAX_BRACKET_PLAYERS, MAX_HILL_PLAYERS,
+    MAX_PLAYERS, MIN_PLAYERS,
+};
+
+/// Options given on the command line.
+#[derive(Debug)]
+pub struct CliArgs {
+    /// `--time-limit <seconds>` plays a timed match.
+    pub time_limit: Option<f32>,
+    /// `--first-to <goals>` ends the match when a player reaches that score.
+    pub score_limit: Option<u32>,
+    /// `--kid` selects the kid friendly preset.
+    pub preset: Preset,
+    /// `--doubles` plays two against two.
+    pub doubles: bool,
+    /// `--wall <2-4>` stacks that many mini paddles per side.
+    pub paddles_per_side: u32,
+    /// `--hazard` adds the moving center obstacle.
+    pub hazard: bool,
+    /// `--invisible` fades the ball out between contacts.
+    pub invisible_ball: bool,
+    /// `--wrap` lets the ball leave through the top and come back in at the bottom.
+    pub wrap_around: bool,
+    /// `--bounce-limit` gives the point away on a third wall bounce in your half.
+    pub bounce_limit: bool,
+    /// `--adaptive-ai` makes the computer player ease off when ahead and try harder when behind.
+    pub adaptive_ai: bool,
+    /// `--mercy` ends the match when a player leads by 7.
+    pub mercy_rule: bool,
+    /// `--catch` lets players catch the ball and throw it.
+    pub catch_and_throw: bool,
+    /// `--gravity-zones` pulls the ball down in the left half and up in the right.
+    pub gravity_zones: bool,
+    /// `--late-join` lets a human take a computer paddle over in the final two points.
+    pub late_join: bool,
+    /// `--comeback-assist` widens the leader's goal while the other player trails by 5.
+    pub comeback_assist: bool,
+    /// `--gates` adds a pair of teleport gates that move every 20 seconds.
+    pub teleport_gates: bool,
+    /// `--balls <1-3>` puts that many balls in play at once.
+    pub balls: u32,
+    /// `--shot-clock <point|reset|off>` picks the penalty for holding the ball in your half.
+    pub shot_clock: ShotClockRule,
+    /// `--seed <u64>` replays the random draws of an earlier match.
+    pub seed: Option<u64>,
+    /// `--import-config <file>` plays with a setup exported from the menu or `--export-config`.
+    pub import_config: Option<String>,
+    /// `--export-config <file>` writes the setup picked on the command line to a file.
+    pub export_config: Option<String>,
+    /// `--tournament <name,name,...>` plays a round-robin, `--resume-tournament` picks the saved
+    /// one up again. With `--elimination` it is a single elimination bracket instead.
+    pub tournament: Option<TournamentEntry>,
+    /// `--king-of-the-hill <name,name,...>` keeps the winner on and queues the rest.
+    pub hill: Option<Vec<String>>,
+    /// `--keep-leaderboard` saves the king of the hill leaderboard when the game is quit.
+    pub keep_leaderboard: bool,
+    /// `--daily` plays today's challenge against the computer.
+    pub daily: bool,
+}
+
+impl Default for CliArgs {
+    fn default() -> Self {
+        CliArgs {
+            time_limit: None,
+            score_limit: None,
+            preset: Preset::default(),
+            doubles: false,
+            paddles_per_side: 1,
+            hazard: false,
+            invisible_ball: false,
+            wrap_around: false,
+            bounce_limit: false,
+            adaptive_ai: false,
+            mercy_rule: false,
+            catch_and_throw: false,
+            gravity_zones: false,
+            late_join: false,
+            comeback_assist: false,
+            teleport_gates: false,
+            balls: 1,
+            shot_clock: ShotClockRule::default(),
+            seed: None,
+            import_config: None,
+            export_config: None,
+            tournament: None,
+            hill: None,
+            keep_leaderboard: false,
+            daily: false,
+        }
+    }
+}
+
+impl CliArgs {
+    pub fn parse(args: impl Iterator<Item = String>) -> Self {
+        let mut cli = CliArgs::default();
+        let mut args = args.skip(1);
+        let mut elimination = false;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--time-limit" => match args.next().map(|value| value.parse::<f32>()) {
+                    Some(Ok(seconds)) if seconds > 0. => cli.time_limit = Some(seconds),
+                    _ => eprintln!("--time-limit expects a positive number of seconds"),
+                },
+                "--first-to" => match args.next().map(|value| value.parse::<u32>()) {
+                    Some(Ok(goals)) if goals > 0 => cli.score_limit = Some(goals),
+                    _ => eprintln!("--first-to expects a positive number of goals"),
+                },
+                "--seed" => match args.next().map(|value| value.parse::<u64>()) {
+                    Some(Ok(seed)) => cli.seed = Some(seed),
+                    _ => eprintln!("--seed expects an unsigned integer"),
+                },
+                "--import-config" => match args.next() {
+                    Some(path) => cli.import_config = Some(path),
+                    None => eprintln!("--import-config expects a file"),
+                },
+                "--export-config" => match args.next() {
+                    Some(path) => cli.export_config = Some(path),
+                    None => eprintln!("--export-config expects a file"),
+                },
+                "--shot-clock" => match args.next().as_deref() {
+                    Some("point") => cli.shot_clock = ShotClockRule::AwardPoint,
+                    Some("reset") => cli.shot_clock = ShotClockRule::ResetBall,
+                    Some("off") => cli.shot_clock = ShotClockRule::Off,
+                    _ => eprintln!("--shot-clock expects point, reset or off"),
+                },
+                "--tournament" => {
+                    let players: Vec<String> = args
+                        .next()
+                        .unwrap_or_default()
+                        .split(',')
+                        .map(|name| name.trim().to_string())
+                        .filter(|name| !name.is_empty())
+                        .collect();
+                    // The count is checked once it is known whether it is a bracket
+                    cli.tournament = Some(TournamentEntry::New(players));
+                }
+                "--elimination" => elimination = true,
+                "--resume-tournament" => cli.tournament = Some(TournamentEntry::Resume),
+                "--king-of-the-hill" => {
+                    let players: Vec<String> = args
+                        .next()
+                        .unwrap_or_default()
+                        .split(',')
+                        .map(|name| name.trim().to_string())
+                        .filter(|name| !name.is_empty())
+                        .collect();
+                    if (MIN_PLAYERS..=MAX_HILL_PLAYERS).contains(&players.len()) {
+                        cli.hill = Some(players);
+                    } else {
+                        eprintln!(
+                            "--king-of-the-hill expects {} to {} comma separated names",
+                            MIN_PLAYERS, MAX_HILL_PLAYERS
+                        );
+                    }
+                }
+                "--keep-leaderboard" => cli.keep_leaderboard = true,
+                "--daily" => cli.daily = true,
+                "--kid" => cli.preset = Preset::Kid,
+                "--doubles" => cli.doubles = true,
+                "--wall" => match args.next().map(|value| value.parse::<u32>()) {
+                    Some(Ok(count)) if (2..=4).contains(&count) => cli.paddles_per_side = count,
+                    _ => eprintln!("--wall expects 2 to 4 paddles"),
+                },
+                "--balls" => match args.next().map(|value| value.parse::<u32>()) {
+                    Some(Ok(count)) if (1..=MAX_BALLS).contains(&count) => cli.balls = count,
+                    _ => eprintln!("--balls expects 1 to {} balls", MAX_BALLS),
+                },
+                "--hazard" => cli.hazard = true,
+                "--invisible" => cli.invisible_ball = true,
+                "--wrap" => cli.wrap_around = true,
+                "--bounce-limit" => cli.bounce_limit = true,
+                "--adaptive-ai" => cli.adaptive_ai = true,
+                "--mercy" => cli.mercy_rule = true,
+                "--catch" => cli.catch_and_throw = true,
+                "--gravity-zones" => cli.gravity_zones = true,
+                "--late-join" => cli.late_join = true,
+                "--comeback-assist" => cli.comeback_assist = true,
+                "--gates" => cli.teleport_gates = true,
+                _ => eprintln!("Unknown argument {}", arg),
+            }
+        }
+
+        cli.tournament = match cli.tournament.take() {
+            Some(TournamentEntry::New(players)) => {
+                let max = if elimination {
+                    MAX_BRACKET_PLAYERS
+                } else {
+                    MAX_PLAYERS
+                };
+                if !(MIN_PLAYERS..=max).contains(&players.len()) {
+                    eprintln!(
+                        "--tournament expects {} to {} comma separated names",
+                        MIN_PLAYERS, max
+                    );
+                    None
+                } else if elimination {
+                    Some(TournamentEntry::Elimination(players))
+                } else {
+                    Some(TournamentEntry::New(players))
+                }
+            }
+            entry => {
+                if elimination {
+                    eprintln!("--elimination only goes with --tournament");
+                }
+                entry
+            }
+        };
+
+        cli
+    }
+}
@@ -0,0 +1,235 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use bevy::prelude::*;
+
+use crate::ball_visuals::{BallGlow, BallShadow};
+use crate::callout::Callout;
+use crate::crt::CrtOverlay;
+use crate::match_state::MatchPhase;
+use crate::persistence::SaveData;
+use crate::raster::{rasterize, Shape};
+use crate::replay::{ReplayBuffer, CLIP_SECONDS};
+use crate::screenshot::{material_picture, screenshot_path, sprite_shapes};
+use crate::{AppState, Ball, GameLabel, Paddle, UiFont, ARENA_HEIGHT, ARENA_WIDTH};
+
+pub struct ClipPlugin;
+
+impl Plugin for ClipPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ClipExport>()
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame).with_system(
+                    export_final_point
+                        .system()
+                        .after(GameLabel::GoalApply)
+                        .before(GameLabel::GoalPresent),
+                ),
+            )
+            // Keeps running after leaving the game, the export finishes in the background
+            .add_system(render_progress.system());
+    }
+}
+
+/// Frames per second written for the clip.
+const CLIP_FPS: f32 = 20.;
+/// Frames are rendered at this fraction of the arena size.
+const CLIP_SCALE: f32 = 0.5;
+
+/// Export running in the background, the thread counts the frames it wrote.
+struct ClipJob {
+    total: usize,
+    saved: Arc<AtomicUsize>,
+    result: Arc<Mutex<Option<Result<PathBuf, String>>>>,
+}
+
+#[derive(Default)]
+struct ClipExport {
+    job: Option<ClipJob>,
+}
+
+struct ClipProgress;
+
+/// On game over the buffered frames of the final point are rendered off screen in software, from
+/// the same sprite shapes, and written as a numbered PNG sequence next to the screenshots.
+/// Walls and other still sprites are taken as they are now, balls and paddles from the buffer.
+fn export_final_point(
+    phase: Res<MatchPhase>,
+    save: Res<SaveData>,
+    buffer: Res<ReplayBuffer>,
+    clear_color: Res<ClearColor>,
+    materials: Res<Assets<ColorMaterial>>,
+    textures: Res<Assets<Texture>>,
+    mut export: ResMut<ClipExport>,
+    still: Query<
+        (
+            &GlobalTransform,
+            &Sprite,
+            &Handle<ColorMaterial>,
+            &Visible,
+            Option<&Ball>,
+            Option<&BallGlow>,
+            Option<&BallShadow>,
+        ),
+        (Without<Paddle>, Without<CrtOverlay>),
+    >,
+    moving: Query<(&Sprite, &Handle<ColorMaterial>, Option<&Ball>)>,
+) {
+    if !phase.is_changed() || !phase.is_finished() || !save.settings.final_point_clip {
+        return;
+    }
+    if export.job.is_some() {
+        warn!("Still saving the last clip, skipping this one");
+        return;
+    }
+
+    let background = sprite_shapes(
+        &materials,
+        &textures,
+        still.iter().filter(|(_, _, _, _, ball, glow, shadow)| {
+            ball.is_none() && glow.is_none() && shadow.is_none()
+        }),
+    );
+
+    let mut next_time = None;
+    let mut frames = Vec::new();
+    for (time, transforms) in buffer.recent_frames(CLIP_SECONDS) {
+        if next_time.map_or(false, |next_time| time < next_time) {
+            continue;
+        }
+        next_time = Some(time + 1. / CLIP_FPS);
+
+        let mut shapes = background.clone();
+        shapes.extend(transforms.iter().filter_map(|(entity, transform)| {
+            let (sprite, material, ball) = moving.get(*entity).ok()?;
+            let material = materials.get(material)?;
+            let direction = transform.rotation * Vec3::X;
+            Some(Shape {
+                center: transform.translation.truncate(),
+                size: sprite.size * transform.scale.truncate(),
+                angle: direction.y.atan2(direction.x),
+                color: material.color,
+                round: ball.is_some(),
+                z: transform.translation.z,
+                picture: material_picture(material, &textures),
+            })
+        }));
+        frames.push(shapes);
+    }
+    if frames.is_empty() {
+        return;
+    }
+
+    let job = ClipJob {
+        total: frames.len(),
+        saved: Arc::new(AtomicUsize::new(0)),
+        result: Arc::new(Mutex::new(None)),
+    };
+    let saved = job.saved.clone();
+    let result = job.result.clone();
+    let background_color = clear_color.0;
+    let dir = screenshot_path("final_point").with_extension("");
+
+    thread::spawn(move || {
+        let written = fs::create_dir_all(&dir)
+            .map_err(|err| err.to_string())
+            .and_then(|_| {
+                for (index, shapes) in frames.iter().enumerate() {
+                    let image = rasterize(
+                        shapes,
+                        background_color,
+                        Vec2::new(ARENA_WIDTH, ARENA_HEIGHT),
+                        CLIP_SCALE,
+                    );
+                    let path = dir.join(format!("frame_{:03}.png", index));
+                    image.save(&path).map_err(|err| err.to_string())?;
+                    saved.fetch_add(1, Ordering::Relaxed);
+                }
+                Ok(dir.clone())
+            });
+
+        if let Ok(mut result) = result.lock() {
+            *result = Some(written);
+        }
+    });
+
+    export.job = Some(job);
+}
+
+/// Shows how far the export got, and announces the outcome. A failed export only costs the clip.
+fn render_progress(
+    mut commands: Commands,
+    font: Option<Res<UiFont>>,
+    mut export: ResMut<ClipExport>,
+    mut callouts: EventWriter<Callout>,
+    mut texts: Query<(Entity, &mut Text), With<ClipProgress>>,
+) {
+    let job = match export.job.as_ref() {
+        Some(job) => job,
+        None => return,
+    };
+
+    let finished = job.result.lock().ok().and_then(|mut result| result.take());
+    if let Some(result) = finished {
+        match result {
+            Ok(dir) => {
+                info!("Saved clip of the final point to {}", dir.display());
+                callouts.send(Callout("Clip saved".to_string()));
+            }
+            Err(err) => {
+                warn!("Could not save clip: {}", err);
+                callouts.send(Callout("Could not save clip".to_string()));
+            }
+        }
+        for (entity, _) in texts.iter_mut() {
+            commands.entity(entity).despawn();
+        }
+        export.job = None;
+        return;
+    }
+
+    let value = format!(
+        "Saving clip {}/{}",
+        job.saved.load(Ordering::Relaxed),
+        job.total
+    );
+    match texts.iter_mut().next() {
+        Some((_, mut text)) => {
+            if text.sections[0].value != value {
+                text.sections[0].value = value;
+            }
+        }
+        None => {
+            let font = match font {
+                Some(font) => font.0.clone(),
+                None => return,
+            };
+            commands
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        value,
+                        TextStyle {
+                            font,
+                            font_size: 20.0,
+                            color: Color::rgb(0.8, 0.8, 0.8),
+                        },
+                        Default::default(),
+                    ),
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: Rect {
+                            bottom: Val::Px(20.),
+                            left: Val::Px(20.),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(ClipProgress);
+        }
+    }
+}
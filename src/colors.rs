@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+
+use crate::accessibility::Accessibility;
+use crate::hill::Queue;
+use crate::persistence::SaveData;
+use crate::profiles;
+use crate::theme::Theme;
+use crate::{AppState, Paddle, Player};
+
+pub struct PlayerColorsPlugin;
+
+impl Plugin for PlayerColorsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<BaseTheme>()
+            .add_system(
+                apply_player_colors
+                    .system()
+                    .after("accessibility")
+                    .before("pause_menu"),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame).with_system(tint_players.system()),
+            );
+    }
+}
+
+/// The theme as the game was started with, put back when a player clears their pick or high
+/// contrast is switched off.
+pub struct BaseTheme(pub Theme);
+
+impl FromWorld for BaseTheme {
+    fn from_world(world: &mut World) -> Self {
+        BaseTheme(world.get_resource::<Theme>().cloned().unwrap_or_default())
+    }
+}
+
+/// Writes the picked colors into the theme, so everything tinted per player follows them. High
+/// contrast replaces the whole theme and overrides the picks.
+fn apply_player_colors(
+    save: Res<SaveData>,
+    accessibility: Res<Accessibility>,
+    base: Res<BaseTheme>,
+    queue: Res<Queue>,
+    mut theme: ResMut<Theme>,
+) {
+    if !save.is_changed()
+        && !accessibility.is_changed()
+        && !base.is_changed()
+        && !queue.is_changed()
+    {
+        return;
+    }
+
+    let wanted = if accessibility.high_contrast {
+        Theme::high_contrast()
+    } else {
+        // In king of the hill the colors go with the players rather than the paddles
+        let color = |player: Player| {
+            queue
+                .color(player)
+                .or_else(|| profiles::player_color(&save, player))
+        };
+        Theme {
+            left: color(Player::Left).unwrap_or(base.0.left),
+            right: color(Player::Right).unwrap_or(base.0.right),
+            ..base.0.clone()
+        }
+    };
+    if *theme != wanted {
+        *theme = wanted;
+    }
+}
+
+/// Paddles and scores are spawned in the player colors, recolor them when a pick changes.
+fn tint_players(
+    theme: Res<Theme>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    paddles: Query<(&Player, &Handle<ColorMaterial>), With<Paddle>>,
+    mut scores: Query<(&Player, &mut Text)>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+
+    for (player, material) in paddles.iter() {
+        if let Some(material) = materials.get_mut(material) {
+            // Keep the alpha, the intro fades the paddles in
+            let mut color = theme.player_color(*player);
+            color.set_a(material.color.a());
+            material.color = color;
+        }
+    }
+
+    for (player, mut text) in scores.iter_mut() {
+        for section in text.sections.iter_mut() {
+            section.style.color = theme.player_color(*player);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use bevy::prelude::*;
+use bevy_rapier2d::physics::{RapierConfiguration, RigidBodyHandleComponent};
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+
+use crate::match_state::{MatchClock, MatchPhase, MatchRules};
+use crate::physics::teleport_body;
+use crate::rules::{comeback_assist, RulesState};
+use crate::theme::Theme;
+use crate::tuning::{Preset, Tuning};
+use crate::{AppState, GameLabel, Paused, Player, Score, Wall, ARENA_HEIGHT};
+
+/// Widens the leader's goal while the other player trails badly.
+pub struct ComebackPlugin;
+
+impl Plugin for ComebackPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ComebackAssist>().add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(
+                    follow_score
+                        .system()
+                        .label("comeback_assist")
+                        .after(GameLabel::GoalApply),
+                )
+                .with_system(
+                    move_flanks
+                        .system()
+                        .after("comeback_assist")
+                        .after("tint_walls"),
+                ),
+        );
+    }
+}
+
+/// Part of each flank beside the leader's goal that pulls back into the corner.
+const FLANK_SHRINK: f32 = 0.2;
+/// Seconds the flanks take to pull back or return.
+const ASSIST_SECONDS: f32 = 1.;
+/// How far the flanks take on the trailing player's color.
+const ASSIST_TINT: f32 = 0.6;
+
+#[derive(Debug, Default)]
+struct ComebackAssist {
+    /// Leader whose goal is widened.
+    leader: Option<Player>,
+    /// Side whose flanks are moved, kept while they return.
+    side: Option<Player>,
+    /// How far the flanks have moved, `0.0..=1.0`.
+    amount: f32,
+}
+
+/// Only with the kid preset's narrow goals, there are no flanks otherwise and the standard
+/// preset is the competitive one.
+fn follow_score(
+    rules: Res<MatchRules>,
+    preset: Res<Preset>,
+    tuning: Res<Tuning>,
+    score: Res<Score>,
+    phase: Res<MatchPhase>,
+    clock: Res<MatchClock>,
+    mut assist: ResMut<ComebackAssist>,
+) {
+    if !score.is_changed() && !rules.is_changed() {
+        return;
+    }
+
+    let enabled = rules.comeback_assist && *preset == Preset::Kid && tuning.goal_mouth.is_some();
+    let leader = if enabled {
+        comeback_assist(&RulesState::new(&score, *phase, &clock), assist.leader)
+    } else {
+        None
+    };
+    if leader != assist.leader {
+        assist.leader = leader;
+    }
+}
+
+/// Slides the flanks beside the leader's goal toward the corners over `ASSIST_SECONDS`, the part
+/// past the arena edge ends up behind the top and bottom walls. Their shape never changes, so they end up exactly
+/// where they were spawned once the assist is over.
+fn move_flanks(
+    time: Res<Time>,
+    paused: Res<Paused>,
+    tuning: Res<Tuning>,
+    theme: Res<Theme>,
+    rapier_config: Res<RapierConfiguration>,
+    mut assist: ResMut<ComebackAssist>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    mut flanks: Query<(
+        &Wall,
+        &RigidBodyHandleComponent,
+        &mut Transform,
+        &Handle<ColorMaterial>,
+    )>,
+) {
+    let goal_mouth = match tuning.goal_mouth {
+        Some(goal_mouth) => goal_mouth,
+        None => return,
+    };
+
+    let target = match assist.leader {
+        // The other side's flanks return first
+        Some(leader) if assist.side.map_or(true, |side| side == leader) => {
+            assist.side = Some(leader);
+            1.
+        }
+        _ => 0.,
+    };
+    let step = if paused.0 {
+        0.
+    } else {
+        time.delta_seconds() / ASSIST_SECONDS
+    };
+    let amount = if target > assist.amount {
+        (assist.amount + step).min(target)
+    } else {
+        (assist.amount - step).max(target)
+    };
+    if amount == assist.amount && !theme.is_changed() {
+        return;
+    }
+    assist.amount = amount;
+
+    let side = match assist.side {
+        Some(side) => side,
+        None => return,
+    };
+    if amount == 0. {
+        assist.side = None;
+    }
+
+    let flank_height = (ARENA_HEIGHT - goal_mouth) / 2.;
+    let tint = mix(
+        theme.wall,
+        theme.player_color(side.opponent()),
+        amount * ASSIST_TINT,
+    );
+
+    for (wall, rigid_body_component, mut transform, material) in flanks.iter_mut() {
+        if *wall != Wall::Flank(side) {
+            continue;
+        }
+
+        if let Some(rb) = rigid_bodies.get_mut(rigid_body_component.handle()) {
+            let mut position = *rb.position();
+            let bottom = position.translation.y * rapier_config.scale < ARENA_HEIGHT / 2.;
+            position.translation.y = flank_y(bottom, flank_height, amount) / rapier_config.scale;
+            teleport_body(rb, &mut transform, position, rapier_config.scale);
+        }
+        if let Some(material) = materials.get_mut(material) {
+            material.color = tint;
+        }
+    }
+}
+
+/// Height of the center of a flank `flank_height` tall with the assist `amount` of the way in,
+/// where it was spawned at 0.
+fn flank_y(bottom: bool, flank_height: f32, amount: f32) -> f32 {
+    let offset = amount * FLANK_SHRINK * flank_height;
+    if bottom {
+        flank_height / 2. - offset
+    } else {
+        ARENA_HEIGHT - flank_height / 2. + offset
+    }
+}
+
+fn mix(a: Color, b: Color, t: f32) -> Color {
+    Color::rgba(
+        a.r() + (b.r() - a.r()) * t,
+        a.g() + (b.g() - a.g()) * t,
+        a.b() + (b.b() - a.b()) * t,
+        a.a() + (b.a() - a.a()) * t,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tournament::Tournament;
+
+    fn assist_world(preset: Preset, rules: MatchRules, left: u32, right: u32) -> World {
+        let mut world = World::default();
+        world.insert_resource(rules);
+        world.insert_resource(preset);
+        world.insert_resource(Tuning::for_preset(Preset::Kid));
+        world.insert_resource(Score {
+            left,
+            right,
+            streak: None,
+        });
+        world.init_resource::<MatchPhase>();
+        world.init_resource::<MatchClock>();
+        world.init_resource::<ComebackAssist>();
+        world
+    }
+
+    fn leader(world: &mut World) -> Option<Player> {
+        SystemStage::single(follow_score.system()).run(world);
+        world.get_resource::<ComebackAssist>().unwrap().leader
+    }
+
+    fn assist_rules() -> MatchRules {
+        MatchRules {
+            comeback_assist: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn assist_only_in_the_kid_preset() {
+        assert_eq!(
+            leader(&mut assist_world(Preset::Kid, assist_rules(), 6, 1)),
+            Some(Player::Left)
+        );
+        assert_eq!(
+            leader(&mut assist_world(Preset::Standard, assist_rules(), 6, 1)),
+            None
+        );
+        assert_eq!(
+            leader(&mut assist_world(Preset::Kid, MatchRules::default(), 6, 1)),
+            None
+        );
+    }
+
+    #[test]
+    fn never_in_tournaments() {
+        let tournament = Tournament::round_robin(vec!["A".into(), "B".into(), "C".into()]);
+        let rules = assist_rules().for_tournament(&tournament);
+        assert_eq!(leader(&mut assist_world(Preset::Kid, rules, 6, 1)), None);
+    }
+
+    #[test]
+    fn assist_follows_the_score() {
+        let mut world = assist_world(Preset::Kid, assist_rules(), 1, 5);
+        assert_eq!(leader(&mut world), Some(Player::Right));
+
+        world.get_resource_mut::<Score>().unwrap().left = 3;
+        assert_eq!(leader(&mut world), Some(Player::Right));
+
+        world.get_resource_mut::<Score>().unwrap().left = 4;
+        assert_eq!(leader(&mut world), None);
+    }
+
+    #[test]
+    fn flanks_return_exactly_to_their_spawn() {
+        let goal_mouth = Tuning::for_preset(Preset::Kid).goal_mouth.unwrap();
+        let flank_height = (ARENA_HEIGHT - goal_mouth) / 2.;
+
+        // Where spawn_walls puts them
+        assert_eq!(flank_y(true, flank_height, 0.), flank_height / 2.);
+        assert_eq!(
+            flank_y(false, flank_height, 0.),
+            ARENA_HEIGHT - flank_height / 2.
+        );
+
+        // Fully in, a fifth of each flank is behind the top or bottom wall
+        let shift = FLANK_SHRINK * flank_height;
+        assert!((flank_y(true, flank_height, 1.) - (flank_height / 2. - shift)).abs() < 1e-3);
+        assert!(
+            (flank_y(false, flank_height, 1.) - (ARENA_HEIGHT - flank_height / 2. + shift)).abs()
+                < 1e-3
+        );
+
+        // Going in and out again in steps lands on the spawn
+        let mut amount = 0.;
+        for _ in 0..7 {
+            amount = (amount + 0.3f32).min(1.);
+        }
+        for _ in 0..7 {
+            amount = (amount - 0.3f32).max(0.);
+        }
+        assert_eq!(flank_y(true, flank_height, amount), flank_height / 2.);
+    }
+}
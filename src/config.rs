@@ -0,0 +1,627 @@
+use std::fmt::Debug;
+use std::fs;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::KeyBindings;
+use crate::callout::Callout;
+use crate::colors::BaseTheme;
+use crate::hill::Queue;
+use crate::match_state::{MatchPhase, MatchRules};
+use crate::profiles::BaseBindings;
+use crate::theme::Theme;
+use crate::tournament::Tournament;
+use crate::tuning::{Preset, Tuning};
+use crate::{AppState, PongSettings, ARENA_HEIGHT, MAX_BALLS};
+
+/// Where the menu exports to and imports from.
+pub const CONFIG_PATH: &str = "pingis_pong_config.ron";
+/// Bumped when a field changes meaning, files from a newer version are read as far as possible.
+const CONFIG_VERSION: u32 = 1;
+
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<ConfigAction>()
+            .init_resource::<PendingConfig>()
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(handle_config_actions.system().label("config_actions"))
+                    .with_system(apply_pending_config.system().after("config_actions")),
+            );
+    }
+}
+
+/// Sent by the menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigAction {
+    Export,
+    Import,
+}
+
+/// Everything that makes up a setup, shared as one file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    pub version: u32,
+    pub bindings: KeyBindings,
+    pub tuning: Tuning,
+    pub theme: Theme,
+    pub preset: Preset,
+    pub rules: MatchRules,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig::from_settings(&PongSettings::default())
+    }
+}
+
+impl GameConfig {
+    pub fn from_settings(settings: &PongSettings) -> Self {
+        GameConfig {
+            version: CONFIG_VERSION,
+            bindings: settings.bindings.clone(),
+            tuning: settings
+                .tuning
+                .clone()
+                .unwrap_or_else(|| Tuning::for_preset(settings.preset)),
+            theme: settings.theme.clone().unwrap_or_default(),
+            preset: settings.preset,
+            rules: settings.rules.clone(),
+        }
+    }
+
+    /// Replaces the setup in `settings`, everything the file holds wins over the command line.
+    pub fn apply_to(self, settings: &mut PongSettings) {
+        settings.bindings = self.bindings;
+        settings.tuning = Some(self.tuning);
+        settings.theme = Some(self.theme);
+        settings.preset = self.preset;
+        settings.rules = self.rules;
+    }
+
+    pub fn export(&self, path: &str) -> Result<(), String> {
+        let content = ron::ser::to_string_pretty(self, Default::default())
+            .map_err(|err| format!("Failed to serialize config: {}", err))?;
+        fs::write(path, content).map_err(|err| format!("Failed to write {}: {}", path, err))
+    }
+
+    /// Reads a config, unknown fields are skipped and values out of range are clamped. Returns
+    /// what was changed on the way along with it, an unreadable file is an error.
+    pub fn import(path: &str) -> Result<(GameConfig, Vec<String>), String> {
+        let content =
+            fs::read_to_string(path).map_err(|err| format!("Failed to read {}: {}", path, err))?;
+        GameConfig::parse(path, &content)
+    }
+
+    /// `import` without the file, `path` only names it in the messages.
+    fn parse(path: &str, content: &str) -> Result<(GameConfig, Vec<String>), String> {
+        let mut config: GameConfig =
+            ron::from_str(content).map_err(|err| format!("Failed to parse {}: {}", path, err))?;
+
+        let mut warnings = Vec::new();
+        if config.version > CONFIG_VERSION {
+            warnings.push(format!(
+                "{} is from a newer version, settings it added are ignored",
+                path
+            ));
+        }
+        config.version = CONFIG_VERSION;
+        warnings.extend(
+            unknown_fields(content)
+                .into_iter()
+                .map(|field| format!("Ignoring unknown field {} in {}", field, path)),
+        );
+        config.clamp(&mut warnings);
+        Ok((config, warnings))
+    }
+
+    fn clamp(&mut self, warnings: &mut Vec<String>) {
+        let tuning = &mut self.tuning;
+        clamp_field(
+            warnings,
+            "tuning.serve_speed",
+            &mut tuning.serve_speed,
+            1.,
+            200.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.max_ball_speed",
+            &mut tuning.max_ball_speed,
+            tuning.serve_speed,
+            200.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.streak_serve_bonus",
+            &mut tuning.streak_serve_bonus,
+            1.,
+            2.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.ball_size",
+            &mut tuning.ball_size,
+            10.,
+            120.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.paddle_width",
+            &mut tuning.paddle_width,
+            5.,
+            100.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.paddle_height",
+            &mut tuning.paddle_height,
+            20.,
+            ARENA_HEIGHT / 2.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.receiver_boost",
+            &mut tuning.receiver_boost,
+            1.,
+            3.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.receiver_boost_seconds",
+            &mut tuning.receiver_boost_seconds,
+            0.,
+            10.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.max_paddle_speed_factor",
+            &mut tuning.max_paddle_speed_factor,
+            1.,
+            3.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.paddle_carry",
+            &mut tuning.paddle_carry,
+            0.,
+            1.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.pushed_shot_threshold",
+            &mut tuning.pushed_shot_threshold,
+            0.,
+            100.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.block_speed",
+            &mut tuning.block_speed,
+            0.,
+            50.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.block_blend",
+            &mut tuning.block_blend,
+            0.,
+            50.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.block_rebound",
+            &mut tuning.block_rebound,
+            0.5,
+            2.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.wall_jump_window",
+            &mut tuning.wall_jump_window,
+            0.,
+            1.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.wall_jump_boost",
+            &mut tuning.wall_jump_boost,
+            1.,
+            3.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.wall_jump_seconds",
+            &mut tuning.wall_jump_seconds,
+            0.,
+            2.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.wall_edge_restitution",
+            &mut tuning.wall_edge_restitution,
+            0.,
+            1.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.wall_edge_fraction",
+            &mut tuning.wall_edge_fraction,
+            0.,
+            0.45,
+        );
+        clamp_field(
+            warnings,
+            "tuning.zone_gravity_left",
+            &mut tuning.zone_gravity_left,
+            0.,
+            50.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.zone_gravity_right",
+            &mut tuning.zone_gravity_right,
+            0.,
+            50.,
+        );
+        if let Some(mouth) = &mut tuning.goal_mouth {
+            clamp_field(warnings, "tuning.goal_mouth", mouth, 20., ARENA_HEIGHT);
+        }
+        clamp_field(
+            warnings,
+            "tuning.ai_skill.reaction_seconds",
+            &mut tuning.ai_skill.reaction_seconds,
+            0.,
+            2.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.ai_skill.miss_chance",
+            &mut tuning.ai_skill.miss_chance,
+            0.,
+            1.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.ai_adaptive.reaction_per_goal",
+            &mut tuning.ai_adaptive.reaction_per_goal,
+            0.,
+            1.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.ai_adaptive.miss_chance_per_goal",
+            &mut tuning.ai_adaptive.miss_chance_per_goal,
+            0.,
+            1.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.ai_adaptive.max_goals",
+            &mut tuning.ai_adaptive.max_goals,
+            0,
+            20,
+        );
+        clamp_field(
+            warnings,
+            "tuning.idle_takeover_seconds",
+            &mut tuning.idle_takeover_seconds,
+            1.,
+            600.,
+        );
+        clamp_field(
+            warnings,
+            "tuning.long_rally_hits",
+            &mut tuning.long_rally_hits,
+            0,
+            100,
+        );
+        clamp_field(
+            warnings,
+            "tuning.long_rally_decay",
+            &mut tuning.long_rally_decay,
+            0.,
+            0.2,
+        );
+
+        let rules = &mut self.rules;
+        if let Some(seconds) = &mut rules.time_limit {
+            clamp_field(warnings, "rules.time_limit", seconds, 1., 3600.);
+        }
+        if let Some(goals) = &mut rules.score_limit {
+            clamp_field(warnings, "rules.score_limit", goals, 1, 99);
+        }
+        clamp_field(
+            warnings,
+            "rules.paddles_per_side",
+            &mut rules.paddles_per_side,
+            1,
+            4,
+        );
+        clamp_field(warnings, "rules.balls", &mut rules.balls, 1, MAX_BALLS);
+    }
+}
+
+fn clamp_field<T: PartialOrd + Copy + Debug>(
+    warnings: &mut Vec<String>,
+    name: &str,
+    value: &mut T,
+    min: T,
+    max: T,
+) {
+    let clamped = if *value < min {
+        min
+    } else if *value > max {
+        max
+    } else {
+        return;
+    };
+    warnings.push(format!(
+        "Config {} is {:?}, clamped to {:?} ({:?} to {:?})",
+        name, value, clamped, min, max
+    ));
+    *value = clamped;
+}
+
+/// Serde skips fields it doesn't know without a word, compares the file with a complete config
+/// to point them out.
+fn unknown_fields(content: &str) -> Vec<String> {
+    let known = ron::to_string(&GameConfig::default())
+        .ok()
+        .and_then(|known| ron::from_str::<ron::Value>(&known).ok());
+    let given = ron::from_str::<ron::Value>(content).ok();
+
+    let mut unknown = Vec::new();
+    if let (Some(given), Some(known)) = (given, known) {
+        collect_unknown("", &given, &known, &mut unknown);
+    }
+    unknown
+}
+
+fn collect_unknown(path: &str, given: &ron::Value, known: &ron::Value, unknown: &mut Vec<String>) {
+    if let (ron::Value::Map(given), ron::Value::Map(known)) = (given, known) {
+        for (key, value) in given.iter() {
+            let name = match key {
+                ron::Value::String(name) => name.clone(),
+                other => format!("{:?}", other),
+            };
+            let field = if path.is_empty() {
+                name
+            } else {
+                format!("{}.{}", path, name)
+            };
+
+            match known.get(key) {
+                Some(known) => collect_unknown(&field, value, known, unknown),
+                None => unknown.push(field),
+            }
+        }
+    }
+}
+
+/// Imported config waiting for the current match to end.
+#[derive(Default)]
+struct PendingConfig(Option<GameConfig>);
+
+fn handle_config_actions(
+    bindings: Res<BaseBindings>,
+    tuning: Res<Tuning>,
+    theme: Res<BaseTheme>,
+    preset: Res<Preset>,
+    rules: Res<MatchRules>,
+    mut pending: ResMut<PendingConfig>,
+    mut actions: EventReader<ConfigAction>,
+    mut callouts: EventWriter<Callout>,
+) {
+    for action in actions.iter() {
+        let message = match action {
+            ConfigAction::Export => {
+                let config = GameConfig {
+                    version: CONFIG_VERSION,
+                    bindings: bindings.0.clone(),
+                    tuning: tuning.clone(),
+                    theme: theme.0.clone(),
+                    preset: *preset,
+                    rules: rules.clone(),
+                };
+                match config.export(CONFIG_PATH) {
+                    Ok(()) => format!("Config saved to {}", CONFIG_PATH),
+                    Err(err) => err,
+                }
+            }
+            ConfigAction::Import => match GameConfig::import(CONFIG_PATH) {
+                Ok((config, warnings)) => {
+                    for warning in warnings.iter() {
+                        warn!("{}", warning);
+                    }
+                    pending.0 = Some(config);
+                    if warnings.is_empty() {
+                        "Config loaded, it applies from the next match".to_string()
+                    } else {
+                        format!(
+                            "Config loaded with {} values fixed up, see the log. It applies \
+                             from the next match",
+                            warnings.len()
+                        )
+                    }
+                }
+                Err(err) => err,
+            },
+        };
+        callouts.send(Callout(message));
+    }
+}
+
+/// Applies an imported config once the match is over. The arena is only built at launch, what
+/// shapes it is kept until the game is started again with the config.
+fn apply_pending_config(
+    phase: Res<MatchPhase>,
+    tournament: Res<Tournament>,
+    queue: Res<Queue>,
+    mut pending: ResMut<PendingConfig>,
+    mut bindings: ResMut<BaseBindings>,
+    mut tuning: ResMut<Tuning>,
+    mut theme: ResMut<BaseTheme>,
+    mut rules: ResMut<MatchRules>,
+    mut callouts: EventWriter<Callout>,
+) {
+    if !phase.is_finished() {
+        return;
+    }
+    let config = match pending.0.take() {
+        Some(config) => config,
+        None => return,
+    };
+
+    let new_tuning = Tuning {
+        ball_size: tuning.ball_size,
+        paddle_width: tuning.paddle_width,
+        paddle_height: tuning.paddle_height,
+        goal_mouth: tuning.goal_mouth,
+        ..config.tuning.clone()
+    };
+    let new_rules = MatchRules {
+        doubles: rules.doubles,
+        paddles_per_side: rules.paddles_per_side,
+        hazard: rules.hazard,
+        invisible_ball: rules.invisible_ball,
+        wrap_around: rules.wrap_around,
+        balls: rules.balls,
+        ..config.rules.clone()
+    }
+    .for_tournament(&tournament)
+    .for_queue(&queue);
+    let expected_rules = config
+        .rules
+        .clone()
+        .for_tournament(&tournament)
+        .for_queue(&queue);
+    if new_tuning != config.tuning || new_rules != expected_rules {
+        callouts.send(Callout(format!(
+            "Arena changes apply when started with --import-config {}",
+            CONFIG_PATH
+        )));
+    }
+
+    bindings.0 = config.bindings;
+    *tuning = new_tuning;
+    theme.0 = config.theme;
+    *rules = new_rules;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_then_import_is_lossless() {
+        let mut settings = PongSettings {
+            preset: Preset::Kid,
+            tuning: Some(Tuning {
+                serve_speed: 12.5,
+                paddle_carry: 0.25,
+                long_rally_decay: 0.05,
+                ..Tuning::for_preset(Preset::Kid)
+            }),
+            theme: Some(Theme {
+                wall: Color::rgb(0.1, 0.2, 0.3),
+                ..Theme::default()
+            }),
+            rules: MatchRules {
+                time_limit: Some(90.),
+                score_limit: Some(7),
+                mercy_rule: true,
+                balls: 2,
+                ..MatchRules::default()
+            },
+            ..PongSettings::default()
+        };
+        settings.bindings.left.up = KeyCode::I;
+        settings.bindings.right_back.power = KeyCode::Key0;
+
+        let exported =
+            ron::ser::to_string_pretty(&GameConfig::from_settings(&settings), Default::default())
+                .unwrap();
+        let (config, warnings) = GameConfig::parse(CONFIG_PATH, &exported).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let mut imported = PongSettings::default();
+        config.apply_to(&mut imported);
+        assert_eq!(imported.bindings, settings.bindings);
+        assert_eq!(imported.tuning, settings.tuning);
+        assert_eq!(imported.theme, settings.theme);
+        assert_eq!(imported.preset, settings.preset);
+        assert_eq!(imported.rules, settings.rules);
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        assert!(GameConfig::import("no/such/dir/pingis_pong_config.ron").is_err());
+    }
+
+    #[test]
+    fn bad_ron_is_an_error() {
+        assert!(GameConfig::parse(CONFIG_PATH, "(tuning: (serve_speed: ").is_err());
+        assert!(GameConfig::parse(CONFIG_PATH, "(rules: (balls: \"two\"))").is_err());
+    }
+
+    #[test]
+    fn out_of_range_values_are_clamped() {
+        let content = "(rules: (balls: 9, paddles_per_side: 0), tuning: (serve_speed: 0.5))";
+        let (config, warnings) = GameConfig::parse(CONFIG_PATH, content).unwrap();
+
+        assert_eq!(config.rules.balls, MAX_BALLS);
+        assert_eq!(config.rules.paddles_per_side, 1);
+        assert_eq!(config.tuning.serve_speed, 1.);
+        assert_eq!(warnings.len(), 3, "{:?}", warnings);
+        for field in [
+            "rules.balls",
+            "rules.paddles_per_side",
+            "tuning.serve_speed",
+        ]
+        .iter()
+        {
+            assert!(
+                warnings.iter().any(|warning| warning.contains(field)),
+                "no warning for {} in {:?}",
+                field,
+                warnings
+            );
+        }
+    }
+
+    #[test]
+    fn values_in_range_are_kept() {
+        let content = "(rules: (balls: 2), tuning: (serve_speed: 30.0))";
+        let (config, warnings) = GameConfig::parse(CONFIG_PATH, content).unwrap();
+
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(config.rules.balls, 2);
+        assert_eq!(config.tuning.serve_speed, 30.);
+    }
+
+    #[test]
+    fn unknown_fields_are_reported() {
+        let content = "(tuning: (serve_speed: 20.0, warp_drive: true), colour: \"red\")";
+        let (config, warnings) = GameConfig::parse(CONFIG_PATH, content).unwrap();
+
+        assert_eq!(config.tuning.serve_speed, 20.);
+        let mut unknown = unknown_fields(content);
+        unknown.sort();
+        assert_eq!(unknown, vec!["colour", "tuning.warp_drive"]);
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+    }
+
+    #[test]
+    fn newer_version_is_read_with_a_warning() {
+        let content = format!("(version: {})", CONFIG_VERSION + 1);
+        let (config, warnings) = GameConfig::parse(CONFIG_PATH, &content).unwrap();
+
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+    }
+}
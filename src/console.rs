@@ -0,0 +1,508 @@
+use std::cmp::Ordering;
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy_rapier2d::physics::RapierConfiguration;
+use bevy_rapier2d::rapier::dynamics::RigidBodyBuilder;
+
+use crate::loading::GameAssets;
+use crate::match_state::{MatchEnd, MatchPhase, MatchRules, RestartMatch};
+use crate::rng::GameRng;
+use crate::rules::EndReason;
+use crate::theme::Theme;
+use crate::tuning::Tuning;
+use crate::{
+    serve_velocity, spawn_ball_body, AppState, Paused, Player, Score, ServeEvent, ServeKind,
+    UiFont, ARENA_HEIGHT, ARENA_WIDTH,
+};
+
+/// Developer console for changing the game while it runs. Left out of competitive builds.
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Console>()
+            .add_event::<ConsoleCommand>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                capture_console.system().after(InputSystem),
+            )
+            .add_system(render_console.system())
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(run_set.system())
+                    .with_system(run_score.system())
+                    .with_system(run_spawn.system())
+                    .with_system(run_seed.system())
+                    .with_system(run_state.system()),
+            );
+    }
+}
+
+const TOGGLE_KEY: KeyCode = KeyCode::Grave;
+/// Output lines kept above the input line.
+const OUTPUT_LINES: usize = 12;
+
+/// Every command with its usage, for `help` and completion.
+const COMMANDS: [(&str, &str); 7] = [
+    ("set", "set tuning.<field> <value>"),
+    ("score", "score <left> <right>"),
+    ("spawn", "spawn ball"),
+    ("seed", "seed <number>"),
+    ("state", "state regular|overtime|gameover"),
+    ("help", "help"),
+    ("clear", "clear"),
+];
+const SPAWNABLE: [&str; 1] = ["ball"];
+const STATES: [&str; 3] = ["regular", "overtime", "gameover"];
+
+#[derive(Debug, Default)]
+struct Console {
+    open: bool,
+    input: String,
+    output: Vec<String>,
+    /// Last command run, brought back by the up key.
+    last: Option<String>,
+    /// The game was running when the console opened.
+    paused_game: bool,
+}
+
+impl Console {
+    fn print(&mut self, line: impl Into<String>) {
+        self.output.push(line.into());
+        let extra = self.output.len().saturating_sub(OUTPUT_LINES);
+        self.output.drain(..extra);
+    }
+}
+
+/// A parsed command, handled by the system owning what it changes.
+#[derive(Debug, Clone)]
+enum ConsoleCommand {
+    /// Dotted path below `tuning` and the new value in RON.
+    Set(String, String),
+    Score(u32, u32),
+    SpawnBall,
+    Seed(u64),
+    State(String),
+}
+
+fn parse(line: &str) -> Result<ConsoleCommand, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let usage = |name: &str| {
+        COMMANDS
+            .iter()
+            .find(|(command, _)| *command == name)
+            .map_or(String::new(), |(_, usage)| format!("Usage: {}", usage))
+    };
+
+    match words.as_slice() {
+        ["set", path, value @ ..] if !value.is_empty() => {
+            let path = path
+                .strip_prefix("tuning.")
+                .ok_or_else(|| "Only tuning.<field> can be set".to_string())?;
+            Ok(ConsoleCommand::Set(path.to_string(), value.join(" ")))
+        }
+        ["score", left, right] => match (left.parse(), right.parse()) {
+            (Ok(left), Ok(right)) => Ok(ConsoleCommand::Score(left, right)),
+            _ => Err(usage("score")),
+        },
+        ["spawn", "ball"] => Ok(ConsoleCommand::SpawnBall),
+        ["seed", seed] => seed
+            .parse()
+            .map(ConsoleCommand::Seed)
+            .map_err(|_| usage("seed")),
+        ["state", state] if STATES.contains(state) => Ok(ConsoleCommand::State(state.to_string())),
+        [name, ..] if COMMANDS.iter().any(|(command, _)| command == name) => Err(usage(*name)),
+        [name, ..] => Err(format!("Unknown command {}, try help", name)),
+        [] => Err(String::new()),
+    }
+}
+
+/// `tuning` as a tree of RON values, fields by name.
+fn tuning_tree(tuning: &Tuning) -> Option<ron::Value> {
+    ron::to_string(tuning)
+        .ok()
+        .and_then(|content| ron::from_str(&content).ok())
+}
+
+/// Dotted paths of every plain value in `tree`, for completing `set`.
+fn leaf_paths(prefix: &str, tree: &ron::Value, paths: &mut Vec<String>) {
+    match tree {
+        ron::Value::Map(map) => {
+            for (key, value) in map.iter() {
+                if let ron::Value::String(name) = key {
+                    let path = if prefix.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{}.{}", prefix, name)
+                    };
+                    leaf_paths(&path, value, paths);
+                }
+            }
+        }
+        _ => paths.push(prefix.to_string()),
+    }
+}
+
+/// Puts `value` at `path` in `tree`, only over a field that is already there.
+fn replace_at(tree: &mut ron::Value, path: &[&str], value: ron::Value) -> Result<(), String> {
+    let (name, rest) = match path.split_first() {
+        Some(split) => split,
+        None => {
+            *tree = value;
+            return Ok(());
+        }
+    };
+    let map = match tree {
+        ron::Value::Map(map) => map,
+        _ => return Err(format!("{} has no fields", name)),
+    };
+
+    let key = ron::Value::String(name.to_string());
+    let mut field = map
+        .remove(&key)
+        .ok_or_else(|| format!("No field called {}", name))?;
+    let result = replace_at(&mut field, rest, value);
+    map.insert(key, field);
+    result
+}
+
+/// Tuning with one field changed, going through RON so every field can be reached by name.
+fn set_tuning(tuning: &Tuning, path: &str, value: &str) -> Result<Tuning, String> {
+    let mut tree = tuning_tree(tuning).ok_or_else(|| "Tuning can't be read".to_string())?;
+    let value: ron::Value =
+        ron::from_str(value).map_err(|err| format!("Bad value {}: {}", value, err))?;
+    let path: Vec<&str> = path.split('.').collect();
+    replace_at(&mut tree, &path, value)?;
+    tree.into_rust()
+        .map_err(|err| format!("Doesn't fit tuning.{}: {}", path.join("."), err))
+}
+
+/// Completes the word being typed from what can go there. One match is filled in, several are
+/// filled in as far as they agree and listed.
+fn complete(console: &mut Console, tuning: &Tuning) {
+    let input = console.input.clone();
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let fresh_word = input.is_empty() || input.ends_with(' ');
+    let (done, partial) = if fresh_word {
+        (words.len(), "")
+    } else {
+        (words.len() - 1, words[words.len() - 1])
+    };
+
+    let candidates: Vec<String> = match (done, words.first()) {
+        (0, _) => COMMANDS
+            .iter()
+            .map(|(command, _)| command.to_string())
+            .collect(),
+        (1, Some(&"set")) => {
+            let mut paths = Vec::new();
+            if let Some(tree) = tuning_tree(tuning) {
+                leaf_paths("tuning", &tree, &mut paths);
+            }
+            paths
+        }
+        (1, Some(&"spawn")) => SPAWNABLE.iter().map(|name| name.to_string()).collect(),
+        (1, Some(&"state")) => STATES.iter().map(|name| name.to_string()).collect(),
+        _ => Vec::new(),
+    };
+    let matching: Vec<&String> = candidates
+        .iter()
+        .filter(|candidate| candidate.starts_with(partial))
+        .collect();
+
+    let completed = match matching.as_slice() {
+        [] => return,
+        [only] => format!("{} ", only),
+        [first, rest @ ..] => {
+            let shared = rest.iter().fold(first.len(), |shared, candidate| {
+                first
+                    .chars()
+                    .zip(candidate.chars())
+                    .take(shared)
+                    .take_while(|(a, b)| a == b)
+                    .count()
+            });
+            let listed: Vec<&str> = matching
+                .iter()
+                .map(|candidate| candidate.as_str())
+                .collect();
+            console.print(listed.join("  "));
+            first.chars().take(shared).collect()
+        }
+    };
+
+    let kept = input.len() - partial.len();
+    console.input.truncate(kept);
+    console.input.push_str(&completed);
+}
+
+/// Runs before anything reads the keyboard. While open, every key goes to the console and is
+/// taken away from the game, the menus and the hotkeys, and the game is paused.
+fn capture_console(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    tuning: Res<Tuning>,
+    mut console: ResMut<Console>,
+    mut paused: ResMut<Paused>,
+    mut commands: EventWriter<ConsoleCommand>,
+) {
+    let typed: Vec<char> = characters.iter().map(|character| character.char).collect();
+
+    if keyboard_input.just_pressed(TOGGLE_KEY) {
+        console.open = !console.open;
+        if console.open {
+            console.paused_game = !paused.0;
+            paused.0 = true;
+        } else if console.paused_game {
+            console.paused_game = false;
+            paused.0 = false;
+        }
+        keyboard_input.reset(TOGGLE_KEY);
+        return;
+    }
+    if !console.open {
+        return;
+    }
+
+    for character in typed {
+        if !character.is_control() && character != '`' && character != '~' {
+            console.input.push(character);
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        console.input.pop();
+    }
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        if let Some(last) = console.last.clone() {
+            console.input = last;
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Tab) {
+        complete(&mut console, &tuning);
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        let line = console.input.trim().to_string();
+        console.input.clear();
+        if !line.is_empty() {
+            console.print(format!("> {}", line));
+            console.last = Some(line.clone());
+            // Every command tried goes in the log, so a match played with it shows it was
+            // tampered with
+            info!("Console: {}", line);
+
+            match line.as_str() {
+                "help" => {
+                    for (_, usage) in COMMANDS.iter() {
+                        console.print(*usage);
+                    }
+                }
+                "clear" => console.output.clear(),
+                _ => match parse(&line) {
+                    Ok(command) => commands.send(command),
+                    Err(err) => console.print(err),
+                },
+            }
+        }
+    }
+
+    let held: Vec<KeyCode> = keyboard_input
+        .get_pressed()
+        .chain(keyboard_input.get_just_released())
+        .copied()
+        .collect();
+    for key in held {
+        keyboard_input.reset(key);
+    }
+}
+
+struct ConsoleRoot;
+
+fn render_console(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    font: Option<Res<UiFont>>,
+    console: Res<Console>,
+    roots: Query<Entity, With<ConsoleRoot>>,
+) {
+    if !console.is_changed() {
+        return;
+    }
+    for entity in roots.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let font = match font {
+        Some(font) if console.open => font.0.clone(),
+        _ => return,
+    };
+
+    let mut lines = console.output.clone();
+    lines.push(format!("> {}_", console.input));
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(0.),
+                    bottom: Val::Px(0.),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.), Val::Auto),
+                padding: Rect::all(Val::Px(8.)),
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0., 0., 0., 0.8).into()),
+            ..Default::default()
+        })
+        .insert(ConsoleRoot)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    lines.join("\n"),
+                    TextStyle {
+                        font,
+                        font_size: 18.0,
+                        color: Color::rgb(0.6, 1.0, 0.6),
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            });
+        });
+}
+
+fn run_set(
+    mut commands: EventReader<ConsoleCommand>,
+    mut console: ResMut<Console>,
+    mut tuning: ResMut<Tuning>,
+) {
+    for command in commands.iter() {
+        if let ConsoleCommand::Set(path, value) = command {
+            match set_tuning(&tuning, path, value) {
+                Ok(changed) => {
+                    *tuning = changed;
+                    console.print(format!("tuning.{} = {}", path, value));
+                }
+                Err(err) => console.print(err),
+            }
+        }
+    }
+}
+
+/// Only the numbers change, the rules look at the score again on the next goal.
+fn run_score(
+    mut commands: EventReader<ConsoleCommand>,
+    mut console: ResMut<Console>,
+    mut score: ResMut<Score>,
+) {
+    for command in commands.iter() {
+        if let ConsoleCommand::Score(left, right) = command {
+            score.left = *left;
+            score.right = *right;
+            score.streak = None;
+            console.print(format!("Score {} - {}", left, right));
+        }
+    }
+}
+
+/// An extra ball served from the center spot, on top of what the rules allow.
+fn run_spawn(
+    mut commands: EventReader<ConsoleCommand>,
+    mut spawner: Commands,
+    mut console: ResMut<Console>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut rng: ResMut<GameRng>,
+    mut serves: EventWriter<ServeEvent>,
+    rapier_config: Res<RapierConfiguration>,
+    tuning: Res<Tuning>,
+    rules: Res<MatchRules>,
+    theme: Res<Theme>,
+    game_assets: Res<GameAssets>,
+) {
+    for command in commands.iter() {
+        if let ConsoleCommand::SpawnBall = command {
+            let velocity = serve_velocity(
+                &mut rng,
+                &rules,
+                &tuning,
+                None,
+                ServeKind::Standard,
+                tuning.serve_speed,
+            );
+            let body = RigidBodyBuilder::new_dynamic()
+                .translation(
+                    ARENA_WIDTH / 2. / rapier_config.scale,
+                    ARENA_HEIGHT / 2. / rapier_config.scale,
+                )
+                .linvel(velocity.x, velocity.y);
+            spawn_ball_body(
+                &mut spawner,
+                &mut materials,
+                &rapier_config,
+                &tuning,
+                &theme,
+                &game_assets,
+                body,
+            );
+            serves.send(ServeEvent::from_velocity(&velocity, ServeKind::Standard));
+            console.print("Ball spawned");
+        }
+    }
+}
+
+fn run_seed(
+    mut commands: EventReader<ConsoleCommand>,
+    mut console: ResMut<Console>,
+    mut rng: ResMut<GameRng>,
+) {
+    for command in commands.iter() {
+        if let ConsoleCommand::Seed(seed) = command {
+            *rng = GameRng::new(*seed);
+            console.print(format!("Seed {}", seed));
+        }
+    }
+}
+
+/// Ending the match needs someone ahead, going back to regular play from a finished match is a
+/// rematch.
+fn run_state(
+    mut commands: EventReader<ConsoleCommand>,
+    mut console: ResMut<Console>,
+    score: Res<Score>,
+    mut phase: ResMut<MatchPhase>,
+    mut end: ResMut<MatchEnd>,
+    mut restarts: EventWriter<RestartMatch>,
+) {
+    for command in commands.iter() {
+        let state = match command {
+            ConsoleCommand::State(state) => state.as_str(),
+            _ => continue,
+        };
+
+        match state {
+            "regular" if phase.is_finished() => restarts.send(RestartMatch),
+            "regular" => *phase = MatchPhase::Regular,
+            "overtime" if phase.is_finished() => {
+                console.print("The match is over, set state regular first");
+                continue;
+            }
+            "overtime" => *phase = MatchPhase::Overtime,
+            _ => match score.left.cmp(&score.right) {
+                Ordering::Greater => {
+                    *phase = MatchPhase::Finished(Player::Left);
+                    end.0 = EndReason::ScoreLimit;
+                }
+                Ordering::Less => {
+                    *phase = MatchPhase::Finished(Player::Right);
+                    end.0 = EndReason::ScoreLimit;
+                }
+                Ordering::Equal => {
+                    console.print("The score is level, set the score first");
+                    continue;
+                }
+            },
+        }
+        console.print(format!("State {}", state));
+    }
+}
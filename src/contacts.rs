@@ -0,0 +1,400 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use bevy_rapier2d::physics::{ColliderHandleComponent, EventQueue, RigidBodyHandleComponent};
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+use bevy_rapier2d::rapier::geometry::{ColliderHandle, ContactEvent};
+
+use crate::gates::GateLink;
+use crate::hazard::Hazard;
+use crate::{AppState, Ball, GameLabel, Paddle, Player, Wall};
+
+pub struct ContactsPlugin;
+
+impl Plugin for ContactsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        // Like the physics sync, despawns from the update stage only show up as removed
+        // components to systems after it
+        app.init_resource::<ColliderMap>()
+            .init_resource::<ContactCooldowns>()
+            .add_event::<BallContact>()
+            .add_system_to_stage(CoreStage::PostUpdate, track_colliders.system())
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(dispatch_contacts.system().label(GameLabel::Contacts)),
+            );
+    }
+}
+
+/// Resolves rapier collider handles back to the entity owning them.
+#[derive(Debug, Default)]
+pub struct ColliderMap {
+    entities: HashMap<ColliderHandle, Entity>,
+    handles: HashMap<Entity, ColliderHandle>,
+}
+
+impl ColliderMap {
+    pub fn get(&self, handle: ColliderHandle) -> Option<Entity> {
+        self.entities.get(&handle).copied()
+    }
+
+    pub fn insert(&mut self, handle: ColliderHandle, entity: Entity) {
+        self.entities.insert(handle, entity);
+        self.handles.insert(entity, handle);
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        if let Some(handle) = self.handles.remove(&entity) {
+            self.entities.remove(&handle);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+/// Seconds after a paddle hit during which the same ball and paddle can't hit again. A ball
+/// grazing along a paddle's face makes the solver start and stop the contact several times in a
+/// few frames.
+const PADDLE_COOLDOWN: f64 = 0.08;
+/// The same for a wall, shorter since a ball can't come back to a wall that quickly.
+const WALL_COOLDOWN: f64 = 0.03;
+
+/// When each ball last hit each paddle or wall, in seconds since startup.
+#[derive(Debug, Default)]
+struct ContactCooldowns {
+    hits: HashMap<(Entity, Entity), f64>,
+    /// Pairs whose last start was swallowed, their stop goes too.
+    muted: HashSet<(Entity, Entity)>,
+}
+
+impl ContactCooldowns {
+    /// Whether a contact between `ball` and `other` that `started` or stopped at `now` counts.
+    fn admit(
+        &mut self,
+        ball: Entity,
+        other: Entity,
+        started: bool,
+        now: f64,
+        cooldown: f64,
+    ) -> bool {
+        let pair = (ball, other);
+        if !started {
+            return !self.muted.remove(&pair);
+        }
+        if let Some(&last) = self.hits.get(&pair) {
+            if now - last < cooldown {
+                self.muted.insert(pair);
+                return false;
+            }
+        }
+        self.hits.insert(pair, now);
+        self.muted.remove(&pair);
+        true
+    }
+
+    fn forget_expired(&mut self, now: f64) {
+        let hits = &mut self.hits;
+        hits.retain(|_, last| now - *last < PADDLE_COOLDOWN.max(WALL_COOLDOWN));
+        self.muted.retain(|pair| hits.contains_key(pair));
+    }
+}
+
+/// What the ball touched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Surface {
+    Paddle(Player),
+    Wall(Wall),
+    Hazard,
+    /// One of the teleport gates.
+    Gate,
+    Ball,
+    Other,
+}
+
+/// A ball started or stopped touching something.
+#[derive(Debug, Clone, Copy)]
+pub struct BallContact {
+    pub ball: Entity,
+    pub other: Entity,
+    pub surface: Surface,
+    pub started: bool,
+    /// Ball position in pixels when the event was read, close to the contact point.
+    pub position: Vec2,
+    /// Unit normal of the surface pointing at the ball, zero if the two overlap exactly.
+    pub normal: Vec2,
+    /// Velocities in physics units when the event was read, after the solver's response.
+    /// Zero for anything without a rigid body.
+    pub ball_velocity: Vec2,
+    pub other_velocity: Vec2,
+}
+
+fn track_colliders(
+    mut map: ResMut<ColliderMap>,
+    added: Query<(Entity, &ColliderHandleComponent), Added<ColliderHandleComponent>>,
+    removed: RemovedComponents<ColliderHandleComponent>,
+) {
+    for entity in removed.iter() {
+        map.remove(entity);
+    }
+
+    for (entity, collider) in added.iter() {
+        map.insert(collider.handle(), entity);
+    }
+}
+
+fn dispatch_contacts(
+    time: Res<Time>,
+    events: Res<EventQueue>,
+    map: Res<ColliderMap>,
+    mut cooldowns: ResMut<ContactCooldowns>,
+    mut contacts: EventWriter<BallContact>,
+    balls: Query<&Ball>,
+    paddles: Query<&Player, With<Paddle>>,
+    walls: Query<&Wall>,
+    hazards: Query<&Hazard>,
+    gates: Query<&GateLink>,
+    transforms: Query<&Transform>,
+    rigid_bodies: Res<RigidBodySet>,
+    bodies: Query<&RigidBodyHandleComponent>,
+) {
+    let velocity = |entity: Entity| {
+        bodies
+            .get(entity)
+            .ok()
+            .and_then(|rigid_body_component| rigid_bodies.get(rigid_body_component.handle()))
+            .map(|rb| Vec2::new(rb.linvel().x, rb.linvel().y))
+            .unwrap_or(Vec2::ZERO)
+    };
+
+    // The top and bottom walls are several colliders each, a ball landing on a seam touches two
+    // of them at once but bounces off the wall once
+    let mut wall_touches = HashSet::new();
+    let now = time.seconds_since_startup();
+    cooldowns.forget_expired(now);
+
+    let mut dispatch = |h1: ColliderHandle, h2: ColliderHandle, started: bool| {
+        let (e1, e2) = match (map.get(h1), map.get(h2)) {
+            (Some(e1), Some(e2)) => (e1, e2),
+            _ => return,
+        };
+
+        for (ball, other) in [(e1, e2), (e2, e1)].iter().copied() {
+            if balls.get(ball).is_err() {
+                continue;
+            }
+
+            let surface = if let Ok(player) = paddles.get(other) {
+                Surface::Paddle(*player)
+            } else if let Ok(wall) = walls.get(other) {
+                Surface::Wall(*wall)
+            } else if hazards.get(other).is_ok() {
+                Surface::Hazard
+            } else if gates.get(other).is_ok() {
+                Surface::Gate
+            } else if balls.get(other).is_ok() {
+                Surface::Ball
+            } else {
+                Surface::Other
+            };
+            if let Surface::Wall(wall) = surface {
+                if !wall_touches.insert((ball, wall, started)) {
+                    continue;
+                }
+            }
+            let cooldown = match surface {
+                Surface::Paddle(_) => Some(PADDLE_COOLDOWN),
+                Surface::Wall(_) => Some(WALL_COOLDOWN),
+                _ => None,
+            };
+            if let Some(cooldown) = cooldown {
+                if !cooldowns.admit(ball, other, started, now, cooldown) {
+                    continue;
+                }
+            }
+
+            let position = transforms
+                .get(ball)
+                .map(|transform| transform.translation.truncate())
+                .unwrap_or(Vec2::ZERO);
+            let normal = match surface {
+                Surface::Wall(Wall::Top) => -Vec2::Y,
+                Surface::Wall(Wall::Bottom) => Vec2::Y,
+                Surface::Wall(Wall::Flank(Player::Left)) => Vec2::X,
+                Surface::Wall(Wall::Flank(Player::Right)) => -Vec2::X,
+                _ => {
+                    let away = transforms
+                        .get(other)
+                        .map(|transform| position - transform.translation.truncate())
+                        .unwrap_or(Vec2::ZERO);
+                    if away.length_squared() > 0. {
+                        away.normalize()
+                    } else {
+                        Vec2::ZERO
+                    }
+                }
+            };
+
+            contacts.send(BallContact {
+                ball,
+                other,
+                surface,
+                started,
+                position,
+                normal,
+                ball_velocity: velocity(ball),
+                other_velocity: velocity(other),
+            });
+        }
+    };
+
+    while let Ok(intersection_event) = events.intersection_events.pop() {
+        dispatch(
+            intersection_event.collider1,
+            intersection_event.collider2,
+            intersection_event.intersecting,
+        );
+    }
+
+    while let Ok(contact_event) = events.contact_events.pop() {
+        match contact_event {
+            ContactEvent::Started(h1, h2) => dispatch(h1, h2, true),
+            ContactEvent::Stopped(h1, h2) => dispatch(h1, h2, false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ball() -> Entity {
+        Entity::new(1)
+    }
+
+    fn paddle() -> Entity {
+        Entity::new(2)
+    }
+
+    #[test]
+    fn burst_is_one_hit() {
+        let mut cooldowns = ContactCooldowns::default();
+        let burst = [
+            (true, 0.),
+            (false, 0.01),
+            (true, 0.02),
+            (false, 0.03),
+            (true, 0.05),
+            (false, 0.06),
+            (true, 0.079),
+            (false, 0.085),
+        ];
+
+        let admitted: Vec<(bool, f64)> = burst
+            .iter()
+            .copied()
+            .filter(|(started, now)| {
+                cooldowns.admit(ball(), paddle(), *started, *now, PADDLE_COOLDOWN)
+            })
+            .collect();
+        assert_eq!(admitted, vec![(true, 0.), (false, 0.01)]);
+    }
+
+    #[test]
+    fn stop_of_a_swallowed_start_is_swallowed() {
+        let mut cooldowns = ContactCooldowns::default();
+        assert!(cooldowns.admit(ball(), paddle(), true, 0., PADDLE_COOLDOWN));
+        assert!(!cooldowns.admit(ball(), paddle(), true, 0.02, PADDLE_COOLDOWN));
+        assert!(!cooldowns.admit(ball(), paddle(), false, 0.03, PADDLE_COOLDOWN));
+        // Only the one stop belonged to the muted start
+        assert!(cooldowns.admit(ball(), paddle(), false, 0.04, PADDLE_COOLDOWN));
+    }
+
+    #[test]
+    fn walls_cool_down_faster() {
+        let wall = Entity::new(3);
+        let mut cooldowns = ContactCooldowns::default();
+        assert!(cooldowns.admit(ball(), wall, true, 0., WALL_COOLDOWN));
+        assert!(!cooldowns.admit(ball(), wall, true, 0.02, WALL_COOLDOWN));
+        assert!(cooldowns.admit(ball(), wall, true, 0.035, WALL_COOLDOWN));
+
+        // The same timing against a paddle is still cooling down
+        assert!(cooldowns.admit(ball(), paddle(), true, 0., PADDLE_COOLDOWN));
+        assert!(!cooldowns.admit(ball(), paddle(), true, 0.035, PADDLE_COOLDOWN));
+    }
+
+    #[test]
+    fn pairs_cool_down_on_their_own() {
+        let other_ball = Entity::new(4);
+        let mut cooldowns = ContactCooldowns::default();
+        assert!(cooldowns.admit(ball(), paddle(), true, 0., PADDLE_COOLDOWN));
+        assert!(cooldowns.admit(other_ball, paddle(), true, 0.01, PADDLE_COOLDOWN));
+    }
+
+    #[test]
+    fn expired_pairs_are_forgotten() {
+        let mut cooldowns = ContactCooldowns::default();
+        assert!(cooldowns.admit(ball(), paddle(), true, 0., PADDLE_COOLDOWN));
+        assert!(!cooldowns.admit(ball(), paddle(), true, 0.01, PADDLE_COOLDOWN));
+
+        cooldowns.forget_expired(0.05);
+        assert_eq!(cooldowns.hits.len(), 1);
+
+        cooldowns.forget_expired(0.2);
+        assert!(cooldowns.hits.is_empty());
+        assert!(cooldowns.muted.is_empty());
+        assert!(cooldowns.admit(ball(), paddle(), true, 0.2, PADDLE_COOLDOWN));
+    }
+
+    /// Despawns the entity in it during the update stage, like any gameplay system would.
+    struct DespawnTarget(Option<Entity>);
+
+    fn despawn_target(mut commands: Commands, mut target: ResMut<DespawnTarget>) {
+        if let Some(entity) = target.0.take() {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    #[test]
+    fn despawned_colliders_leave_the_map() {
+        let mut app = App::build();
+        app.add_state(AppState::Loading)
+            .insert_resource(DespawnTarget(None))
+            .add_plugin(ContactsPlugin)
+            .add_system(despawn_target.system());
+        let mut app = app.app;
+
+        let handle = ColliderHandle::from_raw_parts(0, 0);
+        let entity = app
+            .world
+            .spawn()
+            .insert(ColliderHandleComponent::from(handle))
+            .id();
+        app.update();
+        let map = app.world.get_resource::<ColliderMap>().unwrap();
+        assert_eq!(map.get(handle), Some(entity));
+        assert_eq!(map.len(), 1);
+
+        app.world.get_resource_mut::<DespawnTarget>().unwrap().0 = Some(entity);
+        app.update();
+        let map = app.world.get_resource::<ColliderMap>().unwrap();
+        assert_eq!(map.get(handle), None);
+        assert!(map.is_empty());
+
+        // Rapier hands the freed slot to the next collider, it must resolve to the new owner
+        let next = app
+            .world
+            .spawn()
+            .insert(ColliderHandleComponent::from(handle))
+            .id();
+        app.update();
+        let map = app.world.get_resource::<ColliderMap>().unwrap();
+        assert_eq!(map.get(handle), Some(next));
+        assert_eq!(map.len(), 1);
+    }
+}
@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+use bevy::render::texture::{Extent3d, TextureDimension, TextureFormat};
+
+use crate::quality::QualitySettings;
+use crate::{AppState, ARENA_HEIGHT, ARENA_MIDDLE, ARENA_WIDTH};
+
+/// Scanlines and darkened corners over the arena, for a look of an old CRT screen.
+pub struct CrtPlugin;
+
+impl Plugin for CrtPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame).with_system(update_crt_overlay.system()),
+        );
+    }
+}
+
+/// Window pixels from one dark scanline to the next.
+const SCANLINE_PERIOD: u32 = 3;
+const SCANLINE_ALPHA: u8 = 70;
+/// Side of the vignette texture, filtering smooths it out over the arena.
+const VIGNETTE_SIZE: u32 = 64;
+/// Darkness in the very corners.
+const VIGNETTE_ALPHA: f32 = 0.55;
+/// In front of every gameplay sprite. The UI has its own camera and stays crisp on top.
+const CRT_Z: f32 = 100.;
+
+/// One of the overlay sprites, left out of screenshots and clips. The scanlines remember the
+/// window height they were drawn for.
+pub struct CrtOverlay {
+    scanlines_for: Option<u32>,
+}
+
+/// Column of dark lines one window pixel apart per texel, so they stay sharp when it is stretched
+/// over the arena.
+fn scanline_texture(height: u32) -> Texture {
+    let data = (0..height)
+        .flat_map(|row| {
+            let alpha = if row % SCANLINE_PERIOD == 0 {
+                SCANLINE_ALPHA
+            } else {
+                0
+            };
+            vec![0, 0, 0, alpha]
+        })
+        .collect();
+
+    Texture::new(
+        Extent3d::new(1, height, 1),
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+/// Clear in the middle and darker toward the corners, standing in for the curve of the glass.
+fn vignette_texture() -> Texture {
+    let center = (VIGNETTE_SIZE - 1) as f32 / 2.;
+    let data = (0..VIGNETTE_SIZE * VIGNETTE_SIZE)
+        .flat_map(|index| {
+            let x = (index % VIGNETTE_SIZE) as f32 / center - 1.;
+            let y = (index / VIGNETTE_SIZE) as f32 / center - 1.;
+            let edge = ((x * x + y * y) / 2.).sqrt();
+            let alpha = (edge * edge * edge * VIGNETTE_ALPHA * 255.) as u8;
+            vec![0, 0, 0, alpha]
+        })
+        .collect();
+
+    Texture::new(
+        Extent3d::new(VIGNETTE_SIZE, VIGNETTE_SIZE, 1),
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+/// Shows the overlay while the settings and quality allow it. The scanlines are drawn again
+/// whenever the window height changes, so there is always one per `SCANLINE_PERIOD` pixels.
+fn update_crt_overlay(
+    mut commands: Commands,
+    quality: Res<QualitySettings>,
+    windows: Res<Windows>,
+    mut textures: ResMut<Assets<Texture>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut overlays: Query<(Entity, &mut CrtOverlay, &Handle<ColorMaterial>)>,
+) {
+    if !quality.crt {
+        for (entity, _, _) in overlays.iter_mut() {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+
+    let height = windows
+        .get_primary()
+        .map_or(ARENA_HEIGHT, |window| window.height())
+        .round()
+        .max(1.) as u32;
+
+    if overlays.iter_mut().next().is_none() {
+        let transform = Transform::from_xyz(ARENA_MIDDLE, ARENA_HEIGHT / 2., CRT_Z);
+        let size = Vec2::new(ARENA_WIDTH, ARENA_HEIGHT);
+
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: materials.add(textures.add(scanline_texture(height)).into()),
+                sprite: Sprite::new(size),
+                transform,
+                ..Default::default()
+            })
+            .insert(CrtOverlay {
+                scanlines_for: Some(height),
+            });
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: materials.add(textures.add(vignette_texture()).into()),
+                sprite: Sprite::new(size),
+                transform,
+                ..Default::default()
+            })
+            .insert(CrtOverlay {
+                scanlines_for: None,
+            });
+        return;
+    }
+
+    for (_, mut overlay, material) in overlays.iter_mut() {
+        if overlay.scanlines_for.map_or(true, |drawn| drawn == height) {
+            continue;
+        }
+        let texture = materials
+            .get(material)
+            .and_then(|material| material.texture.clone());
+        if let Some(texture) = texture {
+            textures.set(texture, scanline_texture(height));
+            overlay.scanlines_for = Some(height);
+        }
+    }
+}
@@ -0,0 +1,399 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::hud;
+use crate::input::Controller;
+use crate::match_state::{MatchPhase, MatchRules};
+use crate::persistence::SaveData;
+use crate::rng::GameRng;
+use crate::stats::MatchStats;
+use crate::tuning::{AiSkill, Preset, Tuning};
+use crate::{AppState, GameLabel, Player, PongSettings, Score, UiFont};
+
+pub struct DailyPlugin;
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(cpu_opponent.system().label("cpu_opponent"))
+                .with_system(
+                    record_daily_result
+                        .system()
+                        .label(GameLabel::GoalPresent)
+                        .after(GameLabel::GoalApply),
+                )
+                .with_system(clear_daily_results.system()),
+        );
+    }
+}
+
+/// The human plays the left paddle against the computer on the right.
+const CPU_SIDE: Player = Player::Right;
+
+/// Today's challenge when one is played, empty otherwise.
+#[derive(Debug, Clone, Default)]
+pub struct DailyChallenge {
+    /// ISO date the setup was derived from.
+    date: String,
+}
+
+impl DailyChallenge {
+    pub fn today() -> Self {
+        DailyChallenge { date: today() }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.date.is_empty()
+    }
+
+    /// The day's setup in place of whatever the host picked, only keys, theme and camera are
+    /// kept. The seed drives the match as well, so everyone also gets the same serves.
+    pub fn apply_to(&self, mut settings: PongSettings) -> PongSettings {
+        let seed = daily_seed(&self.date);
+        let mut rng = GameRng::new(seed);
+
+        let preset = if rng.f32() < 0.25 {
+            Preset::Kid
+        } else {
+            Preset::Standard
+        };
+        let mut tuning = Tuning::for_preset(preset);
+        tuning.ai_skill = AiSkill {
+            reaction_seconds: 0.05 + rng.f32() * 0.25,
+            miss_chance: 0.05 + rng.f32() * 0.2,
+        };
+
+        let mut rules = MatchRules {
+            score_limit: Some(if rng.bool() { 5 } else { 7 }),
+            ..MatchRules::default()
+        };
+        let modifiers: [fn(&mut MatchRules); 7] = [
+            |rules| rules.hazard = true,
+            |rules| rules.invisible_ball = true,
+            |rules| rules.wrap_around = true,
+            |rules| rules.bounce_limit = true,
+            |rules| rules.mercy_rule = true,
+            |rules| rules.catch_and_throw = true,
+            |rules| rules.gravity_zones = true,
+        ];
+        let mut unpicked: Vec<usize> = (0..modifiers.len()).collect();
+        for _ in 0..DAILY_MODIFIERS {
+            let index = (rng.u64() % unpicked.len() as u64) as usize;
+            modifiers[unpicked.remove(index)](&mut rules);
+        }
+
+        settings.rules = rules;
+        settings.preset = preset;
+        settings.tuning = Some(tuning);
+        settings.seed = Some(seed);
+        settings.tournament = None;
+        settings.hill = None;
+        settings
+    }
+}
+
+/// Rule modifiers drawn for each day.
+const DAILY_MODIFIERS: usize = 2;
+
+/// Today's date in UTC as `YYYY-MM-DD`, so the challenge changes at the same moment for everyone.
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs() / 86_400)
+        .unwrap_or(0) as i64;
+    civil_date(days)
+}
+
+/// `YYYY-MM-DD` of the day `days` after 1970-01-01.
+fn civil_date(days: i64) -> String {
+    // Days since the epoch to a civil date, counted in 400 year eras starting on March 1st
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// FNV-1a of the date. The standard library's hasher may change between Rust releases, this
+/// one gives every build the same seed for the same day.
+pub fn daily_seed(date: &str) -> u64 {
+    date.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Outcome of a daily challenge, the best attempt of each day is kept in the save file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyResult {
+    pub date: String,
+    pub won: bool,
+    pub goals: u32,
+    pub conceded: u32,
+    pub longest_rally: u32,
+}
+
+impl DailyResult {
+    /// Wins first, then goal difference, then the longest rally.
+    fn beats(&self, other: &DailyResult) -> bool {
+        let rank = |result: &DailyResult| {
+            (
+                result.won,
+                result.goals as i32 - result.conceded as i32,
+                result.longest_rally,
+            )
+        };
+        rank(self) > rank(other)
+    }
+
+    /// One line to paste wherever players compare their days.
+    pub fn share_line(&self) -> String {
+        format!(
+            "Pingis Pong daily {}: {} {}-{}, longest rally {}",
+            self.date,
+            if self.won { "won" } else { "lost" },
+            self.goals,
+            self.conceded,
+            self.longest_rally
+        )
+    }
+}
+
+/// Books `result` in the kept results, replacing the one of its day if it is better. Returns the
+/// best of its day.
+fn keep_best(kept: &mut Vec<DailyResult>, result: &DailyResult) -> DailyResult {
+    match kept.iter().position(|best| best.date == result.date) {
+        Some(index) => {
+            if result.beats(&kept[index]) {
+                kept[index] = result.clone();
+            }
+            kept[index].clone()
+        }
+        None => {
+            kept.push(result.clone());
+            result.clone()
+        }
+    }
+}
+
+/// Best result kept for `date`.
+pub fn best_result<'a>(save: &'a SaveData, date: &str) -> Option<&'a DailyResult> {
+    save.daily.iter().find(|result| result.date == date)
+}
+
+/// Keeps the computer on its paddle from the start and after every respawn.
+fn cpu_opponent(
+    daily: Res<DailyChallenge>,
+    mut paddles: Query<(&Player, &mut Controller), Added<Controller>>,
+) {
+    if !daily.is_active() {
+        return;
+    }
+
+    for (player, mut controller) in paddles.iter_mut() {
+        if *player == CPU_SIDE {
+            *controller = Controller::Ai;
+        }
+    }
+}
+
+/// Results screen shown instead of the rematch prompt.
+struct DailyResultsText;
+
+/// Books every finished attempt, keeps the best of the day in the save file and puts up the
+/// results with the line to share.
+fn record_daily_result(
+    mut commands: Commands,
+    daily: Res<DailyChallenge>,
+    phase: Res<MatchPhase>,
+    score: Res<Score>,
+    stats: Res<MatchStats>,
+    font: Res<UiFont>,
+    mut save: ResMut<SaveData>,
+) {
+    if !phase.is_changed() || !phase.is_finished() || !daily.is_active() {
+        return;
+    }
+
+    let human = CPU_SIDE.opponent();
+    let result = DailyResult {
+        date: daily.date.clone(),
+        won: *phase == MatchPhase::Finished(human),
+        goals: score.of(human),
+        conceded: score.of(CPU_SIDE),
+        longest_rally: stats.longest_rally,
+    };
+    println!("{}", result.share_line());
+
+    let best = keep_best(&mut save.daily, &result);
+    save.save();
+
+    let lines = vec![
+        format!("Daily challenge {}", daily.date),
+        format!(
+            "{} {}-{}, longest rally {}",
+            if result.won { "Won" } else { "Lost" },
+            result.goals,
+            result.conceded,
+            result.longest_rally
+        ),
+        format!(
+            "Best today: {} {}-{}",
+            if best.won { "won" } else { "lost" },
+            best.goals,
+            best.conceded
+        ),
+        String::new(),
+        result.share_line(),
+        String::new(),
+        "Press Enter to try again".to_string(),
+    ];
+
+    commands
+        .spawn_bundle(TextBundle {
+            text: Text::with_section(
+                lines.join("\n"),
+                TextStyle {
+                    font: font.0.clone(),
+                    font_size: 24.0,
+                    color: Color::rgb(1.0, 1.0, 1.0),
+                },
+                Default::default(),
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: hud::percent(1. / 6.),
+                    left: hud::percent(0.25),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(DailyResultsText);
+}
+
+/// The results go once the next attempt starts.
+fn clear_daily_results(
+    mut commands: Commands,
+    phase: Res<MatchPhase>,
+    texts: Query<Entity, With<DailyResultsText>>,
+) {
+    if phase.is_finished() {
+        return;
+    }
+
+    for entity in texts.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(date: &str, won: bool, goals: u32, conceded: u32, longest_rally: u32) -> DailyResult {
+        DailyResult {
+            date: date.to_string(),
+            won,
+            goals,
+            conceded,
+            longest_rally,
+        }
+    }
+
+    fn challenge(date: &str) -> DailyChallenge {
+        DailyChallenge {
+            date: date.to_string(),
+        }
+    }
+
+    #[test]
+    fn seed_is_fnv_1a_of_the_date() {
+        assert_eq!(daily_seed(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(daily_seed("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(daily_seed("2024-01-01"), 0x3763_7d7a_01d6_21a9);
+        assert_ne!(daily_seed("2024-01-01"), daily_seed("2024-01-02"));
+    }
+
+    #[test]
+    fn dates_from_days_since_the_epoch() {
+        assert_eq!(civil_date(0), "1970-01-01");
+        assert_eq!(civil_date(-1), "1969-12-31");
+        assert_eq!(civil_date(11_016), "2000-02-29");
+        assert_eq!(civil_date(19_723), "2024-01-01");
+        assert_eq!(today().len(), 10);
+    }
+
+    #[test]
+    fn same_day_same_setup() {
+        for date in ["2024-01-01", "2024-02-29", "2031-07-15"].iter() {
+            let first = challenge(date).apply_to(PongSettings::default());
+            let again = challenge(date).apply_to(PongSettings {
+                hill: Some(vec!["Ann".into(), "Bo".into(), "Cy".into()]),
+                ..PongSettings::default()
+            });
+
+            assert_eq!(first.rules, again.rules, "{}", date);
+            assert_eq!(first.preset, again.preset, "{}", date);
+            assert_eq!(first.tuning, again.tuning, "{}", date);
+            assert_eq!(first.seed, Some(daily_seed(date)));
+            assert!(again.hill.is_none() && again.tournament.is_none());
+
+            let rules = &first.rules;
+            let modifiers = [
+                rules.hazard,
+                rules.invisible_ball,
+                rules.wrap_around,
+                rules.bounce_limit,
+                rules.mercy_rule,
+                rules.catch_and_throw,
+                rules.gravity_zones,
+            ];
+            let picked = modifiers.iter().filter(|picked| **picked).count();
+            assert_eq!(picked, DAILY_MODIFIERS, "{}", date);
+            assert!(matches!(rules.score_limit, Some(5) | Some(7)));
+        }
+    }
+
+    #[test]
+    fn best_result_of_the_day_is_kept() {
+        let mut kept = vec![result("2024-01-01", true, 5, 0, 30)];
+
+        let lost = result("2024-01-02", false, 2, 5, 12);
+        assert_eq!(keep_best(&mut kept, &lost), lost);
+
+        let won = result("2024-01-02", true, 5, 4, 3);
+        assert_eq!(keep_best(&mut kept, &won), won);
+        // The same margin with a shorter rally doesn't replace it, a wider one does
+        let close = result("2024-01-02", true, 5, 4, 2);
+        assert_eq!(keep_best(&mut kept, &close), won);
+        let wider = result("2024-01-02", true, 5, 1, 2);
+        assert_eq!(keep_best(&mut kept, &wider), wider);
+
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0], result("2024-01-01", true, 5, 0, 30));
+    }
+
+    #[test]
+    fn share_line() {
+        assert_eq!(
+            result("2024-01-02", false, 3, 5, 14).share_line(),
+            "Pingis Pong daily 2024-01-02: lost 3-5, longest rally 14"
+        );
+    }
+}
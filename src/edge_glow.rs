@@ -0,0 +1,163 @@
+use bevy::prelude::*;
+use bevy_rapier2d::physics::RigidBodyHandleComponent;
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+
+use crate::accessibility::Accessibility;
+use crate::match_state::MatchPhase;
+use crate::quality::QualitySettings;
+use crate::theme::Theme;
+use crate::tuning::Tuning;
+use crate::{AppState, Ball, OutOfPlay, Player, ARENA_WIDTH};
+
+/// The edge of the window behind a goal glows in its defender's color while a ball closes in on
+/// that goal.
+pub struct EdgeGlowPlugin;
+
+impl Plugin for EdgeGlowPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_enter(AppState::InGame).with_system(spawn_edge_glow.system()),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::InGame).with_system(render_edge_glow.system()),
+        );
+    }
+}
+
+/// Pixels before the goal line where the glow starts.
+const GLOW_DISTANCE: f32 = 200.;
+/// Strips making up the gradient, UI nodes only come in a single color.
+const GLOW_STRIPS: usize = 8;
+const STRIP_WIDTH: f32 = 4.;
+/// Alpha of the outermost strip with the ball on the goal line at full speed.
+const MAX_ALPHA: f32 = 0.5;
+/// A ball at this many serve speeds closes in at full intensity.
+const FULL_CLOSING_SPEED: f32 = 2.;
+
+/// One strip of the glow, `0` being the outermost.
+struct GlowStrip(Player, usize);
+
+/// Full height nodes along the left and right edge of the window, in the UI so they stay on the
+/// screen's edges whatever the arena.
+fn spawn_edge_glow(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    theme: Res<Theme>,
+) {
+    for player in [Player::Left, Player::Right].iter() {
+        let position = match player {
+            Player::Left => Rect {
+                left: Val::Px(0.),
+                top: Val::Px(0.),
+                ..Default::default()
+            },
+            Player::Right => Rect {
+                right: Val::Px(0.),
+                top: Val::Px(0.),
+                ..Default::default()
+            },
+        };
+        let direction = match player {
+            Player::Left => FlexDirection::Row,
+            Player::Right => FlexDirection::RowReverse,
+        };
+
+        commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position,
+                    flex_direction: direction,
+                    size: Size::new(Val::Auto, Val::Percent(100.)),
+                    ..Default::default()
+                },
+                material: materials.add(Color::NONE.into()),
+                ..Default::default()
+            })
+            .with_children(|parent| {
+                for index in 0..GLOW_STRIPS {
+                    let mut color = theme.player_color(*player);
+                    color.set_a(0.);
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(STRIP_WIDTH), Val::Percent(100.)),
+                                ..Default::default()
+                            },
+                            material: materials.add(color.into()),
+                            ..Default::default()
+                        })
+                        .insert(GlowStrip(*player, index));
+                }
+            });
+    }
+}
+
+/// How strongly a ball at `x` moving at `velocity_x` physics units threatens `defender`'s goal,
+/// from 0 to 1.
+fn threat(defender: Player, x: f32, velocity_x: f32, serve_speed: f32) -> f32 {
+    let (distance, closing) = match defender {
+        Player::Left => (x, -velocity_x),
+        Player::Right => (ARENA_WIDTH - x, velocity_x),
+    };
+    if closing <= 0. || distance >= GLOW_DISTANCE {
+        return 0.;
+    }
+
+    let proximity = 1. - distance.max(0.) / GLOW_DISTANCE;
+    let speed = (closing / (serve_speed * FULL_CLOSING_SPEED)).min(1.);
+    proximity * (0.5 + 0.5 * speed)
+}
+
+/// Each edge follows the ball most threatening its goal. Off with reduced motion, in performance
+/// mode and once the match is over.
+fn render_edge_glow(
+    accessibility: Res<Accessibility>,
+    quality: Res<QualitySettings>,
+    phase: Res<MatchPhase>,
+    tuning: Res<Tuning>,
+    theme: Res<Theme>,
+    rigid_bodies: Res<RigidBodySet>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    balls: Query<(&Transform, &RigidBodyHandleComponent), (With<Ball>, Without<OutOfPlay>)>,
+    strips: Query<(&GlowStrip, &Handle<ColorMaterial>)>,
+) {
+    let enabled = !accessibility.reduced_motion && quality.glow && !phase.is_finished();
+
+    let intensity = |defender: Player| {
+        if !enabled {
+            return 0.;
+        }
+        balls
+            .iter()
+            .filter_map(|(transform, handle)| {
+                let rb = rigid_bodies.get(handle.handle())?;
+                Some(threat(
+                    defender,
+                    transform.translation.x,
+                    rb.linvel().x,
+                    tuning.serve_speed,
+                ))
+            })
+            .fold(0., f32::max)
+    };
+    let left = intensity(Player::Left);
+    let right = intensity(Player::Right);
+
+    for (strip, material) in strips.iter() {
+        let strength = match strip.0 {
+            Player::Left => left,
+            Player::Right => right,
+        };
+        let mut color = theme.player_color(strip.0);
+        color.set_a(strength * MAX_ALPHA * (1. - strip.1 as f32 / GLOW_STRIPS as f32));
+        if materials
+            .get(material)
+            .map_or(false, |material| material.color != color)
+        {
+            if let Some(material) = materials.get_mut(material) {
+                material.color = color;
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_rapier2d::physics::RigidBodyHandleComponent;
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+use bevy_rapier2d::rapier::na::Vector2;
+
+use crate::contacts::{BallContact, Surface};
+use crate::stats::MatchStats;
+use crate::tuning::Tuning;
+use crate::{AppState, Ball, GameLabel};
+
+/// English on the ball, a paddle moving as it hits the ball pushes it along. A paddle held still
+/// blocks instead and sends the ball back with the speed it came in with.
+pub struct EnglishPlugin;
+
+impl Plugin for EnglishPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<IncomingVelocities>().add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(
+                    carry_paddle_velocity
+                        .system()
+                        .after(GameLabel::Contacts)
+                        .before("record_hits")
+                        .before("catch_ball")
+                        .before("clamp_ball_speed"),
+                )
+                .with_system(remember_ball_velocities.system().after("clamp_ball_speed")),
+        );
+    }
+}
+
+/// Velocity of each ball going into the physics step, the contact events only have the one
+/// the solver bounced it off with.
+#[derive(Debug, Default)]
+struct IncomingVelocities(HashMap<Entity, Vec2>);
+
+/// How much of a hit is a block, 1 for a paddle moving slower than `block_speed` and 0 for one
+/// moving at `block_speed + block_blend` or faster. Eased in between, so a paddle just over the
+/// threshold hits almost like a still one.
+fn block_weight(tuning: &Tuning, paddle_speed: f32) -> f32 {
+    if tuning.block_blend <= 0. {
+        return if paddle_speed < tuning.block_speed {
+            1.
+        } else {
+            0.
+        };
+    }
+    let t = ((paddle_speed - tuning.block_speed) / tuning.block_blend)
+        .max(0.)
+        .min(1.);
+    1. - t * t * (3. - 2. * t)
+}
+
+/// Blends two returns after the solver bounced the ball, the speed cap is applied afterwards.
+/// A block keeps the bounced direction at `block_rebound` times the incoming speed, a push adds
+/// part of the paddle's velocity to the bounce.
+fn carry_paddle_velocity(
+    tuning: Res<Tuning>,
+    incoming: Res<IncomingVelocities>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    mut stats: ResMut<MatchStats>,
+    mut contacts: EventReader<BallContact>,
+    balls: Query<&RigidBodyHandleComponent, With<Ball>>,
+) {
+    for contact in contacts.iter() {
+        let player = match contact.surface {
+            Surface::Paddle(player) if contact.started => player,
+            _ => continue,
+        };
+
+        let rb = balls
+            .get(contact.ball)
+            .ok()
+            .and_then(|rigid_body_component| rigid_bodies.get_mut(rigid_body_component.handle()));
+        let rb = match rb {
+            Some(rb) => rb,
+            None => continue,
+        };
+
+        let bounced = Vec2::new(rb.linvel().x, rb.linvel().y);
+        let block = block_weight(&tuning, contact.other_velocity.length());
+        let incoming_speed = incoming
+            .0
+            .get(&contact.ball)
+            .map_or(bounced.length(), |velocity| velocity.length());
+
+        let blocked = if bounced.length_squared() > 0. {
+            bounced.normalize() * incoming_speed * tuning.block_rebound
+        } else {
+            Vec2::ZERO
+        };
+        let carry = contact.other_velocity * tuning.paddle_carry;
+        let linvel = blocked * block + (bounced + carry) * (1. - block);
+        rb.set_linvel(Vector2::new(linvel.x, linvel.y), true);
+
+        if block >= 0.5 {
+            *stats.blocks.entry(player).or_insert(0) += 1;
+        } else if carry.length() * (1. - block) >= tuning.pushed_shot_threshold {
+            *stats.pushed_shots.entry(player).or_insert(0) += 1;
+        }
+    }
+}
+
+fn remember_ball_velocities(
+    rigid_bodies: Res<RigidBodySet>,
+    mut incoming: ResMut<IncomingVelocities>,
+    balls: Query<(Entity, &RigidBodyHandleComponent), With<Ball>>,
+) {
+    incoming.0.clear();
+    for (entity, rigid_body_component) in balls.iter() {
+        if let Some(rb) = rigid_bodies.get(rigid_body_component.handle()) {
+            incoming
+                .0
+                .insert(entity, Vec2::new(rb.linvel().x, rb.linvel().y));
+        }
+    }
+}
@@ -0,0 +1,551 @@
+use bevy::prelude::*;
+use bevy_rapier2d::physics::{RapierConfiguration, RigidBodyHandleComponent};
+use bevy_rapier2d::rapier::dynamics::{RigidBodyBuilder, RigidBodySet};
+use bevy_rapier2d::rapier::geometry::{ColliderBuilder, InteractionGroups};
+use bevy_rapier2d::rapier::na::Vector2;
+
+use crate::catch::Caught;
+use crate::contacts::{BallContact, Surface};
+use crate::hazard::HAZARD_WIDTH;
+use crate::loading::GameAssets;
+use crate::match_state::{MatchPhase, MatchRules};
+use crate::physics::{physics_bundle, teleport_body};
+use crate::rng::GameRng;
+use crate::theme::Theme;
+use crate::tuning::Tuning;
+use crate::{
+    paddle_spawn_x, AppState, Ball, GameLabel, Paddle, PaddleRole, Paused, Player, ARENA_HEIGHT,
+    ARENA_MIDDLE, BALL_GROUP, GATE_GROUP,
+};
+
+pub struct GatesPlugin;
+
+impl Plugin for GatesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<GateCycle>()
+            .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(spawn_gates.system()))
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(
+                        teleport_balls
+                            .system()
+                            .after(GameLabel::Contacts)
+                            .before(GameLabel::GoalDetect),
+                    )
+                    .with_system(cycle_gates.system()),
+            );
+    }
+}
+
+/// A ball entering this sensor comes out of the linked one.
+pub struct GateLink(pub Entity);
+
+/// Set on a ball that just went through a gate, it can't take another until the timer is done.
+struct GateCooldown(Timer);
+
+/// Where the gates are in their cycle of moving to new spots.
+#[derive(Debug, Default)]
+struct GateCycle {
+    elapsed: f32,
+}
+
+const GATE_RADIUS: f32 = 30.;
+/// Seconds the gates stay in one spot, fading out and in included.
+const GATE_CYCLE_SECONDS: f32 = 20.;
+/// Seconds the gates take to fade out before they move, and to fade in after. They only work
+/// while fully shown.
+const GATE_FADE_SECONDS: f32 = 1.;
+const GATE_COOLDOWN_SECONDS: f32 = 0.3;
+/// Space kept between a gate and anything it must not overlap.
+const GATE_MARGIN: f32 = 20.;
+/// Half size of the square around the serve spot kept free of gates.
+const SERVE_CLEARANCE: f32 = 80.;
+/// Draws for a clear spot before a gate stays where it is.
+const PLACEMENT_TRIES: u32 = 20;
+
+/// Rectangle a gate must stay clear of, in pixels.
+struct Keepout {
+    center: Vec2,
+    half_size: Vec2,
+}
+
+impl Keepout {
+    fn new(center: Vec2, half_size: Vec2) -> Self {
+        Keepout { center, half_size }
+    }
+
+    fn column(x: f32, half_width: f32) -> Self {
+        Keepout::new(
+            Vec2::new(x, ARENA_HEIGHT / 2.),
+            Vec2::new(half_width, ARENA_HEIGHT / 2.),
+        )
+    }
+
+    fn overlaps(&self, position: Vec2) -> bool {
+        let nearest = position
+            .max(self.center - self.half_size)
+            .min(self.center + self.half_size);
+        nearest.distance(position) < GATE_RADIUS + GATE_MARGIN
+    }
+}
+
+/// The serve spot and the hazard's path, which never move.
+fn fixed_keepouts(rules: &MatchRules) -> Vec<Keepout> {
+    let mut keepouts = vec![Keepout::new(
+        Vec2::new(ARENA_MIDDLE, ARENA_HEIGHT / 2.),
+        Vec2::splat(SERVE_CLEARANCE),
+    )];
+    if rules.hazard {
+        keepouts.push(Keepout::column(ARENA_MIDDLE, HAZARD_WIDTH / 2.));
+    }
+    keepouts
+}
+
+/// Random spot for the gate in `player`'s half that overlaps none of `keepouts`.
+fn gate_spot(rng: &mut GameRng, player: Player, keepouts: &[Keepout]) -> Option<Vec2> {
+    let near = ARENA_MIDDLE * 0.25;
+    let far = ARENA_MIDDLE - GATE_RADIUS;
+    let low = GATE_RADIUS + GATE_MARGIN;
+    let high = ARENA_HEIGHT - GATE_RADIUS - GATE_MARGIN;
+
+    (0..PLACEMENT_TRIES)
+        .map(|_| {
+            let from_wall = near + rng.f32() * (far - near);
+            let x = match player {
+                Player::Left => from_wall,
+                Player::Right => ARENA_MIDDLE * 2. - from_wall,
+            };
+            Vec2::new(x, low + rng.f32() * (high - low))
+        })
+        .find(|spot| !keepouts.iter().any(|keepout| keepout.overlaps(*spot)))
+}
+
+/// Spot a gate takes when every draw failed, the paddles have not moved yet so it is clear.
+fn fallback_spot(player: Player) -> Vec2 {
+    let y = match player {
+        Player::Left => ARENA_HEIGHT / 4.,
+        Player::Right => ARENA_HEIGHT * 3. / 4.,
+    };
+    Vec2::new(
+        ARENA_MIDDLE - (ARENA_MIDDLE - paddle_spawn_x(player, PaddleRole::Front)) / 2.,
+        y,
+    )
+}
+
+fn spawn_gates(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut rng: ResMut<GameRng>,
+    mut cycle: ResMut<GateCycle>,
+    rules: Res<MatchRules>,
+    tuning: Res<Tuning>,
+    theme: Res<Theme>,
+    game_assets: Res<GameAssets>,
+    rapier_config: Res<RapierConfiguration>,
+) {
+    if !rules.teleport_gates {
+        return;
+    }
+
+    cycle.elapsed = 0.;
+
+    // Paddles are spawned in the same frame, keep clear of every column they start in
+    let mut keepouts = fixed_keepouts(&rules);
+    for player in [Player::Left, Player::Right].iter() {
+        for role in [PaddleRole::Solo, PaddleRole::Front].iter() {
+            keepouts.push(Keepout::column(
+                paddle_spawn_x(*player, *role),
+                tuning.paddle_width / 2.,
+            ));
+        }
+    }
+
+    let mut spawn_gate = |player: Player| {
+        let spot = gate_spot(&mut rng, player, &keepouts).unwrap_or_else(|| fallback_spot(player));
+        let body = RigidBodyBuilder::new_static()
+            .translation(spot.x / rapier_config.scale, spot.y / rapier_config.scale);
+
+        commands
+            .spawn()
+            .insert_bundle(SpriteBundle {
+                material: materials.add(ColorMaterial {
+                    color: theme.gate,
+                    texture: game_assets.ball_texture.clone(),
+                }),
+                sprite: Sprite::new(Vec2::splat(GATE_RADIUS * 2.)),
+                ..Default::default()
+            })
+            .insert_bundle(physics_bundle(
+                body,
+                ColliderBuilder::ball(GATE_RADIUS / rapier_config.scale)
+                    .sensor(true)
+                    .collision_groups(InteractionGroups::new(GATE_GROUP, BALL_GROUP)),
+            ))
+            .id()
+    };
+
+    let left = spawn_gate(Player::Left);
+    let right = spawn_gate(Player::Right);
+    commands.entity(left).insert(GateLink(right));
+    commands.entity(right).insert(GateLink(left));
+}
+
+/// Puts a ball entering a gate just outside the linked one. It leaves on the side opposite to
+/// where it went in, heading straight away from the exit gate at the speed it came in with.
+fn teleport_balls(
+    mut commands: Commands,
+    time: Res<Time>,
+    paused: Res<Paused>,
+    cycle: Res<GateCycle>,
+    tuning: Res<Tuning>,
+    rapier_config: Res<RapierConfiguration>,
+    mut contacts: EventReader<BallContact>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    mut balls: Query<
+        (
+            &RigidBodyHandleComponent,
+            &mut Transform,
+            Option<&mut GateCooldown>,
+        ),
+        (With<Ball>, Without<Caught>, Without<GateLink>),
+    >,
+    gates: Query<(&GateLink, &Transform)>,
+) {
+    if !paused.0 {
+        for (_, _, cooldown) in balls.iter_mut() {
+            if let Some(mut cooldown) = cooldown {
+                cooldown.0.tick(time.delta());
+            }
+        }
+    }
+
+    for contact in contacts.iter() {
+        if contact.surface != Surface::Gate || !contact.started || !cycle.is_open() {
+            continue;
+        }
+
+        let (rigid_body_component, mut transform, cooldown) = match balls.get_mut(contact.ball) {
+            Ok(ball) => ball,
+            Err(_) => continue,
+        };
+        if cooldown.map_or(false, |cooldown| !cooldown.0.finished()) {
+            continue;
+        }
+        let (entry, exit) = match gates.get(contact.other) {
+            Ok((link, entry)) => match gates.get(link.0) {
+                Ok((_, exit)) => (entry.translation.truncate(), exit.translation.truncate()),
+                Err(_) => continue,
+            },
+            Err(_) => continue,
+        };
+        let rb = match rigid_bodies.get_mut(rigid_body_component.handle()) {
+            Some(rb) => rb,
+            None => continue,
+        };
+
+        let velocity = Vec2::new(rb.linvel().x, rb.linvel().y);
+        let direction = if contact.position != entry {
+            (entry - contact.position).normalize()
+        } else if velocity.length_squared() > 0. {
+            velocity.normalize()
+        } else {
+            continue;
+        };
+
+        let exit_position = exit + direction * (GATE_RADIUS + tuning.ball_size / 2. + 1.);
+        let mut position = *rb.position();
+        position.translation.vector =
+            Vector2::new(exit_position.x, exit_position.y) / rapier_config.scale;
+        teleport_body(rb, &mut transform, position, rapier_config.scale);
+        let exit_velocity = direction * velocity.length();
+        rb.set_linvel(Vector2::new(exit_velocity.x, exit_velocity.y), true);
+
+        commands
+            .entity(contact.ball)
+            .insert(GateCooldown(Timer::from_seconds(
+                GATE_COOLDOWN_SECONDS,
+                false,
+            )));
+    }
+}
+
+impl GateCycle {
+    /// How much the gates show, they fade out before moving and fade in after.
+    fn alpha(&self) -> f32 {
+        let fade_in = self.elapsed / GATE_FADE_SECONDS;
+        let fade_out = (GATE_CYCLE_SECONDS - self.elapsed) / GATE_FADE_SECONDS;
+        fade_in.min(fade_out).max(0.).min(1.)
+    }
+
+    fn is_open(&self) -> bool {
+        self.alpha() >= 1.
+    }
+}
+
+/// Moves the gates to new spots every `GATE_CYCLE_SECONDS`, clear of where the paddles are then.
+/// The clock stands still while paused and after the match, a rematch starts it over.
+fn cycle_gates(
+    time: Res<Time>,
+    paused: Res<Paused>,
+    phase: Res<MatchPhase>,
+    rules: Res<MatchRules>,
+    theme: Res<Theme>,
+    rapier_config: Res<RapierConfiguration>,
+    mut rng: ResMut<GameRng>,
+    mut cycle: ResMut<GateCycle>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    gates: Query<
+        (
+            &mut Transform,
+            &RigidBodyHandleComponent,
+            &Handle<ColorMaterial>,
+        ),
+        With<GateLink>,
+    >,
+    paddles: Query<(&Transform, &Sprite), (With<Paddle>, Without<GateLink>)>,
+) {
+    if phase.is_changed() && *phase == MatchPhase::Regular {
+        cycle.elapsed = 0.;
+    }
+    if !paused.0 && !phase.is_finished() {
+        cycle.elapsed += time.delta_seconds();
+    }
+
+    if cycle.elapsed >= GATE_CYCLE_SECONDS {
+        cycle.elapsed = 0.;
+
+        let mut keepouts = fixed_keepouts(&rules);
+        for (transform, sprite) in paddles.iter() {
+            // Rotated paddles reach as far as their longest side
+            let reach = sprite.size.max_element() / 2.;
+            keepouts.push(Keepout::new(
+                transform.translation.truncate(),
+                Vec2::splat(reach),
+            ));
+        }
+
+        for (mut transform, rigid_body_component, _) in gates.iter_mut() {
+            let player = if transform.translation.x < ARENA_MIDDLE {
+                Player::Left
+            } else {
+                Player::Right
+            };
+            let spot = match gate_spot(&mut rng, player, &keepouts) {
+                Some(spot) => spot,
+                None => continue,
+            };
+            if let Some(rb) = rigid_bodies.get_mut(rigid_body_component.handle()) {
+                let mut position = *rb.position();
+                position.translation.vector = Vector2::new(spot.x, spot.y) / rapier_config.scale;
+                teleport_body(rb, &mut transform, position, rapier_config.scale);
+            }
+        }
+    }
+
+    let mut color = theme.gate;
+    color.set_a(theme.gate.a() * cycle.alpha());
+    for (_, _, material) in gates.iter() {
+        if let Some(material) = materials.get_mut(material) {
+            if material.color != color {
+                material.color = color;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::app::Events;
+    use bevy_rapier2d::rapier::dynamics::RigidBodyHandle;
+
+    fn in_half(spot: Vec2, player: Player) -> bool {
+        match player {
+            Player::Left => spot.x < ARENA_MIDDLE,
+            Player::Right => spot.x > ARENA_MIDDLE,
+        }
+    }
+
+    #[test]
+    fn keepouts_count_the_gate_and_its_margin() {
+        let keepout = Keepout::new(Vec2::new(100., 100.), Vec2::new(10., 20.));
+        let reach = GATE_RADIUS + GATE_MARGIN;
+
+        assert!(keepout.overlaps(Vec2::new(100., 100.)));
+        assert!(keepout.overlaps(Vec2::new(110. + reach - 1., 100.)));
+        assert!(!keepout.overlaps(Vec2::new(110. + reach, 100.)));
+        assert!(keepout.overlaps(Vec2::new(100., 80. - reach + 1.)));
+        assert!(!keepout.overlaps(Vec2::new(100., 80. - reach - 1.)));
+        // Off a corner the distance is measured to the corner
+        let diagonal = reach / 2f32.sqrt();
+        assert!(!keepout.overlaps(Vec2::new(110. + diagonal + 1., 120. + diagonal + 1.)));
+    }
+
+    #[test]
+    fn spots_stay_in_their_half_and_clear_of_keepouts() {
+        for hazard in [false, true].iter() {
+            let rules = MatchRules {
+                hazard: *hazard,
+                ..Default::default()
+            };
+            let keepouts = fixed_keepouts(&rules);
+            let mut rng = GameRng::new(3);
+            for _ in 0..200 {
+                for player in [Player::Left, Player::Right].iter() {
+                    let spot = gate_spot(&mut rng, *player, &keepouts).unwrap();
+                    assert!(in_half(spot, *player), "{:?} {:?}", player, spot);
+                    assert!(spot.y >= GATE_RADIUS && spot.y <= ARENA_HEIGHT - GATE_RADIUS);
+                    assert!(keepouts.iter().all(|keepout| !keepout.overlaps(spot)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn spots_follow_the_seed() {
+        let keepouts = fixed_keepouts(&MatchRules::default());
+        let draw = |seed| {
+            let mut rng = GameRng::new(seed);
+            (0..10)
+                .map(|_| gate_spot(&mut rng, Player::Left, &keepouts))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draw(11), draw(11));
+        assert_ne!(draw(11), draw(12));
+    }
+
+    #[test]
+    fn a_blocked_half_has_no_spot() {
+        let keepouts = vec![Keepout::column(ARENA_MIDDLE / 2., ARENA_MIDDLE / 2.)];
+        let mut rng = GameRng::new(5);
+        assert_eq!(gate_spot(&mut rng, Player::Left, &keepouts), None);
+        assert!(gate_spot(&mut rng, Player::Right, &keepouts).is_some());
+    }
+
+    #[test]
+    fn fallback_spots_are_clear() {
+        let rules = MatchRules {
+            hazard: true,
+            ..Default::default()
+        };
+        let keepouts = fixed_keepouts(&rules);
+        for player in [Player::Left, Player::Right].iter() {
+            let spot = fallback_spot(*player);
+            assert!(in_half(spot, *player), "{:?} {:?}", player, spot);
+            assert!(keepouts.iter().all(|keepout| !keepout.overlaps(spot)));
+        }
+    }
+
+    #[test]
+    fn gates_only_work_while_fully_shown() {
+        let at = |elapsed| GateCycle { elapsed };
+
+        assert_eq!(at(0.).alpha(), 0.);
+        assert!(!at(0.).is_open());
+        assert_eq!(at(GATE_FADE_SECONDS / 2.).alpha(), 0.5);
+        assert!(!at(GATE_FADE_SECONDS / 2.).is_open());
+        assert_eq!(at(GATE_FADE_SECONDS).alpha(), 1.);
+        assert!(at(GATE_CYCLE_SECONDS / 2.).is_open());
+        assert_eq!(at(GATE_CYCLE_SECONDS - GATE_FADE_SECONDS / 2.).alpha(), 0.5);
+        assert!(!at(GATE_CYCLE_SECONDS - GATE_FADE_SECONDS / 2.).is_open());
+        assert_eq!(at(GATE_CYCLE_SECONDS).alpha(), 0.);
+    }
+
+    fn gate_world(elapsed: f32) -> (World, Entity, RigidBodyHandle, Entity, Entity) {
+        let mut world = World::default();
+        world.insert_resource(Time::default());
+        world.insert_resource(Paused(false));
+        world.insert_resource(GateCycle { elapsed });
+        world.insert_resource(Tuning::default());
+        world.insert_resource(RapierConfiguration::default());
+        world.insert_resource(Events::<BallContact>::default());
+
+        let mut bodies = RigidBodySet::new();
+        let handle = bodies.insert(RigidBodyBuilder::new_dynamic().linvel(3., 4.).build());
+        world.insert_resource(bodies);
+
+        let ball = world
+            .spawn()
+            .insert_bundle((
+                Ball(10.),
+                RigidBodyHandleComponent::from(handle),
+                Transform::default(),
+            ))
+            .id();
+        let left = world.spawn().id();
+        let right = world.spawn().id();
+        world
+            .entity_mut(left)
+            .insert_bundle((GateLink(right), Transform::from_xyz(200., 300., 0.)));
+        world
+            .entity_mut(right)
+            .insert_bundle((GateLink(left), Transform::from_xyz(1000., 100., 0.)));
+        (world, ball, handle, left, right)
+    }
+
+    fn enter(world: &mut World, ball: Entity, gate: Entity, position: Vec2) {
+        world
+            .get_resource_mut::<Events<BallContact>>()
+            .unwrap()
+            .send(BallContact {
+                ball,
+                other: gate,
+                surface: Surface::Gate,
+                started: true,
+                position,
+                normal: Vec2::ZERO,
+                ball_velocity: Vec2::ZERO,
+                other_velocity: Vec2::ZERO,
+            });
+        SystemStage::single(teleport_balls.system()).run(world);
+    }
+
+    #[test]
+    fn balls_leave_the_linked_gate_the_way_they_entered() {
+        let (mut world, ball, handle, left, _) = gate_world(GATE_CYCLE_SECONDS / 2.);
+        let scale = world.get_resource::<RapierConfiguration>().unwrap().scale;
+
+        // Entering from straight below the gate sends the ball up out of the other one
+        enter(&mut world, ball, left, Vec2::new(200., 260.));
+
+        let reach = GATE_RADIUS + Tuning::default().ball_size / 2. + 1.;
+        let expected = Vec2::new(1000., 100. + reach);
+        let transform = world.get::<Transform>(ball).unwrap();
+        assert!(transform.translation.truncate().distance(expected) < 1e-3);
+        let rb = world
+            .get_resource::<RigidBodySet>()
+            .unwrap()
+            .get(handle)
+            .unwrap();
+        let position = rb.position().translation.vector * scale;
+        assert!(Vec2::new(position.x, position.y).distance(expected) < 1e-3);
+        let velocity = Vec2::new(rb.linvel().x, rb.linvel().y);
+        assert!(velocity.distance(Vec2::new(0., 5.)) < 1e-4);
+        assert!(world.get::<GateCooldown>(ball).is_some());
+    }
+
+    #[test]
+    fn the_cooldown_stops_a_bounce_back() {
+        let (mut world, ball, _, left, right) = gate_world(GATE_CYCLE_SECONDS / 2.);
+        enter(&mut world, ball, left, Vec2::new(200., 260.));
+        let exited = world.get::<Transform>(ball).unwrap().translation;
+
+        enter(&mut world, ball, right, exited.truncate());
+
+        assert_eq!(world.get::<Transform>(ball).unwrap().translation, exited);
+    }
+
+    #[test]
+    fn fading_gates_let_balls_through() {
+        let (mut world, ball, _, left, _) = gate_world(GATE_FADE_SECONDS / 2.);
+
+        enter(&mut world, ball, left, Vec2::new(200., 260.));
+
+        assert_eq!(
+            world.get::<Transform>(ball).unwrap().translation,
+            Vec3::ZERO
+        );
+        assert!(world.get::<GateCooldown>(ball).is_none());
+    }
+}
@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+use bevy_rapier2d::physics::{RapierConfiguration, RigidBodyHandleComponent};
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+use bevy_rapier2d::rapier::na::Vector2;
+
+use crate::catch::Caught;
+use crate::match_state::{MatchPhase, MatchRules};
+use crate::tuning::Tuning;
+use crate::{AppState, Ball, GameLabel, Paused, Player, ARENA_HEIGHT, ARENA_MIDDLE, PHYSICS_SCALE};
+
+/// The left half pulls the ball down and the right half pulls it up.
+pub struct GravityZonesPlugin;
+
+impl Plugin for GravityZonesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame).with_system(
+                pull_balls
+                    .system()
+                    .after(GameLabel::Contacts)
+                    .before("clamp_ball_speed"),
+            ),
+        );
+    }
+}
+
+/// Vertical pull on a ball at `x` in pixels, in physics units per second squared. Up is positive.
+pub fn zone_gravity(x: f32, tuning: &Tuning) -> f32 {
+    if x < ARENA_MIDDLE {
+        -tuning.zone_gravity_left
+    } else {
+        tuning.zone_gravity_right
+    }
+}
+
+/// Pull in the half `player` defends.
+pub fn half_gravity(player: Player, tuning: &Tuning) -> f32 {
+    match player {
+        Player::Left => -tuning.zone_gravity_left,
+        Player::Right => tuning.zone_gravity_right,
+    }
+}
+
+/// Angle a serve toward `player` is turned against the pull of their half, so that it crosses
+/// the half at about the height it would have without gravity.
+pub fn serve_tilt(player: Player, speed: f32, tuning: &Tuning) -> f32 {
+    if speed <= 0. {
+        return 0.;
+    }
+    let seconds = ARENA_MIDDLE / PHYSICS_SCALE / speed;
+    (half_gravity(player, tuning) * seconds / 2. / speed).atan()
+}
+
+/// Height in pixels at which a ball at `position` moving at `velocity`, both in pixels, reaches
+/// `target_x`. Follows the curve through both halves but not the bounces off the walls, so the
+/// computer player doesn't chase where the ball is right now.
+pub fn predicted_y(
+    position: Vec2,
+    velocity: Vec2,
+    target_x: f32,
+    tuning: &Tuning,
+    scale: f32,
+) -> f32 {
+    if velocity.x == 0. {
+        return position.y;
+    }
+
+    let legs = if (position.x < ARENA_MIDDLE) != (target_x < ARENA_MIDDLE) {
+        vec![ARENA_MIDDLE, target_x]
+    } else {
+        vec![target_x]
+    };
+
+    let (mut x, mut y, mut velocity_y) = (position.x, position.y, velocity.y);
+    for end in legs {
+        let seconds = (end - x) / velocity.x;
+        if seconds <= 0. {
+            continue;
+        }
+        let gravity = zone_gravity((x + end) / 2., tuning) * scale;
+        y += velocity_y * seconds + gravity * seconds * seconds / 2.;
+        velocity_y += gravity * seconds;
+        x = end;
+    }
+
+    y.max(0.).min(ARENA_HEIGHT)
+}
+
+/// The world's gravity is off, the pull is added to each ball in play before the physics step.
+/// Paddles are moved by setting their velocity every frame and are left alone.
+fn pull_balls(
+    time: Res<Time>,
+    rules: Res<MatchRules>,
+    tuning: Res<Tuning>,
+    paused: Res<Paused>,
+    phase: Res<MatchPhase>,
+    rapier_config: Res<RapierConfiguration>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    balls: Query<&RigidBodyHandleComponent, (With<Ball>, Without<Caught>)>,
+) {
+    if !rules.gravity_zones || paused.0 || phase.is_finished() {
+        return;
+    }
+
+    for rigid_body_component in balls.iter() {
+        let rb = match rigid_bodies.get_mut(rigid_body_component.handle()) {
+            Some(rb) => rb,
+            None => continue,
+        };
+        // A ball at rest is waiting to be served
+        if rb.linvel().magnitude_squared() == 0. {
+            continue;
+        }
+
+        let x = rb.position().translation.x * rapier_config.scale;
+        let pull = zone_gravity(x, &tuning) * time.delta_seconds();
+        let linvel = *rb.linvel() + Vector2::new(0., pull);
+        rb.set_linvel(linvel, true);
+    }
+}
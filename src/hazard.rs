@@ -0,0 +1,158 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rapier2d::physics::{RapierConfiguration, RigidBodyHandleComponent};
+use bevy_rapier2d::rapier::dynamics::{IntegrationParameters, RigidBodyBuilder, RigidBodySet};
+use bevy_rapier2d::rapier::geometry::{ColliderBuilder, InteractionGroups};
+use bevy_rapier2d::{na::Isometry2, rapier::na::Vector2};
+
+use crate::contacts::{BallContact, Surface};
+use crate::match_state::{MatchPhase, MatchRules};
+use crate::physics::physics_bundle;
+use crate::theme::Theme;
+use crate::{AppState, GameLabel, Paused, ARENA_HEIGHT, ARENA_MIDDLE, BALL_GROUP, HAZARD_GROUP};
+
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<HazardClock>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame).with_system(spawn_hazard.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(sweep_hazard.system().before(GameLabel::Contacts))
+                    .with_system(flash_hazard.system().after(GameLabel::Contacts)),
+            );
+    }
+}
+
+/// Obstacle sweeping up and down the center line, only the ball collides with it.
+pub struct Hazard {
+    flash: Timer,
+}
+
+/// Simulated seconds the hazard has been moving, advanced by the physics step so its path only
+/// depends on how many steps ran.
+#[derive(Debug, Default)]
+struct HazardClock {
+    elapsed: f32,
+}
+
+pub const HAZARD_WIDTH: f32 = 20.;
+const HAZARD_HEIGHT: f32 = 90.;
+/// Seconds for one full sweep down and back up.
+const HAZARD_PERIOD: f32 = 4.;
+/// Distance kept from the walls at the turning points.
+const HAZARD_MARGIN: f32 = 40.;
+const FLASH_SECONDS: f32 = 0.25;
+
+/// Height of the hazard center after `elapsed` seconds. It starts at the top so it is clear of
+/// the serve spot.
+fn hazard_y(elapsed: f32) -> f32 {
+    let amplitude = (ARENA_HEIGHT - HAZARD_HEIGHT) / 2. - HAZARD_MARGIN;
+    let phase = elapsed / HAZARD_PERIOD * std::f32::consts::PI * 2.;
+    ARENA_HEIGHT / 2. + amplitude * phase.cos()
+}
+
+fn spawn_hazard(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    rapier_config: Res<RapierConfiguration>,
+    rules: Res<MatchRules>,
+    theme: Res<Theme>,
+    mut clock: ResMut<HazardClock>,
+) {
+    if !rules.hazard {
+        return;
+    }
+
+    clock.elapsed = 0.;
+
+    // Start with the flash spent so the hazard spawns in its resting color
+    let mut flash = Timer::from_seconds(FLASH_SECONDS, false);
+    flash.tick(Duration::from_secs_f32(FLASH_SECONDS));
+
+    let body = RigidBodyBuilder::new_kinematic().translation(
+        ARENA_MIDDLE / rapier_config.scale,
+        hazard_y(0.) / rapier_config.scale,
+    );
+
+    commands
+        .spawn()
+        .insert_bundle(SpriteBundle {
+            material: materials.add(theme.hazard.into()),
+            sprite: Sprite::new(Vec2::new(HAZARD_WIDTH, HAZARD_HEIGHT)),
+            ..Default::default()
+        })
+        .insert_bundle(physics_bundle(
+            body,
+            ColliderBuilder::cuboid(
+                HAZARD_WIDTH / 2. / rapier_config.scale,
+                HAZARD_HEIGHT / 2. / rapier_config.scale,
+            )
+            .restitution(1.0)
+            .collision_groups(InteractionGroups::new(HAZARD_GROUP, BALL_GROUP)),
+        ))
+        .insert(Hazard { flash });
+}
+
+fn sweep_hazard(
+    paused: Res<Paused>,
+    phase: Res<MatchPhase>,
+    rapier_config: Res<RapierConfiguration>,
+    integration_parameters: Res<IntegrationParameters>,
+    mut clock: ResMut<HazardClock>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    hazards: Query<&RigidBodyHandleComponent, With<Hazard>>,
+) {
+    // A rematch starts the sweep over from the top
+    if phase.is_changed() && *phase == MatchPhase::Regular {
+        clock.elapsed = 0.;
+    }
+
+    // Physics doesn't step while paused or after the match, so neither does the hazard
+    if paused.0 || phase.is_finished() {
+        return;
+    }
+
+    clock.elapsed += integration_parameters.dt();
+    let y = hazard_y(clock.elapsed);
+
+    for rigid_body_component in hazards.iter() {
+        if let Some(rb) = rigid_bodies.get_mut(rigid_body_component.handle()) {
+            let position = Isometry2::new(Vector2::new(ARENA_MIDDLE, y) / rapier_config.scale, 0.);
+            rb.set_next_kinematic_position(position);
+        }
+    }
+}
+
+fn flash_hazard(
+    time: Res<Time>,
+    theme: Res<Theme>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut contacts: EventReader<BallContact>,
+    mut hazards: Query<(&mut Hazard, &Handle<ColorMaterial>)>,
+) {
+    for contact in contacts.iter() {
+        if contact.surface != Surface::Hazard || !contact.started {
+            continue;
+        }
+        if let Ok((mut hazard, _)) = hazards.get_mut(contact.other) {
+            hazard.flash.reset();
+        }
+    }
+
+    for (mut hazard, material) in hazards.iter_mut() {
+        hazard.flash.tick(time.delta());
+
+        if let Some(material) = materials.get_mut(material) {
+            material.color = if hazard.flash.finished() {
+                theme.hazard
+            } else {
+                theme.hazard_flash
+            };
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::VecDeque;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::callout::Callout;
+use crate::hud;
+use crate::match_state::MatchPhase;
+use crate::persistence::SaveData;
+use crate::theme::{Theme, PALETTE};
+use crate::{AppState, GameLabel, Player, UiFont};
+
+pub struct HillPlugin;
+
+impl Plugin for HillPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(SystemSet::on_enter(AppState::InGame).with_system(spawn_names.system()))
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(
+                        rotate_loser
+                            .system()
+                            .label(GameLabel::GoalPresent)
+                            .after(GameLabel::GoalApply),
+                    )
+                    .with_system(next_challenger.system())
+                    .with_system(render_names.system()),
+            )
+            // Last, so it sees the exit sent by the pause menu or the window closing that frame
+            .add_system_to_stage(CoreStage::Last, dump_leaderboard.system());
+    }
+}
+
+/// Every king of the hill match is first to this many goals.
+pub const HILL_SCORE_LIMIT: u32 = 5;
+pub const MAX_HILL_PLAYERS: usize = 10;
+/// Step through `PALETTE` between players, so players next to each other in the queue don't get
+/// neighbouring hues.
+const COLOR_STRIDE: usize = 5;
+
+/// One player of the session and how they did.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Contender {
+    pub name: String,
+    /// Matches won in a row on the current stay.
+    pub streak: u32,
+    pub best_streak: u32,
+    pub wins: u32,
+    pub played: u32,
+}
+
+/// King of the hill between named players sharing the keyboard. The winner stays on, the loser
+/// goes to the back of the queue and the next in line takes over their paddle. Empty when no
+/// session is played.
+#[derive(Debug, Default)]
+pub struct Queue {
+    contenders: Vec<Contender>,
+    /// Indices into `contenders` of the players on the left and right paddle.
+    left: usize,
+    right: usize,
+    /// Players waiting their turn, next in line first.
+    waiting: VecDeque<usize>,
+    /// Keep the leaderboard in the save file when the session ends.
+    keep_leaderboard: bool,
+}
+
+impl Queue {
+    /// The first two names play the first match, the rest wait in the order given.
+    pub fn new(names: Vec<String>, keep_leaderboard: bool) -> Self {
+        let contenders: Vec<Contender> = names
+            .into_iter()
+            .map(|name| Contender {
+                name,
+                streak: 0,
+                best_streak: 0,
+                wins: 0,
+                played: 0,
+            })
+            .collect();
+        Queue {
+            waiting: (2..contenders.len()).collect(),
+            contenders,
+            left: 0,
+            right: 1,
+            keep_leaderboard,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.contenders.is_empty()
+    }
+
+    fn index(&self, player: Player) -> usize {
+        match player {
+            Player::Left => self.left,
+            Player::Right => self.right,
+        }
+    }
+
+    pub fn name(&self, player: Player) -> Option<&str> {
+        self.contenders
+            .get(self.index(player))
+            .map(|contender| contender.name.as_str())
+    }
+
+    /// Color of the player on `player`'s paddle, it goes with them when they come back on.
+    pub fn color(&self, player: Player) -> Option<Color> {
+        if !self.is_active() {
+            return None;
+        }
+        Some(PALETTE[self.index(player) * COLOR_STRIDE % PALETTE.len()])
+    }
+
+    /// Books a finished match and sends the loser to the back of the queue.
+    fn rotate(&mut self, winner: Player) {
+        let (winning, losing) = (self.index(winner), self.index(winner.opponent()));
+        let champion = &mut self.contenders[winning];
+        champion.streak += 1;
+        champion.best_streak = champion.best_streak.max(champion.streak);
+        champion.wins += 1;
+        champion.played += 1;
+        let loser = &mut self.contenders[losing];
+        loser.streak = 0;
+        loser.played += 1;
+
+        self.waiting.push_back(losing);
+        let challenger = self.waiting.pop_front().unwrap_or(losing);
+        match winner {
+            Player::Left => self.right = challenger,
+            Player::Right => self.left = challenger,
+        }
+    }
+
+    /// Sorted by longest streak, then wins.
+    pub fn leaderboard(&self) -> Vec<Contender> {
+        let mut board = self.contenders.clone();
+        board.sort_by(|a, b| b.best_streak.cmp(&a.best_streak).then(b.wins.cmp(&a.wins)));
+        board
+    }
+
+    fn leaderboard_lines(&self) -> Vec<String> {
+        self.leaderboard()
+            .iter()
+            .enumerate()
+            .map(|(rank, contender)| {
+                format!(
+                    "{}. {}  best streak {}  wins {}  played {}",
+                    rank + 1,
+                    contender.name,
+                    contender.best_streak,
+                    contender.wins,
+                    contender.played
+                )
+            })
+            .collect()
+    }
+}
+
+/// Name of the player on each paddle, above their score.
+struct NameLabel(Player);
+
+/// Top of the names as a fraction of the window height.
+const NAME_TOP: f32 = 1. / 3.;
+
+/// Who is up next and the leaderboard, shown between matches.
+struct BetweenMatchesText;
+
+fn spawn_names(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    font: Res<UiFont>,
+    theme: Res<Theme>,
+    queue: Res<Queue>,
+) {
+    if !queue.is_active() {
+        return;
+    }
+
+    for player in [Player::Left, Player::Right].iter() {
+        commands
+            .spawn_bundle(NodeBundle {
+                style: hud::half_row(*player, NAME_TOP),
+                material: materials.add(Color::NONE.into()),
+                ..Default::default()
+            })
+            .with_children(|parent| {
+                parent
+                    .spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            queue.name(*player).unwrap_or_default().to_string(),
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 32.0,
+                                color: theme.player_color(*player),
+                            },
+                            Default::default(),
+                        ),
+                        ..Default::default()
+                    })
+                    .insert(NameLabel(*player));
+            });
+    }
+}
+
+fn render_names(queue: Res<Queue>, theme: Res<Theme>, mut labels: Query<(&NameLabel, &mut Text)>) {
+    if !queue.is_changed() && !theme.is_changed() {
+        return;
+    }
+
+    for (label, mut text) in labels.iter_mut() {
+        let section = &mut text.sections[0];
+        section.value = queue.name(label.0).unwrap_or_default().to_string();
+        section.style.color = theme.player_color(label.0);
+    }
+}
+
+/// The challenger takes the loser's paddle, with their name and color, as soon as the match is
+/// over. The screen between matches says who stays on and who is up.
+fn rotate_loser(
+    mut commands: Commands,
+    phase: Res<MatchPhase>,
+    font: Res<UiFont>,
+    mut queue: ResMut<Queue>,
+) {
+    let winner = match *phase {
+        MatchPhase::Finished(winner) if phase.is_changed() && queue.is_active() => winner,
+        _ => return,
+    };
+
+    let loser = queue
+        .name(winner.opponent())
+        .unwrap_or_default()
+        .to_string();
+    queue.rotate(winner);
+    let champion = &queue.contenders[queue.index(winner)];
+    let challenger = queue.name(winner.opponent()).unwrap_or_default();
+
+    let mut lines = vec![
+        format!("{} stays on, {} in a row", champion.name, champion.streak),
+        format!("Up next: {} takes over from {}", challenger, loser),
+        String::new(),
+    ];
+    lines.extend(queue.leaderboard_lines());
+    lines.push(String::new());
+    lines.push("Press Enter for the next match".to_string());
+
+    commands
+        .spawn_bundle(TextBundle {
+            text: Text::with_section(
+                lines.join("\n"),
+                TextStyle {
+                    font: font.0.clone(),
+                    font_size: 24.0,
+                    color: Color::rgb(1.0, 1.0, 1.0),
+                },
+                Default::default(),
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: hud::percent(1. / 6.),
+                    left: hud::percent(0.3),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(BetweenMatchesText);
+}
+
+/// The rematch key starts the next match, the screen is only up between matches.
+fn next_challenger(
+    mut commands: Commands,
+    phase: Res<MatchPhase>,
+    queue: Res<Queue>,
+    mut callouts: EventWriter<Callout>,
+    texts: Query<Entity, With<BetweenMatchesText>>,
+) {
+    if phase.is_finished() || texts.iter().next().is_none() {
+        return;
+    }
+
+    for entity in texts.iter() {
+        commands.entity(entity).despawn();
+    }
+    if let (Some(left), Some(right)) = (queue.name(Player::Left), queue.name(Player::Right)) {
+        callouts.send(Callout(format!("{} vs {}", left, right)));
+    }
+}
+
+/// Leaving the game ends the session, its leaderboard goes to the log and, when asked for, to the
+/// save file.
+fn dump_leaderboard(
+    queue: Res<Queue>,
+    mut save: ResMut<SaveData>,
+    mut exits: EventReader<AppExit>,
+) {
+    if exits.iter().next().is_none() || !queue.is_active() {
+        return;
+    }
+
+    for line in queue.leaderboard_lines() {
+        info!("Leaderboard: {}", line);
+    }
+    if queue.keep_leaderboard {
+        save.leaderboard = Some(queue.leaderboard());
+        save.save();
+    }
+}
@@ -0,0 +1,117 @@
+use bevy::prelude::*;
+
+use crate::Player;
+
+/// Window height the HUD's font sizes are picked for, they scale with the actual window.
+pub const REFERENCE_HEIGHT: f32 = 600.;
+
+/// Fraction of the window's width or height as a UI position.
+pub fn percent(fraction: f32) -> Val {
+    Val::Percent(fraction * 100.)
+}
+
+/// Left edge of `player`'s half of the window, as a fraction of its width.
+pub fn half_left(player: Player) -> f32 {
+    match player {
+        Player::Left => 0.,
+        Player::Right => 0.5,
+    }
+}
+
+/// Absolute node across the window `top` down from its top edge, children are centered in it.
+/// Everything is a fraction of the window, so the HUD stays anchored to the screen whatever the
+/// window or arena size.
+pub fn row(left: f32, width: f32, top: f32) -> Style {
+    Style {
+        position_type: PositionType::Absolute,
+        position: Rect {
+            left: percent(left),
+            top: percent(top),
+            ..Default::default()
+        },
+        size: Size::new(percent(width), Val::Auto),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..Default::default()
+    }
+}
+
+/// Row spanning the whole window.
+pub fn full_row(top: f32) -> Style {
+    row(0., 1., top)
+}
+
+/// Row spanning `player`'s half of the window.
+pub fn half_row(player: Player, top: f32) -> Style {
+    row(half_left(player), 0.5, top)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOWS: [(f32, f32); 3] = [(1000., 600.), (1920., 1080.), (640., 900.)];
+
+    fn pixels(val: Val, extent: f32) -> f32 {
+        match val {
+            Val::Percent(percent) => percent / 100. * extent,
+            Val::Px(px) => px,
+            other => panic!("{:?} isn't anchored", other),
+        }
+    }
+
+    /// Left edge, top edge and width in pixels of a row in a `window` sized window.
+    fn anchor(style: &Style, window: (f32, f32)) -> (f32, f32, f32) {
+        (
+            pixels(style.position.left, window.0),
+            pixels(style.position.top, window.1),
+            pixels(style.size.width, window.0),
+        )
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    #[test]
+    fn full_row_spans_every_window() {
+        for window in WINDOWS.iter() {
+            let (left, top, width) = anchor(&full_row(0.1), *window);
+            assert!(close(left, 0.), "{:?}", window);
+            assert!(close(width, window.0), "{:?}", window);
+            assert!(close(top, window.1 * 0.1), "{:?}", window);
+        }
+    }
+
+    #[test]
+    fn half_rows_follow_their_half_when_resized() {
+        for window in WINDOWS.iter() {
+            let (left, top, width) = anchor(&half_row(Player::Left, 0.85), *window);
+            assert!(
+                close(left, 0.) && close(width, window.0 / 2.),
+                "{:?}",
+                window
+            );
+            assert!(close(top, window.1 * 0.85), "{:?}", window);
+
+            let (left, _, width) = anchor(&half_row(Player::Right, 0.85), *window);
+            assert!(close(left, window.0 / 2.), "{:?}", window);
+            assert!(close(left + width, window.0), "{:?}", window);
+        }
+    }
+
+    #[test]
+    fn anchors_are_the_same_fraction_of_any_window() {
+        let style = row(0.25, 0.5, 0.4);
+        let (small, large) = ((800., 600.), (1600., 1200.));
+        let (left, top, width) = anchor(&style, small);
+        let (left_large, top_large, width_large) = anchor(&style, large);
+
+        assert!(close(left_large, left * 2.));
+        assert!(close(top_large, top * 2.));
+        assert!(close(width_large, width * 2.));
+        // Centered in an 800 wide window
+        assert!(close(left + width / 2., 400.));
+        assert_eq!(style.position_type, PositionType::Absolute);
+    }
+}
@@ -0,0 +1,238 @@
+use bevy::prelude::*;
+
+use crate::bindings::{key_name, KeyBindings};
+use crate::callout::Callout;
+use crate::hud;
+use crate::match_state::{MatchPhase, MatchRules};
+use crate::tuning::Tuning;
+use crate::{AppState, GameLabel, PaddleRole, Paused, Player, Score, UiFont};
+
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<HandToCpu>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame).with_system(spawn_cpu_badges.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(keyboard_intent.system().label(GameLabel::Input))
+                    .with_system(idle_takeover.system().after(GameLabel::Input))
+                    .with_system(hand_to_cpu.system().after(GameLabel::Input))
+                    .with_system(join_paddles.system().after(GameLabel::Input))
+                    .with_system(render_cpu_badges.system()),
+            );
+    }
+}
+
+/// What a paddle wants to do this frame, written by whoever controls it.
+/// Axes are in `-1.0..=1.0`, positive is up/right and counter clockwise.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PaddleIntent {
+    pub movement: Vec2,
+    pub rotation: f32,
+    /// Release a power shot this frame.
+    pub power: bool,
+    /// Hold on to the ball, or keep holding it, in catch and throw matches.
+    pub catch: bool,
+}
+
+/// Who drives a paddle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Controller {
+    Human,
+    Ai,
+    /// Computer standing in for a human who walked away.
+    IdleAi,
+}
+
+impl Controller {
+    pub fn is_ai(&self) -> bool {
+        !matches!(self, Controller::Human)
+    }
+}
+
+/// Sent by the pause menu, the computer takes every paddle of the player over until a human
+/// joins again.
+pub struct HandToCpu(pub Player);
+
+/// Seconds since a human paddle last received input.
+#[derive(Debug, Default)]
+pub struct Idle(pub f32);
+
+struct CpuBadge(Player);
+
+fn keyboard_intent(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    tuning: Res<Tuning>,
+    mut paddles: Query<(&Player, &PaddleRole, &Controller, &mut PaddleIntent)>,
+) {
+    for (player, role, controller, mut intent) in paddles.iter_mut() {
+        if *controller != Controller::Human {
+            continue;
+        }
+
+        let keys = bindings.for_paddle(*player, *role);
+        let axis = |negative: KeyCode, positive: KeyCode| {
+            -(keyboard_input.pressed(negative) as i8 as f32)
+                + keyboard_input.pressed(positive) as i8 as f32
+        };
+
+        intent.movement = Vec2::new(axis(keys.left, keys.right), axis(keys.down, keys.up));
+        intent.rotation = if tuning.paddle_rotation {
+            axis(keys.rotate_right, keys.rotate_left)
+        } else {
+            0.
+        };
+        intent.power = keyboard_input.just_pressed(keys.power);
+        intent.catch = keyboard_input.pressed(keys.catch);
+    }
+}
+
+fn idle_takeover(
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    tuning: Res<Tuning>,
+    paused: Res<Paused>,
+    phase: Res<MatchPhase>,
+    mut paddles: Query<(&Player, &PaddleRole, &mut Controller, &mut Idle)>,
+) {
+    let active_play = !paused.0 && !phase.is_finished();
+
+    for (player, role, mut controller, mut idle) in paddles.iter_mut() {
+        let keys = bindings.for_paddle(*player, *role);
+        let touched = keys.all().iter().any(|key| keyboard_input.pressed(*key));
+
+        match *controller {
+            Controller::Human if touched => idle.0 = 0.,
+            Controller::Human if active_play => {
+                idle.0 += time.delta_seconds();
+                if idle.0 >= tuning.idle_takeover_seconds {
+                    *controller = Controller::IdleAi;
+                }
+            }
+            Controller::IdleAi if touched => {
+                *controller = Controller::Human;
+                idle.0 = 0.;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn hand_to_cpu(
+    mut handovers: EventReader<HandToCpu>,
+    mut paddles: Query<(&Player, &mut Controller)>,
+) {
+    for handover in handovers.iter() {
+        for (player, mut controller) in paddles.iter_mut() {
+            if *player == handover.0 && *controller != Controller::Ai {
+                *controller = Controller::Ai;
+            }
+        }
+        info!("{:?} handed to the computer", handover.0);
+    }
+}
+
+/// A computer paddle handed over from the menu goes to whoever presses its power key, only the
+/// controller changes so the paddle carries on where it is. Blocked in the final points unless
+/// the rules allow late joins.
+fn join_paddles(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    rules: Res<MatchRules>,
+    score: Res<Score>,
+    paused: Res<Paused>,
+    phase: Res<MatchPhase>,
+    mut callouts: EventWriter<Callout>,
+    mut paddles: Query<(&Player, &PaddleRole, &mut Controller, &mut Idle)>,
+) {
+    if paused.0 {
+        return;
+    }
+
+    for (player, role, mut controller, mut idle) in paddles.iter_mut() {
+        let join_key = bindings.for_paddle(*player, *role).power;
+        if *controller != Controller::Ai || !keyboard_input.just_pressed(join_key) {
+            continue;
+        }
+
+        if rules.joining_locked(&score, *phase) {
+            callouts.send(Callout("No joining in the final points".to_string()));
+            continue;
+        }
+        *controller = Controller::Human;
+        idle.0 = 0.;
+        info!("{:?} {:?} paddle joined by a human", player, role);
+        callouts.send(Callout(format!("{:?} joins!", player)));
+    }
+}
+
+/// Top of the badges as a fraction of the window height, under the scores.
+const BADGE_TOP: f32 = 0.6;
+
+fn spawn_cpu_badges(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    ui_font: Res<UiFont>,
+) {
+    for player in [Player::Left, Player::Right].iter() {
+        commands
+            .spawn_bundle(NodeBundle {
+                style: hud::half_row(*player, BADGE_TOP),
+                material: materials.add(Color::NONE.into()),
+                ..Default::default()
+            })
+            .with_children(|parent| {
+                parent
+                    .spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            "".to_string(),
+                            TextStyle {
+                                font: ui_font.0.clone(),
+                                font_size: 24.0,
+                                color: Color::rgb(0.8, 0.8, 0.8),
+                            },
+                            Default::default(),
+                        ),
+                        ..Default::default()
+                    })
+                    .insert(CpuBadge(*player));
+            });
+    }
+}
+
+/// A side handed to the computer from the menu tells how to join it again.
+fn render_cpu_badges(
+    bindings: Res<KeyBindings>,
+    controllers: Query<(&Player, &Controller), Changed<Controller>>,
+    all_controllers: Query<(&Player, &Controller)>,
+    mut badges: Query<(&mut Text, &CpuBadge)>,
+) {
+    if controllers.iter().next().is_none() && !bindings.is_changed() {
+        return;
+    }
+
+    for (mut text, badge) in badges.iter_mut() {
+        let side = || {
+            all_controllers
+                .iter()
+                .filter(move |(player, _)| **player == badge.0)
+                .map(|(_, controller)| *controller)
+        };
+
+        text.sections[0].value = if side().any(|controller| controller == Controller::Ai) {
+            format!(
+                "CPU, {} to join",
+                key_name(bindings.for_player(&badge.0).power)
+            )
+        } else if side().any(|controller| controller.is_ai()) {
+            "CPU".to_string()
+        } else {
+            "".to_string()
+        };
+    }
+}
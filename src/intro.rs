@@ -0,0 +1,350 @@
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use bevy_rapier2d::physics::{RapierConfiguration, RigidBodyHandleComponent};
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+
+use crate::accessibility::Accessibility;
+use crate::hud;
+use crate::input::Controller;
+use crate::match_state::MatchRules;
+use crate::persistence::SaveData;
+use crate::ready_check::ReadyCheck;
+use crate::rng::GameRng;
+use crate::shot_clock::ShotClockRule;
+use crate::theme::Theme;
+use crate::tournament::Tournament;
+use crate::tuning::{Preset, Tuning};
+use crate::tutorial::Tutorial;
+use crate::{
+    reset_ball, serve_velocity, AppState, Ball, Paddle, Paused, Player, Score, ServeEvent,
+    ServeKind, UiFont, Wall, ARENA_HEIGHT,
+};
+
+pub struct IntroPlugin;
+
+impl Plugin for IntroPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Intro>()
+            .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(start_intro.system()))
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(run_intro.system())
+                    .with_system(show_splash.system()),
+            )
+            // Physics writes the transforms of every body before the post update stage, the
+            // intro moves them away from there afterwards
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                animate_intro
+                    .system()
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+const INTRO_SECONDS: f32 = 1.5;
+/// The splash with the match-up stays up a little past the animation, the first serve
+/// follows it.
+const SPLASH_SECONDS: f32 = 2.;
+
+/// Stretches of the intro in seconds, each part runs from its start to its end.
+const WALLS: (f32, f32) = (0., 0.6);
+const PADDLES: (f32, f32) = (0.3, 0.9);
+const SCORES: (f32, f32) = (0.6, 1.1);
+const BALL: (f32, f32) = (1.1, INTRO_SECONDS);
+
+/// Arena intro played when a match starts. The game is paused while it plays, so the physics
+/// bodies stay put and only their sprites are animated. The first serve follows it.
+#[derive(Debug, Default)]
+pub struct Intro {
+    requested: bool,
+    /// Seconds played, `None` when the intro isn't running.
+    elapsed: Option<f32>,
+    /// Show the final frame of the animation once after the intro ends.
+    settle: bool,
+    paused_game: bool,
+}
+
+impl Intro {
+    /// Plays the intro from the start on the next frame.
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.requested || self.elapsed.is_some()
+    }
+}
+
+fn start_intro(mut intro: ResMut<Intro>) {
+    intro.request();
+}
+
+fn run_intro(
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    tutorial: Res<Tutorial>,
+    ready_check: Res<ReadyCheck>,
+    tuning: Res<Tuning>,
+    rules: Res<MatchRules>,
+    rapier_config: Res<RapierConfiguration>,
+    mut intro: ResMut<Intro>,
+    mut paused: ResMut<Paused>,
+    mut rng: ResMut<GameRng>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    mut serves: EventWriter<ServeEvent>,
+    mut balls: Query<(&RigidBodyHandleComponent, &mut Transform), With<Ball>>,
+) {
+    // The tutorial and the ready check pause the game on their own, start and run once they
+    // are done
+    if tutorial.is_open() || ready_check.is_open() {
+        return;
+    }
+
+    if intro.requested {
+        intro.requested = false;
+        if intro.elapsed.is_none() {
+            intro.paused_game = !paused.0;
+        }
+        intro.elapsed = Some(0.);
+        paused.0 = true;
+        return;
+    }
+
+    let elapsed = match intro.elapsed {
+        Some(elapsed) => elapsed,
+        None => return,
+    };
+
+    let skipped = keyboard_input.get_just_pressed().next().is_some();
+    let elapsed = elapsed + time.delta_seconds();
+    if elapsed < SPLASH_SECONDS && !skipped {
+        intro.elapsed = Some(elapsed);
+        return;
+    }
+
+    intro.elapsed = None;
+    intro.settle = true;
+    if intro.paused_game {
+        paused.0 = false;
+    }
+    intro.paused_game = false;
+
+    for (rigid_body_component, mut transform) in balls.iter_mut() {
+        if let Some(rb) = rigid_bodies.get_mut(rigid_body_component.handle()) {
+            let velocity = serve_velocity(
+                &mut rng,
+                &rules,
+                &tuning,
+                None,
+                ServeKind::Standard,
+                tuning.serve_speed,
+            );
+            reset_ball(rb, &mut transform, &rapier_config, velocity);
+            serves.send(ServeEvent::from_velocity(&velocity, ServeKind::Standard));
+        }
+    }
+}
+
+/// Who plays who under which rules, shown while the intro plays.
+struct Splash;
+
+fn show_splash(
+    mut commands: Commands,
+    intro: Res<Intro>,
+    tutorial: Res<Tutorial>,
+    ready_check: Res<ReadyCheck>,
+    font: Res<UiFont>,
+    theme: Res<Theme>,
+    rules: Res<MatchRules>,
+    preset: Res<Preset>,
+    tournament: Res<Tournament>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    controllers: Query<(&Player, &Controller), With<Paddle>>,
+    splashes: Query<Entity, With<Splash>>,
+) {
+    if !intro.is_playing() || tutorial.is_open() || ready_check.is_open() {
+        for entity in splashes.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    if splashes.iter().next().is_some() {
+        return;
+    }
+
+    let name = |player: Player| {
+        let computer = controllers
+            .iter()
+            .any(|(owner, controller)| *owner == player && matches!(controller, Controller::Ai));
+        match (tournament.name(player), player) {
+            (Some(name), _) => name.to_string(),
+            _ if computer => "CPU".to_string(),
+            (None, Player::Left) => "Left".to_string(),
+            (None, Player::Right) => "Right".to_string(),
+        }
+    };
+    let section = |value: String, color: Color| TextSection {
+        value,
+        style: TextStyle {
+            font: font.0.clone(),
+            font_size: 40.0,
+            color,
+        },
+    };
+    let white = Color::rgb(1.0, 1.0, 1.0);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: hud::full_row(1. / 8.),
+            material: materials.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .insert(Splash)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text {
+                    sections: vec![
+                        section(name(Player::Left), theme.left),
+                        section(" vs ".to_string(), white),
+                        section(name(Player::Right), theme.right),
+                        section(
+                            format!(" \u{2014} {}", rules_summary(&rules, *preset)),
+                            white,
+                        ),
+                    ],
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+        });
+}
+
+/// "first to 11, Kid preset, wrap-around" for the splash.
+fn rules_summary(rules: &MatchRules, preset: Preset) -> String {
+    let mut parts = Vec::new();
+    match (rules.score_limit, rules.time_limit) {
+        (Some(limit), _) => parts.push(format!("first to {}", limit)),
+        (None, Some(seconds)) => {
+            let seconds = seconds.round() as u32;
+            parts.push(format!("{}:{:02} on the clock", seconds / 60, seconds % 60))
+        }
+        (None, None) => parts.push("endless".to_string()),
+    }
+    if rules.score_limit.is_some() {
+        if let Some(seconds) = rules.time_limit {
+            parts.push(format!("{} seconds", seconds.round() as u32));
+        }
+    }
+    if preset == Preset::Kid {
+        parts.push("Kid preset".to_string());
+    }
+    if rules.doubles {
+        parts.push("doubles".to_string());
+    }
+    if rules.paddles_per_side > 1 {
+        parts.push(format!("{} paddles a side", rules.paddles_per_side));
+    }
+    if rules.hazard {
+        parts.push("hazard".to_string());
+    }
+    if rules.invisible_ball {
+        parts.push("invisible ball".to_string());
+    }
+    if rules.shot_clock == ShotClockRule::ResetBall {
+        parts.push("shot clock resets".to_string());
+    } else if rules.shot_clock == ShotClockRule::Off {
+        parts.push("no shot clock".to_string());
+    }
+    if rules.wrap_around {
+        parts.push("wrap-around".to_string());
+    }
+    if rules.bounce_limit {
+        parts.push("bounce limit".to_string());
+    }
+    if rules.mercy_rule {
+        parts.push("mercy rule".to_string());
+    }
+    if rules.catch_and_throw {
+        parts.push("catch and throw".to_string());
+    }
+    if rules.gravity_zones {
+        parts.push("gravity zones".to_string());
+    }
+    if rules.late_join {
+        parts.push("late joins".to_string());
+    }
+    if rules.comeback_assist && preset == Preset::Kid {
+        parts.push("comeback assist".to_string());
+    }
+    if rules.teleport_gates {
+        parts.push("teleport gates".to_string());
+    }
+    if rules.balls > 1 {
+        parts.push(format!("{} balls", rules.balls));
+    }
+    if rules.adaptive_ai {
+        parts.push("adaptive AI".to_string());
+    }
+    parts.join(", ")
+}
+
+/// How far along `part` is, from 0 to 1, eased out.
+fn progress(elapsed: f32, part: (f32, f32)) -> f32 {
+    let (start, end) = part;
+    let linear = ((elapsed - start) / (end - start)).max(0.).min(1.);
+    1. - (1. - linear) * (1. - linear)
+}
+
+fn animate_intro(
+    accessibility: Res<Accessibility>,
+    mut intro: ResMut<Intro>,
+    score: Res<Score>,
+    save: Res<SaveData>,
+    rapier_config: Res<RapierConfiguration>,
+    rigid_bodies: Res<RigidBodySet>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut walls: Query<(&RigidBodyHandleComponent, &mut Transform), With<Wall>>,
+    paddles: Query<&Handle<ColorMaterial>, With<Paddle>>,
+    mut balls: Query<&mut Transform, (With<Ball>, Without<Wall>)>,
+    mut scores: Query<(&Player, &mut Text)>,
+) {
+    // Reduced motion shows the arena in place right away, the serve still waits for the intro
+    let elapsed = match (intro.elapsed, intro.settle) {
+        (Some(_), _) if accessibility.reduced_motion => INTRO_SECONDS,
+        (Some(elapsed), _) => elapsed,
+        (None, true) => INTRO_SECONDS,
+        (None, false) => return,
+    };
+    intro.settle = false;
+
+    // Walls slide in from the nearest edge, starting just out of sight
+    let offset = (1. - progress(elapsed, WALLS)) * ARENA_HEIGHT / 2.;
+    for (rigid_body_component, mut transform) in walls.iter_mut() {
+        if let Some(rb) = rigid_bodies.get(rigid_body_component.handle()) {
+            let y = rb.position().translation.y * rapier_config.scale;
+            let direction = if y > ARENA_HEIGHT / 2. { 1. } else { -1. };
+            transform.translation.y = y + direction * offset;
+        }
+    }
+
+    let alpha = progress(elapsed, PADDLES);
+    for material in paddles.iter() {
+        if let Some(material) = materials.get_mut(material) {
+            material.color.set_a(alpha);
+        }
+    }
+
+    let counted = progress(elapsed, SCORES);
+    for (player, mut text) in scores.iter_mut() {
+        let own = (score.of(*player) as f32 * counted).round() as u32;
+        let other = (score.of(player.opponent()) as f32 * counted).round() as u32;
+        text.sections[0].value = save.settings.scoreboard_style.format(own, other);
+    }
+
+    let size = progress(elapsed, BALL);
+    for mut transform in balls.iter_mut() {
+        transform.scale = Vec3::new(size, size, 1.);
+    }
+}
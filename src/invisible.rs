@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+
+use crate::contacts::{BallContact, Surface};
+use crate::match_state::MatchRules;
+use crate::{AppState, Ball, GameLabel, GoalEvent, Paused, ServeEvent};
+
+pub struct InvisiblePlugin;
+
+impl Plugin for InvisiblePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(hide_new_balls.system())
+                .with_system(
+                    fade_balls
+                        .system()
+                        .after(GameLabel::GoalApply)
+                        .before(GameLabel::GoalPresent),
+                ),
+        );
+    }
+}
+
+/// Seconds the ball stays fully visible after a contact.
+const SHOWN_SECONDS: f32 = 0.1;
+/// Seconds after a contact when the ball has faded out completely.
+const HIDDEN_AFTER_SECONDS: f32 = 0.4;
+
+/// Ball that fades out between contacts. Physics is untouched, only the sprite fades.
+#[derive(Debug, Default)]
+pub struct InvisibleBall {
+    /// `None` while serving, the ball stays visible until it is first touched.
+    since_contact: Option<f32>,
+}
+
+impl InvisibleBall {
+    /// Opacity factor for the ball sprite.
+    pub fn alpha(&self) -> f32 {
+        match self.since_contact {
+            Some(seconds) if seconds > SHOWN_SECONDS => {
+                let fade = (seconds - SHOWN_SECONDS) / (HIDDEN_AFTER_SECONDS - SHOWN_SECONDS);
+                (1. - fade).max(0.)
+            }
+            _ => 1.,
+        }
+    }
+}
+
+fn hide_new_balls(
+    mut commands: Commands,
+    rules: Res<MatchRules>,
+    balls: Query<Entity, Added<Ball>>,
+) {
+    if !rules.invisible_ball {
+        return;
+    }
+
+    for ball in balls.iter() {
+        commands.entity(ball).insert(InvisibleBall::default());
+    }
+}
+
+fn fade_balls(
+    time: Res<Time>,
+    paused: Res<Paused>,
+    mut contacts: EventReader<BallContact>,
+    mut goals: EventReader<GoalEvent>,
+    mut serves: EventReader<ServeEvent>,
+    mut balls: Query<&mut InvisibleBall>,
+) {
+    // Goals and serves put the ball back in plain sight
+    let reset = goals.iter().next().is_some() | serves.iter().next().is_some();
+
+    for mut ball in balls.iter_mut() {
+        if reset {
+            ball.since_contact = None;
+        } else if let Some(seconds) = ball.since_contact.as_mut() {
+            if !paused.0 {
+                *seconds += time.delta_seconds();
+            }
+        }
+    }
+
+    if reset {
+        return;
+    }
+
+    for contact in contacts.iter() {
+        let touched = matches!(contact.surface, Surface::Paddle(_) | Surface::Wall(_));
+        if !contact.started || !touched {
+            continue;
+        }
+
+        if let Ok(mut ball) = balls.get_mut(contact.ball) {
+            ball.since_contact = Some(0.);
+        }
+    }
+}
@@ -0,0 +1,2158 @@
+//! Pingis Pong as a Bevy plugin. The `pingis_pong` binary runs it in a window of its own, other
+//! apps can add `PingisPongPlugin` to play it as a minigame.
+
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
+use std::u128;
+
+use bevy::{
+    prelude::*,
+    render::camera::{ScalingMode, WindowOrigin},
+};
+use bevy_rapier2d::rapier::dynamics::{RigidBody, RigidBodyBuilder, RigidBodySet};
+use bevy_rapier2d::rapier::geometry::{ColliderBuilder, InteractionGroups};
+use bevy_rapier2d::rapier::na::Vector2;
+use bevy_rapier2d::{
+    na::Isometry2,
+    physics::{RapierConfiguration, RapierPhysicsPlugin, RigidBodyHandleComponent},
+};
+use serde::{Deserialize, Serialize};
+
+mod accessibility;
+mod ai;
+mod background;
+mod ball_icons;
+mod ball_visuals;
+mod bindings;
+mod boost;
+mod bounce_limit;
+mod bracket;
+mod buttons;
+mod callout;
+mod catch;
+mod cleanup;
+mod clip;
+mod colors;
+mod comeback;
+mod config;
+#[cfg(not(feature = "competitive"))]
+mod console;
+mod contacts;
+mod crt;
+mod daily;
+mod edge_glow;
+mod english;
+mod gates;
+mod gravity_zones;
+mod hazard;
+mod hill;
+mod hud;
+mod input;
+mod intro;
+mod invisible;
+mod loading;
+mod long_rally;
+mod match_state;
+mod menu;
+mod momentum;
+mod paddle_feedback;
+mod particles;
+mod persistence;
+mod physics;
+mod physics_guard;
+mod physics_sync;
+mod power;
+mod pressure;
+mod profiles;
+mod quality;
+mod raster;
+mod ready_check;
+mod replay;
+mod resume;
+mod rng;
+mod rules;
+mod screenshot;
+mod shot_clock;
+mod slow_motion;
+mod snapshot;
+mod spin;
+mod startup;
+mod stats;
+mod stats_overlay;
+mod step;
+mod streak;
+mod taunt;
+mod territory;
+mod theme;
+mod throttle;
+mod tournament;
+mod tuning;
+mod tutorial;
+mod wall_jump;
+mod win_meter;
+mod wrap;
+
+use accessibility::AccessibilityPlugin;
+use ai::{AiPlugin, AiState};
+use background::BackgroundPlugin;
+use ball_icons::BallIconsPlugin;
+use ball_visuals::{
+    ball_materials, glow_bundle, shadow_bundle, BallGlow, BallShadow, BallVisualsPlugin,
+};
+use boost::{BoostPlugin, ReceiverBoost};
+use bounce_limit::BounceLimitPlugin;
+use buttons::ButtonsPlugin;
+use callout::{Callout, CalloutPlugin};
+use catch::CatchPlugin;
+use cleanup::CleanupPlugin;
+use clip::ClipPlugin;
+use colors::PlayerColorsPlugin;
+use comeback::ComebackPlugin;
+use config::ConfigPlugin;
+#[cfg(not(feature = "competitive"))]
+use console::ConsolePlugin;
+use contacts::ContactsPlugin;
+use crt::CrtPlugin;
+use daily::{DailyChallenge, DailyPlugin};
+use edge_glow::EdgeGlowPlugin;
+use english::EnglishPlugin;
+use gates::GatesPlugin;
+use gravity_zones::{serve_tilt, GravityZonesPlugin};
+use hazard::HazardPlugin;
+use hill::{HillPlugin, Queue};
+use input::{Controller, Idle, InputPlugin, PaddleIntent};
+use intro::IntroPlugin;
+use invisible::InvisiblePlugin;
+use loading::{GameAssets, LoadingPlugin};
+use long_rally::LongRallyPlugin;
+use match_state::{MatchClock, MatchEnd, MatchPhase, MatchPlugin, OVERTIME_SPEED_FACTOR};
+use menu::MenuPlugin;
+use momentum::MomentumPlugin;
+use paddle_feedback::PaddleFeedbackPlugin;
+use particles::ParticlesPlugin;
+use persistence::SaveData;
+use physics::{physics_bundle, teleport_body, PhysicsMarkersPlugin};
+use physics_guard::PhysicsGuardPlugin;
+use physics_sync::PhysicsSyncPlugin;
+use power::{PowerPlugin, PowerShot, POWER_SHOT_CAP_FACTOR};
+use pressure::PressurePlugin;
+use profiles::ProfilesPlugin;
+use quality::QualityPlugin;
+use ready_check::ReadyCheckPlugin;
+use replay::ReplayPlugin;
+use resume::ResumePlugin;
+use rng::GameRng;
+use rules::{on_goals, RuleOutcome, RulesConfig, RulesState};
+use screenshot::ScreenshotPlugin;
+use shot_clock::ShotClockPlugin;
+use slow_motion::SlowMotionPlugin;
+use snapshot::SnapshotPlugin;
+use spin::SpinPlugin;
+use startup::StartupPlugin;
+use stats::StatsPlugin;
+use stats_overlay::StatsOverlayPlugin;
+use step::StepPlugin;
+use streak::StreakPlugin;
+use taunt::TauntPlugin;
+use territory::TerritoryPlugin;
+use throttle::ThrottlePlugin;
+use tournament::{seed, Tournament, TournamentPlugin};
+use tutorial::TutorialPlugin;
+use wall_jump::{WallJump, WallJumpPlugin};
+use win_meter::WinMeterPlugin;
+use wrap::{spawn_wrap_markers, WrapPlugin};
+
+pub use bindings::{KeyBindings, PlayerKeys};
+pub use bracket::MAX_BRACKET_PLAYERS;
+pub use config::GameConfig;
+pub use hill::MAX_HILL_PLAYERS;
+pub use match_state::MatchRules;
+pub use shot_clock::ShotClockRule;
+pub use startup::StartupReport;
+pub use theme::Theme;
+pub use tournament::{TournamentEntry, MAX_PLAYERS, MIN_PLAYERS};
+pub use tuning::{Preset, Tuning};
+
+/// Everything the host app picks before the game starts.
+#[derive(Debug, Clone)]
+pub struct PongSettings {
+    pub rules: MatchRules,
+    pub preset: Preset,
+    /// Seed for the random draws of the match, a fresh one is picked when `None`.
+    pub seed: Option<u64>,
+    pub bindings: KeyBindings,
+    /// Gameplay numbers, `None` takes them from the preset.
+    pub tuning: Option<Tuning>,
+    /// Colors, `None` uses the default theme.
+    pub theme: Option<Theme>,
+    /// Spawn the cameras looking at the arena. Turn it off when the host app has its own, its 2D
+    /// camera then needs to show `0..ARENA_WIDTH` by `0..ARENA_HEIGHT`.
+    pub spawn_camera: bool,
+    /// Play a round-robin or single elimination between named players, its matches are first
+    /// to 5. Players are seeded by the win rate of their profiles.
+    pub tournament: Option<TournamentEntry>,
+    /// Play king of the hill between these names in queue order, its matches are first to 5.
+    /// A tournament takes precedence.
+    pub hill: Option<Vec<String>>,
+    /// Keep the king of the hill leaderboard in the save file when the session ends.
+    pub keep_leaderboard: bool,
+    /// Play today's daily challenge against the computer, the rules, preset, tuning and seed are
+    /// derived from the date and replace the ones picked.
+    pub daily: bool,
+}
+
+impl Default for PongSettings {
+    fn default() -> Self {
+        PongSettings {
+            rules: MatchRules::default(),
+            preset: Preset::default(),
+            seed: None,
+            bindings: KeyBindings::default(),
+            tuning: None,
+            theme: None,
+            spawn_camera: true,
+            tournament: None,
+            hill: None,
+            keep_leaderboard: false,
+            daily: false,
+        }
+    }
+}
+
+/// The whole game. Window setup and `DefaultPlugins` are left to the app adding it.
+#[derive(Debug, Default)]
+pub struct PingisPongPlugin(pub PongSettings);
+
+impl Plugin for PingisPongPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let daily = if self.0.daily {
+            DailyChallenge::today()
+        } else {
+            DailyChallenge::default()
+        };
+        let settings = &if daily.is_active() {
+            daily.apply_to(self.0.clone())
+        } else {
+            self.0.clone()
+        };
+        // Problems the host found before adding the plugin come first
+        let mut report = app
+            .world_mut()
+            .remove_resource::<StartupReport>()
+            .unwrap_or_default();
+        let save = SaveData::load(&mut report).unwrap_or_default();
+        let rng = settings.seed.map(GameRng::new).unwrap_or_default();
+
+        let tournament = match &settings.tournament {
+            Some(TournamentEntry::New(players)) => {
+                Tournament::round_robin(seed(players.clone(), &save))
+            }
+            Some(TournamentEntry::Elimination(players)) => {
+                Tournament::elimination(seed(players.clone(), &save))
+            }
+            Some(TournamentEntry::Resume) => save.tournament.clone().unwrap_or_else(|| {
+                report.warn(
+                    "No unfinished tournament to resume, playing a regular match".to_string(),
+                );
+                Tournament::default()
+            }),
+            None => Tournament::default(),
+        };
+        let queue = match &settings.hill {
+            Some(_) if tournament.is_active() => {
+                report.warn("King of the hill ignored during a tournament".to_string());
+                Queue::default()
+            }
+            Some(names) => Queue::new(names.clone(), settings.keep_leaderboard),
+            None => Queue::default(),
+        };
+        let rules = settings
+            .rules
+            .clone()
+            .for_tournament(&tournament)
+            .for_queue(&queue);
+
+        app.insert_resource(save)
+            .insert_resource(report)
+            .insert_resource(settings.clone())
+            .insert_resource(settings.bindings.clone())
+            .init_resource::<Paused>()
+            .insert_resource(settings.preset)
+            .insert_resource(
+                settings
+                    .tuning
+                    .clone()
+                    .unwrap_or_else(|| Tuning::for_preset(settings.preset)),
+            )
+            .insert_resource(settings.theme.clone().unwrap_or_default())
+            .insert_resource(rng)
+            .insert_resource(rules)
+            .insert_resource(tournament)
+            .insert_resource(queue)
+            .insert_resource(daily)
+            .add_event::<GoalEvent>()
+            .add_event::<ServeEvent>()
+            .add_event::<PaddleHitLimit>()
+            .add_state(AppState::Loading)
+            .add_startup_system(setup_game.system())
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame)
+                    .with_system(spawn_walls.system())
+                    .with_system(spawn_paddles.system().label("paddles"))
+                    .with_system(spawn_ball.system().label("ball"))
+                    .with_system(spawn_scoreboard.system().after("paddles").after("ball")),
+            )
+            .add_system(pause_physics.system())
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(paddle_movement.system().after(GameLabel::Input))
+                    .with_system(
+                        ball_goal
+                            .system()
+                            .label(GameLabel::GoalDetect)
+                            .after(GameLabel::Contacts),
+                    )
+                    .with_system(
+                        apply_goal
+                            .system()
+                            .label(GameLabel::GoalApply)
+                            .after(GameLabel::GoalDetect),
+                    )
+                    .with_system(serve_out_of_play.system().after(GameLabel::GoalApply))
+                    .with_system(
+                        remember_ball_positions
+                            .system()
+                            .after(GameLabel::GoalDetect),
+                    )
+                    .with_system(
+                        clamp_ball_speed
+                            .system()
+                            .label("clamp_ball_speed")
+                            .after(GameLabel::GoalApply),
+                    )
+                    .with_system(
+                        render_scoreboard
+                            .system()
+                            .label(GameLabel::GoalPresent)
+                            .after(GameLabel::GoalApply),
+                    )
+                    .with_system(layout_scoreboard.system()),
+            )
+            .add_plugin(RapierPhysicsPlugin)
+            .add_plugin(LoadingPlugin)
+            .add_plugin(TutorialPlugin)
+            .add_plugin(CalloutPlugin)
+            .add_plugin(ContactsPlugin)
+            .add_plugin(MatchPlugin)
+            .add_plugin(BallVisualsPlugin)
+            .add_plugin(ScreenshotPlugin)
+            .add_plugin(StatsPlugin)
+            .add_plugin(InputPlugin)
+            .add_plugin(AiPlugin)
+            .add_plugin(ButtonsPlugin)
+            .add_plugin(MenuPlugin)
+            .add_plugin(HazardPlugin)
+            .add_plugin(PressurePlugin)
+            .add_plugin(ReplayPlugin)
+            .add_plugin(BoostPlugin)
+            .add_plugin(PowerPlugin)
+            .add_plugin(SnapshotPlugin)
+            .add_plugin(StepPlugin)
+            .add_plugin(InvisiblePlugin)
+            .add_plugin(CleanupPlugin)
+            .add_plugin(IntroPlugin)
+            .add_plugin(ShotClockPlugin)
+            .add_plugin(StreakPlugin)
+            .add_plugin(ParticlesPlugin)
+            .add_plugin(TauntPlugin)
+            .add_plugin(ResumePlugin)
+            .add_plugin(QualityPlugin)
+            .add_plugin(WrapPlugin)
+            .add_plugin(PlayerColorsPlugin)
+            .add_plugin(TournamentPlugin)
+            .add_plugin(TerritoryPlugin)
+            .add_plugin(PhysicsSyncPlugin)
+            .add_plugin(BounceLimitPlugin)
+            .add_plugin(PaddleFeedbackPlugin)
+            .add_plugin(ClipPlugin)
+            .add_plugin(AccessibilityPlugin)
+            .add_plugin(WallJumpPlugin)
+            .add_plugin(ConfigPlugin)
+            .add_plugin(EnglishPlugin)
+            .add_plugin(CatchPlugin)
+            .add_plugin(GravityZonesPlugin)
+            .add_plugin(StatsOverlayPlugin)
+            .add_plugin(ComebackPlugin)
+            .add_plugin(DailyPlugin)
+            .add_plugin(GatesPlugin)
+            .add_plugin(CrtPlugin)
+            .add_plugin(ReadyCheckPlugin)
+            .add_plugin(StartupPlugin)
+            .add_plugin(SlowMotionPlugin)
+            .add_plugin(BallIconsPlugin)
+            .add_plugin(WinMeterPlugin)
+            .add_plugin(HillPlugin)
+            .add_plugin(PhysicsGuardPlugin)
+            .add_plugin(PhysicsMarkersPlugin)
+            .add_plugin(EdgeGlowPlugin)
+            .add_plugin(ProfilesPlugin)
+            .add_plugin(SpinPlugin)
+            .add_plugin(ThrottlePlugin)
+            .add_plugin(MomentumPlugin)
+            .add_plugin(BackgroundPlugin)
+            .add_plugin(LongRallyPlugin);
+
+        #[cfg(not(feature = "competitive"))]
+        app.add_plugin(ConsolePlugin);
+    }
+}
+
+/// Order of the gameplay systems within a frame. Physics steps after the update stage, so
+/// contacts and goals are read from the step that was just rendered and every system reacting to
+/// a goal sees it in the same frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemLabel)]
+pub enum GameLabel {
+    /// Humans and computer players write their `PaddleIntent`.
+    Input,
+    /// Physics events are turned into game events.
+    Contacts,
+    /// A ball crossed a goal line, `GoalEvent` is sent.
+    GoalDetect,
+    /// Score, stats and the next serve are updated.
+    GoalApply,
+    /// Everything shown to the players catches up.
+    GoalPresent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AppState {
+    Loading,
+    InGame,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Player {
+    Left,
+    Right,
+}
+
+impl Player {
+    pub fn opponent(self) -> Player {
+        match self {
+            Player::Left => Player::Right,
+            Player::Right => Player::Left,
+        }
+    }
+}
+
+pub struct Paddle(f32);
+
+/// Position from the bottom within a wall of paddles all driven by the same player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubPaddle(pub u32);
+
+/// Position of a paddle within its team, doubles have a front and a back paddle per side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PaddleRole {
+    Solo,
+    Front,
+    Back,
+}
+pub struct Ball(f32);
+
+/// Ball position after the previous physics step, for finding where it crossed a line.
+#[derive(Debug, Default)]
+pub struct PreviousPosition(pub Option<Vec2>);
+
+/// A ball that went in, it flies on past the goal line for a moment before it is served again.
+/// Goals aren't checked on it.
+pub struct OutOfPlay {
+    timer: Timer,
+    scorer: Player,
+    /// The goal ended the match.
+    final_goal: bool,
+}
+
+/// Longest a ball flies on after a goal.
+const OUT_OF_PLAY_SECONDS: f32 = 0.5;
+/// Pixels past the arena edge at which a ball that went in is served again.
+const OUT_OF_PLAY_DISTANCE: f32 = 100.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Wall {
+    Top,
+    Bottom,
+    /// Segment beside the goal of the given player.
+    Flank(Player),
+}
+
+const WALL_TOP: u128 = 1;
+const WALL_BOTTOM: u128 = 2;
+
+/// Collision groups for bodies that must not touch everything, the rest stay in every group.
+pub const BALL_GROUP: u16 = 0b0001;
+pub const PADDLE_GROUP: u16 = 0b0010;
+pub const HAZARD_GROUP: u16 = 0b0100;
+pub const GATE_GROUP: u16 = 0b1000;
+
+// struct BallTexture(pub Handle<ColorMaterial>) ;
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Score {
+    pub left: u32,
+    pub right: u32,
+    /// Player who scored the latest goals and how many in a row.
+    pub streak: Option<(Player, u32)>,
+}
+
+/// Goals in a row before a player is on fire.
+pub const STREAK_LENGTH: u32 = 3;
+
+impl Score {
+    pub fn award(&mut self, player: Player) {
+        match player {
+            Player::Left => self.left += 1,
+            Player::Right => self.right += 1,
+        }
+
+        self.streak = match self.streak {
+            Some((streaker, goals)) if streaker == player => Some((player, goals + 1)),
+            _ => Some((player, 1)),
+        };
+    }
+
+    pub fn of(&self, player: Player) -> u32 {
+        match player {
+            Player::Left => self.left,
+            Player::Right => self.right,
+        }
+    }
+
+    /// The player currently on a scoring streak, if any.
+    pub fn on_fire(&self) -> Option<Player> {
+        match self.streak {
+            Some((player, goals)) if goals >= STREAK_LENGTH => Some(player),
+            _ => None,
+        }
+    }
+
+    /// `velocity` of a serve with the bonus for a player on fire, on the serves their opponent
+    /// has to take. The player on fire scored the last goal, so after a goal they receive rather
+    /// than serve, and serves coming at them keep the normal speed.
+    pub fn with_streak_bonus(&self, velocity: Vector2<f32>, tuning: &Tuning) -> Vector2<f32> {
+        match self.on_fire() {
+            Some(player) if serve_receiver(&velocity) != player => {
+                velocity * tuning.streak_serve_bonus
+            }
+            _ => velocity,
+        }
+    }
+}
+
+/// Sent when the ball crosses a goal line, before it is served again.
+pub struct GoalEvent {
+    pub scorer: Player,
+    pub ball: Entity,
+    /// Height the ball crossed the goal line at, `None` for points not scored through the goal.
+    pub crossing_y: Option<f32>,
+}
+
+/// Sent when the ball is launched from the center spot.
+pub struct ServeEvent {
+    pub receiver: Player,
+    pub kind: ServeKind,
+}
+
+impl ServeEvent {
+    /// The receiver is the player whose goal the serve is heading for.
+    pub fn from_velocity(velocity: &Vector2<f32>, kind: ServeKind) -> Self {
+        ServeEvent {
+            receiver: serve_receiver(velocity),
+            kind,
+        }
+    }
+}
+
+/// Player whose goal a serve with `velocity` is heading for.
+pub fn serve_receiver(velocity: &Vector2<f32>) -> Player {
+    if velocity.x < 0. {
+        Player::Left
+    } else {
+        Player::Right
+    }
+}
+
+/// Sent when a paddle driven into its movement limits is stopped there. Only sent as it reaches
+/// the limit, not again while it is held against it.
+pub struct PaddleHitLimit {
+    pub paddle: Entity,
+    pub player: Player,
+    pub axis: LimitAxis,
+}
+
+/// Which limit a paddle reached, the sides of its zone or the band of a paddle in a wall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitAxis {
+    X,
+    Y,
+}
+
+/// Limits a paddle was held against last frame.
+#[derive(Debug, Default)]
+struct PaddleClamp {
+    x: bool,
+    y: bool,
+}
+
+/// How the ball leaves the center spot. The serving player picks a lob or a flat serve by
+/// holding their up or down key as the ball is served.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ServeKind {
+    /// Random angle within the serve cone.
+    Standard,
+    /// Slow and steep, it comes down off the top wall.
+    Lob,
+    /// Fast and close to horizontal.
+    Flat,
+}
+
+impl ServeKind {
+    pub fn held(keyboard_input: &Input<KeyCode>, keys: &PlayerKeys) -> Self {
+        if keyboard_input.pressed(keys.up) {
+            ServeKind::Lob
+        } else if keyboard_input.pressed(keys.down) {
+            ServeKind::Flat
+        } else {
+            ServeKind::Standard
+        }
+    }
+}
+
+/// Freezes physics and paddle input while set.
+#[derive(Debug, Default)]
+pub struct Paused(pub bool);
+
+/// Most balls `MatchRules::balls` allows in play at once.
+pub const MAX_BALLS: u32 = 3;
+
+pub const ARENA_WIDTH: f32 = 1000.;
+pub const ARENA_HEIGHT: f32 = 600.;
+pub const ARENA_MIDDLE: f32 = ARENA_WIDTH / 2.;
+/// Pixels per physics unit.
+pub const PHYSICS_SCALE: f32 = 20.;
+
+const PADDLE_HEIGHT: f32 = 110.0;
+const PADDLE_WIDTH: f32 = 15.0;
+const PADDLE_WALL_OFFSET: f32 = 50.;
+/// Full paddle speed in pixels per second.
+const PADDLE_SPEED: f32 = 600.;
+const SUB_PADDLE_GAP: f32 = 20.;
+const WALL_THICKNESS: f32 = 20.;
+
+/// Range of lob serve angles in radians, about 50 to 65 degrees.
+const LOB_ANGLES: (f32, f32) = (0.87, 1.13);
+const LOB_SPEED_FACTOR: f32 = 0.75;
+/// Flat serves stay within about 5 degrees of the horizontal.
+const FLAT_SPREAD: f32 = 0.09;
+const FLAT_SPEED_FACTOR: f32 = 1.2;
+
+fn setup_game(
+    mut commands: Commands,
+    // mut materials: ResMut<Assets<ColorMaterial>>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    settings: Res<PongSettings>,
+    rng: Res<GameRng>,
+    // asset_server: Res<AssetServer>,
+) {
+    info!(
+        "Match seed {}, pass --seed {} to replay it",
+        rng.seed(),
+        rng.seed()
+    );
+
+    // Set gravity to 0.0
+    rapier_config.gravity = Vector2::zeros();
+
+    // Setup camera
+    if settings.spawn_camera {
+        spawn_cameras(&mut commands);
+    }
+
+    // Set physics scale
+    rapier_config.scale = PHYSICS_SCALE;
+
+    // Load materials
+    // let texture_handle = asset_server.load("assets/sprites/ball.png");
+    // let material_handle = materials.add(asset_server.load("sprites/ball.png").into());
+    // commands.insert_resource(BallTexture(material_handle));
+    commands.insert_resource(Score::default());
+}
+
+/// 2D camera showing the arena from the bottom left corner, plus one for the UI.
+fn spawn_cameras(commands: &mut Commands) {
+    let mut cam = OrthographicCameraBundle::new_2d();
+
+    cam.orthographic_projection.scaling_mode = ScalingMode::None;
+    cam.orthographic_projection.left = 0.;
+    cam.orthographic_projection.right = ARENA_WIDTH;
+    cam.orthographic_projection.top = ARENA_HEIGHT;
+    cam.orthographic_projection.bottom = 0.;
+    cam.orthographic_projection.window_origin = WindowOrigin::BottomLeft;
+
+    commands.spawn().insert_bundle(cam);
+    commands.spawn_bundle(UiCameraBundle::default());
+}
+
+pub struct UiFont(pub Handle<Font>);
+
+fn spawn_scoreboard(
+    mut commands: Commands,
+    ui_font: Res<UiFont>,
+    theme: Res<Theme>,
+    save: Res<SaveData>,
+) {
+    let handle = ui_font.0.clone();
+
+    let layout = scoreboard_layout(
+        Vec2::new(ARENA_WIDTH, hud::REFERENCE_HEIGHT),
+        SCOREBOARD_FONT_SIZE,
+        save.settings.scoreboard_style,
+    );
+
+    for player in [Player::Left, Player::Right].iter() {
+        commands
+            .spawn_bundle(TextBundle {
+                text: Text {
+                    sections: vec![TextSection {
+                        value: "".to_string(),
+                        style: TextStyle {
+                            font: handle.clone(),
+                            font_size: layout.font_size,
+                            color: theme.player_color(*player),
+                        },
+                    }],
+                    alignment: TextAlignment {
+                        horizontal: HorizontalAlign::Center,
+                        vertical: VerticalAlign::Center,
+                    },
+                },
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        top: Val::Px(layout.top),
+                        left: Val::Px(layout.center(*player)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(*player);
+    }
+}
+
+fn spawn_paddles(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    rapier_config: Res<RapierConfiguration>,
+    tuning: Res<Tuning>,
+    rules: Res<MatchRules>,
+    theme: Res<Theme>,
+    // asset_server: Res<AssetServer>,
+) {
+    let roles: &[PaddleRole] = if rules.doubles {
+        &[PaddleRole::Front, PaddleRole::Back]
+    } else {
+        &[PaddleRole::Solo]
+    };
+
+    let count = rules.paddles_per_side;
+    let height = sub_paddle_height(count, &tuning);
+
+    for player in [Player::Left, Player::Right].iter() {
+        for role in roles.iter() {
+            for index in 0..count {
+                let (bottom, top) = paddle_y_range(index, count, height);
+                let entity = spawn_paddle(
+                    &mut commands,
+                    &mut materials,
+                    &rapier_config,
+                    &tuning,
+                    &theme,
+                    *player,
+                    *role,
+                    height,
+                    (bottom + top) / 2.,
+                );
+
+                if count > 1 {
+                    commands.entity(entity).insert(SubPaddle(index));
+                }
+            }
+        }
+    }
+}
+
+fn spawn_paddle(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    rapier_config: &RapierConfiguration,
+    tuning: &Tuning,
+    theme: &Theme,
+    player: Player,
+    role: PaddleRole,
+    height: f32,
+    y: f32,
+) -> Entity {
+    let sprite_size_x = tuning.paddle_width;
+    let sprite_size_y = height;
+
+    let collider_size_x = sprite_size_x / rapier_config.scale;
+    let collider_size_y = sprite_size_y / rapier_config.scale;
+
+    let body = RigidBodyBuilder::new_dynamic()
+        .translation(
+            paddle_spawn_x(player, role) / rapier_config.scale,
+            y / rapier_config.scale,
+        )
+        // .lock_translations()
+        .ccd_enabled(true)
+        .lock_rotations();
+
+    let density = 20.;
+    let restitution = 1.0;
+    let friction = -0.5;
+
+    // Spawn entity with `Player` struct as a component for access in movement query.
+    commands
+        .spawn()
+        .insert_bundle(SpriteBundle {
+            material: materials.add(theme.player_color(player).into()),
+            sprite: Sprite::new(Vec2::new(sprite_size_x, sprite_size_y)),
+            ..Default::default()
+        })
+        .insert_bundle(physics_bundle(
+            body,
+            ColliderBuilder::cuboid(collider_size_x / 2.0, collider_size_y / 2.0)
+                .density(density)
+                .friction(friction)
+                .restitution(restitution)
+                .collision_groups(InteractionGroups::new(PADDLE_GROUP, !HAZARD_GROUP)),
+        ))
+        .insert(Paddle(PADDLE_SPEED))
+        .insert(role)
+        .insert(player)
+        .insert(Controller::Human)
+        .insert(PaddleIntent::default())
+        .insert(Idle::default())
+        .insert(AiState::default())
+        .insert(PaddleClamp::default())
+        .id()
+}
+
+/// Height of each paddle in a stack of `count`, shrunk so the stack takes at most half the arena.
+pub fn sub_paddle_height(count: u32, tuning: &Tuning) -> f32 {
+    let gaps = SUB_PADDLE_GAP * (count - 1) as f32;
+    tuning
+        .paddle_height
+        .min((ARENA_HEIGHT / 2. - gaps) / count as f32)
+}
+
+/// Vertical range the center of the `index`th paddle from the bottom of a stack may move within.
+/// Every band is as long as the free space, so the stack stops as a whole against a wall.
+pub fn paddle_y_range(index: u32, count: u32, height: f32) -> (f32, f32) {
+    let below = (height + SUB_PADDLE_GAP) * index as f32;
+    let above = (height + SUB_PADDLE_GAP) * (count - 1 - index) as f32;
+    let spare = ARENA_HEIGHT - 2. * WALL_THICKNESS - (below + above + height);
+
+    let bottom = WALL_THICKNESS + height / 2. + below;
+    (bottom, bottom + spare.max(0.))
+}
+
+/// Spawn position of a paddle, back paddles and solo paddles guard the goal.
+pub fn paddle_spawn_x(player: Player, role: PaddleRole) -> f32 {
+    let from_wall = match role {
+        PaddleRole::Solo | PaddleRole::Back => PADDLE_WALL_OFFSET,
+        PaddleRole::Front => ARENA_MIDDLE * 0.75,
+    };
+
+    match player {
+        Player::Left => from_wall,
+        Player::Right => ARENA_WIDTH - from_wall,
+    }
+}
+
+/// Horizontal range a paddle center may move within. Doubles teammates split their half into a
+/// goal band and a middle band so they can't overlap.
+pub fn paddle_x_range(player: Player, role: PaddleRole, paddle_width: f32) -> (f32, f32) {
+    let quarter = ARENA_MIDDLE / 2.;
+    let (near, far) = match role {
+        PaddleRole::Solo => (paddle_width, ARENA_MIDDLE - paddle_width),
+        PaddleRole::Back => (paddle_width, quarter - paddle_width),
+        PaddleRole::Front => (quarter + paddle_width, ARENA_MIDDLE - paddle_width),
+    };
+
+    match player {
+        Player::Left => (near, far),
+        Player::Right => (ARENA_WIDTH - far, ARENA_WIDTH - near),
+    }
+}
+
+/// Every ball starts on the center spot, the intro serves them all at once.
+fn spawn_ball(
+    mut commands: Commands,
+    rapier_config: Res<RapierConfiguration>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    tuning: Res<Tuning>,
+    rules: Res<MatchRules>,
+    theme: Res<Theme>,
+    game_assets: Res<GameAssets>,
+) {
+    for _ in 0..rules.balls.max(1) {
+        // At rest, a push toward either side before the intro pauses the game would favor it
+        let body = RigidBodyBuilder::new_dynamic().translation(
+            ARENA_WIDTH / 2. / rapier_config.scale,
+            ARENA_HEIGHT / 2. / rapier_config.scale,
+        );
+
+        spawn_ball_body(
+            &mut commands,
+            &mut materials,
+            &rapier_config,
+            &tuning,
+            &theme,
+            &game_assets,
+            body,
+        );
+    }
+}
+
+/// Spawns a ball with its sprite, collider, glow and shadow around `body`, which sets where it starts.
+pub fn spawn_ball_body(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    rapier_config: &RapierConfiguration,
+    tuning: &Tuning,
+    theme: &Theme,
+    game_assets: &GameAssets,
+    body: RigidBodyBuilder,
+) -> Entity {
+    let (material_handle, glow_material, shadow_material) =
+        ball_materials(materials, game_assets, theme);
+
+    let sprite_size_x = tuning.ball_size;
+    let sprite_size_y = tuning.ball_size;
+
+    // While we want our sprite to look ~40 px square, we want to keep the physics units smaller
+    // to prevent float rounding problems. To do this, we set the scale factor in RapierConfiguration
+    // and divide our sprite_size by the scale.
+    let collider_size_x = sprite_size_x / rapier_config.scale;
+
+    let body = body
+        .angular_damping(-0.01)
+        // .linear_damping(-0.2)
+        .can_sleep(false)
+        .ccd_enabled(true);
+
+    let density = 0.001;
+    // let density = 5.0;
+    let restitution = 1.1;
+    let friction = 1.4;
+
+    // Spawn entity with `Player` struct as a component for access in movement query.
+    commands
+        .spawn()
+        .insert_bundle(SpriteBundle {
+            material: material_handle.clone(),
+            // material: materials.add(Color::rgb(0.0, 0.0, 0.0).into()),
+            sprite: Sprite::new(Vec2::new(sprite_size_x, sprite_size_y)),
+            ..Default::default()
+        })
+        .insert_bundle(physics_bundle(
+            body,
+            ColliderBuilder::ball(collider_size_x / 2.0)
+                .friction(friction)
+                .restitution(restitution)
+                .density(density)
+                // Balls pass through each other, they all serve from the same spot
+                .collision_groups(InteractionGroups::new(BALL_GROUP, !BALL_GROUP)),
+        ))
+        .insert(Ball(10.0))
+        .insert(PreviousPosition::default())
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(glow_bundle(glow_material, tuning.ball_size))
+                .insert(BallGlow);
+            parent
+                .spawn_bundle(shadow_bundle(shadow_material, tuning.ball_size))
+                .insert(BallShadow);
+        })
+        .id()
+}
+
+fn spawn_walls(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    rapier_config: Res<RapierConfiguration>,
+    tuning: Res<Tuning>,
+    rules: Res<MatchRules>,
+    theme: Res<Theme>,
+) {
+    let sprite_size_x = ARENA_WIDTH;
+    let sprite_size_y = WALL_THICKNESS;
+
+    // While we want our sprite to look ~40 px square, we want to keep the physics units smaller
+    // to prevent float rounding problems. To do this, we set the scale factor in RapierConfiguration
+    // and divide our sprite_size by the scale.
+    let collider_size_x = sprite_size_x / rapier_config.scale;
+    let collider_size_y = sprite_size_y / rapier_config.scale;
+
+    let density = 1.0;
+    let restitution = 1.0;
+    let friction = -1.0;
+
+    // With wrap-around the ball passes through, the walls only keep the paddles in the arena
+    let groups = if rules.wrap_around {
+        InteractionGroups::new(u16::MAX, !BALL_GROUP)
+    } else {
+        InteractionGroups::all()
+    };
+    let visible = Visible {
+        is_visible: !rules.wrap_around,
+        is_transparent: true,
+    };
+    let marker_material = materials.add(theme.wrap_marker.into());
+
+    // Top and bottom, split into segments that bounce less toward the goals. The sprite goes
+    // on the middle segment, the others are bare colliders marked as the same wall.
+    for (wall, y, user_data) in [
+        (Wall::Bottom, sprite_size_y / 2., WALL_BOTTOM),
+        (Wall::Top, ARENA_HEIGHT - sprite_size_y / 2., WALL_TOP),
+    ]
+    .iter()
+    {
+        for (start, end, restitution) in tuning.wall_segments() {
+            let b = RigidBodyBuilder::new_static()
+                .translation(
+                    (start + end) / 2. * collider_size_x,
+                    y / rapier_config.scale,
+                )
+                .lock_rotations();
+            let collider = ColliderBuilder::cuboid(
+                (end - start) * collider_size_x / 2.,
+                collider_size_y / 2.0,
+            )
+            .density(density)
+            .friction(friction)
+            .restitution(restitution)
+            .collision_groups(groups)
+            .user_data(*user_data);
+
+            let mut segment = commands.spawn();
+            segment
+                .insert_bundle(physics_bundle(b, collider))
+                .insert(*wall);
+            if start > 0.5 || end < 0.5 {
+                segment.insert_bundle((Transform::default(), GlobalTransform::default()));
+                continue;
+            }
+
+            segment
+                .insert_bundle(SpriteBundle {
+                    material: materials.add(theme.wall.into()),
+                    sprite: Sprite::new(Vec2::new(sprite_size_x, sprite_size_y)),
+                    visible: visible.clone(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    if rules.wrap_around {
+                        spawn_wrap_markers(parent, marker_material.clone());
+                    }
+                });
+        }
+    }
+
+    // Flanks narrowing the goal mouth, the goal line stays at the arena edge
+    if let Some(goal_mouth) = tuning.goal_mouth {
+        let flank_height = (ARENA_HEIGHT - goal_mouth) / 2.;
+        let flank_size = Vec2::new(sprite_size_y, flank_height);
+
+        for (player, x) in [(Player::Left, 0.), (Player::Right, ARENA_WIDTH)].iter() {
+            for y in [flank_height / 2., ARENA_HEIGHT - flank_height / 2.].iter() {
+                let b = RigidBodyBuilder::new_static()
+                    .translation(x / rapier_config.scale, y / rapier_config.scale)
+                    .lock_rotations();
+
+                commands
+                    .spawn()
+                    .insert_bundle(SpriteBundle {
+                        material: materials.add(theme.wall.into()),
+                        sprite: Sprite::new(flank_size),
+                        ..Default::default()
+                    })
+                    .insert_bundle(physics_bundle(
+                        b,
+                        ColliderBuilder::cuboid(
+                            flank_size.x / 2. / rapier_config.scale,
+                            flank_size.y / 2. / rapier_config.scale,
+                        )
+                        .density(density)
+                        .friction(friction)
+                        .restitution(restitution),
+                    ))
+                    .insert(Wall::Flank(*player));
+            }
+        }
+    }
+}
+
+fn pause_physics(
+    paused: Res<Paused>,
+    phase: Res<MatchPhase>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    if paused.is_changed() || phase.is_changed() {
+        rapier_config.physics_pipeline_active = !paused.0 && !phase.is_finished();
+    }
+}
+
+fn paddle_movement(
+    paused: Res<Paused>,
+    tuning: Res<Tuning>,
+    rules: Res<MatchRules>,
+    rapier_parameters: Res<RapierConfiguration>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    mut limits: EventWriter<PaddleHitLimit>,
+    mut player_info: Query<(
+        Entity,
+        &Paddle,
+        &mut Transform,
+        &RigidBodyHandleComponent,
+        &Player,
+        &PaddleRole,
+        &PaddleIntent,
+        Option<&SubPaddle>,
+        Option<&ReceiverBoost>,
+        Option<&WallJump>,
+        &mut PaddleClamp,
+    )>,
+) {
+    // let lim_top = 20.;
+    // let lim_bottom= ARENA_HEIGHT -20.;
+
+    if paused.0 {
+        return;
+    }
+
+    let sub_height = sub_paddle_height(rules.paddles_per_side, &tuning);
+
+    for (
+        entity,
+        paddle,
+        mut transform,
+        rigid_body_component,
+        player,
+        role,
+        intent,
+        sub_paddle,
+        boost,
+        wall_jump,
+        mut clamp,
+    ) in player_info.iter_mut()
+    {
+        let mut move_delta = Vector2::new(intent.movement.x, intent.movement.y);
+        if move_delta.magnitude() > 1. {
+            move_delta /= move_delta.magnitude();
+        }
+        // Note that the RapierConfiguration::Scale factor is also used here to transform
+        // the move_delta from: 'pixels/second' to 'physics_units/second'
+        move_delta /= rapier_parameters.scale;
+
+        // Update the velocity on the rigid_body_component,
+        // the bevy_rapier plugin will update the Sprite transform.
+        if let Some(rb) = rigid_bodies.get_mut(rigid_body_component.handle()) {
+            // Move paddle
+            let speed_factor = (boost.map_or(1., |boost| boost.multiplier)
+                * wall_jump.map_or(1., |jump| jump.multiplier))
+            .min(tuning.max_paddle_speed_factor);
+            rb.set_linvel(move_delta * paddle.0 * speed_factor, true);
+
+            // Clamp paddle
+            let pos = rb.position();
+            // let delta = move_delta * paddle.0;
+
+            let (lim_left, lim_right) = paddle_x_range(*player, *role, tuning.paddle_width);
+
+            // Scale to physics engine
+            let (lim_left, lim_right) = (
+                lim_left / rapier_parameters.scale,
+                lim_right / rapier_parameters.scale,
+            );
+
+            let clamped_x = pos.translation.x < lim_left || pos.translation.x > lim_right;
+            if pos.translation.x < lim_left {
+                // println!("delta l {:?} ", delta);
+                let mut trans = pos.translation.clone();
+                trans.x = lim_left;
+                teleport_body(rb, &mut transform, trans.into(), rapier_parameters.scale);
+            } else if pos.translation.x > lim_right {
+                // println!("delta r {:?} ", delta);
+                let mut trans = pos.translation.clone();
+                trans.x = lim_right;
+                teleport_body(rb, &mut transform, trans.into(), rapier_parameters.scale);
+            }
+
+            // A wall of paddles keeps its spacing, each paddle stays in its own band. A lone
+            // paddle's band ends at the walls, which would stop it there anyway.
+            let (lim_bottom, lim_top) = match sub_paddle {
+                Some(sub_paddle) => {
+                    paddle_y_range(sub_paddle.0, rules.paddles_per_side, sub_height)
+                }
+                None => paddle_y_range(0, 1, tuning.paddle_height),
+            };
+            let (lim_bottom, lim_top) = (
+                lim_bottom / rapier_parameters.scale,
+                lim_top / rapier_parameters.scale,
+            );
+
+            let mut trans = rb.position().translation;
+            let y = trans.y.max(lim_bottom).min(lim_top);
+            let clamped_y = y != trans.y;
+            if clamped_y {
+                trans.y = y;
+                teleport_body(rb, &mut transform, trans.into(), rapier_parameters.scale);
+            }
+
+            for (clamped, was_clamped, axis) in [
+                (clamped_x, clamp.x, LimitAxis::X),
+                (clamped_y, clamp.y, LimitAxis::Y),
+            ]
+            .iter()
+            {
+                if *clamped && !*was_clamped {
+                    limits.send(PaddleHitLimit {
+                        paddle: entity,
+                        player: *player,
+                        axis: *axis,
+                    });
+                }
+            }
+            if clamp.x != clamped_x || clamp.y != clamped_y {
+                clamp.x = clamped_x;
+                clamp.y = clamped_y;
+            }
+        }
+
+        // *** Angle the paddle **
+        if let Some(rb) = rigid_bodies.get_mut(rigid_body_component.handle()) {
+            let rotation = intent.rotation * 3.;
+            let cur_angle = rb.position().rotation.angle();
+
+            if rotation > 0. && cur_angle <= 0.8 {
+                rb.set_angvel(rotation, true);
+            } else if rotation < 0. && cur_angle >= -0.8 {
+                rb.set_angvel(rotation, true);
+            } else {
+                rb.set_angvel(0.0, true);
+            }
+
+            // println!("Angle: {:?} {} {}", player, cur_angle, rotation);
+        }
+    }
+}
+
+fn ball_goal(
+    phase: Res<MatchPhase>,
+    mut goals: EventWriter<GoalEvent>,
+    ball_info: Query<(Entity, &Transform, &PreviousPosition), (With<Ball>, Without<OutOfPlay>)>,
+) {
+    if phase.is_finished() {
+        return;
+    }
+
+    let lim_left = 0.;
+    let lim_right = ARENA_WIDTH;
+
+    for (ball, transform, previous) in ball_info.iter() {
+        let position = transform.translation.truncate();
+        let (scorer, line_x) = if position.x < lim_left {
+            (Player::Right, lim_left)
+        } else if position.x > lim_right {
+            (Player::Left, lim_right)
+        } else {
+            continue;
+        };
+
+        let crossing_y = match previous.0 {
+            Some(previous) => crossing_y(previous, position, line_x),
+            None => position.y,
+        };
+        goals.send(GoalEvent {
+            scorer,
+            ball,
+            crossing_y: Some(crossing_y),
+        });
+    }
+}
+
+/// Height where the straight path from `from` to `to` crosses `x = line_x`.
+fn crossing_y(from: Vec2, to: Vec2, line_x: f32) -> f32 {
+    let dx = to.x - from.x;
+    if dx.abs() < f32::EPSILON {
+        return to.y;
+    }
+    let t = ((line_x - from.x) / dx).max(0.).min(1.);
+    from.y + (to.y - from.y) * t
+}
+
+/// Runs after goal detection so it still sees the position from the step before.
+fn remember_ball_positions(mut balls: Query<(&Transform, &mut PreviousPosition), With<Ball>>) {
+    for (transform, mut previous) in balls.iter_mut() {
+        previous.0 = Some(transform.translation.truncate());
+    }
+}
+
+/// Scores the goals of this frame right away, several balls may go in at once. A ball that went
+/// in through a goal line flies on and is served again by `serve_out_of_play`, after an awarded
+/// point it is served from here. Balls still in play when the match ends wait on the center spot.
+fn apply_goal(
+    mut commands: Commands,
+    rapier_config: Res<RapierConfiguration>,
+    tuning: Res<Tuning>,
+    rules: Res<MatchRules>,
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut rng: ResMut<GameRng>,
+    clock: Res<MatchClock>,
+    mut phase: ResMut<MatchPhase>,
+    mut end: ResMut<MatchEnd>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    mut score: ResMut<Score>,
+    mut callouts: EventWriter<Callout>,
+    mut serves: EventWriter<ServeEvent>,
+    mut goals: EventReader<GoalEvent>,
+    mut balls: Query<
+        (
+            Entity,
+            &RigidBodyHandleComponent,
+            &mut Transform,
+            Option<&OutOfPlay>,
+        ),
+        With<Ball>,
+    >,
+) {
+    let goals: Vec<&GoalEvent> = goals.iter().collect();
+    if goals.is_empty() {
+        return;
+    }
+
+    let config = RulesConfig::from_rules(&rules);
+    let state = RulesState::new(&score, *phase, &clock);
+    let scorers: Vec<Player> = goals.iter().map(|goal| goal.scorer).collect();
+    for outcome in on_goals(&config, &state, &scorers) {
+        match outcome {
+            RuleOutcome::AwardPoint(player) => {
+                score.award(player);
+                if score.streak == Some((player, STREAK_LENGTH)) {
+                    callouts.send(Callout("On fire!".to_string()));
+                }
+            }
+            RuleOutcome::EndMatch(winner, reason) => {
+                *phase = MatchPhase::Finished(winner);
+                end.0 = reason;
+            }
+            RuleOutcome::StartOvertime => *phase = MatchPhase::Overtime,
+        }
+    }
+
+    for goal in goals.iter() {
+        let scorer = goal.scorer;
+        if goal.crossing_y.is_some() {
+            commands.entity(goal.ball).insert(OutOfPlay {
+                timer: Timer::from_seconds(OUT_OF_PLAY_SECONDS, false),
+                scorer,
+                final_goal: phase.is_finished(),
+            });
+            continue;
+        }
+
+        let velocity = serve_after_goal(
+            scorer,
+            *phase,
+            &keyboard_input,
+            &bindings,
+            &mut rng,
+            &rules,
+            &tuning,
+            &score,
+            &mut serves,
+        );
+        if let Ok((_, rigid_body_component, mut transform, _)) = balls.get_mut(goal.ball) {
+            if let Some(rb) = rigid_bodies.get_mut(rigid_body_component.handle()) {
+                reset_ball(rb, &mut transform, &rapier_config, velocity);
+            }
+        }
+    }
+
+    if !phase.is_finished() {
+        return;
+    }
+    for (ball, rigid_body_component, mut transform, out) in balls.iter_mut() {
+        if out.is_some() || goals.iter().any(|goal| goal.ball == ball) {
+            continue;
+        }
+        if let Some(rb) = rigid_bodies.get_mut(rigid_body_component.handle()) {
+            reset_ball(rb, &mut transform, &rapier_config, Vector2::zeros());
+        }
+    }
+}
+
+/// Velocity of the serve after `scorer` scored, none once the match is over. The player who
+/// conceded serves, a lob or flat serve goes to the scorer.
+fn serve_after_goal(
+    scorer: Player,
+    phase: MatchPhase,
+    keyboard_input: &Input<KeyCode>,
+    bindings: &KeyBindings,
+    rng: &mut GameRng,
+    rules: &MatchRules,
+    tuning: &Tuning,
+    score: &Score,
+    serves: &mut EventWriter<ServeEvent>,
+) -> Vector2<f32> {
+    if phase.is_finished() {
+        return Vector2::zeros();
+    }
+
+    let server = scorer.opponent();
+    let kind = ServeKind::held(keyboard_input, bindings.for_player(&server));
+    let toward = if kind == ServeKind::Standard {
+        None
+    } else {
+        Some(scorer)
+    };
+    let velocity = serve_velocity(rng, rules, tuning, toward, kind, tuning.serve_speed);
+    let velocity = score.with_streak_bonus(velocity, tuning);
+    serves.send(ServeEvent::from_velocity(&velocity, kind));
+    velocity
+}
+
+/// Serves a ball that went in once it had its moment past the goal line, or is far enough out.
+/// A rematch started in the meantime has already put the ball back.
+fn serve_out_of_play(
+    mut commands: Commands,
+    time: Res<Time>,
+    paused: Res<Paused>,
+    phase: Res<MatchPhase>,
+    rules: Res<MatchRules>,
+    tuning: Res<Tuning>,
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    rapier_config: Res<RapierConfiguration>,
+    score: Res<Score>,
+    mut rng: ResMut<GameRng>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    mut serves: EventWriter<ServeEvent>,
+    mut balls: Query<(
+        Entity,
+        &mut Transform,
+        &RigidBodyHandleComponent,
+        &mut OutOfPlay,
+    )>,
+) {
+    if paused.0 {
+        return;
+    }
+
+    for (ball, mut transform, rigid_body_component, mut out) in balls.iter_mut() {
+        let x = transform.translation.x;
+        let far_out = x < -OUT_OF_PLAY_DISTANCE || x > ARENA_WIDTH + OUT_OF_PLAY_DISTANCE;
+        let rematched = out.final_goal && !phase.is_finished();
+        if !out.timer.tick(time.delta()).finished() && !far_out && !rematched {
+            continue;
+        }
+
+        commands.entity(ball).remove::<OutOfPlay>();
+        if rematched {
+            continue;
+        }
+
+        let velocity = serve_after_goal(
+            out.scorer,
+            *phase,
+            &keyboard_input,
+            &bindings,
+            &mut rng,
+            &rules,
+            &tuning,
+            &score,
+            &mut serves,
+        );
+        if let Some(rb) = rigid_bodies.get_mut(rigid_body_component.handle()) {
+            reset_ball(rb, &mut transform, &rapier_config, velocity);
+        }
+    }
+}
+
+/// Serve velocity in physics units. A serve toward a player is launched within the envelope of
+/// its kind aimed at their goal. A standard serve toward nobody goes in any direction, the other
+/// kinds pick a side.
+pub fn serve_velocity(
+    rng: &mut GameRng,
+    rules: &MatchRules,
+    tuning: &Tuning,
+    toward: Option<Player>,
+    kind: ServeKind,
+    speed: f32,
+) -> Vector2<f32> {
+    let player = match (toward, kind) {
+        (Some(player), _) => player,
+        (None, ServeKind::Standard) => {
+            let angle = rng.f32() * std::f32::consts::PI * 2.;
+            return Vector2::new(f32::cos(angle), f32::sin(angle)) * speed;
+        }
+        (None, _) if rng.bool() => Player::Left,
+        (None, _) => Player::Right,
+    };
+
+    // Angle above the horizontal toward the receiver's goal
+    let (angle, speed) = match kind {
+        ServeKind::Standard => ((rng.f32() * 2. - 1.) * rules.serve_spread(), speed),
+        ServeKind::Lob => (
+            LOB_ANGLES.0 + rng.f32() * (LOB_ANGLES.1 - LOB_ANGLES.0),
+            speed * LOB_SPEED_FACTOR,
+        ),
+        ServeKind::Flat => (
+            (rng.f32() * 2. - 1.) * FLAT_SPREAD,
+            speed * FLAT_SPEED_FACTOR,
+        ),
+    };
+    // Aimed against the pull of the receiver's half, so the ball curves back on course
+    let angle = if rules.gravity_zones {
+        angle - serve_tilt(player, speed, tuning)
+    } else {
+        angle
+    };
+    let angle = match player {
+        Player::Left => std::f32::consts::PI - angle,
+        Player::Right => angle,
+    };
+
+    Vector2::new(f32::cos(angle), f32::sin(angle)) * speed
+}
+
+/// Puts the ball back on the center spot with the given velocity.
+pub fn reset_ball(
+    rb: &mut RigidBody,
+    transform: &mut Transform,
+    rapier_config: &RapierConfiguration,
+    linvel: Vector2<f32>,
+) {
+    let x = ARENA_WIDTH / 2. / rapier_config.scale;
+    let y = ARENA_HEIGHT / 2. / rapier_config.scale;
+    let start_pos = Isometry2::translation(x, y);
+
+    rb.set_linvel(linvel, true);
+    rb.set_angvel(0., true);
+    teleport_body(rb, transform, start_pos, rapier_config.scale);
+}
+
+fn clamp_ball_speed(
+    tuning: Res<Tuning>,
+    phase: Res<MatchPhase>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    balls: Query<(&RigidBodyHandleComponent, Option<&PowerShot>), With<Ball>>,
+) {
+    let cap = match *phase {
+        MatchPhase::Overtime => tuning.max_ball_speed * OVERTIME_SPEED_FACTOR,
+        _ => tuning.max_ball_speed,
+    };
+
+    for (rigid_body_component, power_shot) in balls.iter() {
+        // A power shot may break the cap by a bounded amount until the next hit
+        let cap = if power_shot.is_some() {
+            cap * POWER_SHOT_CAP_FACTOR
+        } else {
+            cap
+        };
+
+        if let Some(rb) = rigid_bodies.get_mut(rigid_body_component.handle()) {
+            let linvel = *rb.linvel();
+            if linvel.magnitude() > cap {
+                rb.set_linvel(linvel.normalize() * cap, true);
+            }
+        }
+    }
+}
+
+/// How each half of the scoreboard shows the score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScoreboardStyle {
+    /// Just the player's own goals.
+    Plain,
+    /// Own goals and the opponent's, `5 – 3`.
+    Versus,
+    /// The leader's half also shows the goal difference, `5 (+2)`.
+    Difference,
+}
+
+impl Default for ScoreboardStyle {
+    fn default() -> Self {
+        ScoreboardStyle::Plain
+    }
+}
+
+impl ScoreboardStyle {
+    pub fn next(self) -> Self {
+        match self {
+            ScoreboardStyle::Plain => ScoreboardStyle::Versus,
+            ScoreboardStyle::Versus => ScoreboardStyle::Difference,
+            ScoreboardStyle::Difference => ScoreboardStyle::Plain,
+        }
+    }
+
+    /// Text for the half of a player with `own` goals against `other`.
+    pub fn format(self, own: u32, other: u32) -> String {
+        match self {
+            ScoreboardStyle::Plain => format!("{}", own),
+            ScoreboardStyle::Versus => format!("{} – {}", own, other),
+            ScoreboardStyle::Difference if own > other => format!("{} (+{})", own, own - other),
+            ScoreboardStyle::Difference => format!("{}", own),
+        }
+    }
+}
+
+fn render_scoreboard(
+    score: Res<Score>,
+    save: Res<SaveData>,
+    mut query: Query<(&mut Text, &Player)>,
+) {
+    let style = save.settings.scoreboard_style;
+    // let mut text = query.single_mut().unwrap();
+    for (mut text, player) in query.iter_mut() {
+        let own = score.of(*player);
+        let other = score.of(player.opponent());
+        text.sections[0].value = style.format(own, other);
+    }
+}
+
+/// Font size of the scores in a window of the HUD's reference height.
+const SCOREBOARD_FONT_SIZE: f32 = 96.;
+
+/// Where the scores go on screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreboardLayout {
+    pub font_size: f32,
+    /// Distance of the top of the text from the top of the window.
+    pub top: f32,
+    left_center: f32,
+    right_center: f32,
+}
+
+impl ScoreboardLayout {
+    /// Horizontal center of the score of `player`.
+    pub fn center(&self, player: Player) -> f32 {
+        match player {
+            Player::Left => self.left_center,
+            Player::Right => self.right_center,
+        }
+    }
+}
+
+/// Lays out the scores for a window `window` pixels large. Each score sits vertically centered
+/// in the middle of its half, the font scales with the window height and the longer styles get a
+/// smaller one so they stay clear of the center line.
+pub fn scoreboard_layout(window: Vec2, font_size: f32, style: ScoreboardStyle) -> ScoreboardLayout {
+    let style_factor = match style {
+        ScoreboardStyle::Plain => 1.,
+        ScoreboardStyle::Versus | ScoreboardStyle::Difference => 0.75,
+    };
+    let font_size = font_size * window.y / hud::REFERENCE_HEIGHT * style_factor;
+
+    ScoreboardLayout {
+        font_size,
+        top: window.y / 2. - font_size / 2.,
+        left_center: window.x / 4.,
+        right_center: window.x * 3. / 4.,
+    }
+}
+
+/// Keeps the scoreboard laid out for the window and the chosen style. The text is centered on its position using the size the layout measured for it.
+fn layout_scoreboard(
+    windows: Res<Windows>,
+    save: Res<SaveData>,
+    mut query: Query<(&Node, &Player, &mut Style, &mut Text)>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => Vec2::new(window.width(), window.height()),
+        None => Vec2::new(ARENA_WIDTH, hud::REFERENCE_HEIGHT),
+    };
+    let layout = scoreboard_layout(window, SCOREBOARD_FONT_SIZE, save.settings.scoreboard_style);
+
+    for (node, player, mut style, mut text) in query.iter_mut() {
+        // Only touch what moved, writing the text makes it measure again
+        if text.sections[0].style.font_size != layout.font_size {
+            for section in text.sections.iter_mut() {
+                section.style.font_size = layout.font_size;
+            }
+        }
+
+        let position = Rect {
+            left: Val::Px(layout.center(*player) - node.size.x / 2.),
+            top: Val::Px(layout.top),
+            ..style.position
+        };
+        if style.position != position {
+            style.position = position;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [ServeKind; 3] = [ServeKind::Standard, ServeKind::Lob, ServeKind::Flat];
+
+    /// Velocities of a run of serves of every kind, as drawn from `seed`.
+    fn serves(seed: u64, toward: Option<Player>, rules: &MatchRules) -> Vec<(f32, f32)> {
+        let mut rng = GameRng::new(seed);
+        let tuning = Tuning::default();
+        (0..20)
+            .map(|serve| {
+                let kind = KINDS[serve % KINDS.len()];
+                let velocity =
+                    serve_velocity(&mut rng, rules, &tuning, toward, kind, tuning.serve_speed);
+                (velocity.x, velocity.y)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn serves_are_reproducible_from_the_seed() {
+        let rules = MatchRules::default();
+        for toward in [None, Some(Player::Left), Some(Player::Right)].iter() {
+            assert_eq!(serves(42, *toward, &rules), serves(42, *toward, &rules));
+            assert_ne!(serves(42, *toward, &rules), serves(43, *toward, &rules));
+        }
+    }
+
+    const STYLES: [ScoreboardStyle; 3] = [
+        ScoreboardStyle::Plain,
+        ScoreboardStyle::Versus,
+        ScoreboardStyle::Difference,
+    ];
+    const WINDOWS: [(f32, f32); 3] = [(1000., 600.), (1920., 1080.), (640., 900.)];
+
+    #[test]
+    fn scores_sit_in_the_middle_of_their_half() {
+        for style in STYLES.iter() {
+            for (width, height) in WINDOWS.iter() {
+                let layout =
+                    scoreboard_layout(Vec2::new(*width, *height), SCOREBOARD_FONT_SIZE, *style);
+
+                assert_eq!(layout.center(Player::Left), width / 4.);
+                assert_eq!(layout.center(Player::Right), width * 3. / 4.);
+                assert!(layout.top >= 0., "{:?} in {}x{}", style, width, height);
+                assert!(layout.top + layout.font_size <= *height);
+                assert_eq!(layout.top + layout.font_size / 2., height / 2.);
+            }
+        }
+    }
+
+    #[test]
+    fn scoreboard_scales_with_the_window() {
+        for style in STYLES.iter() {
+            let small = scoreboard_layout(Vec2::new(1000., 600.), SCOREBOARD_FONT_SIZE, *style);
+            let large = scoreboard_layout(Vec2::new(1000., 1200.), SCOREBOARD_FONT_SIZE, *style);
+            assert_eq!(large.font_size, small.font_size * 2.);
+        }
+
+        let window = Vec2::new(1000., 600.);
+        let plain = scoreboard_layout(window, SCOREBOARD_FONT_SIZE, ScoreboardStyle::Plain);
+        let versus = scoreboard_layout(window, SCOREBOARD_FONT_SIZE, ScoreboardStyle::Versus);
+        assert!(versus.font_size < plain.font_size);
+    }
+
+    fn mirrored(x: f32) -> f32 {
+        ARENA_WIDTH - x
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    #[test]
+    fn serves_mirror_between_the_sides() {
+        let tuning = Tuning::default();
+        let wrap_around = MatchRules {
+            wrap_around: true,
+            ..MatchRules::default()
+        };
+        for rules in [MatchRules::default(), wrap_around].iter() {
+            for kind in KINDS.iter() {
+                for seed in 0..20 {
+                    let serve = |toward| {
+                        let mut rng = GameRng::new(seed);
+                        serve_velocity(&mut rng, rules, &tuning, Some(toward), *kind, 20.)
+                    };
+                    let (left, right) = (serve(Player::Left), serve(Player::Right));
+                    assert!(left.x < 0. && right.x > 0., "{:?} seed {}", kind, seed);
+                    assert!(close(left.x, -right.x), "{:?} seed {}", kind, seed);
+                    assert!(close(left.y, right.y), "{:?} seed {}", kind, seed);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn gravity_zone_tilt_mirrors_between_the_sides() {
+        let tuning = Tuning::default();
+        for speed in [5., 20., 60.].iter() {
+            let left = serve_tilt(Player::Left, *speed, &tuning);
+            let right = serve_tilt(Player::Right, *speed, &tuning);
+            assert!(close(left, -right), "{} against {}", left, right);
+        }
+    }
+
+    #[test]
+    fn paddles_mirror_between_the_sides() {
+        for role in [PaddleRole::Solo, PaddleRole::Front, PaddleRole::Back].iter() {
+            let left = paddle_spawn_x(Player::Left, *role);
+            let right = paddle_spawn_x(Player::Right, *role);
+            assert!(close(left, mirrored(right)), "{:?}", role);
+
+            for width in [PADDLE_WIDTH, 25.].iter() {
+                let (left_near, left_far) = paddle_x_range(Player::Left, *role, *width);
+                let (right_far, right_near) = paddle_x_range(Player::Right, *role, *width);
+                assert!(close(left_near, mirrored(right_near)), "{:?}", role);
+                assert!(close(left_far, mirrored(right_far)), "{:?}", role);
+                assert!(left_far <= ARENA_MIDDLE && right_far >= ARENA_MIDDLE);
+            }
+        }
+    }
+
+    #[test]
+    fn paddle_stacks_mirror_top_to_bottom() {
+        let tuning = Tuning::default();
+        for count in 1..=4 {
+            let height = sub_paddle_height(count, &tuning);
+            for index in 0..count {
+                let (bottom, _) = paddle_y_range(index, count, height);
+                let (_, top) = paddle_y_range(count - 1 - index, count, height);
+                assert!(
+                    close(bottom, ARENA_HEIGHT - top),
+                    "paddle {} of {}",
+                    index,
+                    count
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn goal_lines_mirror_between_the_sides() {
+        let paths = [
+            (Vec2::new(10., 200.), Vec2::new(-15., 260.)),
+            (Vec2::new(3., 500.), Vec2::new(-40., 420.)),
+            (Vec2::new(-2., 300.), Vec2::new(-2., 310.)),
+        ];
+        for (from, to) in paths.iter() {
+            let mirror = |point: &Vec2| Vec2::new(mirrored(point.x), point.y);
+            let left = crossing_y(*from, *to, 0.);
+            let right = crossing_y(mirror(from), mirror(to), ARENA_WIDTH);
+            assert!(close(left, right), "{} against {}", left, right);
+        }
+    }
+
+    #[test]
+    fn scoreboard_mirrors_about_the_center() {
+        for style in STYLES.iter() {
+            for (width, height) in WINDOWS.iter() {
+                let layout =
+                    scoreboard_layout(Vec2::new(*width, *height), SCOREBOARD_FONT_SIZE, *style);
+                let left_gap = layout.center(Player::Left);
+                let right_gap = width - layout.center(Player::Right);
+                assert!(
+                    close(left_gap, right_gap),
+                    "{:?} in {}x{}",
+                    style,
+                    width,
+                    height
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn crossing_height_follows_the_path() {
+        let line = 0.;
+        assert!(close(
+            crossing_y(Vec2::new(10., 100.), Vec2::new(-10., 200.), line),
+            150.
+        ));
+        assert!(close(
+            crossing_y(Vec2::new(30., 300.), Vec2::new(-10., 260.), line),
+            270.
+        ));
+        // Already past the line on the step before, it crossed where it was then
+        assert!(close(
+            crossing_y(Vec2::new(-5., 100.), Vec2::new(-10., 200.), line),
+            100.
+        ));
+        // Straight up or down, the line is crossed where the ball is
+        assert!(close(
+            crossing_y(Vec2::new(-3., 100.), Vec2::new(-3., 140.), line),
+            140.
+        ));
+    }
+
+    /// The goal pipeline the way the plugins register it, with the goal systems of this file
+    /// added backwards so only their labels can put them in order.
+    fn goal_app() -> App {
+        let mut app = App::build();
+        app.add_event::<GoalEvent>()
+            .add_event::<ServeEvent>()
+            .add_event::<Callout>()
+            .add_event::<contacts::BallContact>()
+            .insert_resource(RapierConfiguration::default())
+            .insert_resource(RigidBodySet::new())
+            .insert_resource(Input::<KeyCode>::default())
+            .insert_resource(KeyBindings::default())
+            .insert_resource(GameRng::new(7))
+            .insert_resource(Tuning::default())
+            .init_resource::<MatchRules>()
+            .init_resource::<MatchClock>()
+            .init_resource::<MatchPhase>()
+            .init_resource::<MatchEnd>()
+            .init_resource::<Score>()
+            .init_resource::<SaveData>()
+            .init_resource::<Tournament>()
+            .init_resource::<Queue>()
+            .add_state(AppState::InGame)
+            .add_plugin(StatsPlugin)
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(
+                        render_scoreboard
+                            .system()
+                            .label(GameLabel::GoalPresent)
+                            .after(GameLabel::GoalApply),
+                    )
+                    .with_system(
+                        apply_goal
+                            .system()
+                            .label(GameLabel::GoalApply)
+                            .after(GameLabel::GoalDetect),
+                    )
+                    .with_system(
+                        ball_goal
+                            .system()
+                            .label(GameLabel::GoalDetect)
+                            .after(GameLabel::Contacts),
+                    ),
+            );
+        app.app
+    }
+
+    #[test]
+    fn a_goal_shows_everywhere_in_its_frame() {
+        let mut app = goal_app();
+        app.update();
+
+        let world = &mut app.world;
+        for player in [Player::Left, Player::Right].iter() {
+            world.spawn().insert_bundle((
+                Text::with_section("0", TextStyle::default(), TextAlignment::default()),
+                *player,
+            ));
+        }
+        let ball = world
+            .spawn()
+            .insert_bundle((
+                Ball(10.),
+                Transform::from_xyz(ARENA_WIDTH + 5., 300., 0.),
+                PreviousPosition(Some(Vec2::new(ARENA_WIDTH - 5., 280.))),
+            ))
+            .id();
+
+        app.update();
+
+        let score = app.world.get_resource::<Score>().unwrap().clone();
+        assert_eq!((score.left, score.right), (1, 0));
+        let style = app
+            .world
+            .get_resource::<SaveData>()
+            .unwrap()
+            .settings
+            .scoreboard_style;
+        let mut texts = app.world.query::<(&Text, &Player)>();
+        for (text, player) in texts.iter(&app.world) {
+            let expected = style.format(score.of(*player), score.of(player.opponent()));
+            assert_eq!(text.sections[0].value, expected, "{:?}", player);
+        }
+
+        let stats = app.world.get_resource::<stats::MatchStats>().unwrap();
+        assert_eq!(stats.point_winners, vec![Player::Left]);
+        assert_eq!(stats.aces.get(&Player::Left), Some(&1));
+
+        let out = app.world.get::<OutOfPlay>(ball).unwrap();
+        assert_eq!(out.scorer, Player::Left);
+        assert!(!out.final_goal);
+        assert_eq!(
+            *app.world.get_resource::<MatchPhase>().unwrap(),
+            MatchPhase::Regular
+        );
+
+        // Out of play the ball isn't scored again while it flies on
+        app.update();
+        let score = app.world.get_resource::<Score>().unwrap();
+        assert_eq!((score.left, score.right), (1, 0));
+    }
+
+    #[test]
+    fn opposite_goals_in_one_frame_only_take_their_balls() {
+        let mut app = goal_app();
+        app.world
+            .get_resource_mut::<MatchRules>()
+            .unwrap()
+            .score_limit = Some(5);
+        *app.world.get_resource_mut::<Score>().unwrap() = Score {
+            left: 4,
+            right: 4,
+            streak: None,
+        };
+        app.update();
+
+        let world = &mut app.world;
+        let mut ball = |x: f32, previous_x: f32| {
+            world
+                .spawn()
+                .insert_bundle((
+                    Ball(10.),
+                    Transform::from_xyz(x, 300., 0.),
+                    PreviousPosition(Some(Vec2::new(previous_x, 300.))),
+                ))
+                .id()
+        };
+        let into_right = ball(ARENA_WIDTH + 5., ARENA_WIDTH - 5.);
+        let into_left = ball(-5., 5.);
+        let in_play = ball(ARENA_MIDDLE, ARENA_MIDDLE - 10.);
+        let paddle = (Player::Left, PaddleRole::Solo);
+        {
+            let mut stats = world.get_resource_mut::<stats::MatchStats>().unwrap();
+            stats.rally.insert(into_right, 2);
+            stats.rally.insert(in_play, 3);
+            stats.last_touch.insert(into_right, paddle);
+            stats.last_touch.insert(in_play, paddle);
+        }
+
+        app.update();
+
+        // Both points count and the level score goes to overtime, whichever ball came first
+        let score = app.world.get_resource::<Score>().unwrap();
+        assert_eq!((score.left, score.right), (5, 5));
+        assert_eq!(
+            *app.world.get_resource::<MatchPhase>().unwrap(),
+            MatchPhase::Overtime
+        );
+        assert_eq!(
+            app.world.get::<OutOfPlay>(into_right).unwrap().scorer,
+            Player::Left
+        );
+        assert_eq!(
+            app.world.get::<OutOfPlay>(into_left).unwrap().scorer,
+            Player::Right
+        );
+        assert!(app.world.get::<OutOfPlay>(in_play).is_none());
+
+        // Only the rallies of the balls that went in end
+        let stats = app.world.get_resource::<stats::MatchStats>().unwrap();
+        assert_eq!(stats.rally.get(&in_play), Some(&3));
+        assert_eq!(stats.rally.get(&into_right), None);
+        assert_eq!(stats.last_touch.get(&in_play), Some(&paddle));
+        assert_eq!(stats.last_touch.get(&into_right), None);
+        assert_eq!(stats.aces.get(&Player::Left), None);
+        assert_eq!(stats.aces.get(&Player::Right), Some(&1));
+    }
+
+    fn on_fire(player: Player) -> Score {
+        let mut score = Score::default();
+        for _ in 0..STREAK_LENGTH {
+            score.award(player);
+        }
+        score
+    }
+
+    #[test]
+    fn streak_bonus_goes_on_serves_to_the_opponent() {
+        let tuning = Tuning::default();
+        let toward_left = Vector2::new(-10., 2.);
+        let toward_right = Vector2::new(10., 2.);
+
+        let score = on_fire(Player::Left);
+        assert_eq!(
+            score.with_streak_bonus(toward_right, &tuning),
+            toward_right * tuning.streak_serve_bonus
+        );
+        assert_eq!(score.with_streak_bonus(toward_left, &tuning), toward_left);
+
+        let score = on_fire(Player::Right);
+        assert_eq!(
+            score.with_streak_bonus(toward_left, &tuning),
+            toward_left * tuning.streak_serve_bonus
+        );
+        assert_eq!(score.with_streak_bonus(toward_right, &tuning), toward_right);
+    }
+
+    #[test]
+    fn no_streak_bonus_before_or_after_the_streak() {
+        let tuning = Tuning::default();
+        let serve = Vector2::new(10., -3.);
+
+        let mut score = Score::default();
+        for _ in 1..STREAK_LENGTH {
+            score.award(Player::Left);
+            assert_eq!(score.with_streak_bonus(serve, &tuning), serve);
+        }
+
+        let mut score = on_fire(Player::Left);
+        score.award(Player::Right);
+        assert_eq!(score.with_streak_bonus(serve, &tuning), serve);
+        assert_eq!(score.with_streak_bonus(-serve, &tuning), -serve);
+    }
+
+    #[test]
+    fn serve_after_a_streak_goal_is_faster_for_the_conceder() {
+        let mut world = World::default();
+        world.insert_resource(Events::<ServeEvent>::default());
+        world.insert_resource(GameRng::new(3));
+        world.insert_resource(on_fire(Player::Left));
+
+        fn serve(mut rng: ResMut<GameRng>, score: Res<Score>, mut serves: EventWriter<ServeEvent>) {
+            let tuning = Tuning::default();
+            let velocity = serve_after_goal(
+                Player::Left,
+                MatchPhase::Regular,
+                &Input::default(),
+                &KeyBindings::default(),
+                &mut rng,
+                &MatchRules::default(),
+                &tuning,
+                &score,
+                &mut serves,
+            );
+            let speed = velocity.magnitude();
+            let expected = match serve_receiver(&velocity) {
+                Player::Right => tuning.serve_speed * tuning.streak_serve_bonus,
+                Player::Left => tuning.serve_speed,
+            };
+            assert!(close(speed, expected), "{} toward {:?}", speed, velocity);
+        }
+        for _ in 0..20 {
+            SystemStage::single(serve.system()).run(&mut world);
+        }
+
+        let events = world.get_resource::<Events<ServeEvent>>().unwrap();
+        assert_eq!(events.get_reader().iter(events).count(), 20);
+    }
+}
@@ -0,0 +1,162 @@
+use bevy::{asset::LoadState, prelude::*};
+
+use crate::cleanup::Cleanup;
+use crate::startup::StartupReport;
+use crate::{AppState, UiFont, ARENA_HEIGHT, ARENA_MIDDLE, ARENA_WIDTH};
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_enter(AppState::Loading).with_system(start_loading.system()),
+        )
+        .add_system_set(SystemSet::on_update(AppState::Loading).with_system(check_loading.system()))
+        .add_system_set(
+            SystemSet::on_exit(AppState::Loading).with_system(cleanup_loading.system()),
+        );
+    }
+}
+
+/// Give up waiting after this long and use fallbacks for whatever isn't ready.
+const LOAD_TIMEOUT: f32 = 10.0;
+
+/// Under the assets directory, a missing directory shows up as all three failing.
+const FONT_PATH: &str = "fonts/Pattaya-Regular.ttf";
+const BALL_PATH: &str = "sprites/ball.png";
+const CROWN_PATH: &str = "sprites/crown.png";
+
+const BAR_WIDTH: f32 = ARENA_WIDTH / 2.;
+const BAR_HEIGHT: f32 = 20.;
+
+/// Assets resolved by the loading state, either loaded or replaced by a fallback.
+pub struct GameAssets {
+    pub ball_texture: Option<Handle<Texture>>,
+    pub crown_texture: Option<Handle<Texture>>,
+}
+
+struct Loading {
+    font: Handle<Font>,
+    ball: Handle<Texture>,
+    crown: Handle<Texture>,
+    timer: Timer,
+}
+
+impl Loading {
+    fn handles(&self) -> [HandleUntyped; 3] {
+        [
+            self.font.clone_untyped(),
+            self.ball.clone_untyped(),
+            self.crown.clone_untyped(),
+        ]
+    }
+}
+
+struct LoadingBar;
+
+fn start_loading(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(Loading {
+        font: asset_server.load(FONT_PATH),
+        ball: asset_server.load(BALL_PATH),
+        crown: asset_server.load(CROWN_PATH),
+        timer: Timer::from_seconds(LOAD_TIMEOUT, false),
+    });
+
+    // No font is available yet, so progress is a plain bar of quads
+    commands
+        .spawn_bundle(SpriteBundle {
+            material: materials.add(Color::rgb(0.2, 0.2, 0.2).into()),
+            sprite: Sprite::new(Vec2::new(BAR_WIDTH, BAR_HEIGHT)),
+            transform: Transform::from_xyz(ARENA_MIDDLE, ARENA_HEIGHT / 2., 0.),
+            ..Default::default()
+        })
+        .insert(Cleanup(AppState::Loading));
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            material: materials.add(Color::rgb(1.0, 1.0, 1.0).into()),
+            sprite: Sprite::new(Vec2::new(0., BAR_HEIGHT)),
+            transform: Transform::from_xyz(ARENA_MIDDLE - BAR_WIDTH / 2., ARENA_HEIGHT / 2., 1.),
+            ..Default::default()
+        })
+        .insert(Cleanup(AppState::Loading))
+        .insert(LoadingBar);
+}
+
+fn check_loading(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    mut loading: ResMut<Loading>,
+    mut report: ResMut<StartupReport>,
+    mut state: ResMut<State<AppState>>,
+    mut bar: Query<(&mut Sprite, &mut Transform), With<LoadingBar>>,
+) {
+    loading.timer.tick(time.delta());
+
+    let handles = loading.handles();
+    let settled = handles
+        .iter()
+        .filter(|handle| {
+            matches!(
+                asset_server.get_load_state(handle.id),
+                LoadState::Loaded | LoadState::Failed
+            )
+        })
+        .count();
+
+    let progress = settled as f32 / handles.len() as f32;
+    for (mut sprite, mut transform) in bar.iter_mut() {
+        sprite.size.x = BAR_WIDTH * progress;
+        transform.translation.x = ARENA_MIDDLE - BAR_WIDTH / 2. + sprite.size.x / 2.;
+    }
+
+    let group_state = asset_server.get_group_load_state(handles.iter().map(|handle| handle.id));
+    if group_state != LoadState::Loaded && settled < handles.len() && !loading.timer.finished() {
+        return;
+    }
+
+    // Everything is settled or we timed out, pick a fallback for each asset that isn't loaded
+    if asset_server.get_load_state(loading.font.id) != LoadState::Loaded {
+        report.warn(format!(
+            "Font {} failed to load, text will not be rendered",
+            FONT_PATH
+        ));
+    }
+
+    let ball_texture = if asset_server.get_load_state(loading.ball.id) == LoadState::Loaded {
+        Some(loading.ball.clone())
+    } else {
+        report.warn(format!(
+            "Ball sprite {} failed to load, using a plain quad",
+            BALL_PATH
+        ));
+        None
+    };
+
+    let crown_texture = if asset_server.get_load_state(loading.crown.id) == LoadState::Loaded {
+        Some(loading.crown.clone())
+    } else {
+        report.warn(format!(
+            "Crown sprite {} failed to load, using a plain quad",
+            CROWN_PATH
+        ));
+        None
+    };
+
+    commands.insert_resource(UiFont(loading.font.clone()));
+    commands.insert_resource(GameAssets {
+        ball_texture,
+        crown_texture,
+    });
+
+    state.set(AppState::InGame).unwrap();
+}
+
+fn cleanup_loading(mut commands: Commands) {
+    commands.remove_resource::<Loading>();
+}
@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+use bevy_rapier2d::physics::RigidBodyHandleComponent;
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+use bevy_rapier2d::rapier::na::Vector2;
+
+use crate::contacts::{BallContact, Surface};
+use crate::stats::MatchStats;
+use crate::tuning::Tuning;
+use crate::{AppState, Ball, GameLabel};
+
+/// Long rallies wind down. Past `long_rally_hits` paddle hits every wall bounce gives the ball
+/// back a little less, so a marathon at the speed cap ends up decided. Off unless
+/// `long_rally_decay` is set.
+pub struct LongRallyPlugin;
+
+impl Plugin for LongRallyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame).with_system(
+                damp_wall_bounces
+                    .system()
+                    .after(GameLabel::Contacts)
+                    .before("clamp_ball_speed"),
+            ),
+        );
+    }
+}
+
+/// A collider's restitution can't change from one bounce to the next, so the solver bounces the
+/// ball in full and the part of its velocity away from the wall is scaled down afterwards. The
+/// rally of each ball is its own and ends with every point.
+fn damp_wall_bounces(
+    tuning: Res<Tuning>,
+    stats: Res<MatchStats>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    mut contacts: EventReader<BallContact>,
+    balls: Query<&RigidBodyHandleComponent, With<Ball>>,
+) {
+    for contact in contacts.iter() {
+        if !contact.started || !matches!(contact.surface, Surface::Wall(_)) {
+            continue;
+        }
+
+        let rally = stats.rally.get(&contact.ball).copied().unwrap_or(0);
+        let bounce = tuning.long_rally_bounce(rally);
+        if bounce >= 1. {
+            continue;
+        }
+
+        let rb = balls
+            .get(contact.ball)
+            .ok()
+            .and_then(|rigid_body_component| rigid_bodies.get_mut(rigid_body_component.handle()));
+        let rb = match rb {
+            Some(rb) => rb,
+            None => continue,
+        };
+
+        let velocity = Vec2::new(rb.linvel().x, rb.linvel().y);
+        let away = velocity.dot(contact.normal);
+        if away <= 0. {
+            continue;
+        }
+        let damped = velocity - contact.normal * away * (1. - bounce);
+        rb.set_linvel(Vector2::new(damped.x, damped.y), true);
+    }
+}
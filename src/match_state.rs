@@ -0,0 +1,462 @@
+use bevy::prelude::*;
+use bevy_rapier2d::physics::{RapierConfiguration, RigidBodyHandleComponent};
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+use bevy_rapier2d::rapier::na::Vector2;
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::KeyBindings;
+use crate::callout::Callout;
+use crate::daily::DailyChallenge;
+use crate::hill::{Queue, HILL_SCORE_LIMIT};
+use crate::hud;
+use crate::intro::Intro;
+use crate::persistence::SaveData;
+use crate::rng::GameRng;
+use crate::rules::{on_tick, EndReason, RuleOutcome, RulesConfig, RulesState};
+use crate::shot_clock::ShotClockRule;
+use crate::stats::MatchStats;
+use crate::tournament::{Tournament, TOURNAMENT_SCORE_LIMIT};
+use crate::tuning::Tuning;
+use crate::{
+    reset_ball, serve_velocity, AppState, Ball, GameLabel, Paused, Player, Score, ServeEvent,
+    ServeKind, UiFont,
+};
+
+pub struct MatchPlugin;
+
+impl Plugin for MatchPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<MatchPhase>()
+            .init_resource::<MatchClock>()
+            .init_resource::<MatchEnd>()
+            .add_event::<RestartMatch>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame).with_system(spawn_match_clock.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(tick_match_clock.system().before(GameLabel::GoalDetect))
+                    .with_system(render_match_clock.system())
+                    .with_system(
+                        show_game_over
+                            .system()
+                            .label(GameLabel::GoalPresent)
+                            .after(GameLabel::GoalApply),
+                    )
+                    .with_system(rematch.system()),
+            );
+    }
+}
+
+/// Rules picked before the match starts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MatchRules {
+    /// Length of a timed match in seconds, `None` plays forever.
+    pub time_limit: Option<f32>,
+    /// Goals needed to win, `None` has no limit.
+    pub score_limit: Option<u32>,
+    /// Two paddles per side, a front and a back player.
+    pub doubles: bool,
+    /// Paddles stacked on top of each other per side, all moving together.
+    pub paddles_per_side: u32,
+    /// An obstacle sweeps up and down the center line.
+    pub hazard: bool,
+    /// The ball fades out between contacts.
+    pub invisible_ball: bool,
+    /// Penalty for keeping the ball in your own half.
+    pub shot_clock: ShotClockRule,
+    /// The ball passes through the top and bottom edges and comes back in on the other side.
+    pub wrap_around: bool,
+    /// A third top or bottom wall bounce in one half gives the point to the other player.
+    pub bounce_limit: bool,
+    /// The computer player handicaps itself by the score, never in a tournament.
+    pub adaptive_ai: bool,
+    /// A player leading by `MERCY_LEAD` goals wins at once.
+    pub mercy_rule: bool,
+    /// Holding the catch key as the ball hits the paddle holds on to it for a throw.
+    pub catch_and_throw: bool,
+    /// The ball is pulled down in the left half and up in the right half.
+    pub gravity_zones: bool,
+    /// Humans may take a computer paddle over during the final two points.
+    pub late_join: bool,
+    /// A player trailing badly gets a wider goal to shoot at, casual matches only.
+    pub comeback_assist: bool,
+    /// Two linked gates, one in each half, send a ball entering one out of the other.
+    pub teleport_gates: bool,
+    /// Balls in play at once, 1 to `MAX_BALLS`. Each one is served again on its own after a goal.
+    pub balls: u32,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        MatchRules {
+            time_limit: None,
+            score_limit: None,
+            doubles: false,
+            paddles_per_side: 1,
+            hazard: false,
+            invisible_ball: false,
+            shot_clock: ShotClockRule::default(),
+            wrap_around: false,
+            bounce_limit: false,
+            adaptive_ai: false,
+            mercy_rule: false,
+            catch_and_throw: false,
+            gravity_zones: false,
+            late_join: false,
+            comeback_assist: false,
+            teleport_gates: false,
+            balls: 1,
+        }
+    }
+}
+
+impl MatchRules {
+    /// Largest angle off the horizontal a serve toward a player may take. Steep serves are
+    /// only worth it when they can wrap around.
+    pub fn serve_spread(&self) -> f32 {
+        if self.wrap_around {
+            std::f32::consts::FRAC_PI_3
+        } else {
+            std::f32::consts::FRAC_PI_6
+        }
+    }
+
+    /// Whether a human is kept from taking a computer paddle over, from when a player is two
+    /// goals from winning and in overtime.
+    pub fn joining_locked(&self, score: &Score, phase: MatchPhase) -> bool {
+        if self.late_join {
+            return false;
+        }
+        match (phase, self.score_limit) {
+            (MatchPhase::Overtime, _) => true,
+            (MatchPhase::Regular, Some(limit)) => score.left.max(score.right) + 2 >= limit,
+            _ => false,
+        }
+    }
+
+    /// The rules with what a tournament overrides, its matches are first to 5 and neither the
+    /// computer nor the goals go easy on anyone.
+    pub fn for_tournament(mut self, tournament: &Tournament) -> Self {
+        if tournament.is_active() {
+            self.score_limit = Some(TOURNAMENT_SCORE_LIMIT);
+            self.adaptive_ai = false;
+            self.comeback_assist = false;
+        }
+        self
+    }
+
+    /// King of the hill matches are first to 5 as well.
+    pub fn for_queue(mut self, queue: &Queue) -> Self {
+        if queue.is_active() {
+            self.score_limit = Some(HILL_SCORE_LIMIT);
+        }
+        self
+    }
+}
+
+/// How the last match was decided, set together with `MatchPhase::Finished`.
+#[derive(Debug, Default)]
+pub struct MatchEnd(pub EndReason);
+
+/// Sub-state of a match while in `AppState::InGame`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchPhase {
+    Regular,
+    /// Time ran out with the score level, next goal wins.
+    Overtime,
+    Finished(Player),
+}
+
+impl Default for MatchPhase {
+    fn default() -> Self {
+        MatchPhase::Regular
+    }
+}
+
+impl MatchPhase {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, MatchPhase::Finished(_))
+    }
+}
+
+/// Overtime raises the ball speed cap by this factor to force a decision.
+pub const OVERTIME_SPEED_FACTOR: f32 = 1.2;
+
+#[derive(Debug, Default)]
+pub struct MatchClock {
+    pub remaining: f32,
+}
+
+struct MatchClockText;
+struct GameOverText;
+
+fn spawn_match_clock(
+    mut commands: Commands,
+    rules: Res<MatchRules>,
+    ui_font: Res<UiFont>,
+    mut clock: ResMut<MatchClock>,
+) {
+    clock.remaining = rules.time_limit.unwrap_or(0.);
+
+    commands
+        .spawn_bundle(TextBundle {
+            text: Text::with_section(
+                "".to_string(),
+                TextStyle {
+                    font: ui_font.0.clone(),
+                    font_size: 48.0,
+                    color: Color::rgb(1.0, 1.0, 1.0),
+                },
+                TextAlignment {
+                    horizontal: HorizontalAlign::Center,
+                    vertical: VerticalAlign::Center,
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(20.),
+                    left: Val::Percent(50.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(MatchClockText);
+}
+
+fn tick_match_clock(
+    time: Res<Time>,
+    paused: Res<Paused>,
+    rules: Res<MatchRules>,
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    tuning: Res<Tuning>,
+    rapier_config: Res<RapierConfiguration>,
+    score: Res<Score>,
+    mut rng: ResMut<GameRng>,
+    mut clock: ResMut<MatchClock>,
+    mut phase: ResMut<MatchPhase>,
+    mut end: ResMut<MatchEnd>,
+    mut callouts: EventWriter<Callout>,
+    mut serves: EventWriter<ServeEvent>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    mut balls: Query<(&RigidBodyHandleComponent, &mut Transform), With<Ball>>,
+) {
+    if paused.0 {
+        return;
+    }
+
+    let mut state = RulesState::new(&score, *phase, &clock);
+    let outcomes = on_tick(
+        &RulesConfig::from_rules(&rules),
+        &mut state,
+        time.delta_seconds(),
+    );
+    if clock.remaining != state.remaining {
+        clock.remaining = state.remaining;
+    }
+
+    for outcome in outcomes {
+        match outcome {
+            RuleOutcome::EndMatch(winner, reason) => {
+                *phase = MatchPhase::Finished(winner);
+                end.0 = reason;
+            }
+            RuleOutcome::StartOvertime => {
+                *phase = MatchPhase::Overtime;
+                callouts.send(Callout("Golden goal!".to_string()));
+
+                // Coin flip for who receives the overtime serve
+                let receiver = if rng.bool() {
+                    Player::Left
+                } else {
+                    Player::Right
+                };
+
+                for (rigid_body_component, mut transform) in balls.iter_mut() {
+                    if let Some(rb) = rigid_bodies.get_mut(rigid_body_component.handle()) {
+                        let server = receiver.opponent();
+                        let kind = ServeKind::held(&keyboard_input, bindings.for_player(&server));
+                        let velocity = serve_velocity(
+                            &mut rng,
+                            &rules,
+                            &tuning,
+                            Some(receiver),
+                            kind,
+                            tuning.serve_speed,
+                        );
+                        let velocity = score.with_streak_bonus(velocity, &tuning);
+                        reset_ball(rb, &mut transform, &rapier_config, velocity);
+                        serves.send(ServeEvent { receiver, kind });
+                    }
+                }
+            }
+            // Only goals give points
+            RuleOutcome::AwardPoint(_) => {}
+        }
+    }
+}
+
+fn render_match_clock(
+    time: Res<Time>,
+    clock: Res<MatchClock>,
+    phase: Res<MatchPhase>,
+    rules: Res<MatchRules>,
+    mut query: Query<&mut Text, With<MatchClockText>>,
+) {
+    for mut text in query.iter_mut() {
+        let section = &mut text.sections[0];
+
+        match *phase {
+            MatchPhase::Overtime => {
+                section.value = "OVERTIME".to_string();
+                let pulse = (time.seconds_since_startup() as f32 * 6.).sin();
+                section.style.color = Color::rgba(1.0, 0.3, 0.2, 0.7 + pulse * 0.3);
+            }
+            _ if rules.time_limit.is_some() => {
+                let seconds = clock.remaining.ceil() as u32;
+                section.value = format!("{}:{:02}", seconds / 60, seconds % 60);
+                section.style.color = Color::rgb(1.0, 1.0, 1.0);
+            }
+            _ => section.value.clear(),
+        }
+    }
+}
+
+fn show_game_over(
+    mut commands: Commands,
+    phase: Res<MatchPhase>,
+    end: Res<MatchEnd>,
+    ui_font: Res<UiFont>,
+    rng: Res<GameRng>,
+    tournament: Res<Tournament>,
+    queue: Res<Queue>,
+    daily: Res<DailyChallenge>,
+    mut callouts: EventWriter<Callout>,
+) {
+    if !phase.is_changed() {
+        return;
+    }
+
+    let winner = match *phase {
+        MatchPhase::Finished(winner) => winner,
+        _ => return,
+    };
+
+    // Tournaments and king of the hill name the winner and put up the standings instead, daily
+    // challenges their results
+    let winner = match tournament.name(winner).or_else(|| queue.name(winner)) {
+        Some(name) => name.to_string(),
+        None => format!("{:?}", winner),
+    };
+    if end.0 == EndReason::Mercy {
+        callouts.send(Callout("Skunk!".to_string()));
+    }
+    callouts.send(Callout(format!("{} wins!", winner)));
+    if tournament.is_active() || queue.is_active() || daily.is_active() {
+        return;
+    }
+
+    let mercy = if end.0 == EndReason::Mercy {
+        "Won by mercy rule\n"
+    } else {
+        ""
+    };
+
+    commands
+        .spawn_bundle(TextBundle {
+            text: Text::with_section(
+                format!("{}Press Enter for a rematch\nSeed {}", mercy, rng.seed()),
+                TextStyle {
+                    font: ui_font.0.clone(),
+                    font_size: 32.0,
+                    color: Color::rgb(1.0, 1.0, 1.0),
+                },
+                Default::default(),
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: hud::percent(1. / 6.),
+                    left: hud::percent(0.35),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(GameOverText);
+}
+
+/// Sent by the pause menu, starts the match over the way a rematch does.
+pub struct RestartMatch;
+
+/// Enter after a match, or a restart from the pause menu at any time.
+fn rematch(
+    mut commands: Commands,
+    (keyboard_input, paused, mut restarts): (
+        Res<Input<KeyCode>>,
+        Res<Paused>,
+        EventReader<RestartMatch>,
+    ),
+    rules: Res<MatchRules>,
+    tuning: Res<Tuning>,
+    rapier_config: Res<RapierConfiguration>,
+    save: Res<SaveData>,
+    mut intro: ResMut<Intro>,
+    mut rng: ResMut<GameRng>,
+    mut phase: ResMut<MatchPhase>,
+    mut score: ResMut<Score>,
+    mut stats: ResMut<MatchStats>,
+    mut clock: ResMut<MatchClock>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    mut serves: EventWriter<ServeEvent>,
+    mut balls: Query<(&RigidBodyHandleComponent, &mut Transform), With<Ball>>,
+    texts: Query<Entity, With<GameOverText>>,
+) {
+    // Enter also picks menu entries, the pause menu pauses the game while it is up
+    let restarted = restarts.iter().count() > 0;
+    let rematch = phase.is_finished() && !paused.0 && keyboard_input.just_pressed(KeyCode::Return);
+    if !restarted && !rematch {
+        return;
+    }
+
+    for entity in texts.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    *phase = MatchPhase::Regular;
+    *score = Score::default();
+    *stats = MatchStats::default();
+    clock.remaining = rules.time_limit.unwrap_or(0.);
+
+    // The intro serves once it is done, until then the ball waits on the center spot
+    if !save.settings.skip_rematch_intro {
+        intro.request();
+        for (rigid_body_component, mut transform) in balls.iter_mut() {
+            if let Some(rb) = rigid_bodies.get_mut(rigid_body_component.handle()) {
+                reset_ball(rb, &mut transform, &rapier_config, Vector2::zeros());
+            }
+        }
+        return;
+    }
+
+    for (rigid_body_component, mut transform) in balls.iter_mut() {
+        if let Some(rb) = rigid_bodies.get_mut(rigid_body_component.handle()) {
+            let velocity = serve_velocity(
+                &mut rng,
+                &rules,
+                &tuning,
+                None,
+                ServeKind::Standard,
+                tuning.serve_speed,
+            );
+            reset_ball(rb, &mut transform, &rapier_config, velocity);
+            serves.send(ServeEvent::from_velocity(&velocity, ServeKind::Standard));
+        }
+    }
+}
@@ -0,0 +1,1053 @@
+use std::collections::HashMap;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use crate::background::{next_background, next_dim};
+use crate::bindings::{key_name, KeyBindings};
+use crate::buttons::{spawn_button, ButtonMaterials};
+use crate::callout::Callout;
+use crate::config::ConfigAction;
+use crate::daily::{best_result, today};
+use crate::input::HandToCpu;
+use crate::intro::Intro;
+use crate::match_state::RestartMatch;
+use crate::persistence::{LifetimeStats, SaveData};
+use crate::profiles::{self, MAX_NAME_CHARS};
+use crate::resume::ResumeCountdown;
+use crate::rng::GameRng;
+use crate::screenshot::toggle_goal_screenshots;
+use crate::startup::StartupReport;
+use crate::stats::MatchStats;
+use crate::theme::{Theme, PALETTE};
+use crate::tournament::{Tournament, Withdraw};
+use crate::tutorial::Tutorial;
+use crate::{AppState, Paused, Player, UiFont};
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PauseMenu>()
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame).with_system(
+                    pause_menu
+                        .system()
+                        .label("pause_menu")
+                        .before("tutorial_input"),
+                ),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::InGame).with_system(close_pause_menu.system()),
+            );
+    }
+}
+
+#[derive(Debug, Default)]
+struct PauseMenu {
+    open: bool,
+    paused_game: bool,
+    page: MenuPage,
+    /// Buttons of the page from the top, and the one picked with the arrow keys.
+    actions: Vec<MenuAction>,
+    focus: usize,
+    /// Name typed for a new profile.
+    typed: String,
+    /// Profile asked about on the delete confirmation.
+    deleting: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuPage {
+    Main,
+    /// Everything that can be changed without leaving the match.
+    Settings,
+    Statistics,
+    /// Asks before the lifetime statistics are thrown away.
+    ConfirmReset,
+    Colors,
+    Accessibility,
+    Players,
+    Profiles,
+    /// Takes the name of a new profile from the keyboard.
+    NewProfile,
+    /// Asks before a profile and its statistics are thrown away.
+    ConfirmDeleteProfile,
+}
+
+impl Default for MenuPage {
+    fn default() -> Self {
+        MenuPage::Main
+    }
+}
+
+/// Root of the pause menu, its buttons go with it.
+struct PauseMenuRoot;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuAction {
+    Resume,
+    Restart,
+    Settings,
+    Quit,
+    Controls,
+    GoalScreenshots,
+    ScoreboardStyle,
+    Particles,
+    Taunts,
+    PerformanceMode,
+    CrtEffect,
+    SpinMeter,
+    Background,
+    BackgroundDim,
+    FinalPointClip,
+    StatsOverlay,
+    Accessibility,
+    Players,
+    HandToCpu(Player),
+    /// Takes the player on the paddle out of an elimination tournament.
+    Withdraw(Player),
+    ExportConfig,
+    ImportConfig,
+    DismissWarnings,
+    HighContrast,
+    ReducedMotion,
+    ReducedClutter,
+    Colors,
+    PreviousColor(Player),
+    NextColor(Player),
+    PickColor(Player, usize),
+    Statistics,
+    ResetStatistics,
+    ConfirmReset,
+    Profiles,
+    PreviousProfile(Player),
+    NextProfile(Player),
+    NewProfile,
+    CreateProfile,
+    /// Index into the saved profiles.
+    DeleteProfile(usize),
+    ConfirmDeleteProfile,
+    Back,
+}
+
+const MENU_KEY: KeyCode = KeyCode::Escape;
+const SWATCH_SIZE: f32 = 32.;
+const PICKED_SWATCH_SIZE: f32 = 44.;
+
+/// Keyboard shortcuts for the buttons, the menu key is handled separately as it depends on the
+/// page. On the colors and profiles pages each player steps through the choices with their own
+/// left and right keys.
+fn key_action(
+    page: MenuPage,
+    keyboard_input: &Input<KeyCode>,
+    bindings: &KeyBindings,
+) -> Option<MenuAction> {
+    if keyboard_input.just_pressed(MENU_KEY) {
+        return match page {
+            MenuPage::Main => Some(MenuAction::Resume),
+            _ => Some(MenuAction::Back),
+        };
+    }
+
+    let shortcuts = match page {
+        MenuPage::Main => vec![
+            (KeyCode::R, MenuAction::Restart),
+            (KeyCode::O, MenuAction::Settings),
+            (KeyCode::S, MenuAction::Statistics),
+            (KeyCode::Q, MenuAction::Quit),
+            (KeyCode::D, MenuAction::DismissWarnings),
+        ],
+        MenuPage::Settings => vec![
+            (KeyCode::C, MenuAction::Colors),
+            (KeyCode::A, MenuAction::Accessibility),
+            (KeyCode::P, MenuAction::Players),
+            (KeyCode::F, MenuAction::Profiles),
+            (KeyCode::X, MenuAction::ExportConfig),
+            (KeyCode::I, MenuAction::ImportConfig),
+        ],
+        MenuPage::Accessibility => vec![
+            (KeyCode::H, MenuAction::HighContrast),
+            (KeyCode::M, MenuAction::ReducedMotion),
+            (KeyCode::C, MenuAction::ReducedClutter),
+        ],
+        MenuPage::Players => vec![
+            (KeyCode::L, MenuAction::HandToCpu(Player::Left)),
+            (KeyCode::R, MenuAction::HandToCpu(Player::Right)),
+        ],
+        MenuPage::Statistics => vec![(KeyCode::R, MenuAction::ResetStatistics)],
+        MenuPage::ConfirmReset => vec![(KeyCode::Y, MenuAction::ConfirmReset)],
+        // Every other key goes into the name
+        MenuPage::NewProfile => vec![(KeyCode::Return, MenuAction::CreateProfile)],
+        MenuPage::ConfirmDeleteProfile => vec![(KeyCode::Y, MenuAction::ConfirmDeleteProfile)],
+        MenuPage::Profiles => {
+            let mut shortcuts = vec![(KeyCode::N, MenuAction::NewProfile)];
+            for player in [Player::Left, Player::Right].iter() {
+                let keys = bindings.for_player(player);
+                shortcuts.push((keys.left, MenuAction::PreviousProfile(*player)));
+                shortcuts.push((keys.right, MenuAction::NextProfile(*player)));
+            }
+            shortcuts
+        }
+        MenuPage::Colors => [Player::Left, Player::Right]
+            .iter()
+            .flat_map(|player| {
+                let keys = bindings.for_player(player);
+                vec![
+                    (keys.left, MenuAction::PreviousColor(*player)),
+                    (keys.right, MenuAction::NextColor(*player)),
+                ]
+            })
+            .collect(),
+    };
+
+    shortcuts
+        .into_iter()
+        .find(|(key, _)| keyboard_input.just_pressed(*key))
+        .map(|(_, action)| action)
+}
+
+/// Palette index next to the player's color, passing over the color the opponent has so both
+/// players never share one.
+fn step_color(theme: &Theme, player: Player, forward: bool) -> Option<usize> {
+    let count = PALETTE.len();
+    let current = PALETTE
+        .iter()
+        .position(|color| *color == theme.player_color(player));
+    let start = current.unwrap_or(if forward { count - 1 } else { 0 });
+    let taken = theme.player_color(player.opponent());
+
+    (1..=count)
+        .map(|offset| {
+            if forward {
+                (start + offset) % count
+            } else {
+                (start + count - offset) % count
+            }
+        })
+        .find(|index| PALETTE[*index] != taken)
+}
+
+fn pause_menu(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    font: Res<UiFont>,
+    theme: Res<Theme>,
+    button_materials: Res<ButtonMaterials>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut menu: ResMut<PauseMenu>,
+    mut paused: ResMut<Paused>,
+    mut tutorial: ResMut<Tutorial>,
+    intro: Res<Intro>,
+    mut countdown: ResMut<ResumeCountdown>,
+    mut save: ResMut<SaveData>,
+    (
+        rng,
+        stats,
+        bindings,
+        tournament,
+        mut config_actions,
+        mut handovers,
+        mut withdrawals,
+        mut report,
+        mut restarts,
+        mut exits,
+        mut characters,
+    ): (
+        Res<GameRng>,
+        Res<MatchStats>,
+        Res<KeyBindings>,
+        Res<Tournament>,
+        EventWriter<ConfigAction>,
+        EventWriter<HandToCpu>,
+        EventWriter<Withdraw>,
+        ResMut<StartupReport>,
+        EventWriter<RestartMatch>,
+        EventWriter<AppExit>,
+        EventReader<ReceivedCharacter>,
+    ),
+    mut callouts: EventWriter<Callout>,
+    buttons: Query<(&Interaction, &MenuAction), Changed<Interaction>>,
+    roots: Query<Entity, With<PauseMenuRoot>>,
+) {
+    // The tutorial takes every key while it is up, and any key skips the intro
+    if tutorial.is_open() || intro.is_playing() {
+        return;
+    }
+
+    // Read every frame, so the key that opened the name page doesn't end up in the name
+    let typed: Vec<char> = characters.iter().map(|character| character.char).collect();
+
+    if !menu.open {
+        if keyboard_input.just_pressed(MENU_KEY) {
+            menu.open = true;
+            // Pausing during the resume countdown takes the game back from it
+            if countdown.is_running() {
+                countdown.cancel();
+                menu.paused_game = true;
+            } else {
+                menu.paused_game = !paused.0;
+                if menu.paused_game {
+                    countdown.freeze();
+                }
+            }
+            menu.page = MenuPage::Main;
+            menu.focus = 0;
+            paused.0 = true;
+            menu.actions = spawn_menu(
+                &mut commands,
+                &font,
+                &theme,
+                &button_materials,
+                &mut materials,
+                &save,
+                &stats,
+                &bindings,
+                &tournament,
+                &report,
+                rng.seed(),
+                menu.page,
+                menu.focus,
+                &menu.typed,
+                menu.deleting.as_deref(),
+            );
+        }
+        return;
+    }
+
+    let clicked = buttons
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Clicked)
+        .map(|(_, action)| *action);
+    // Up and down pick a button, Enter presses it
+    let focus = menu.focus;
+    let count = menu.actions.len().max(1);
+    if keyboard_input.just_pressed(KeyCode::Down) {
+        menu.focus = (menu.focus + 1) % count;
+    } else if keyboard_input.just_pressed(KeyCode::Up) {
+        menu.focus = (menu.focus + count - 1) % count;
+    }
+    let entered = if keyboard_input.just_pressed(KeyCode::Return) {
+        menu.actions.get(menu.focus).copied()
+    } else {
+        None
+    };
+    let action = key_action(menu.page, &keyboard_input, &bindings)
+        .or(clicked)
+        .or(entered);
+    let page = menu.page;
+
+    let name_length = menu.typed.len();
+    if page == MenuPage::NewProfile {
+        if keyboard_input.just_pressed(KeyCode::Back) {
+            menu.typed.pop();
+        }
+        for character in typed {
+            if !character.is_control() && menu.typed.chars().count() < MAX_NAME_CHARS {
+                menu.typed.push(character);
+            }
+        }
+    }
+
+    match action {
+        Some(MenuAction::Resume) | Some(MenuAction::Controls) => {
+            for entity in roots.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            // The countdown unpauses the game, the tutorial pauses it right away again
+            if menu.paused_game && action == Some(MenuAction::Resume) {
+                countdown.start();
+            } else if menu.paused_game {
+                paused.0 = false;
+            }
+            *menu = PauseMenu::default();
+
+            if action == Some(MenuAction::Controls) {
+                tutorial.request();
+            }
+        }
+        // The rematch serves or plays the intro itself, the frozen velocities are dropped. A
+        // withdrawal restarts with the next pairing.
+        Some(MenuAction::Restart) | Some(MenuAction::Withdraw(_)) => {
+            for entity in roots.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            countdown.cancel();
+            if menu.paused_game {
+                paused.0 = false;
+            }
+            *menu = PauseMenu::default();
+            match action {
+                Some(MenuAction::Withdraw(player)) => withdrawals.send(Withdraw(player)),
+                _ => restarts.send(RestartMatch),
+            }
+        }
+        Some(MenuAction::Settings) => menu.page = MenuPage::Settings,
+        Some(MenuAction::Quit) => exits.send(AppExit),
+        Some(MenuAction::GoalScreenshots) => toggle_goal_screenshots(&mut save, &mut callouts),
+        Some(MenuAction::ScoreboardStyle) => {
+            save.settings.scoreboard_style = save.settings.scoreboard_style.next();
+            save.save();
+        }
+        Some(MenuAction::Particles) => {
+            save.settings.particles = !save.settings.particles;
+            save.save();
+        }
+        Some(MenuAction::Taunts) => {
+            save.settings.taunts = !save.settings.taunts;
+            save.save();
+        }
+        Some(MenuAction::PerformanceMode) => {
+            save.settings.performance_mode = !save.settings.performance_mode;
+            save.save();
+        }
+        Some(MenuAction::CrtEffect) => {
+            save.settings.crt_effect = !save.settings.crt_effect;
+            save.save();
+        }
+        Some(MenuAction::SpinMeter) => {
+            save.settings.spin_meter = !save.settings.spin_meter;
+            save.save();
+        }
+        Some(MenuAction::Background) => {
+            save.settings.background = next_background(save.settings.background.as_deref());
+            save.save();
+        }
+        Some(MenuAction::BackgroundDim) => {
+            save.settings.background_dim = next_dim(save.settings.background_dim);
+            save.save();
+        }
+        Some(MenuAction::FinalPointClip) => {
+            save.settings.final_point_clip = !save.settings.final_point_clip;
+            save.save();
+        }
+        Some(MenuAction::StatsOverlay) => {
+            save.settings.hold_stats_overlay = !save.settings.hold_stats_overlay;
+            save.save();
+        }
+        Some(MenuAction::Accessibility) => menu.page = MenuPage::Accessibility,
+        Some(MenuAction::Players) => menu.page = MenuPage::Players,
+        Some(MenuAction::HandToCpu(player)) => {
+            handovers.send(HandToCpu(player));
+            callouts.send(Callout(format!("{:?} paddle handed to the CPU", player)));
+        }
+        Some(MenuAction::ExportConfig) => config_actions.send(ConfigAction::Export),
+        Some(MenuAction::ImportConfig) => config_actions.send(ConfigAction::Import),
+        Some(MenuAction::DismissWarnings) => report.dismiss(),
+        Some(MenuAction::HighContrast) => {
+            save.settings.high_contrast = !save.settings.high_contrast;
+            save.save();
+        }
+        Some(MenuAction::ReducedMotion) => {
+            save.settings.reduced_motion = !save.settings.reduced_motion;
+            save.save();
+        }
+        Some(MenuAction::ReducedClutter) => {
+            save.settings.reduced_clutter = !save.settings.reduced_clutter;
+            save.save();
+        }
+        Some(MenuAction::Colors) => menu.page = MenuPage::Colors,
+        Some(MenuAction::PreviousColor(player)) | Some(MenuAction::NextColor(player)) => {
+            let forward = action == Some(MenuAction::NextColor(player));
+            if let Some(index) = step_color(&theme, player, forward) {
+                profiles::set_player_color(&mut save, player, index);
+                save.save();
+            }
+        }
+        Some(MenuAction::PickColor(player, index)) => {
+            profiles::set_player_color(&mut save, player, index);
+            save.save();
+        }
+        Some(MenuAction::Statistics) => menu.page = MenuPage::Statistics,
+        Some(MenuAction::ResetStatistics) => menu.page = MenuPage::ConfirmReset,
+        Some(MenuAction::ConfirmReset) => {
+            save.stats = LifetimeStats::default();
+            save.save();
+            callouts.send(Callout("Statistics reset".to_string()));
+            menu.page = MenuPage::Statistics;
+        }
+        Some(MenuAction::Profiles) => menu.page = MenuPage::Profiles,
+        Some(MenuAction::PreviousProfile(player)) | Some(MenuAction::NextProfile(player)) => {
+            let forward = action == Some(MenuAction::NextProfile(player));
+            profiles::step_seat(&mut save, player, forward);
+            save.save();
+        }
+        Some(MenuAction::NewProfile) => {
+            menu.typed.clear();
+            menu.page = MenuPage::NewProfile;
+        }
+        Some(MenuAction::CreateProfile) => match profiles::create(&mut save, &menu.typed) {
+            Ok(name) => {
+                save.save();
+                callouts.send(Callout(format!("Profile {} created", name)));
+                menu.typed.clear();
+                menu.page = MenuPage::Profiles;
+            }
+            Err(err) => callouts.send(Callout(err)),
+        },
+        Some(MenuAction::DeleteProfile(index)) => {
+            if let Some(profile) = save.profiles.get(index) {
+                menu.deleting = Some(profile.name.clone());
+                menu.page = MenuPage::ConfirmDeleteProfile;
+            }
+        }
+        Some(MenuAction::ConfirmDeleteProfile) => {
+            if let Some(name) = menu.deleting.take() {
+                profiles::delete(&mut save, &name);
+                save.save();
+                callouts.send(Callout(format!("Profile {} deleted", name)));
+            }
+            menu.page = MenuPage::Profiles;
+        }
+        Some(MenuAction::Back) => {
+            menu.page = match menu.page {
+                MenuPage::ConfirmReset => MenuPage::Statistics,
+                MenuPage::Colors
+                | MenuPage::Accessibility
+                | MenuPage::Players
+                | MenuPage::Profiles => MenuPage::Settings,
+                MenuPage::NewProfile | MenuPage::ConfirmDeleteProfile => MenuPage::Profiles,
+                _ => MenuPage::Main,
+            };
+            menu.typed.clear();
+            menu.deleting = None;
+        }
+        None => {}
+    }
+
+    // Settings can also change from their own keys, keep the labels current. Picked colors
+    // reach the theme a frame after the save.
+    if menu.page != page {
+        menu.focus = 0;
+    }
+    if menu.open
+        && (save.is_changed()
+            || theme.is_changed()
+            || report.is_changed()
+            || menu.page != page
+            || menu.focus != focus
+            || menu.typed.len() != name_length)
+    {
+        for entity in roots.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        menu.actions = spawn_menu(
+            &mut commands,
+            &font,
+            &theme,
+            &button_materials,
+            &mut materials,
+            &save,
+            &stats,
+            &bindings,
+            &tournament,
+            &report,
+            rng.seed(),
+            menu.page,
+            menu.focus,
+            &menu.typed,
+            menu.deleting.as_deref(),
+        );
+        menu.focus = menu.focus.min(menu.actions.len().saturating_sub(1));
+    }
+}
+
+fn spawn_menu(
+    commands: &mut Commands,
+    font: &UiFont,
+    theme: &Theme,
+    button_materials: &ButtonMaterials,
+    materials: &mut Assets<ColorMaterial>,
+    save: &SaveData,
+    stats: &MatchStats,
+    bindings: &KeyBindings,
+    tournament: &Tournament,
+    report: &StartupReport,
+    seed: u64,
+    page: MenuPage,
+    focus: usize,
+    typed: &str,
+    deleting: Option<&str>,
+) -> Vec<MenuAction> {
+    let mut actions = Vec::new();
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(0.),
+                    top: Val::Px(0.),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.7).into()),
+            ..Default::default()
+        })
+        .insert(PauseMenuRoot)
+        .with_children(|parent| {
+            let buttons = match page {
+                MenuPage::Main => {
+                    spawn_label(parent, font, theme, "Paused", 48.0);
+                    spawn_label(parent, font, theme, &format!("Seed {}", seed), 20.0);
+                    spawn_warnings(parent, font, theme, report.pending());
+
+                    let mut buttons = vec![
+                        ("Resume (Esc)".to_string(), MenuAction::Resume),
+                        ("Restart match (R)".to_string(), MenuAction::Restart),
+                        ("Settings (O)".to_string(), MenuAction::Settings),
+                        ("Controls (F1)".to_string(), MenuAction::Controls),
+                        ("Statistics (S)".to_string(), MenuAction::Statistics),
+                        ("Quit game (Q)".to_string(), MenuAction::Quit),
+                    ];
+                    if !report.pending().is_empty() {
+                        buttons.push((
+                            "Dismiss warnings (D)".to_string(),
+                            MenuAction::DismissWarnings,
+                        ));
+                    }
+                    buttons
+                }
+                MenuPage::Settings => {
+                    let snapshots = if save.settings.goal_screenshots {
+                        "on"
+                    } else {
+                        "off"
+                    };
+                    let particles = if save.settings.particles { "on" } else { "off" };
+                    let taunts = if save.settings.taunts { "on" } else { "off" };
+                    let performance = if save.settings.performance_mode {
+                        "on"
+                    } else {
+                        "off"
+                    };
+                    let crt = match (save.settings.crt_effect, save.settings.performance_mode) {
+                        (true, true) => "on, paused by performance mode",
+                        (true, false) => "on",
+                        (false, _) => "off",
+                    };
+                    let spin_meter =
+                        match (save.settings.spin_meter, save.settings.performance_mode) {
+                            (true, true) => "on, paused by performance mode",
+                            (true, false) => "on",
+                            (false, _) => "off",
+                        };
+                    let background = save.settings.background.as_deref().unwrap_or("none");
+                    let clip = if save.settings.final_point_clip {
+                        "on"
+                    } else {
+                        "off"
+                    };
+                    let overlay = if save.settings.hold_stats_overlay {
+                        "hold"
+                    } else {
+                        "toggle"
+                    };
+
+                    spawn_label(parent, font, theme, "Settings", 48.0);
+
+                    vec![
+                        (
+                            format!("Goal snapshots: {} (Shift+F12)", snapshots),
+                            MenuAction::GoalScreenshots,
+                        ),
+                        (
+                            format!(
+                                "Scoreboard: {}",
+                                save.settings.scoreboard_style.format(5, 3)
+                            ),
+                            MenuAction::ScoreboardStyle,
+                        ),
+                        (format!("Particles: {}", particles), MenuAction::Particles),
+                        (format!("Taunts: {}", taunts), MenuAction::Taunts),
+                        (
+                            format!("Performance mode: {}", performance),
+                            MenuAction::PerformanceMode,
+                        ),
+                        (format!("CRT effect: {}", crt), MenuAction::CrtEffect),
+                        (format!("Spin meter: {}", spin_meter), MenuAction::SpinMeter),
+                        (
+                            format!("Background: {}", background),
+                            MenuAction::Background,
+                        ),
+                        (
+                            format!(
+                                "Background dimming: {:.0}%",
+                                save.settings.background_dim * 100.
+                            ),
+                            MenuAction::BackgroundDim,
+                        ),
+                        (
+                            format!("Final point clip: {}", clip),
+                            MenuAction::FinalPointClip,
+                        ),
+                        (
+                            format!("Stats overlay: {} (Tab)", overlay),
+                            MenuAction::StatsOverlay,
+                        ),
+                        ("Player colors (C)".to_string(), MenuAction::Colors),
+                        ("Accessibility (A)".to_string(), MenuAction::Accessibility),
+                        ("Players (P)".to_string(), MenuAction::Players),
+                        ("Profiles (F)".to_string(), MenuAction::Profiles),
+                        ("Export config (X)".to_string(), MenuAction::ExportConfig),
+                        ("Import config (I)".to_string(), MenuAction::ImportConfig),
+                        ("Back (Esc)".to_string(), MenuAction::Back),
+                    ]
+                }
+                MenuPage::Statistics => {
+                    spawn_label(parent, font, theme, "Statistics", 48.0);
+                    for line in statistics_lines(&save.stats).iter() {
+                        spawn_label(parent, font, theme, line, 24.0);
+                    }
+                    spawn_label(parent, font, theme, &missed_by_line(stats), 24.0);
+                    spawn_label(parent, font, theme, &territory_line(stats), 24.0);
+                    spawn_label(parent, font, theme, &pushed_line(stats), 24.0);
+                    spawn_label(parent, font, theme, &daily_line(save), 24.0);
+
+                    vec![
+                        (
+                            "Reset statistics (R)".to_string(),
+                            MenuAction::ResetStatistics,
+                        ),
+                        ("Back (Esc)".to_string(), MenuAction::Back),
+                    ]
+                }
+                MenuPage::Colors => {
+                    spawn_label(parent, font, theme, "Player colors", 48.0);
+                    for player in [Player::Left, Player::Right].iter() {
+                        let keys = bindings.for_player(player);
+                        let label = format!(
+                            "{:?}  {} / {}",
+                            player,
+                            key_name(keys.left),
+                            key_name(keys.right)
+                        );
+                        spawn_label(parent, font, theme, &label, 24.0);
+                        spawn_swatches(parent, theme, materials, *player);
+                    }
+
+                    vec![("Back (Esc)".to_string(), MenuAction::Back)]
+                }
+                MenuPage::Accessibility => {
+                    let on_off = |on: bool| if on { "on" } else { "off" };
+                    spawn_label(parent, font, theme, "Accessibility", 48.0);
+
+                    vec![
+                        (
+                            format!("High contrast: {} (H)", on_off(save.settings.high_contrast)),
+                            MenuAction::HighContrast,
+                        ),
+                        (
+                            format!(
+                                "Reduced motion: {} (M)",
+                                on_off(save.settings.reduced_motion)
+                            ),
+                            MenuAction::ReducedMotion,
+                        ),
+                        (
+                            format!(
+                                "Reduced clutter: {} (C)",
+                                on_off(save.settings.reduced_clutter)
+                            ),
+                            MenuAction::ReducedClutter,
+                        ),
+                        ("Back (Esc)".to_string(), MenuAction::Back),
+                    ]
+                }
+                MenuPage::Players => {
+                    spawn_label(parent, font, theme, "Players", 48.0);
+                    let label = format!(
+                        "Press a paddle's power key during play to take it back ({} / {})",
+                        key_name(bindings.left.power),
+                        key_name(bindings.right.power)
+                    );
+                    spawn_label(parent, font, theme, &label, 24.0);
+
+                    let mut buttons = vec![
+                        (
+                            "Hand left to the CPU (L)".to_string(),
+                            MenuAction::HandToCpu(Player::Left),
+                        ),
+                        (
+                            "Hand right to the CPU (R)".to_string(),
+                            MenuAction::HandToCpu(Player::Right),
+                        ),
+                    ];
+                    if tournament.is_elimination() {
+                        for player in [Player::Left, Player::Right].iter() {
+                            if let Some(name) = tournament.name(*player) {
+                                buttons.push((
+                                    format!("{} withdraws from the tournament", name),
+                                    MenuAction::Withdraw(*player),
+                                ));
+                            }
+                        }
+                    }
+                    buttons.push(("Back (Esc)".to_string(), MenuAction::Back));
+                    buttons
+                }
+                MenuPage::Profiles => {
+                    spawn_label(parent, font, theme, "Profiles", 48.0);
+                    for player in [Player::Left, Player::Right].iter() {
+                        let keys = bindings.for_player(player);
+                        let label = format!(
+                            "{:?}: {}  {} / {}",
+                            player,
+                            profiles::display_name(save, *player),
+                            key_name(keys.left),
+                            key_name(keys.right)
+                        );
+                        spawn_label(parent, font, theme, &label, 24.0);
+                    }
+                    for profile in save.profiles.iter() {
+                        let line = format!(
+                            "{}  played {}  won {}  best streak {}  longest rally {}",
+                            profile.name,
+                            profile.stats.matches,
+                            profile.stats.wins,
+                            profile.stats.best_streak,
+                            profile.stats.longest_rally
+                        );
+                        spawn_label(parent, font, theme, &line, 20.0);
+                    }
+
+                    let mut buttons = vec![("New profile (N)".to_string(), MenuAction::NewProfile)];
+                    buttons.extend(save.profiles.iter().enumerate().map(|(index, profile)| {
+                        (
+                            format!("Delete {}", profile.name),
+                            MenuAction::DeleteProfile(index),
+                        )
+                    }));
+                    buttons.push(("Back (Esc)".to_string(), MenuAction::Back));
+                    buttons
+                }
+                MenuPage::NewProfile => {
+                    spawn_label(parent, font, theme, "New profile", 48.0);
+                    spawn_label(parent, font, theme, &format!("{}_", typed), 32.0);
+                    let hint = format!("Type a name, up to {} characters", MAX_NAME_CHARS);
+                    spawn_label(parent, font, theme, &hint, 20.0);
+
+                    vec![
+                        ("Create (Enter)".to_string(), MenuAction::CreateProfile),
+                        ("Back (Esc)".to_string(), MenuAction::Back),
+                    ]
+                }
+                MenuPage::ConfirmDeleteProfile => {
+                    let label = format!(
+                        "Delete {} and its statistics?",
+                        deleting.unwrap_or_default()
+                    );
+                    spawn_label(parent, font, theme, &label, 48.0);
+
+                    vec![
+                        (
+                            "Yes, delete (Y)".to_string(),
+                            MenuAction::ConfirmDeleteProfile,
+                        ),
+                        ("Back (Esc)".to_string(), MenuAction::Back),
+                    ]
+                }
+                MenuPage::ConfirmReset => {
+                    spawn_label(parent, font, theme, "Reset all statistics?", 48.0);
+
+                    vec![
+                        ("Yes, reset (Y)".to_string(), MenuAction::ConfirmReset),
+                        ("Back (Esc)".to_string(), MenuAction::Back),
+                    ]
+                }
+            };
+
+            for (index, (label, action)) in buttons.iter().enumerate() {
+                spawn_button(
+                    parent,
+                    font,
+                    theme,
+                    button_materials,
+                    label,
+                    index == focus,
+                    *action,
+                );
+            }
+            actions = buttons.into_iter().map(|(_, action)| action).collect();
+        });
+    actions
+}
+
+fn statistics_lines(stats: &LifetimeStats) -> Vec<String> {
+    vec![
+        format!("Matches played: {}", stats.matches),
+        format!(
+            "Wins left: {}  right: {}",
+            stats.left_wins, stats.right_wins
+        ),
+        format!("Goals: {}", stats.goals),
+        format!("Paddle hits: {}", stats.paddle_hits),
+        format!("Longest rally: {}", stats.longest_rally),
+        format!("Fastest shot: {:.0} px/s", stats.fastest_shot),
+    ]
+}
+
+/// Average distance each player's closest paddle was from the goals they conceded this match.
+fn missed_by_line(stats: &MatchStats) -> String {
+    let average = |player: Player| match stats.average_missed_by(player) {
+        Some(distance) => format!("{:.0} px", distance),
+        None => "-".to_string(),
+    };
+    format!(
+        "This match, missed by  left: {}  right: {}",
+        average(Player::Left),
+        average(Player::Right)
+    )
+}
+
+/// Share of this match's play the ball spent in each half.
+fn territory_line(stats: &MatchStats) -> String {
+    let share = |player: Player| match stats.territory_share(player) {
+        Some(share) => format!("{:.0}%", share * 100.),
+        None => "-".to_string(),
+    };
+    format!(
+        "This match, ball in own half  left: {}  right: {}",
+        share(Player::Left),
+        share(Player::Right)
+    )
+}
+
+/// Shots this match that carried the paddle's movement into the ball, and blocks with it held
+/// still.
+fn pushed_line(stats: &MatchStats) -> String {
+    let count =
+        |counts: &HashMap<Player, u32>, player: Player| counts.get(&player).copied().unwrap_or(0);
+    format!(
+        "This match, blocks / pushed shots  left: {} / {}  right: {} / {}",
+        count(&stats.blocks, Player::Left),
+        count(&stats.pushed_shots, Player::Left),
+        count(&stats.blocks, Player::Right),
+        count(&stats.pushed_shots, Player::Right)
+    )
+}
+
+/// Whether today's daily challenge was played, and its best result.
+fn daily_line(save: &SaveData) -> String {
+    let date = today();
+    match best_result(save, &date) {
+        Some(best) => format!(
+            "Daily challenge {}: done, best {} {}-{}, longest rally {}",
+            date,
+            if best.won { "won" } else { "lost" },
+            best.goals,
+            best.conceded,
+            best.longest_rally
+        ),
+        None => format!("Daily challenge {}: not played yet (--daily)", date),
+    }
+}
+
+/// Row of palette swatches for `player`. The picked color is drawn larger, the opponent's color
+/// is faded and can't be clicked.
+fn spawn_swatches(
+    parent: &mut ChildBuilder,
+    theme: &Theme,
+    materials: &mut Assets<ColorMaterial>,
+    player: Player,
+) {
+    parent
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                margin: Rect::all(Val::Px(6.)),
+                ..Default::default()
+            },
+            material: materials.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .with_children(|row| {
+            for (index, color) in PALETTE.iter().enumerate() {
+                let picked = *color == theme.player_color(player);
+                let taken = *color == theme.player_color(player.opponent());
+
+                let size = if picked {
+                    PICKED_SWATCH_SIZE
+                } else {
+                    SWATCH_SIZE
+                };
+                let mut color = *color;
+                if taken {
+                    color.set_a(0.2);
+                }
+
+                let mut swatch = row.spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(size), Val::Px(size)),
+                        margin: Rect::all(Val::Px(4.)),
+                        ..Default::default()
+                    },
+                    material: materials.add(color.into()),
+                    ..Default::default()
+                });
+                if !taken {
+                    swatch
+                        .insert(Interaction::None)
+                        .insert(MenuAction::PickColor(player, index));
+                }
+            }
+        });
+}
+
+/// Most warnings shown, the log has all of them.
+const MAX_WARNINGS: usize = 5;
+
+/// What fell back to defaults at startup, above the buttons until dismissed.
+fn spawn_warnings(parent: &mut ChildBuilder, font: &UiFont, theme: &Theme, warnings: &[String]) {
+    if warnings.is_empty() {
+        return;
+    }
+
+    let mut lines = vec!["Problems at startup, defaults were used:".to_string()];
+    lines.extend(warnings.iter().take(MAX_WARNINGS).cloned());
+    if warnings.len() > MAX_WARNINGS {
+        lines.push(format!(
+            "and {} more, see the log",
+            warnings.len() - MAX_WARNINGS
+        ));
+    }
+
+    for line in lines.iter() {
+        spawn_label(parent, font, theme, line, 18.0);
+    }
+}
+
+fn spawn_label(parent: &mut ChildBuilder, font: &UiFont, theme: &Theme, value: &str, size: f32) {
+    parent.spawn_bundle(TextBundle {
+        text: Text::with_section(
+            value.to_string(),
+            TextStyle {
+                font: font.0.clone(),
+                font_size: size,
+                color: theme.button_text,
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    });
+}
+
+fn close_pause_menu(
+    mut commands: Commands,
+    mut menu: ResMut<PauseMenu>,
+    mut paused: ResMut<Paused>,
+    roots: Query<Entity, With<PauseMenuRoot>>,
+) {
+    for entity in roots.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if menu.open && menu.paused_game {
+        paused.0 = false;
+    }
+    *menu = PauseMenu::default();
+}
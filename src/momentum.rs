@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+
+use crate::accessibility::Accessibility;
+use crate::callout::Callout;
+use crate::daily::DailyChallenge;
+use crate::hud;
+use crate::stats::{momentum_leader, MatchStats};
+use crate::theme::Theme;
+use crate::tournament::Tournament;
+use crate::{AppState, GameLabel, Player};
+
+/// A tug-of-war bar under the territory bar showing who is on a roll, and a callout when the
+/// momentum changes hands.
+pub struct MomentumPlugin;
+
+impl Plugin for MomentumPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_enter(AppState::InGame).with_system(spawn_momentum_bar.system()),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(
+                    announce_shift
+                        .system()
+                        .label("announce_shift")
+                        .after(GameLabel::GoalApply),
+                )
+                .with_system(render_momentum_bar.system().after("announce_shift")),
+        );
+    }
+}
+
+const BAR_WIDTH: f32 = 200.;
+const BAR_HEIGHT: f32 = 4.;
+/// Below the territory bar, as a fraction of the window height.
+const BAR_TOP: f32 = 0.64;
+
+struct MomentumRow;
+
+/// One player's part of the bar, left first so the two fill it from the left edge.
+struct MomentumBar(Player);
+
+fn spawn_momentum_bar(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    theme: Res<Theme>,
+) {
+    let none = materials.add(Color::NONE.into());
+    commands
+        .spawn_bundle(NodeBundle {
+            style: hud::full_row(BAR_TOP),
+            material: none.clone(),
+            ..Default::default()
+        })
+        .insert(MomentumRow)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(BAR_WIDTH), Val::Px(BAR_HEIGHT)),
+                        ..Default::default()
+                    },
+                    material: none,
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for player in [Player::Left, Player::Right].iter() {
+                        parent
+                            .spawn_bundle(NodeBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(BAR_WIDTH / 2.), Val::Px(BAR_HEIGHT)),
+                                    ..Default::default()
+                                },
+                                material: materials.add(theme.player_color(*player).into()),
+                                ..Default::default()
+                            })
+                            .insert(MomentumBar(*player));
+                    }
+                });
+        });
+}
+
+/// Same places as the win meter, tournaments, the daily challenge and reduced clutter.
+fn is_hidden(
+    tournament: &Tournament,
+    daily: &DailyChallenge,
+    accessibility: &Accessibility,
+) -> bool {
+    tournament.is_active() || daily.is_active() || accessibility.reduced_clutter
+}
+
+/// Only a player taking the momentum over from the other is a shift, getting it from an even
+/// match isn't. The stats reset with every match, so each one starts even.
+fn announce_shift(
+    tournament: Res<Tournament>,
+    daily: Res<DailyChallenge>,
+    accessibility: Res<Accessibility>,
+    mut stats: ResMut<MatchStats>,
+    mut callouts: EventWriter<Callout>,
+) {
+    let leader = match momentum_leader(stats.momentum) {
+        Some(leader) => leader,
+        None => return,
+    };
+    if stats.momentum_leader == Some(leader) {
+        return;
+    }
+
+    let shifted = stats.momentum_leader.is_some();
+    stats.momentum_leader = Some(leader);
+    if shifted && !is_hidden(&tournament, &daily, &accessibility) {
+        callouts.send(Callout("Momentum shift!".to_string()));
+    }
+}
+
+fn render_momentum_bar(
+    tournament: Res<Tournament>,
+    daily: Res<DailyChallenge>,
+    accessibility: Res<Accessibility>,
+    theme: Res<Theme>,
+    stats: Res<MatchStats>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut rows: Query<&mut Style, (With<MomentumRow>, Without<MomentumBar>)>,
+    mut bars: Query<(&MomentumBar, &mut Style, &Handle<ColorMaterial>)>,
+) {
+    let hidden = is_hidden(&tournament, &daily, &accessibility);
+    let display = if hidden { Display::None } else { Display::Flex };
+    for mut style in rows.iter_mut() {
+        if style.display != display {
+            style.display = display;
+        }
+    }
+    if hidden {
+        return;
+    }
+
+    if theme.is_changed() {
+        for (bar, _, material) in bars.iter_mut() {
+            if let Some(material) = materials.get_mut(material) {
+                material.color = theme.player_color(bar.0);
+            }
+        }
+    }
+
+    if !stats.is_changed() && !accessibility.is_changed() {
+        return;
+    }
+    for (bar, mut style, _) in bars.iter_mut() {
+        let share = match bar.0 {
+            Player::Left => 0.5 + stats.momentum / 2.,
+            Player::Right => 0.5 - stats.momentum / 2.,
+        };
+        let width = Val::Px(BAR_WIDTH * share);
+        if style.size.width != width {
+            style.size.width = width;
+        }
+    }
+}
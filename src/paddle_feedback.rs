@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+
+use crate::accessibility::Accessibility;
+use crate::{AppState, LimitAxis, PaddleHitLimit};
+
+pub struct PaddleFeedbackPlugin;
+
+impl Plugin for PaddleFeedbackPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(start_squash.system().label("start_squash"))
+                .with_system(animate_squash.system().after("start_squash")),
+        );
+    }
+}
+
+const SQUASH_SECONDS: f32 = 0.15;
+/// Fraction the paddle is flattened along the axis it hit the limit on.
+const SQUASH_AMOUNT: f32 = 0.15;
+/// A paddle bumping its limits again within this time gets no new squash, so jiggling against
+/// the limit doesn't keep it wobbling.
+const COOLDOWN_SECONDS: f32 = 0.4;
+
+/// Short squash of a paddle that was stopped at its limits.
+struct Squash {
+    axis: LimitAxis,
+    elapsed: f32,
+}
+
+fn start_squash(
+    mut commands: Commands,
+    accessibility: Res<Accessibility>,
+    mut limits: EventReader<PaddleHitLimit>,
+    squashed: Query<(), With<Squash>>,
+) {
+    for limit in limits.iter() {
+        if accessibility.reduced_motion {
+            continue;
+        }
+        if squashed.get(limit.paddle).is_ok() {
+            continue;
+        }
+        commands.entity(limit.paddle).insert(Squash {
+            axis: limit.axis,
+            elapsed: 0.,
+        });
+    }
+}
+
+fn animate_squash(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut paddles: Query<(Entity, &mut Squash, &mut Transform)>,
+) {
+    for (entity, mut squash, mut transform) in paddles.iter_mut() {
+        squash.elapsed += time.delta_seconds();
+        if squash.elapsed >= COOLDOWN_SECONDS {
+            commands.entity(entity).remove::<Squash>();
+            continue;
+        }
+
+        let progress = (squash.elapsed / SQUASH_SECONDS).min(1.);
+        let amount = SQUASH_AMOUNT * (1. - progress);
+        // Flatten along the blocked axis and bulge a little along the other one
+        let (x, y) = match squash.axis {
+            LimitAxis::X => (1. - amount, 1. + amount / 2.),
+            LimitAxis::Y => (1. + amount / 2., 1. - amount),
+        };
+        transform.scale = Vec3::new(x, y, 1.);
+    }
+}
@@ -0,0 +1,184 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use crate::contacts::{BallContact, Surface};
+use crate::quality::QualitySettings;
+use crate::theme::Theme;
+use crate::tuning::Tuning;
+use crate::{AppState, GameLabel, Paused};
+
+pub struct ParticlesPlugin;
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ParticleMaterials>().add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(bounce_particles.system().after(GameLabel::Contacts))
+                .with_system(update_particles.system()),
+        );
+    }
+}
+
+/// Above the ball and paddles at z = 0.
+const PARTICLE_Z: f32 = 0.5;
+
+const SPARKS_PER_BOUNCE: usize = 4;
+const SPARK_SIZE: f32 = 4.;
+const SPARK_SECONDS: f32 = 0.3;
+const PUFFS_PER_HIT: usize = 8;
+const PUFF_SIZE: f32 = 8.;
+const PUFF_SECONDS: f32 = 0.25;
+
+/// Small square flying off in a straight line, shrinking until it is despawned.
+pub struct Particle {
+    velocity: Vec2,
+    size: f32,
+    life: Timer,
+}
+
+/// Shared materials so particles don't each add one.
+pub struct ParticleMaterials {
+    pub spark: Handle<ColorMaterial>,
+    pub puff: Handle<ColorMaterial>,
+}
+
+impl FromWorld for ParticleMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let theme = world.get_resource::<Theme>().unwrap().clone();
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+        ParticleMaterials {
+            spark: materials.add(theme.spark.into()),
+            puff: materials.add(theme.puff.into()),
+        }
+    }
+}
+
+/// Hands out spawns while the live particles stay under the quality budget. Long rallies and
+/// many balls stay cheap, new particles are dropped.
+pub struct ParticleBudget {
+    live: usize,
+    limit: usize,
+}
+
+impl ParticleBudget {
+    pub fn new(quality: &QualitySettings, particles: &Query<&Particle>) -> Self {
+        ParticleBudget {
+            live: particles.iter().count(),
+            limit: quality.particle_budget,
+        }
+    }
+
+    /// Returns `false` once the budget is used up and nothing was spawned.
+    pub fn spawn(
+        &mut self,
+        commands: &mut Commands,
+        material: Handle<ColorMaterial>,
+        position: Vec2,
+        velocity: Vec2,
+        size: f32,
+        seconds: f32,
+    ) -> bool {
+        if self.live >= self.limit {
+            return false;
+        }
+        self.live += 1;
+
+        commands
+            .spawn_bundle(SpriteBundle {
+                material,
+                sprite: Sprite::new(Vec2::new(size, size)),
+                transform: Transform::from_translation(position.extend(PARTICLE_Z)),
+                ..Default::default()
+            })
+            .insert(Particle {
+                velocity,
+                size,
+                life: Timer::from_seconds(seconds, false),
+            });
+        true
+    }
+}
+
+/// Sparks slide along a wall the ball bounced off, a paddle hit gives a short radial puff.
+fn bounce_particles(
+    mut commands: Commands,
+    quality: Res<QualitySettings>,
+    tuning: Res<Tuning>,
+    materials: Res<ParticleMaterials>,
+    mut contacts: EventReader<BallContact>,
+    particles: Query<&Particle>,
+) {
+    if !quality.particles {
+        return;
+    }
+
+    let mut budget = ParticleBudget::new(&quality, &particles);
+
+    for contact in contacts.iter() {
+        if !contact.started {
+            continue;
+        }
+
+        let point = contact.position - contact.normal * tuning.ball_size / 2.;
+
+        match contact.surface {
+            Surface::Wall(_) => {
+                let tangent = contact.normal.perp();
+                for _ in 0..SPARKS_PER_BOUNCE {
+                    let along = if fastrand::bool() { 1. } else { -1. };
+                    let velocity = tangent * along * (80. + fastrand::f32() * 120.)
+                        + contact.normal * fastrand::f32() * 40.;
+                    budget.spawn(
+                        &mut commands,
+                        materials.spark.clone(),
+                        point,
+                        velocity,
+                        SPARK_SIZE,
+                        SPARK_SECONDS,
+                    );
+                }
+            }
+            Surface::Paddle(_) => {
+                for index in 0..PUFFS_PER_HIT {
+                    let angle =
+                        index as f32 / PUFFS_PER_HIT as f32 * 2. * PI + fastrand::f32() * 0.4;
+                    let velocity =
+                        Vec2::new(angle.cos(), angle.sin()) * (60. + fastrand::f32() * 60.);
+                    budget.spawn(
+                        &mut commands,
+                        materials.puff.clone(),
+                        point,
+                        velocity,
+                        PUFF_SIZE,
+                        PUFF_SECONDS,
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    paused: Res<Paused>,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+    if paused.0 {
+        return;
+    }
+
+    for (entity, mut particle, mut transform, mut sprite) in particles.iter_mut() {
+        particle.life.tick(time.delta());
+        if particle.life.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.);
+        let size = particle.size * particle.life.percent_left();
+        sprite.size = Vec2::new(size, size);
+    }
+}
@@ -0,0 +1,412 @@
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+
+use bevy::prelude::{warn, Color};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::daily::DailyResult;
+use crate::hill::Contender;
+use crate::profiles::{Profile, Seats};
+use crate::startup::StartupReport;
+use crate::theme::PALETTE;
+use crate::tournament::Tournament;
+use crate::{Player, ScoreboardStyle};
+
+const SAVE_PATH: &str = "pingis_pong.ron";
+
+/// Everything we remember between launches.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SaveData {
+    pub tutorial_seen: bool,
+    pub settings: Settings,
+    pub stats: LifetimeStats,
+    /// Tournament left unfinished, kept until it is resumed or played to the end.
+    pub tournament: Option<Tournament>,
+    /// Best daily challenge result of every day played.
+    pub daily: Vec<DailyResult>,
+    /// Leaderboard of the last king of the hill session played with `keep_leaderboard`.
+    pub leaderboard: Option<Vec<Contender>>,
+    /// Named players, the guest isn't among them.
+    pub profiles: Vec<Profile>,
+    pub seats: Seats,
+}
+
+/// Player facing options.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Save a screenshot every time a goal is scored.
+    pub goal_screenshots: bool,
+    pub scoreboard_style: ScoreboardStyle,
+    /// Go straight to the serve on a rematch instead of playing the arena intro again.
+    pub skip_rematch_intro: bool,
+    /// Sparks and puffs on ball contacts, can be turned off on slow machines.
+    pub particles: bool,
+    /// Show the scorer's taunt after a goal.
+    pub taunts: bool,
+    /// Low-spec mode, turns off every visual effect and lightens the physics solver.
+    pub performance_mode: bool,
+    /// Scanlines and darkened corners over the arena like an old CRT, off in performance mode.
+    pub crt_effect: bool,
+    /// White on black arena, the ball outlined and the paddles bordered in yellow.
+    pub high_contrast: bool,
+    /// No replays, intro animation or squashing, the game itself plays the same.
+    pub reduced_motion: bool,
+    /// Only the scores and what the rules need, no meters just for fun.
+    pub reduced_clutter: bool,
+    /// Arc beside the ball showing how fast it spins, off in performance mode.
+    pub spin_meter: bool,
+    /// Picture in `assets/backgrounds/` behind the arena, `None` for the flat color.
+    pub background: Option<String>,
+    /// Share of black laid over the background picture so the ball stands out.
+    pub background_dim: f32,
+    /// Save the last seconds of a match as PNG frames when it ends.
+    pub final_point_clip: bool,
+    /// The live stats overlay shows while its key is held instead of toggling on each press.
+    pub hold_stats_overlay: bool,
+    /// Index into `PALETTE` each player picked, `None` keeps the theme's color.
+    pub left_color: Option<usize>,
+    pub right_color: Option<usize>,
+    #[serde(deserialize_with = "taunt")]
+    pub left_taunt: String,
+    #[serde(deserialize_with = "taunt")]
+    pub right_taunt: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            goal_screenshots: false,
+            scoreboard_style: ScoreboardStyle::default(),
+            skip_rematch_intro: false,
+            particles: true,
+            taunts: true,
+            performance_mode: false,
+            crt_effect: false,
+            final_point_clip: false,
+            hold_stats_overlay: false,
+            high_contrast: false,
+            reduced_motion: false,
+            reduced_clutter: false,
+            spin_meter: false,
+            background: None,
+            background_dim: 0.5,
+            left_color: None,
+            right_color: None,
+            left_taunt: "GG".to_string(),
+            right_taunt: "GG".to_string(),
+        }
+    }
+}
+
+impl Settings {
+    /// The palette color a player picked, a hand edited index past the palette counts as none.
+    pub fn player_color(&self, player: Player) -> Option<Color> {
+        let index = match player {
+            Player::Left => self.left_color,
+            Player::Right => self.right_color,
+        };
+        index.and_then(|index| PALETTE.get(index).copied())
+    }
+
+    pub fn set_player_color(&mut self, player: Player, index: usize) {
+        match player {
+            Player::Left => self.left_color = Some(index),
+            Player::Right => self.right_color = Some(index),
+        }
+    }
+
+    pub fn taunt(&self, player: Player) -> &str {
+        match player {
+            Player::Left => &self.left_taunt,
+            Player::Right => &self.right_taunt,
+        }
+    }
+}
+
+/// Longest taunt kept, in characters.
+const MAX_TAUNT_CHARS: usize = 16;
+
+/// Taunts are typed into the save file by hand, drop control characters and clamp the length.
+fn sanitize_taunt(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_TAUNT_CHARS)
+        .collect();
+    text.trim().to_string()
+}
+
+fn taunt<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    String::deserialize(deserializer).map(|text| sanitize_taunt(&text))
+}
+
+/// Totals over every finished match.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LifetimeStats {
+    pub matches: u32,
+    pub left_wins: u32,
+    pub right_wins: u32,
+    pub goals: u32,
+    pub paddle_hits: u32,
+    pub longest_rally: u32,
+    /// Ball speed in pixels per second right after a paddle hit.
+    pub fastest_shot: f32,
+}
+
+/// Splits the top level `(name: value, ...)` of a RON struct into its fields and the text of
+/// their values, `None` if it isn't one. Each section can then be read on its own.
+fn top_level_sections(content: &str) -> Option<Vec<(&str, &str)>> {
+    let bytes = content.as_bytes();
+    let is_name = |byte: u8| byte.is_ascii_alphanumeric() || byte == b'_';
+
+    // A struct name may go before the parenthesis
+    let mut pos = skip_blank(bytes, 0);
+    while pos < bytes.len() && is_name(bytes[pos]) {
+        pos += 1;
+    }
+    pos = skip_blank(bytes, pos);
+    if bytes.get(pos) != Some(&b'(') {
+        return None;
+    }
+    pos += 1;
+
+    let mut sections = Vec::new();
+    loop {
+        pos = skip_blank(bytes, pos);
+        if bytes.get(pos) == Some(&b')') {
+            return Some(sections);
+        }
+
+        let name_start = pos;
+        while pos < bytes.len() && is_name(bytes[pos]) {
+            pos += 1;
+        }
+        let name = &content[name_start..pos];
+        pos = skip_blank(bytes, pos);
+        if name.is_empty() || bytes.get(pos) != Some(&b':') {
+            return None;
+        }
+
+        let value_start = pos + 1;
+        pos = value_end(bytes, value_start)?;
+        sections.push((name, content[value_start..pos].trim()));
+        if bytes[pos] == b',' {
+            pos += 1;
+        }
+    }
+}
+
+/// Position of the first byte from `pos` on that isn't whitespace or in a comment.
+fn skip_blank(bytes: &[u8], mut pos: usize) -> usize {
+    loop {
+        match (bytes.get(pos), bytes.get(pos + 1)) {
+            (Some(byte), _) if byte.is_ascii_whitespace() => pos += 1,
+            (Some(b'/'), Some(b'/')) => {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+            }
+            (Some(b'/'), Some(b'*')) => {
+                pos += 2;
+                while pos < bytes.len() && !bytes[pos..].starts_with(b"*/") {
+                    pos += 1;
+                }
+                pos += 2;
+            }
+            _ => return pos.min(bytes.len()),
+        }
+    }
+}
+
+/// Position of the `,` or `)` ending the field value starting at `pos`, past anything nested
+/// and whatever is in strings and comments.
+fn value_end(bytes: &[u8], mut pos: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' if depth == 0 => return Some(pos),
+            b']' | b'}' if depth == 0 => return None,
+            b')' | b']' | b'}' => depth -= 1,
+            b',' if depth == 0 => return Some(pos),
+            b'"' => {
+                pos += 1;
+                while bytes.get(pos)? != &b'"' {
+                    pos += if bytes[pos] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b'/' => {
+                let after = skip_blank(bytes, pos);
+                if after > pos {
+                    pos = after;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        pos += 1;
+    }
+    None
+}
+
+/// Reads one section of the save file straight into its type.
+fn read_section<T: DeserializeOwned>(text: &str, section: &mut T) -> Result<(), ron::Error> {
+    *section = ron::from_str(text)?;
+    Ok(())
+}
+
+impl SaveData {
+    /// Returns `None` when there is no save file yet, i.e. on first launch. Whatever couldn't be
+    /// read is reported and left at its default.
+    pub fn load(report: &mut StartupReport) -> Option<SaveData> {
+        if !Path::new(SAVE_PATH).exists() {
+            return None;
+        }
+
+        let data = match fs::read_to_string(SAVE_PATH) {
+            Ok(content) => SaveData::parse(&content, report),
+            Err(err) => {
+                report.warn(format!(
+                    "Failed to read {}, starting from default settings and statistics: {}",
+                    SAVE_PATH, err
+                ));
+                SaveData::default()
+            }
+        };
+
+        // Found now rather than on the first goal, when nobody is looking at the terminal
+        if let Err(err) = OpenOptions::new().append(true).open(SAVE_PATH) {
+            report.warn(format!(
+                "{} can't be written, changes won't be kept: {}",
+                SAVE_PATH, err
+            ));
+        }
+        Some(data)
+    }
+
+    pub(crate) fn parse(content: &str, report: &mut StartupReport) -> SaveData {
+        let err = match ron::from_str(content) {
+            Ok(data) => return data,
+            Err(err) => err,
+        };
+
+        // Read again one section at a time so a bad one doesn't throw away the rest of the save.
+        // Each goes straight into its type, ron::Value can't hold the enums some of them have.
+        let sections = match top_level_sections(content) {
+            Some(sections) => sections,
+            None => {
+                report.warn(format!(
+                    "Failed to parse {}, starting from default settings and statistics: {}",
+                    SAVE_PATH, err
+                ));
+                return SaveData::default();
+            }
+        };
+
+        let mut data = SaveData::default();
+        for (name, text) in sections {
+            let read = match name {
+                "tutorial_seen" => read_section(text, &mut data.tutorial_seen),
+                "settings" => read_section(text, &mut data.settings),
+                "stats" => read_section(text, &mut data.stats),
+                "tournament" => read_section(text, &mut data.tournament),
+                "daily" => read_section(text, &mut data.daily),
+                "leaderboard" => read_section(text, &mut data.leaderboard),
+                "profiles" => read_section(text, &mut data.profiles),
+                "seats" => read_section(text, &mut data.seats),
+                _ => Ok(()),
+            };
+            if let Err(err) = read {
+                report.warn(format!(
+                    "Ignoring unreadable section {} in {}: {}",
+                    name, SAVE_PATH, err
+                ));
+            }
+        }
+        data
+    }
+
+    pub fn save(&self) {
+        let content = match ron::ser::to_string_pretty(self, Default::default()) {
+            Ok(content) => content,
+            Err(err) => {
+                warn!("Failed to serialize save data: {}", err);
+                return;
+            }
+        };
+
+        if let Err(err) = fs::write(SAVE_PATH, content) {
+            warn!("Failed to write {}: {}", SAVE_PATH, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn malformed_save_falls_back_to_defaults() {
+        let mut report = StartupReport::default();
+        let data = SaveData::parse("(settings: (goal_screenshots: ", &mut report);
+
+        assert!(!data.tutorial_seen);
+        assert_eq!(data.stats.matches, 0);
+        assert_eq!(report.pending().len(), 1);
+    }
+
+    #[test]
+    fn bad_section_keeps_the_rest() {
+        let mut report = StartupReport::default();
+        let data = SaveData::parse(
+            "(tutorial_seen: true, stats: \"lots\", profiles: [])",
+            &mut report,
+        );
+
+        assert!(data.tutorial_seen);
+        assert_eq!(data.stats.matches, 0);
+        assert_eq!(report.pending().len(), 1, "{:?}", report.pending());
+    }
+
+    #[test]
+    fn sections_split_past_nesting_strings_and_comments() {
+        let sections = top_level_sections(
+            "SaveData( // written by hand\n tutorial_seen: true, \
+             profiles: [(name: \"a, (b)\\\" c\")], /* stats: (), */ seats: (left: Some(0)), )",
+        )
+        .unwrap();
+        let names: Vec<&str> = sections.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, vec!["tutorial_seen", "profiles", "seats"]);
+        assert_eq!(sections[1].1, "[(name: \"a, (b)\\\" c\")]");
+        assert_eq!(sections[2].1, "(left: Some(0))");
+
+        assert!(top_level_sections("(stats: (matches: 1)").is_none());
+        assert!(top_level_sections("[1, 2]").is_none());
+        assert!(top_level_sections("(stats: (matches: 1]").is_none());
+        assert!(top_level_sections("( /* never closed").is_none());
+    }
+
+    #[test]
+    fn bad_section_keeps_the_tournament() {
+        let mut report = StartupReport::default();
+        let data = SaveData::parse(
+            "(stats: \"lots\", tournament: Some((players: [\"A\", \"B\", \"C\"], \
+             bracket: Some((slots: [Some(0), None, Some(1), Some(2)], rounds: [[Some((winner: 0, \
+             outcome: Bye)), Some((winner: 2, outcome: Played(left_goals: 1, right_goals: 3)))], \
+             [None]])))))",
+            &mut report,
+        );
+
+        assert_eq!(report.pending().len(), 1, "{:?}", report.pending());
+        let tournament = data.tournament.unwrap();
+        assert!(tournament.is_elimination());
+        assert_eq!(tournament.pairing(), Some(("A", "C")));
+    }
+}
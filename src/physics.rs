@@ -0,0 +1,253 @@
+use bevy::prelude::*;
+use bevy_rapier2d::na::Isometry2;
+use bevy_rapier2d::physics::ColliderHandleComponent;
+use bevy_rapier2d::rapier::dynamics::{RigidBody, RigidBodyBuilder};
+use bevy_rapier2d::rapier::geometry::ColliderBuilder;
+
+use crate::gates::GateLink;
+use crate::hazard::Hazard;
+use crate::{AppState, Ball, Paddle, Wall};
+
+/// Checks in debug builds that colliders only end up on gameplay entities, and that gameplay
+/// entities have one.
+pub struct PhysicsMarkersPlugin;
+
+impl Plugin for PhysicsMarkersPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        if cfg!(debug_assertions) {
+            app.add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(colliders_without_marker.system().chain(log_errors.system()))
+                    .with_system(markers_without_collider.system().chain(log_errors.system())),
+            );
+        }
+    }
+}
+
+/// Set on every entity spawned with a rigid body and collider.
+pub struct PhysicsObject;
+
+/// Rigid body and collider of a gameplay entity. Everything that collides is spawned with one of
+/// these, sprites and UI never get rapier builders of their own.
+#[derive(Bundle)]
+pub struct PhysicsBundle {
+    body: RigidBodyBuilder,
+    collider: ColliderBuilder,
+    marker: PhysicsObject,
+}
+
+pub fn physics_bundle(body: RigidBodyBuilder, collider: ColliderBuilder) -> PhysicsBundle {
+    PhysicsBundle {
+        body,
+        collider,
+        marker: PhysicsObject,
+    }
+}
+
+/// Moves a body to `position`, in physics units, and its `Transform` along with it. Rapier only
+/// writes the transform back after the next step, every system later in the frame would still
+/// see the body where it was, e.g. the goal check a ball that was already served again.
+/// Everything that puts a body somewhere goes through here rather than `set_position`.
+pub fn teleport_body(
+    rb: &mut RigidBody,
+    transform: &mut Transform,
+    position: Isometry2<f32>,
+    scale: f32,
+) {
+    rb.set_position(position, true);
+    transform.translation.x = position.translation.x * scale;
+    transform.translation.y = position.translation.y * scale;
+    transform.rotation = Quat::from_rotation_z(position.rotation.angle());
+}
+
+fn log_errors(In(errors): In<Vec<String>>) {
+    for err in errors {
+        error!("{}", err);
+    }
+}
+
+/// A collider on anything else, a trail or some decor, would have the ball bouncing off thin air.
+fn colliders_without_marker(
+    colliders: Query<
+        (Entity, Option<&PhysicsObject>),
+        (
+            Added<ColliderHandleComponent>,
+            Without<Ball>,
+            Without<Paddle>,
+            Without<Wall>,
+            Without<Hazard>,
+            Without<GateLink>,
+        ),
+    >,
+) -> Vec<String> {
+    colliders
+        .iter()
+        .map(|(entity, physics_object)| {
+            let spawned_by = if physics_object.is_some() {
+                "physics_bundle"
+            } else {
+                "a bare ColliderBuilder"
+            };
+            format!(
+                "{:?} got a collider from {} but is no ball, paddle, wall, hazard or gate",
+                entity, spawned_by
+            )
+        })
+        .collect()
+}
+
+/// Rapier turns the builders into handles before the frame after the spawn, a new gameplay
+/// entity with neither was spawned without its physics.
+fn markers_without_collider(
+    entities: Query<
+        Entity,
+        (
+            Or<(
+                Added<Ball>,
+                Added<Paddle>,
+                Added<Wall>,
+                Added<Hazard>,
+                Added<GateLink>,
+            )>,
+            Without<ColliderHandleComponent>,
+            Without<ColliderBuilder>,
+        ),
+    >,
+) -> Vec<String> {
+    entities
+        .iter()
+        .map(|entity| {
+            format!(
+                "{:?} is a ball, paddle, wall, hazard or gate without a collider, spawn it with \
+                 physics_bundle",
+                entity
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_rapier2d::na::Vector2;
+    use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+    use bevy_rapier2d::rapier::geometry::ColliderHandle;
+
+    use crate::Player;
+
+    #[derive(Default)]
+    struct Reported(Vec<String>);
+
+    fn collect(In(errors): In<Vec<String>>, mut reported: ResMut<Reported>) {
+        reported.0.extend(errors);
+    }
+
+    fn collider() -> ColliderHandleComponent {
+        ColliderHandleComponent::from(ColliderHandle::from_raw_parts(0, 0))
+    }
+
+    #[test]
+    fn collider_without_marker_is_reported() {
+        let mut world = World::default();
+        world.init_resource::<Reported>();
+        let bare = world.spawn().insert(collider()).id();
+        let bundled = world
+            .spawn()
+            .insert_bundle((collider(), PhysicsObject))
+            .id();
+        world
+            .spawn()
+            .insert_bundle((collider(), PhysicsObject, Wall::Top));
+        world
+            .spawn()
+            .insert_bundle((collider(), Player::Left, Paddle(0.)));
+
+        SystemStage::single(colliders_without_marker.system().chain(collect.system()))
+            .run(&mut world);
+
+        let mut reported = world.get_resource::<Reported>().unwrap().0.clone();
+        reported.sort();
+        let mut expected = vec![
+            format!(
+                "{:?} got a collider from a bare ColliderBuilder but is no ball, paddle, wall, \
+                 hazard or gate",
+                bare
+            ),
+            format!(
+                "{:?} got a collider from physics_bundle but is no ball, paddle, wall, hazard \
+                 or gate",
+                bundled
+            ),
+        ];
+        expected.sort();
+        assert_eq!(reported, expected);
+    }
+
+    #[test]
+    fn marker_without_collider_is_reported() {
+        let mut world = World::default();
+        world.init_resource::<Reported>();
+        let wall = world.spawn().insert(Wall::Top).id();
+        world.spawn().insert_bundle((Wall::Bottom, collider()));
+        world
+            .spawn()
+            .insert_bundle((Wall::Flank(Player::Left), ColliderBuilder::ball(1.)));
+
+        SystemStage::single(markers_without_collider.system().chain(collect.system()))
+            .run(&mut world);
+
+        let reported = &world.get_resource::<Reported>().unwrap().0;
+        assert_eq!(reported.len(), 1, "{:?}", reported);
+        assert!(reported[0].starts_with(&format!("{:?} is a ball", wall)));
+    }
+
+    #[test]
+    fn teleport_moves_the_transform_along() {
+        let mut bodies = RigidBodySet::new();
+        let handle = bodies.insert(
+            RigidBodyBuilder::new_dynamic()
+                .translation(1., 2.)
+                .linvel(5., -3.)
+                .build(),
+        );
+        let mut transform = Transform::from_xyz(20., 40., 1.);
+        let position = Isometry2::new(Vector2::new(3., 4.5), 0.5);
+
+        teleport_body(
+            bodies.get_mut(handle).unwrap(),
+            &mut transform,
+            position,
+            20.,
+        );
+
+        assert_eq!(*bodies[handle].position(), position);
+        assert_eq!(transform.translation, Vec3::new(60., 90., 1.));
+        let rotation = Quat::from_rotation_z(0.5);
+        assert!(
+            (transform.rotation.z - rotation.z).abs() < 1e-6
+                && (transform.rotation.w - rotation.w).abs() < 1e-6,
+            "{:?}",
+            transform.rotation
+        );
+    }
+
+    #[test]
+    fn teleport_clears_an_old_rotation() {
+        let mut bodies = RigidBodySet::new();
+        let handle = bodies.insert(RigidBodyBuilder::new_dynamic().rotation(1.).build());
+        let mut transform = Transform {
+            rotation: Quat::from_rotation_z(1.),
+            ..Default::default()
+        };
+
+        teleport_body(
+            bodies.get_mut(handle).unwrap(),
+            &mut transform,
+            Isometry2::translation(10., 15.),
+            20.,
+        );
+
+        assert_eq!(transform.translation.truncate(), Vec2::new(200., 300.));
+        assert!(transform.rotation.z.abs() < 1e-6 && (transform.rotation.w - 1.).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_rapier2d::na::Isometry2;
+use bevy_rapier2d::physics::{RapierConfiguration, RigidBodyHandleComponent};
+use bevy_rapier2d::rapier::dynamics::{RigidBody, RigidBodySet};
+use bevy_rapier2d::rapier::na::Vector2;
+
+use crate::match_state::{MatchPhase, MatchRules};
+use crate::physics::teleport_body;
+use crate::rng::GameRng;
+use crate::tuning::Tuning;
+use crate::{
+    paddle_spawn_x, paddle_y_range, reset_ball, serve_velocity, sub_paddle_height, AppState, Ball,
+    GameLabel, OutOfPlay, Paddle, PaddleRole, Player, Score, ServeEvent, ServeKind, SubPaddle,
+    ARENA_HEIGHT, ARENA_WIDTH,
+};
+
+/// Puts a ball or paddle whose body the solver broke back where it can play on.
+pub struct PhysicsGuardPlugin;
+
+impl Plugin for PhysicsGuardPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PhysicsHistory>()
+            .init_resource::<PhysicsResets>()
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame).with_system(
+                    guard_bodies
+                        .system()
+                        .label("guard_bodies")
+                        .before(GameLabel::GoalDetect),
+                ),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::InGame).with_system(clear_history.system()),
+            );
+    }
+}
+
+/// Faster than anything the game launches, in pixels per second.
+const ABSURD_SPEED: f32 = 20_000.;
+/// How far outside the arena a body may be before it can't be coming back, in arena sizes.
+const ABSURD_DISTANCE: f32 = 2.;
+
+/// Bodies reset since the game started, shown in the step overlay.
+#[derive(Debug, Default)]
+pub struct PhysicsResets(pub u32);
+
+/// Position and velocity of a body at the end of the last frame it was sound, in pixels.
+#[derive(Debug, Clone, Copy)]
+struct BodyState {
+    position: Vec2,
+    velocity: Vec2,
+}
+
+#[derive(Debug, Default)]
+struct PhysicsHistory(HashMap<Entity, BodyState>);
+
+/// Reads the state of `rb`, `None` once it is NaN or out of all reason.
+fn sound_state(rb: &RigidBody, scale: f32) -> Option<BodyState> {
+    let translation = rb.position().translation;
+    let position = Vec2::new(translation.x, translation.y) * scale;
+    let velocity = Vec2::new(rb.linvel().x, rb.linvel().y) * scale;
+
+    let finite = position.is_finite() && velocity.is_finite() && rb.angvel().is_finite();
+    let in_reach = (position.x - ARENA_WIDTH / 2.).abs() < ARENA_WIDTH * ABSURD_DISTANCE
+        && (position.y - ARENA_HEIGHT / 2.).abs() < ARENA_HEIGHT * ABSURD_DISTANCE;
+    if finite && in_reach && velocity.length() < ABSURD_SPEED {
+        Some(BodyState { position, velocity })
+    } else {
+        None
+    }
+}
+
+/// A broken ball is served again from the center spot like after a goal, one that went in only
+/// goes back there and waits for its serve. A broken paddle goes back to where it was spawned.
+fn guard_bodies(
+    rapier_config: Res<RapierConfiguration>,
+    tuning: Res<Tuning>,
+    rules: Res<MatchRules>,
+    phase: Res<MatchPhase>,
+    score: Res<Score>,
+    mut rng: ResMut<GameRng>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    mut history: ResMut<PhysicsHistory>,
+    mut resets: ResMut<PhysicsResets>,
+    mut serves: EventWriter<ServeEvent>,
+    mut balls: Query<
+        (
+            Entity,
+            &RigidBodyHandleComponent,
+            &mut Transform,
+            Option<&OutOfPlay>,
+        ),
+        (With<Ball>, Without<Paddle>),
+    >,
+    mut paddles: Query<
+        (
+            Entity,
+            &RigidBodyHandleComponent,
+            &mut Transform,
+            &Player,
+            &PaddleRole,
+            Option<&SubPaddle>,
+        ),
+        With<Paddle>,
+    >,
+) {
+    let scale = rapier_config.scale;
+
+    for (ball, rigid_body_component, mut transform, out) in balls.iter_mut() {
+        let rb = match rigid_bodies.get_mut(rigid_body_component.handle()) {
+            Some(rb) => rb,
+            None => continue,
+        };
+        if let Some(state) = sound_state(rb, scale) {
+            history.0.insert(ball, state);
+            continue;
+        }
+
+        error!(
+            "Ball {:?} broke, at {:?} moving {:?}, the frame before {:?}. Serving it again",
+            ball,
+            rb.position().translation,
+            rb.linvel(),
+            history.0.get(&ball)
+        );
+        resets.0 += 1;
+        let velocity = if out.is_some() || phase.is_finished() {
+            Vector2::zeros()
+        } else {
+            let velocity = serve_velocity(
+                &mut rng,
+                &rules,
+                &tuning,
+                None,
+                ServeKind::Standard,
+                tuning.serve_speed,
+            );
+            let velocity = score.with_streak_bonus(velocity, &tuning);
+            serves.send(ServeEvent::from_velocity(&velocity, ServeKind::Standard));
+            velocity
+        };
+        reset_ball(rb, &mut transform, &rapier_config, velocity);
+        history.0.remove(&ball);
+    }
+
+    let count = rules.paddles_per_side;
+    let height = sub_paddle_height(count, &tuning);
+    for (paddle, rigid_body_component, mut transform, player, role, sub_paddle) in
+        paddles.iter_mut()
+    {
+        let rb = match rigid_bodies.get_mut(rigid_body_component.handle()) {
+            Some(rb) => rb,
+            None => continue,
+        };
+        if let Some(state) = sound_state(rb, scale) {
+            history.0.insert(paddle, state);
+            continue;
+        }
+
+        error!(
+            "{:?} {:?} paddle broke, at {:?} moving {:?}, the frame before {:?}. Putting it back",
+            player,
+            role,
+            rb.position().translation,
+            rb.linvel(),
+            history.0.get(&paddle)
+        );
+        resets.0 += 1;
+        let index = sub_paddle.map_or(0, |sub_paddle| sub_paddle.0);
+        let (bottom, top) = paddle_y_range(index, count, height);
+        let spawn = Isometry2::translation(
+            paddle_spawn_x(*player, *role) / scale,
+            (bottom + top) / 2. / scale,
+        );
+        rb.set_linvel(Vector2::zeros(), true);
+        rb.set_angvel(0., true);
+        teleport_body(rb, &mut transform, spawn, scale);
+        history.0.remove(&paddle);
+    }
+}
+
+fn clear_history(mut history: ResMut<PhysicsHistory>) {
+    history.0.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_rapier2d::rapier::dynamics::RigidBodyBuilder;
+
+    use crate::OutOfPlay;
+
+    const SCALE: f32 = 20.;
+
+    fn body(x: f32, y: f32, linvel: Vector2<f32>) -> RigidBody {
+        RigidBodyBuilder::new_dynamic()
+            .translation(x, y)
+            .linvel(linvel.x, linvel.y)
+            .build()
+    }
+
+    #[test]
+    fn sane_body_is_sound() {
+        let state = sound_state(&body(25., 15., Vector2::new(10., 5.)), SCALE).unwrap();
+        assert_eq!(state.position, Vec2::new(500., 300.));
+        assert_eq!(state.velocity, Vec2::new(200., 100.));
+
+        // A ball flying on past the goal line is still in reach
+        assert!(sound_state(&body(-5., 15., Vector2::new(-30., 0.)), SCALE).is_some());
+    }
+
+    #[test]
+    fn broken_bodies_are_not_sound() {
+        let broken = [
+            body(f32::NAN, 15., Vector2::zeros()),
+            body(25., f32::INFINITY, Vector2::zeros()),
+            body(25., 15., Vector2::new(f32::INFINITY, 0.)),
+            body(25., 15., Vector2::new(0., f32::NAN)),
+            // Just over the speed cap
+            body(25., 15., Vector2::new(ABSURD_SPEED / SCALE + 1., 0.)),
+            // Two arena widths beyond the middle
+            body(ARENA_WIDTH * 3. / SCALE, 15., Vector2::zeros()),
+            body(25., -ARENA_HEIGHT * 2. / SCALE, Vector2::zeros()),
+        ];
+        for rb in broken.iter() {
+            assert!(
+                sound_state(rb, SCALE).is_none(),
+                "{:?} moving {:?}",
+                rb.position(),
+                rb.linvel()
+            );
+        }
+
+        let mut spinning = body(25., 15., Vector2::zeros());
+        spinning.set_angvel(f32::NAN, true);
+        assert!(sound_state(&spinning, SCALE).is_none());
+    }
+
+    /// A world with what `guard_bodies` reads, and a stage running it.
+    fn guarded_world() -> (World, SystemStage) {
+        let mut world = World::default();
+        world.insert_resource(RapierConfiguration {
+            scale: SCALE,
+            ..Default::default()
+        });
+        world.insert_resource(Tuning::default());
+        world.insert_resource(MatchRules::default());
+        world.insert_resource(MatchPhase::default());
+        world.insert_resource(Score::default());
+        world.insert_resource(GameRng::new(7));
+        world.insert_resource(RigidBodySet::new());
+        world.insert_resource(PhysicsHistory::default());
+        world.insert_resource(PhysicsResets::default());
+        world.insert_resource(Events::<ServeEvent>::default());
+        (world, SystemStage::single(guard_bodies.system()))
+    }
+
+    fn spawn_body(world: &mut World, rb: RigidBody) -> Entity {
+        let handle = world.get_resource_mut::<RigidBodySet>().unwrap().insert(rb);
+        world
+            .spawn()
+            .insert(RigidBodyHandleComponent::from(handle))
+            .insert(Transform::default())
+            .id()
+    }
+
+    fn body_of(world: &World, entity: Entity) -> &RigidBody {
+        let handle = world
+            .get::<RigidBodyHandleComponent>(entity)
+            .unwrap()
+            .handle();
+        &world.get_resource::<RigidBodySet>().unwrap()[handle]
+    }
+
+    fn serves(world: &World) -> usize {
+        let events = world.get_resource::<Events<ServeEvent>>().unwrap();
+        events.get_reader().iter(events).count()
+    }
+
+    #[test]
+    fn broken_ball_is_served_again() {
+        let (mut world, mut stage) = guarded_world();
+        let sane = spawn_body(&mut world, body(10., 10., Vector2::new(5., 0.)));
+        world.entity_mut(sane).insert(Ball(0.));
+        let broken = spawn_body(&mut world, body(25., 15., Vector2::new(f32::NAN, 0.)));
+        world.entity_mut(broken).insert(Ball(0.));
+
+        stage.run(&mut world);
+
+        let rb = body_of(&world, broken);
+        assert_eq!(rb.position().translation.vector, Vector2::new(25., 15.));
+        assert!(rb.linvel().norm() > 0. && rb.linvel().x.is_finite());
+        assert_eq!(
+            world
+                .get::<Transform>(broken)
+                .unwrap()
+                .translation
+                .truncate(),
+            Vec2::new(ARENA_WIDTH / 2., ARENA_HEIGHT / 2.)
+        );
+        assert_eq!(serves(&world), 1);
+        assert_eq!(world.get_resource::<PhysicsResets>().unwrap().0, 1);
+
+        let history = world.get_resource::<PhysicsHistory>().unwrap();
+        assert!(history.0.contains_key(&sane));
+        assert!(!history.0.contains_key(&broken));
+        assert_eq!(body_of(&world, sane).linvel(), &Vector2::new(5., 0.));
+    }
+
+    #[test]
+    fn broken_ball_out_of_play_waits_for_its_serve() {
+        let (mut world, mut stage) = guarded_world();
+        let ball = spawn_body(&mut world, body(-3., 15., Vector2::new(f32::INFINITY, 0.)));
+        world.entity_mut(ball).insert(Ball(0.)).insert(OutOfPlay {
+            timer: Timer::from_seconds(0.5, false),
+            scorer: Player::Right,
+            final_goal: false,
+        });
+
+        stage.run(&mut world);
+
+        let rb = body_of(&world, ball);
+        assert_eq!(rb.position().translation.vector, Vector2::new(25., 15.));
+        assert_eq!(rb.linvel(), &Vector2::zeros());
+        assert_eq!(serves(&world), 0);
+    }
+
+    #[test]
+    fn broken_paddle_goes_back_to_its_spawn() {
+        let (mut world, mut stage) = guarded_world();
+        let paddle = spawn_body(&mut world, body(f32::NAN, 3., Vector2::new(1., 1.)));
+        world
+            .entity_mut(paddle)
+            .insert(Paddle(0.))
+            .insert(Player::Right)
+            .insert(PaddleRole::Solo);
+
+        stage.run(&mut world);
+
+        let tuning = Tuning::default();
+        let (bottom, top) = paddle_y_range(0, 1, sub_paddle_height(1, &tuning));
+        let spawn = Vec2::new(
+            paddle_spawn_x(Player::Right, PaddleRole::Solo),
+            (bottom + top) / 2.,
+        );
+        assert_eq!(
+            world
+                .get::<Transform>(paddle)
+                .unwrap()
+                .translation
+                .truncate(),
+            spawn
+        );
+        let rb = body_of(&world, paddle);
+        assert_eq!(rb.linvel(), &Vector2::zeros());
+        assert!(sound_state(rb, SCALE).is_some());
+        assert_eq!(world.get_resource::<PhysicsResets>().unwrap().0, 1);
+    }
+}
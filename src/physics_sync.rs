@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_rapier2d::physics::{ColliderHandleComponent, RigidBodyHandleComponent};
+use bevy_rapier2d::rapier::dynamics::{JointSet, RigidBodyHandle, RigidBodySet};
+use bevy_rapier2d::rapier::geometry::{ColliderHandle, ColliderSet};
+
+use crate::match_state::MatchPhase;
+
+pub struct PhysicsSyncPlugin;
+
+impl Plugin for PhysicsSyncPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        // Despawns are applied at the end of the update stage, the removed components are only
+        // visible to systems running after that in the same frame
+        app.init_resource::<PhysicsHandles>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                remove_despawned_bodies
+                    .system()
+                    .label("remove_despawned_bodies"),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                check_physics_sets.system().after("remove_despawned_bodies"),
+            );
+    }
+}
+
+/// Rapier handles of every entity with a body or collider. The handle components are gone by
+/// the time a despawn is noticed, so they are kept here.
+#[derive(Debug, Default)]
+struct PhysicsHandles {
+    bodies: HashMap<Entity, RigidBodyHandle>,
+    colliders: HashMap<Entity, ColliderHandle>,
+}
+
+/// Takes the bodies and colliders of despawned entities out of the rapier sets, along with any
+/// joint attached to them, so the physics world doesn't grow over many matches.
+fn remove_despawned_bodies(
+    mut handles: ResMut<PhysicsHandles>,
+    mut bodies: ResMut<RigidBodySet>,
+    mut colliders: ResMut<ColliderSet>,
+    mut joints: ResMut<JointSet>,
+    added_bodies: Query<(Entity, &RigidBodyHandleComponent), Added<RigidBodyHandleComponent>>,
+    added_colliders: Query<(Entity, &ColliderHandleComponent), Added<ColliderHandleComponent>>,
+    removed_bodies: RemovedComponents<RigidBodyHandleComponent>,
+    removed_colliders: RemovedComponents<ColliderHandleComponent>,
+) {
+    for entity in removed_colliders.iter() {
+        if let Some(handle) = handles.colliders.remove(&entity) {
+            colliders.remove(handle, &mut bodies, true);
+        }
+    }
+
+    // Removing a body also removes the colliders and joints still attached to it
+    for entity in removed_bodies.iter() {
+        if let Some(handle) = handles.bodies.remove(&entity) {
+            bodies.remove(handle, &mut colliders, &mut joints);
+        }
+    }
+
+    for (entity, body) in added_bodies.iter() {
+        handles.bodies.insert(entity, body.handle());
+    }
+    for (entity, collider) in added_colliders.iter() {
+        handles.colliders.insert(entity, collider.handle());
+    }
+}
+
+/// Every match starts with exactly one rapier body and collider per physics entity, anything
+/// else is a body that outlived its entity.
+fn check_physics_sets(
+    phase: Res<MatchPhase>,
+    bodies: Res<RigidBodySet>,
+    colliders: Res<ColliderSet>,
+    body_entities: Query<(), With<RigidBodyHandleComponent>>,
+    collider_entities: Query<(), With<ColliderHandleComponent>>,
+) {
+    if !phase.is_changed() || *phase != MatchPhase::Regular {
+        return;
+    }
+
+    let body_count = body_entities.iter().count();
+    let collider_count = collider_entities.iter().count();
+    debug!(
+        "Match start: {} bodies for {} entities, {} colliders for {} entities",
+        bodies.len(),
+        body_count,
+        colliders.len(),
+        collider_count
+    );
+    debug_assert_eq!(bodies.len(), body_count, "rapier bodies without an entity");
+    debug_assert_eq!(
+        colliders.len(),
+        collider_count,
+        "rapier colliders without an entity"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_rapier2d::rapier::dynamics::RigidBodyBuilder;
+    use bevy_rapier2d::rapier::geometry::ColliderBuilder;
+
+    const MATCHES: usize = 200;
+
+    /// Ball or paddle of the soak test.
+    struct Soaked;
+
+    /// Set to despawn everything of the match from the update stage, like a rematch would.
+    struct EndMatch(bool);
+
+    fn end_match(
+        mut commands: Commands,
+        mut end: ResMut<EndMatch>,
+        soaked: Query<Entity, With<Soaked>>,
+    ) {
+        if !std::mem::replace(&mut end.0, false) {
+            return;
+        }
+        for entity in soaked.iter() {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    fn soak_app() -> App {
+        let mut app = App::build();
+        app.init_resource::<MatchPhase>()
+            .insert_resource(EndMatch(false))
+            .insert_resource(RigidBodySet::new())
+            .insert_resource(ColliderSet::new())
+            .insert_resource(JointSet::new())
+            .add_plugin(PhysicsSyncPlugin)
+            .add_system(end_match.system());
+        app.app
+    }
+
+    /// A ball and two paddles, each with a body and a collider, the way rapier hands them out.
+    fn start_match(world: &mut World) {
+        for _ in 0..3 {
+            let mut bodies = world.remove_resource::<RigidBodySet>().unwrap();
+            let body = bodies.insert(RigidBodyBuilder::new_dynamic().build());
+            let collider = world.get_resource_mut::<ColliderSet>().unwrap().insert(
+                ColliderBuilder::ball(1.).build(),
+                body,
+                &mut bodies,
+            );
+            world.insert_resource(bodies);
+
+            world.spawn().insert_bundle((
+                Soaked,
+                RigidBodyHandleComponent::from(body),
+                ColliderHandleComponent::from(collider),
+            ));
+        }
+        *world.get_resource_mut::<MatchPhase>().unwrap() = MatchPhase::Regular;
+    }
+
+    fn set_sizes(world: &World) -> (usize, usize, usize, usize) {
+        let handles = world.get_resource::<PhysicsHandles>().unwrap();
+        (
+            world.get_resource::<RigidBodySet>().unwrap().len(),
+            world.get_resource::<ColliderSet>().unwrap().len(),
+            handles.bodies.len(),
+            handles.colliders.len(),
+        )
+    }
+
+    #[test]
+    fn rematches_keep_the_physics_sets_flat() {
+        let mut app = soak_app();
+        app.update();
+
+        for round in 0..MATCHES {
+            // check_physics_sets asserts the sets match the entities at every match start
+            start_match(&mut app.world);
+            app.update();
+            assert_eq!(set_sizes(&app.world), (3, 3, 3, 3), "match {}", round);
+
+            *app.world.get_resource_mut::<MatchPhase>().unwrap() =
+                MatchPhase::Finished(crate::Player::Left);
+            app.world.get_resource_mut::<EndMatch>().unwrap().0 = true;
+            app.update();
+            assert_eq!(set_sizes(&app.world), (0, 0, 0, 0), "after match {}", round);
+        }
+    }
+
+    #[test]
+    fn despawning_a_collider_keeps_its_body() {
+        let mut app = soak_app();
+        start_match(&mut app.world);
+        app.update();
+
+        let mut colliders = app
+            .world
+            .query_filtered::<Entity, (With<ColliderHandleComponent>, With<Soaked>)>();
+        let entity = colliders.iter(&app.world).next().unwrap();
+        app.world
+            .entity_mut(entity)
+            .remove::<ColliderHandleComponent>();
+        app.update();
+
+        assert_eq!(set_sizes(&app.world), (3, 2, 3, 2));
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_rapier2d::physics::RigidBodyHandleComponent;
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+
+use crate::contacts::{BallContact, Surface};
+use crate::hud;
+use crate::input::PaddleIntent;
+use crate::theme::Theme;
+use crate::{AppState, Ball, GameLabel, GoalEvent, Paddle, Paused, Player};
+
+pub struct PowerPlugin;
+
+impl Plugin for PowerPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PowerMeters>()
+            .init_resource::<PaddleReach>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame).with_system(spawn_power_pips.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(
+                        charge_power
+                            .system()
+                            .label("charge_power")
+                            .after(GameLabel::Contacts),
+                    )
+                    .with_system(
+                        release_power
+                            .system()
+                            .label("release_power")
+                            .after(GameLabel::Input)
+                            .after("charge_power")
+                            .after("clamp_ball_speed"),
+                    )
+                    .with_system(
+                        reset_power
+                            .system()
+                            .label(GameLabel::GoalApply)
+                            .after(GameLabel::GoalDetect)
+                            .after("release_power"),
+                    )
+                    .with_system(fade_power_shots.system())
+                    .with_system(
+                        render_power_pips
+                            .system()
+                            .label(GameLabel::GoalPresent)
+                            .after(GameLabel::GoalApply),
+                    ),
+            );
+    }
+}
+
+pub const MAX_PIPS: u32 = 3;
+/// Speed gained per pip spent on a power shot.
+const SPEED_PER_PIP: f32 = 0.25;
+/// A power shot may go this much faster than the regular speed cap.
+pub const POWER_SHOT_CAP_FACTOR: f32 = 1.5;
+/// Seconds after the ball leaves a paddle that a power shot is still accepted.
+const REACH_SECONDS: f64 = 0.15;
+const FLASH_SECONDS: f32 = 0.3;
+const PIP_SIZE: f32 = 12.;
+/// Top of the pip row as a fraction of the window height.
+const PIP_TOP: f32 = 0.66;
+
+/// Charge of each player's power meter, one pip per return.
+#[derive(Debug, Default)]
+pub struct PowerMeters {
+    left: u32,
+    right: u32,
+}
+
+impl PowerMeters {
+    pub fn pips(&self, player: Player) -> u32 {
+        match player {
+            Player::Left => self.left,
+            Player::Right => self.right,
+        }
+    }
+
+    fn pips_mut(&mut self, player: Player) -> &mut u32 {
+        match player {
+            Player::Left => &mut self.left,
+            Player::Right => &mut self.right,
+        }
+    }
+}
+
+/// Ball and paddle pairs that touch or just stopped touching, keyed by `(paddle, ball)`.
+#[derive(Debug, Default)]
+struct PaddleReach {
+    pairs: HashMap<(Entity, Entity), Reach>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Reach {
+    touching: bool,
+    /// Time of the latest started or stopped contact.
+    since: f64,
+}
+
+impl Reach {
+    fn in_reach(&self, now: f64) -> bool {
+        self.touching || now - self.since <= REACH_SECONDS
+    }
+}
+
+/// Ball launched by a power shot, lifts the speed cap until the next paddle hit or goal.
+pub struct PowerShot {
+    flash: Timer,
+}
+
+impl PowerShot {
+    pub fn is_flashing(&self) -> bool {
+        !self.flash.finished()
+    }
+}
+
+struct PowerPip {
+    player: Player,
+    index: u32,
+}
+
+fn charge_power(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut meters: ResMut<PowerMeters>,
+    mut reach: ResMut<PaddleReach>,
+    mut contacts: EventReader<BallContact>,
+    power_shots: Query<&PowerShot>,
+) {
+    let now = time.seconds_since_startup();
+
+    for contact in contacts.iter() {
+        let player = match contact.surface {
+            Surface::Paddle(player) => player,
+            _ => continue,
+        };
+
+        reach.pairs.insert(
+            (contact.other, contact.ball),
+            Reach {
+                touching: contact.started,
+                since: now,
+            },
+        );
+
+        if contact.started {
+            let pips = meters.pips_mut(player);
+            *pips = (*pips + 1).min(MAX_PIPS);
+
+            if power_shots.get(contact.ball).is_ok() {
+                commands.entity(contact.ball).remove::<PowerShot>();
+            }
+        }
+    }
+}
+
+fn release_power(
+    mut commands: Commands,
+    time: Res<Time>,
+    paused: Res<Paused>,
+    reach: Res<PaddleReach>,
+    mut meters: ResMut<PowerMeters>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    paddles: Query<(Entity, &Player, &PaddleIntent), With<Paddle>>,
+    balls: Query<&RigidBodyHandleComponent, With<Ball>>,
+) {
+    if paused.0 {
+        return;
+    }
+
+    let now = time.seconds_since_startup();
+
+    for (paddle, player, intent) in paddles.iter() {
+        let pips = meters.pips(*player);
+        if !intent.power || pips == 0 {
+            continue;
+        }
+
+        let ball = reach
+            .pairs
+            .iter()
+            .find(|((reach_paddle, _), reach)| *reach_paddle == paddle && reach.in_reach(now))
+            .map(|((_, ball), _)| *ball);
+        let ball = match ball {
+            Some(ball) => ball,
+            None => continue,
+        };
+
+        let rb = balls
+            .get(ball)
+            .ok()
+            .and_then(|rigid_body_component| rigid_bodies.get_mut(rigid_body_component.handle()));
+        if let Some(rb) = rb {
+            let linvel = *rb.linvel() * (1. + SPEED_PER_PIP * pips as f32);
+            rb.set_linvel(linvel, true);
+
+            *meters.pips_mut(*player) = 0;
+            commands.entity(ball).insert(PowerShot {
+                flash: Timer::from_seconds(FLASH_SECONDS, false),
+            });
+        }
+    }
+}
+
+fn reset_power(
+    mut commands: Commands,
+    mut meters: ResMut<PowerMeters>,
+    mut reach: ResMut<PaddleReach>,
+    mut goals: EventReader<GoalEvent>,
+    power_shots: Query<&PowerShot>,
+) {
+    for goal in goals.iter() {
+        *meters = PowerMeters::default();
+        reach.pairs.clear();
+
+        if power_shots.get(goal.ball).is_ok() {
+            commands.entity(goal.ball).remove::<PowerShot>();
+        }
+    }
+}
+
+fn fade_power_shots(time: Res<Time>, mut power_shots: Query<&mut PowerShot>) {
+    for mut power_shot in power_shots.iter_mut() {
+        power_shot.flash.tick(time.delta());
+    }
+}
+
+/// Pips sit in a row under each player's score.
+fn spawn_power_pips(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    theme: Res<Theme>,
+) {
+    for player in [Player::Left, Player::Right].iter() {
+        commands
+            .spawn_bundle(NodeBundle {
+                style: hud::half_row(*player, PIP_TOP),
+                material: materials.add(Color::NONE.into()),
+                ..Default::default()
+            })
+            .with_children(|parent| {
+                for index in 0..MAX_PIPS {
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(PIP_SIZE), Val::Px(PIP_SIZE)),
+                                margin: Rect {
+                                    left: Val::Px(PIP_SIZE / 4.),
+                                    right: Val::Px(PIP_SIZE / 4.),
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            },
+                            material: materials.add(theme.power_pip_empty.into()),
+                            ..Default::default()
+                        })
+                        .insert(PowerPip {
+                            player: *player,
+                            index,
+                        });
+                }
+            });
+    }
+}
+
+fn render_power_pips(
+    meters: Res<PowerMeters>,
+    theme: Res<Theme>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    pips: Query<(&PowerPip, &Handle<ColorMaterial>)>,
+) {
+    if !meters.is_changed() {
+        return;
+    }
+
+    for (pip, material) in pips.iter() {
+        if let Some(material) = materials.get_mut(material) {
+            material.color = if pip.index < meters.pips(pip.player) {
+                theme.power_pip
+            } else {
+                theme.power_pip_empty
+            };
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+
+use crate::match_state::{MatchPhase, MatchRules};
+use crate::theme::Theme;
+use crate::{AppState, GameLabel, Player, Score, ARENA_HEIGHT, ARENA_MIDDLE, ARENA_WIDTH};
+
+pub struct PressurePlugin;
+
+impl Plugin for PressurePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_enter(AppState::InGame).with_system(spawn_pressure.system()),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::InGame).with_system(
+                pulse_pressure
+                    .system()
+                    .label(GameLabel::GoalPresent)
+                    .after(GameLabel::GoalApply),
+            ),
+        );
+    }
+}
+
+/// Translucent quad over the half of the arena defended by the player.
+struct PressureQuad(Player);
+
+/// Highest alpha of the pulse.
+const PRESSURE_ALPHA: f32 = 0.15;
+/// Pulses per second.
+const PRESSURE_RATE: f32 = 1.2;
+/// The 2d camera sees down to z = -0.1, this keeps the quads behind every sprite at z = 0.
+const PRESSURE_Z: f32 = -0.05;
+
+/// Whether the next goal against `player` loses them the match.
+fn facing_match_point(
+    player: Player,
+    score: &Score,
+    rules: &MatchRules,
+    phase: MatchPhase,
+) -> bool {
+    let opponent = player.opponent();
+
+    match phase {
+        MatchPhase::Overtime => true,
+        MatchPhase::Finished(_) => false,
+        MatchPhase::Regular => rules
+            .score_limit
+            .map_or(false, |limit| score.of(opponent) + 1 >= limit),
+    }
+}
+
+fn spawn_pressure(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    theme: Res<Theme>,
+) {
+    for (player, x) in [
+        (Player::Left, ARENA_MIDDLE / 2.),
+        (Player::Right, ARENA_MIDDLE + ARENA_MIDDLE / 2.),
+    ]
+    .iter()
+    {
+        let mut color = theme.player_color(*player);
+        color.set_a(0.);
+
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: materials.add(color.into()),
+                sprite: Sprite::new(Vec2::new(ARENA_WIDTH / 2., ARENA_HEIGHT)),
+                transform: Transform::from_xyz(*x, ARENA_HEIGHT / 2., PRESSURE_Z),
+                ..Default::default()
+            })
+            .insert(PressureQuad(*player));
+    }
+}
+
+fn pulse_pressure(
+    time: Res<Time>,
+    theme: Res<Theme>,
+    score: Res<Score>,
+    rules: Res<MatchRules>,
+    phase: Res<MatchPhase>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    quads: Query<(&PressureQuad, &Handle<ColorMaterial>)>,
+) {
+    let wave = (time.seconds_since_startup() as f32 * PRESSURE_RATE * std::f32::consts::PI * 2.)
+        .sin()
+        * 0.5
+        + 0.5;
+
+    for (quad, material) in quads.iter() {
+        let alpha = if facing_match_point(quad.0, &score, &rules, *phase) {
+            wave * PRESSURE_ALPHA
+        } else {
+            0.
+        };
+
+        if let Some(material) = materials.get_mut(material) {
+            let mut color = theme.player_color(quad.0);
+            color.set_a(alpha);
+            material.color = color;
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::{KeyBindings, PlayerKeys};
+use crate::hill::Queue;
+use crate::hud;
+use crate::persistence::SaveData;
+use crate::stats::MatchStats;
+use crate::theme::{Theme, PALETTE};
+use crate::tournament::Tournament;
+use crate::{AppState, Player, Score, UiFont};
+
+/// Named players sharing the machine. Whoever sits on a paddle brings their color, keys and
+/// statistics along, nobody picked plays as the guest.
+pub struct ProfilesPlugin;
+
+impl Plugin for ProfilesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<BaseBindings>()
+            .add_system(apply_profile_keys.system().before("pause_menu"))
+            .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(spawn_names.system()))
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame).with_system(render_names.system()),
+            );
+    }
+}
+
+/// Name shown for a paddle without a profile. The guest isn't stored and can't be deleted.
+pub const GUEST: &str = "Guest";
+/// Longest profile name kept, in characters.
+pub const MAX_NAME_CHARS: usize = 16;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    /// Index into `PALETTE`, `None` keeps the theme's color.
+    pub color: Option<usize>,
+    /// Keys used instead of the paddle's own, edited in the save file.
+    pub keys: Option<PlayerKeys>,
+    pub stats: ProfileStats,
+}
+
+/// Totals over every finished match played with the profile.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileStats {
+    pub matches: u32,
+    pub wins: u32,
+    pub goals: u32,
+    pub paddle_hits: u32,
+    pub longest_rally: u32,
+    /// Ball speed in pixels per second right after a paddle hit.
+    pub fastest_shot: f32,
+    /// Matches won in a row.
+    pub streak: u32,
+    pub best_streak: u32,
+}
+
+/// Name of the profile on each paddle, `None` for the guest. A name no longer in the profiles
+/// counts as the guest.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Seats {
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+impl Seats {
+    pub fn get(&self, player: Player) -> Option<&str> {
+        match player {
+            Player::Left => self.left.as_deref(),
+            Player::Right => self.right.as_deref(),
+        }
+    }
+
+    fn set(&mut self, player: Player, name: Option<String>) {
+        match player {
+            Player::Left => self.left = name,
+            Player::Right => self.right = name,
+        }
+    }
+}
+
+/// Profile on `player`'s paddle, `None` for the guest.
+pub fn seated(save: &SaveData, player: Player) -> Option<&Profile> {
+    let name = save.seats.get(player)?;
+    save.profiles.iter().find(|profile| profile.name == name)
+}
+
+fn seated_mut(save: &mut SaveData, player: Player) -> Option<&mut Profile> {
+    let name = save.seats.get(player)?.to_string();
+    save.profiles
+        .iter_mut()
+        .find(|profile| profile.name == name)
+}
+
+pub fn display_name(save: &SaveData, player: Player) -> &str {
+    seated(save, player).map_or(GUEST, |profile| profile.name.as_str())
+}
+
+/// The color picked by the profile on the paddle, or for the guest in the settings.
+pub fn player_color(save: &SaveData, player: Player) -> Option<Color> {
+    match seated(save, player) {
+        Some(profile) => profile.color.and_then(|index| PALETTE.get(index).copied()),
+        None => save.settings.player_color(player),
+    }
+}
+
+pub fn set_player_color(save: &mut SaveData, player: Player, index: usize) {
+    match seated_mut(save, player) {
+        Some(profile) => profile.color = Some(index),
+        None => save.settings.set_player_color(player, index),
+    }
+}
+
+/// Adds a profile named `name`, returns the name as kept or why it can't be.
+pub fn create(save: &mut SaveData, name: &str) -> Result<String, String> {
+    let name: String = name
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_NAME_CHARS)
+        .collect();
+    let name = name.trim().to_string();
+
+    if name.is_empty() {
+        return Err("Type a name first".to_string());
+    }
+    if name.eq_ignore_ascii_case(GUEST)
+        || save
+            .profiles
+            .iter()
+            .any(|profile| profile.name.eq_ignore_ascii_case(&name))
+    {
+        return Err(format!("There already is a profile called {}", name));
+    }
+
+    save.profiles.push(Profile {
+        name: name.clone(),
+        ..Default::default()
+    });
+    Ok(name)
+}
+
+/// Removes the profile and its statistics, a paddle it was on goes to the guest.
+pub fn delete(save: &mut SaveData, name: &str) {
+    save.profiles.retain(|profile| profile.name != name);
+    for player in [Player::Left, Player::Right].iter() {
+        if save.seats.get(*player) == Some(name) {
+            save.seats.set(*player, None);
+        }
+    }
+}
+
+/// Puts the next profile, or the one before, on `player`'s paddle. The guest comes first and can
+/// sit on both paddles, a profile only on one.
+pub fn step_seat(save: &mut SaveData, player: Player, forward: bool) {
+    let mut names: Vec<Option<String>> = vec![None];
+    names.extend(
+        save.profiles
+            .iter()
+            .map(|profile| Some(profile.name.clone())),
+    );
+    let count = names.len();
+    let current = seated(save, player)
+        .and_then(|profile| {
+            names
+                .iter()
+                .position(|name| name.as_deref() == Some(&profile.name))
+        })
+        .unwrap_or(0);
+    let taken = seated(save, player.opponent()).map(|profile| profile.name.clone());
+
+    let next = (1..count)
+        .map(|offset| {
+            if forward {
+                (current + offset) % count
+            } else {
+                (current + count - offset) % count
+            }
+        })
+        .find(|index| names[*index].is_none() || names[*index] != taken);
+    if let Some(index) = next {
+        save.seats.set(player, names[index].clone());
+    }
+}
+
+/// Books a finished match on the profiles of both paddles.
+pub fn record_match(save: &mut SaveData, winner: Player, score: &Score, stats: &MatchStats) {
+    for player in [Player::Left, Player::Right].iter() {
+        let profile = match seated_mut(save, *player) {
+            Some(profile) => profile,
+            None => continue,
+        };
+
+        let totals = &mut profile.stats;
+        totals.matches += 1;
+        if *player == winner {
+            totals.wins += 1;
+            totals.streak += 1;
+            totals.best_streak = totals.best_streak.max(totals.streak);
+        } else {
+            totals.streak = 0;
+        }
+        totals.goals += match player {
+            Player::Left => score.left,
+            Player::Right => score.right,
+        };
+        totals.paddle_hits += stats.player_hits(*player);
+        totals.longest_rally = totals.longest_rally.max(stats.longest_rally);
+        if let Some(fastest) = stats.fastest_shots.get(player) {
+            totals.fastest_shot = totals.fastest_shot.max(*fastest);
+        }
+    }
+}
+
+/// Keys as the game was started with or last imported, profiles put their own over them.
+pub struct BaseBindings(pub KeyBindings);
+
+impl FromWorld for BaseBindings {
+    fn from_world(world: &mut World) -> Self {
+        BaseBindings(
+            world
+                .get_resource::<KeyBindings>()
+                .cloned()
+                .unwrap_or_default(),
+        )
+    }
+}
+
+/// A profile's keys replace those of the front paddle of its side, the back paddles in doubles
+/// keep theirs.
+fn apply_profile_keys(
+    save: Res<SaveData>,
+    base: Res<BaseBindings>,
+    mut bindings: ResMut<KeyBindings>,
+) {
+    if !save.is_changed() && !base.is_changed() {
+        return;
+    }
+
+    let mut wanted = base.0.clone();
+    for player in [Player::Left, Player::Right].iter() {
+        if let Some(keys) = seated(&save, *player).and_then(|profile| profile.keys.clone()) {
+            match player {
+                Player::Left => wanted.left = keys,
+                Player::Right => wanted.right = keys,
+            }
+        }
+    }
+    if *bindings != wanted {
+        *bindings = wanted;
+    }
+}
+
+/// Name of the profile on each paddle, above their score.
+struct NameLabel(Player);
+
+/// Top of the names as a fraction of the window height.
+const NAME_TOP: f32 = 1. / 3.;
+
+/// The guest plays without a name.
+fn name_label(save: &SaveData, player: Player) -> String {
+    seated(save, player)
+        .map(|profile| profile.name.clone())
+        .unwrap_or_default()
+}
+
+/// Tournaments and king of the hill show the names of their own players instead.
+fn spawn_names(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    font: Res<UiFont>,
+    theme: Res<Theme>,
+    save: Res<SaveData>,
+    tournament: Res<Tournament>,
+    queue: Res<Queue>,
+) {
+    if tournament.is_active() || queue.is_active() {
+        return;
+    }
+
+    for player in [Player::Left, Player::Right].iter() {
+        commands
+            .spawn_bundle(NodeBundle {
+                style: hud::half_row(*player, NAME_TOP),
+                material: materials.add(Color::NONE.into()),
+                ..Default::default()
+            })
+            .with_children(|parent| {
+                parent
+                    .spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            name_label(&save, *player),
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 32.0,
+                                color: theme.player_color(*player),
+                            },
+                            Default::default(),
+                        ),
+                        ..Default::default()
+                    })
+                    .insert(NameLabel(*player));
+            });
+    }
+}
+
+fn render_names(
+    save: Res<SaveData>,
+    theme: Res<Theme>,
+    mut labels: Query<(&NameLabel, &mut Text)>,
+) {
+    if !save.is_changed() && !theme.is_changed() {
+        return;
+    }
+
+    for (label, mut text) in labels.iter_mut() {
+        let section = &mut text.sections[0];
+        section.value = name_label(&save, label.0);
+        section.style.color = theme.player_color(label.0);
+    }
+}
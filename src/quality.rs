@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+use bevy_rapier2d::rapier::dynamics::IntegrationParameters;
+
+use crate::particles::Particle;
+use crate::persistence::{SaveData, Settings};
+
+pub struct QualityPlugin;
+
+impl Plugin for QualityPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<QualitySettings>()
+            .add_system(apply_quality.system());
+    }
+}
+
+/// Upper bound on live particles at full quality.
+const MAX_PARTICLES: usize = 200;
+/// Solver iterations in performance mode, rapier defaults to 4 velocity and 1 position
+/// iteration. Two are still enough for the few bodies in the arena.
+const LOW_VELOCITY_ITERATIONS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QualityLevel {
+    High,
+    Low,
+}
+
+/// What the visual effects may do, derived from the settings in one place so effect systems
+/// don't each combine the switches themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QualitySettings {
+    pub level: QualityLevel,
+    pub particles: bool,
+    pub glow: bool,
+    pub shadow: bool,
+    /// Scanline overlay, never in performance mode.
+    pub crt: bool,
+    /// Stripe turning with the ball.
+    pub spin_marker: bool,
+    /// Arc of pips beside the ball showing its spin.
+    pub spin_meter: bool,
+    /// Particles allowed alive at the same time, 0 when they are off.
+    pub particle_budget: usize,
+}
+
+impl QualitySettings {
+    pub fn from_settings(settings: &Settings) -> Self {
+        if settings.performance_mode {
+            QualitySettings {
+                level: QualityLevel::Low,
+                particles: false,
+                glow: false,
+                shadow: false,
+                crt: false,
+                spin_marker: false,
+                spin_meter: false,
+                particle_budget: 0,
+            }
+        } else {
+            QualitySettings {
+                level: QualityLevel::High,
+                particles: settings.particles,
+                glow: true,
+                shadow: true,
+                crt: settings.crt_effect,
+                spin_marker: true,
+                spin_meter: settings.spin_meter,
+                particle_budget: if settings.particles { MAX_PARTICLES } else { 0 },
+            }
+        }
+    }
+}
+
+impl FromWorld for QualitySettings {
+    fn from_world(world: &mut World) -> Self {
+        let save = world.get_resource::<SaveData>().unwrap();
+        QualitySettings::from_settings(&save.settings)
+    }
+}
+
+/// Follows the settings at runtime, effects that are switched off are removed right away
+/// instead of being left to run out.
+fn apply_quality(
+    mut commands: Commands,
+    save: Res<SaveData>,
+    mut quality: ResMut<QualitySettings>,
+    integration_parameters: Option<ResMut<IntegrationParameters>>,
+    particles: Query<Entity, With<Particle>>,
+) {
+    if let Some(mut integration_parameters) = integration_parameters {
+        let iterations = match quality.level {
+            QualityLevel::High => IntegrationParameters::default().max_velocity_iterations,
+            QualityLevel::Low => LOW_VELOCITY_ITERATIONS,
+        };
+        if integration_parameters.max_velocity_iterations != iterations {
+            integration_parameters.max_velocity_iterations = iterations;
+        }
+    }
+
+    if !save.is_changed() {
+        return;
+    }
+
+    let wanted = QualitySettings::from_settings(&save.settings);
+    if wanted == *quality {
+        return;
+    }
+    *quality = wanted;
+
+    if !quality.particles {
+        for entity in particles.iter() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
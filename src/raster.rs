@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::render::texture::TextureFormat;
+use image::{Rgba, RgbaImage};
+
+/// A rect or circle in world pixels, as drawn by the sprite renderer. Flat colored, or its
+/// picture tinted by the color.
+#[derive(Debug, Clone)]
+pub struct Shape {
+    pub center: Vec2,
+    pub size: Vec2,
+    pub angle: f32,
+    pub color: Color,
+    pub round: bool,
+    pub z: f32,
+    pub picture: Option<Picture>,
+}
+
+/// Pixels of a sprite texture copied out of the assets, RGBA rows from the top.
+#[derive(Debug, Clone)]
+pub struct Picture {
+    width: u32,
+    height: u32,
+    pixels: Arc<Vec<u8>>,
+}
+
+impl Picture {
+    /// Only 8 bit RGBA textures are read, what PNGs load as. Anything else is drawn flat.
+    pub fn from_texture(texture: &Texture) -> Option<Picture> {
+        match texture.format {
+            TextureFormat::Rgba8UnormSrgb | TextureFormat::Rgba8Unorm => {}
+            _ => return None,
+        }
+        let (width, height) = (texture.size.width, texture.size.height);
+        if width == 0 || height == 0 || texture.data.len() < (width * height * 4) as usize {
+            return None;
+        }
+        Some(Picture {
+            width,
+            height,
+            pixels: Arc::new(texture.data.clone()),
+        })
+    }
+
+    /// Nearest pixel at `u`, `v` from the top left corner, both `0.0..=1.0`.
+    fn sample(&self, u: f32, v: f32) -> Color {
+        let x = ((u * self.width as f32) as u32).min(self.width - 1);
+        let y = ((v * self.height as f32) as u32).min(self.height - 1);
+        let at = ((y * self.width + x) * 4) as usize;
+        let channel = |i: usize| self.pixels[at + i] as f32 / 255.;
+        Color::rgba(channel(0), channel(1), channel(2), channel(3))
+    }
+}
+
+/// Software renders shapes into an image. The world has its origin in the bottom left corner
+/// like the arena camera, `scale` shrinks the output.
+pub fn rasterize(shapes: &[Shape], background: Color, world_size: Vec2, scale: f32) -> RgbaImage {
+    let width = (world_size.x * scale).round().max(1.) as u32;
+    let height = (world_size.y * scale).round().max(1.) as u32;
+    let mut image = RgbaImage::from_pixel(width, height, to_rgba(background));
+
+    let mut shapes = shapes.to_vec();
+    shapes.sort_by(|a, b| a.z.partial_cmp(&b.z).unwrap_or(std::cmp::Ordering::Equal));
+
+    for shape in shapes.iter() {
+        draw_shape(&mut image, shape, scale);
+    }
+
+    image
+}
+
+fn draw_shape(image: &mut RgbaImage, shape: &Shape, scale: f32) {
+    let half = shape.size * scale / 2.;
+    let center = shape.center * scale;
+    // Bounding radius covers any rotation
+    let reach = half.length().ceil();
+    let (sin, cos) = (-shape.angle).sin_cos();
+
+    let height = image.height() as f32;
+    let min_x = (center.x - reach).max(0.) as u32;
+    let max_x = (center.x + reach).min(image.width() as f32 - 1.).max(0.) as u32;
+    let min_y = (height - center.y - reach).max(0.) as u32;
+    let max_y = (height - center.y + reach).min(height - 1.).max(0.) as u32;
+
+    for py in min_y..=max_y {
+        for px in min_x..=max_x {
+            // Pixel center back in world orientation, y up
+            let dx = px as f32 + 0.5 - center.x;
+            let dy = (height - py as f32 - 0.5) - center.y;
+            let lx = dx * cos - dy * sin;
+            let ly = dx * sin + dy * cos;
+
+            let inside = if shape.round {
+                let nx = lx / half.x.max(f32::EPSILON);
+                let ny = ly / half.y.max(f32::EPSILON);
+                nx * nx + ny * ny <= 1.
+            } else {
+                lx.abs() <= half.x && ly.abs() <= half.y
+            };
+
+            if !inside {
+                continue;
+            }
+            let color = match &shape.picture {
+                Some(picture) => {
+                    let u = (lx / half.x.max(f32::EPSILON) + 1.) / 2.;
+                    let v = (1. - ly / half.y.max(f32::EPSILON)) / 2.;
+                    tint(picture.sample(u, v), shape.color)
+                }
+                None => shape.color,
+            };
+            let pixel = image.get_pixel_mut(px, py);
+            *pixel = blend(*pixel, color);
+        }
+    }
+}
+
+fn tint(color: Color, by: Color) -> Color {
+    Color::rgba(
+        color.r() * by.r(),
+        color.g() * by.g(),
+        color.b() * by.b(),
+        color.a() * by.a(),
+    )
+}
+
+fn to_rgba(color: Color) -> Rgba<u8> {
+    Rgba([
+        (color.r() * 255.) as u8,
+        (color.g() * 255.) as u8,
+        (color.b() * 255.) as u8,
+        255,
+    ])
+}
+
+fn blend(under: Rgba<u8>, color: Color) -> Rgba<u8> {
+    let a = color.a().max(0.).min(1.);
+    let over = to_rgba(color);
+    let mix = |i: usize| (over.0[i] as f32 * a + under.0[i] as f32 * (1. - a)) as u8;
+    Rgba([mix(0), mix(1), mix(2), 255])
+}
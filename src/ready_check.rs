@@ -0,0 +1,239 @@
+use bevy::prelude::*;
+
+use crate::bindings::{key_name, KeyBindings};
+use crate::hud;
+use crate::input::Controller;
+use crate::theme::Theme;
+use crate::tutorial::Tutorial;
+use crate::{AppState, PaddleRole, Paused, Player, UiFont};
+
+/// Holds the first serve of a match between two humans until both have pressed their power key.
+pub struct ReadyCheckPlugin;
+
+impl Plugin for ReadyCheckPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ReadyCheck>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame).with_system(start_ready_check.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(ready_up.system().label("ready_up").after("cpu_opponent"))
+                    .with_system(render_ready_check.system().after("ready_up")),
+            );
+    }
+}
+
+/// Seconds between both sides being ready and the intro starting, either can still back out.
+const COUNTDOWN_SECONDS: f32 = 2.;
+/// Alpha of a side's half while it waits, and once it is ready.
+const WAITING_ALPHA: f32 = 0.15;
+const READY_ALPHA: f32 = 0.5;
+
+#[derive(Debug, Default)]
+pub struct ReadyCheck {
+    requested: bool,
+    open: bool,
+    left: bool,
+    right: bool,
+    /// Seconds left once both are ready.
+    countdown: Option<f32>,
+    paused_game: bool,
+}
+
+impl ReadyCheck {
+    pub fn is_open(&self) -> bool {
+        self.open || self.requested
+    }
+
+    /// Opens the check before the next serve, when both sides have a human by then.
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    fn is_ready(&self, player: Player) -> bool {
+        match player {
+            Player::Left => self.left,
+            Player::Right => self.right,
+        }
+    }
+
+    fn set_ready(&mut self, player: Player, ready: bool) {
+        match player {
+            Player::Left => self.left = ready,
+            Player::Right => self.right = ready,
+        }
+    }
+}
+
+fn start_ready_check(mut ready_check: ResMut<ReadyCheck>) {
+    ready_check.request();
+}
+
+/// A side without a human paddle, from the start or handed to the computer during the check, is
+/// ready on its own. Only opens when both sides start out with a human.
+fn ready_up(
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    tutorial: Res<Tutorial>,
+    mut ready_check: ResMut<ReadyCheck>,
+    mut paused: ResMut<Paused>,
+    paddles: Query<(&Player, &PaddleRole, &Controller)>,
+) {
+    let has_human = |player: Player| {
+        paddles
+            .iter()
+            .any(|(owner, _, controller)| *owner == player && !controller.is_ai())
+    };
+
+    if ready_check.requested {
+        ready_check.requested = false;
+        if !has_human(Player::Left) || !has_human(Player::Right) {
+            return;
+        }
+        *ready_check = ReadyCheck {
+            open: true,
+            paused_game: !paused.0,
+            ..ReadyCheck::default()
+        };
+        paused.0 = true;
+        return;
+    }
+
+    if !ready_check.open {
+        return;
+    }
+
+    // Any key closes the tutorial, it shouldn't ready anyone up on the way
+    if !tutorial.is_open() {
+        for (player, role, controller) in paddles.iter() {
+            let confirm = bindings.for_paddle(*player, *role).power;
+            if !controller.is_ai() && keyboard_input.just_pressed(confirm) {
+                let ready = !ready_check.is_ready(*player);
+                ready_check.set_ready(*player, ready);
+            }
+        }
+    }
+    for player in [Player::Left, Player::Right].iter() {
+        if !has_human(*player) {
+            ready_check.set_ready(*player, true);
+        }
+    }
+
+    if !ready_check.left || !ready_check.right {
+        ready_check.countdown = None;
+        return;
+    }
+
+    let remaining = ready_check.countdown.unwrap_or(COUNTDOWN_SECONDS) - time.delta_seconds();
+    if remaining > 0. {
+        ready_check.countdown = Some(remaining);
+        return;
+    }
+
+    if ready_check.paused_game {
+        paused.0 = false;
+    }
+    *ready_check = ReadyCheck::default();
+}
+
+/// Half of the screen for one side, lit in the player's color once they are ready.
+struct ReadyHalf(Player);
+
+/// Prompt in a side's half, with the countdown under it.
+struct ReadyText(Player);
+
+fn render_ready_check(
+    mut commands: Commands,
+    ready_check: Res<ReadyCheck>,
+    theme: Res<Theme>,
+    bindings: Res<KeyBindings>,
+    font: Res<UiFont>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    halves: Query<(Entity, &ReadyHalf, &Handle<ColorMaterial>)>,
+    controllers: Query<(&Player, &Controller)>,
+    mut texts: Query<(&ReadyText, &mut Text)>,
+) {
+    if !ready_check.open {
+        for (entity, _, _) in halves.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    if halves.iter().next().is_none() {
+        for player in [Player::Left, Player::Right].iter() {
+            commands
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: Rect {
+                            left: hud::percent(hud::half_left(*player)),
+                            top: Val::Px(0.),
+                            ..Default::default()
+                        },
+                        size: Size::new(Val::Percent(50.), Val::Percent(100.)),
+                        flex_direction: FlexDirection::ColumnReverse,
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    material: materials.add(Color::NONE.into()),
+                    ..Default::default()
+                })
+                .insert(ReadyHalf(*player))
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                "".to_string(),
+                                TextStyle {
+                                    font: font.0.clone(),
+                                    font_size: 32.0,
+                                    color: Color::rgb(1.0, 1.0, 1.0),
+                                },
+                                TextAlignment {
+                                    horizontal: HorizontalAlign::Center,
+                                    ..Default::default()
+                                },
+                            ),
+                            ..Default::default()
+                        })
+                        .insert(ReadyText(*player));
+                });
+        }
+        return;
+    }
+
+    for (_, half, material) in halves.iter() {
+        let alpha = if ready_check.is_ready(half.0) {
+            READY_ALPHA
+        } else {
+            WAITING_ALPHA
+        };
+        if let Some(material) = materials.get_mut(material) {
+            let mut color = theme.player_color(half.0);
+            color.set_a(alpha);
+            material.color = color;
+        }
+    }
+
+    for (text, mut value) in texts.iter_mut() {
+        let key = key_name(bindings.for_player(&text.0).power);
+        let computer = controllers
+            .iter()
+            .all(|(owner, controller)| *owner != text.0 || controller.is_ai());
+        let prompt = if computer {
+            "CPU ready".to_string()
+        } else if ready_check.is_ready(text.0) {
+            format!("Ready! {} to back out", key)
+        } else {
+            format!("Press {} when ready", key)
+        };
+        value.sections[0].value = match ready_check.countdown {
+            Some(seconds) => format!("{}\nStarting in {}", prompt, seconds.ceil() as u32),
+            None => prompt,
+        };
+    }
+}
@@ -0,0 +1,288 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::accessibility::Accessibility;
+use crate::ball_visuals::{BallGlow, BallShadow};
+use crate::slow_motion::TimeScale;
+use crate::{AppState, Ball, GameLabel, GoalEvent, Paddle, Paused, UiFont};
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ReplayBuffer>()
+            .init_resource::<Replay>()
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(record_frame.system().before(GameLabel::GoalDetect))
+                    .with_system(
+                        start_replay
+                            .system()
+                            .label(GameLabel::GoalPresent)
+                            .after(GameLabel::GoalApply),
+                    )
+                    .with_system(play_replay.system().after(GameLabel::GoalPresent)),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::InGame).with_system(stop_replay.system()));
+    }
+}
+
+/// Seconds of play shown in the replay.
+const REPLAY_SECONDS: f32 = 2.;
+/// Seconds of play in the clip of the final point.
+pub const CLIP_SECONDS: f32 = 4.;
+/// Frames kept, enough for `CLIP_SECONDS` at 60 fps with a slow-motion finish stretching the end.
+const REPLAY_CAPACITY: usize = 420;
+const REPLAY_SPEED: f32 = 0.5;
+
+/// Ball and paddle transforms of one frame.
+struct ReplayFrame {
+    time: f32,
+    /// Seconds of game time since the buffer started, runs slower than `time` in slow motion.
+    play_time: f32,
+    slow_motion: bool,
+    transforms: Vec<(Entity, Transform)>,
+}
+
+/// The most recent frames of the current point, oldest first.
+pub struct ReplayBuffer {
+    frames: VecDeque<ReplayFrame>,
+}
+
+impl Default for ReplayBuffer {
+    fn default() -> Self {
+        ReplayBuffer {
+            frames: VecDeque::with_capacity(REPLAY_CAPACITY),
+        }
+    }
+}
+
+impl ReplayBuffer {
+    fn push(&mut self, frame: ReplayFrame) {
+        if self.frames.len() == REPLAY_CAPACITY {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    /// Time and transforms of the frames in the last `seconds` of play, oldest first. Measured in
+    /// game time, so a slow-motion finish is kept whole and plays back slowed.
+    pub fn recent_frames(&self, seconds: f32) -> Vec<(f32, Vec<(Entity, Transform)>)> {
+        let last = match self.frames.back() {
+            Some(last) => last.play_time,
+            None => return Vec::new(),
+        };
+        self.frames
+            .iter()
+            .filter(|frame| frame.play_time >= last - seconds)
+            .map(|frame| (frame.time, frame.transforms.clone()))
+            .collect()
+    }
+
+    /// Whether the point ended in the slow-motion finish of a match-winning goal.
+    fn ends_in_slow_motion(&self) -> bool {
+        self.frames.back().map_or(false, |frame| frame.slow_motion)
+    }
+
+    /// Latest frame at or before `time`.
+    fn frame_at(&self, time: f32) -> Option<&ReplayFrame> {
+        self.frames
+            .iter()
+            .take_while(|frame| frame.time <= time)
+            .last()
+    }
+}
+
+/// Playback of the buffer after a goal, the game stays paused while it runs.
+#[derive(Debug, Default)]
+pub struct Replay {
+    playing: bool,
+    /// Point of play time being shown.
+    cursor: f32,
+    end: f32,
+    paused_game: bool,
+}
+
+impl Replay {
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+}
+
+/// Sprite copy following a recorded entity, it has no rigid body.
+struct ReplayGhost(Entity);
+struct ReplayLabel;
+
+fn record_frame(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    paused: Res<Paused>,
+    replay: Res<Replay>,
+    mut buffer: ResMut<ReplayBuffer>,
+    recorded: Query<(Entity, &Transform), Or<(With<Ball>, With<Paddle>)>>,
+) {
+    if paused.0 || replay.playing {
+        return;
+    }
+
+    let play_time = buffer.frames.back().map_or(0., |last| {
+        last.play_time + time.delta_seconds() * time_scale.0
+    });
+    buffer.push(ReplayFrame {
+        time: time.seconds_since_startup() as f32,
+        play_time,
+        slow_motion: time_scale.is_slowed(),
+        transforms: recorded
+            .iter()
+            .map(|(entity, transform)| (entity, *transform))
+            .collect(),
+    });
+}
+
+fn start_replay(
+    mut commands: Commands,
+    ui_font: Res<UiFont>,
+    accessibility: Res<Accessibility>,
+    buffer: Res<ReplayBuffer>,
+    mut replay: ResMut<Replay>,
+    mut paused: ResMut<Paused>,
+    mut goals: EventReader<GoalEvent>,
+    mut recorded: Query<
+        (
+            Entity,
+            &Sprite,
+            &Handle<ColorMaterial>,
+            &mut Visible,
+            Option<&Parent>,
+        ),
+        Or<(With<Ball>, With<Paddle>, With<BallGlow>, With<BallShadow>)>,
+    >,
+) {
+    if goals.iter().next().is_none() || replay.playing || accessibility.reduced_motion {
+        return;
+    }
+    // The slow motion already showed it, cut straight to the celebration
+    if buffer.ends_in_slow_motion() {
+        return;
+    }
+
+    let (first, last) = match (buffer.frames.front(), buffer.frames.back()) {
+        (Some(first), Some(last)) => (first.time, last.time),
+        _ => return,
+    };
+
+    replay.playing = true;
+    replay.cursor = first.max(last - REPLAY_SECONDS);
+    replay.end = last;
+    replay.paused_game = !paused.0;
+    paused.0 = true;
+
+    // The real sprites already moved on to the serve, ghosts stand in for them
+    for (entity, sprite, material, mut visible, parent) in recorded.iter_mut() {
+        visible.is_visible = false;
+        // Glows and shadows only follow their ball
+        if parent.is_some() {
+            continue;
+        }
+
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: material.clone(),
+                sprite: sprite.clone(),
+                ..Default::default()
+            })
+            .insert(ReplayGhost(entity));
+    }
+
+    commands
+        .spawn_bundle(TextBundle {
+            text: Text::with_section(
+                "REPLAY".to_string(),
+                TextStyle {
+                    font: ui_font.0.clone(),
+                    font_size: 48.0,
+                    color: Color::rgb(1.0, 0.3, 0.2),
+                },
+                Default::default(),
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(20.),
+                    left: Val::Px(20.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(ReplayLabel);
+}
+
+fn play_replay(
+    mut commands: Commands,
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut buffer: ResMut<ReplayBuffer>,
+    mut replay: ResMut<Replay>,
+    mut paused: ResMut<Paused>,
+    mut ghosts: Query<(Entity, &ReplayGhost, &mut Transform)>,
+    labels: Query<Entity, With<ReplayLabel>>,
+    mut recorded: Query<
+        &mut Visible,
+        Or<(With<Ball>, With<Paddle>, With<BallGlow>, With<BallShadow>)>,
+    >,
+) {
+    if !replay.playing {
+        return;
+    }
+
+    replay.cursor += time.delta_seconds() * REPLAY_SPEED;
+
+    let skipped = keyboard_input.get_just_pressed().next().is_some();
+    if !skipped && replay.cursor <= replay.end {
+        if let Some(frame) = buffer.frame_at(replay.cursor) {
+            for (_, ghost, mut transform) in ghosts.iter_mut() {
+                if let Some((_, recorded)) = frame.transforms.iter().find(|(e, _)| *e == ghost.0) {
+                    *transform = *recorded;
+                }
+            }
+        }
+        return;
+    }
+
+    // Done or skipped, clear everything before the serve is played
+    for (entity, _, _) in ghosts.iter_mut() {
+        commands.entity(entity).despawn();
+    }
+    for entity in labels.iter() {
+        commands.entity(entity).despawn();
+    }
+    for mut visible in recorded.iter_mut() {
+        visible.is_visible = true;
+    }
+
+    if replay.paused_game {
+        paused.0 = false;
+    }
+    *replay = Replay::default();
+    buffer.frames.clear();
+}
+
+fn stop_replay(
+    mut commands: Commands,
+    mut buffer: ResMut<ReplayBuffer>,
+    mut replay: ResMut<Replay>,
+    mut paused: ResMut<Paused>,
+    ghosts: Query<Entity, Or<(With<ReplayGhost>, With<ReplayLabel>)>>,
+) {
+    for entity in ghosts.iter() {
+        commands.entity(entity).despawn();
+    }
+    if replay.playing && replay.paused_game {
+        paused.0 = false;
+    }
+    *replay = Replay::default();
+    buffer.frames.clear();
+}
@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+use bevy_rapier2d::physics::RigidBodyHandleComponent;
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+use bevy_rapier2d::rapier::na::Vector2;
+
+use crate::tutorial::Tutorial;
+use crate::{AppState, Paused, UiFont};
+
+pub struct ResumePlugin;
+
+impl Plugin for ResumePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ResumeCountdown>()
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(run_countdown.system().after("pause_menu")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::InGame).with_system(stop_countdown.system()),
+            );
+    }
+}
+
+const COUNTDOWN_SECONDS: f32 = 3.;
+
+/// Countdown between closing the pause menu and the game running again. The velocities of every
+/// dynamic body are kept from the moment the menu paused the game and put back when it ends.
+#[derive(Debug, Default)]
+pub struct ResumeCountdown {
+    capture: bool,
+    timer: Option<Timer>,
+    velocities: Vec<(Entity, Vector2<f32>, f32)>,
+}
+
+impl ResumeCountdown {
+    /// Remembers the body velocities this frame, call when pausing a running game.
+    pub fn freeze(&mut self) {
+        self.capture = true;
+        self.timer = None;
+    }
+
+    pub fn start(&mut self) {
+        self.timer = Some(Timer::from_seconds(COUNTDOWN_SECONDS, false));
+    }
+
+    /// Stops a running countdown, the game stays paused and the kept velocities stay too.
+    pub fn cancel(&mut self) {
+        self.timer = None;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.timer.is_some()
+    }
+}
+
+struct CountdownText;
+
+fn run_countdown(
+    mut commands: Commands,
+    time: Res<Time>,
+    font: Res<UiFont>,
+    tutorial: Res<Tutorial>,
+    mut countdown: ResMut<ResumeCountdown>,
+    mut paused: ResMut<Paused>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    bodies: Query<(Entity, &RigidBodyHandleComponent)>,
+    mut texts: Query<(Entity, &mut Text), With<CountdownText>>,
+) {
+    if countdown.capture {
+        countdown.capture = false;
+        countdown.velocities = bodies
+            .iter()
+            .filter_map(|(entity, handle)| {
+                let rb = rigid_bodies.get(handle.handle())?;
+                if rb.is_dynamic() {
+                    Some((entity, *rb.linvel(), rb.angvel()))
+                } else {
+                    None
+                }
+            })
+            .collect();
+    }
+
+    let remaining = match countdown.timer.as_mut() {
+        // The tutorial pauses on its own, wait until it is dismissed
+        Some(_) if tutorial.is_open() => return,
+        Some(timer) => {
+            timer.tick(time.delta());
+            timer.duration().as_secs_f32() - timer.elapsed_secs()
+        }
+        None => {
+            for (entity, _) in texts.iter_mut() {
+                commands.entity(entity).despawn();
+            }
+            return;
+        }
+    };
+
+    if remaining > 0. {
+        let value = format!("{}", remaining.ceil() as u32);
+        match texts.iter_mut().next() {
+            Some((_, mut text)) => text.sections[0].value = value,
+            None => spawn_text(&mut commands, font.0.clone(), value),
+        }
+        return;
+    }
+
+    for (entity, linvel, angvel) in countdown.velocities.drain(..) {
+        let rb = bodies
+            .get(entity)
+            .ok()
+            .and_then(|(_, handle)| rigid_bodies.get_mut(handle.handle()));
+        if let Some(rb) = rb {
+            rb.set_linvel(linvel, true);
+            rb.set_angvel(angvel, true);
+        }
+    }
+
+    countdown.timer = None;
+    paused.0 = false;
+}
+
+fn spawn_text(commands: &mut Commands, font: Handle<Font>, value: String) {
+    commands
+        .spawn_bundle(TextBundle {
+            text: Text::with_section(
+                value,
+                TextStyle {
+                    font,
+                    font_size: 96.0,
+                    color: Color::rgb(1.0, 1.0, 1.0),
+                },
+                Default::default(),
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(30.),
+                    left: Val::Percent(48.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(CountdownText);
+}
+
+fn stop_countdown(mut countdown: ResMut<ResumeCountdown>, mut paused: ResMut<Paused>) {
+    if countdown.is_running() {
+        paused.0 = false;
+    }
+    *countdown = ResumeCountdown::default();
+}
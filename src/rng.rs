@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// Seeded generator for every random draw in a match, so a seed reproduces it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameRng {
+    seed: u64,
+    state: u64,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng { seed, state: seed }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Next value of a wyrand generator.
+    pub fn u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0xa076_1d64_78bd_642f);
+        let t = u128::from(self.state) * u128::from(self.state ^ 0xe703_7ed1_a0b4_28db);
+        (t as u64) ^ (t >> 64) as u64
+    }
+
+    /// Uniform in `0.0..1.0`.
+    pub fn f32(&mut self) -> f32 {
+        (self.u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn bool(&mut self) -> bool {
+        self.u64() & 1 == 1
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng::new(fastrand::u64(..))
+    }
+}
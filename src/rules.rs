@@ -0,0 +1,393 @@
+use std::cmp::Ordering;
+
+use crate::match_state::{MatchClock, MatchPhase, MatchRules};
+use crate::{Player, Score};
+
+/// Goals a player has to lead by for the mercy rule to end the match.
+pub const MERCY_LEAD: u32 = 7;
+/// Goals a player has to trail by for the comeback assist to widen the leader's goal.
+pub const ASSIST_DEFICIT: u32 = 5;
+/// The assist stays on until the gap is smaller than this.
+pub const ASSIST_END_DEFICIT: u32 = 3;
+
+/// The parts of the match rules that decide points and the end of a match.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RulesConfig {
+    pub score_limit: Option<u32>,
+    pub time_limit: Option<f32>,
+    /// Lead that ends the match at once, `None` plays on.
+    pub mercy_lead: Option<u32>,
+}
+
+impl RulesConfig {
+    pub fn from_rules(rules: &MatchRules) -> Self {
+        RulesConfig {
+            score_limit: rules.score_limit,
+            time_limit: rules.time_limit,
+            mercy_lead: if rules.mercy_rule {
+                Some(MERCY_LEAD)
+            } else {
+                None
+            },
+        }
+    }
+}
+
+/// Everything about the running match the rules look at, copied out of the world.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RulesState {
+    pub left: u32,
+    pub right: u32,
+    pub phase: MatchPhase,
+    /// Seconds left on the match clock.
+    pub remaining: f32,
+}
+
+impl RulesState {
+    pub fn new(score: &Score, phase: MatchPhase, clock: &MatchClock) -> Self {
+        RulesState {
+            left: score.left,
+            right: score.right,
+            phase,
+            remaining: clock.remaining,
+        }
+    }
+
+    fn score(&self, player: Player) -> u32 {
+        match player {
+            Player::Left => self.left,
+            Player::Right => self.right,
+        }
+    }
+}
+
+/// What the systems have to carry out, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleOutcome {
+    AwardPoint(Player),
+    EndMatch(Player, EndReason),
+    StartOvertime,
+}
+
+/// Why a match ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndReason {
+    ScoreLimit,
+    GoldenGoal,
+    TimeUp,
+    /// The winner led by `MERCY_LEAD` goals.
+    Mercy,
+}
+
+impl Default for EndReason {
+    fn default() -> Self {
+        EndReason::ScoreLimit
+    }
+}
+
+/// A goal counts for the scorer. It wins the match when it is the golden goal of overtime,
+/// reaches the score limit or puts the scorer out of reach by the mercy rule.
+pub fn on_goal(config: &RulesConfig, state: &RulesState, scorer: Player) -> Vec<RuleOutcome> {
+    on_goals(config, state, &[scorer])
+}
+
+/// Goals scored on the same frame, by several balls. Every one counts and the end of the match is
+/// judged on the score after all of them, so the order they come in doesn't matter. Goals for
+/// both players in overtime cancel out, reaching the score limit level goes to overtime.
+pub fn on_goals(config: &RulesConfig, state: &RulesState, scorers: &[Player]) -> Vec<RuleOutcome> {
+    let mut outcomes: Vec<RuleOutcome> = scorers
+        .iter()
+        .map(|scorer| RuleOutcome::AwardPoint(*scorer))
+        .collect();
+
+    let scored = |player: Player| scorers.iter().filter(|scorer| **scorer == player).count() as u32;
+    let (left_goals, right_goals) = (scored(Player::Left), scored(Player::Right));
+    let (left, right) = (state.left + left_goals, state.right + right_goals);
+    let ahead = |a: u32, b: u32| match a.cmp(&b) {
+        Ordering::Greater => Some(Player::Left),
+        Ordering::Less => Some(Player::Right),
+        Ordering::Equal => None,
+    };
+    let reached = |goals: u32| config.score_limit.map_or(false, |limit| goals >= limit);
+
+    let end = if state.phase == MatchPhase::Overtime {
+        ahead(left_goals, right_goals).map(|winner| (winner, EndReason::GoldenGoal))
+    } else if reached(left) || reached(right) {
+        match ahead(left, right) {
+            Some(winner) => Some((winner, EndReason::ScoreLimit)),
+            None => {
+                outcomes.push(RuleOutcome::StartOvertime);
+                None
+            }
+        }
+    } else {
+        let lead = left.max(right) - left.min(right);
+        ahead(left, right)
+            .filter(|leader| scored(*leader) > 0)
+            .filter(|_| config.mercy_lead.map_or(false, |mercy| lead >= mercy))
+            .map(|leader| (leader, EndReason::Mercy))
+    };
+    if let Some((winner, reason)) = end {
+        outcomes.push(RuleOutcome::EndMatch(winner, reason));
+    }
+    outcomes
+}
+
+/// Whether a goal by `scorer` would end the match right now.
+pub fn goal_wins(config: &RulesConfig, state: &RulesState, scorer: Player) -> bool {
+    on_goal(config, state, scorer)
+        .iter()
+        .any(|outcome| matches!(outcome, RuleOutcome::EndMatch(..)))
+}
+
+/// Leader whose goal the comeback assist widens, given the leader it widens now. It starts when
+/// the other player trails by `ASSIST_DEFICIT` and holds until the gap closes below
+/// `ASSIST_END_DEFICIT`, so it doesn't flicker on and off with every goal.
+pub fn comeback_assist(state: &RulesState, current: Option<Player>) -> Option<Player> {
+    let leader = match state.left.cmp(&state.right) {
+        Ordering::Greater => Player::Left,
+        Ordering::Less => Player::Right,
+        Ordering::Equal => return None,
+    };
+    let gap = state.score(leader) - state.score(leader.opponent());
+
+    let needed = if current == Some(leader) {
+        ASSIST_END_DEFICIT
+    } else {
+        ASSIST_DEFICIT
+    };
+    if gap >= needed {
+        Some(leader)
+    } else {
+        None
+    }
+}
+
+/// Runs the clock of a timed match down by `delta` seconds. When it runs out the leader wins, a
+/// level score goes to overtime.
+pub fn on_tick(config: &RulesConfig, state: &mut RulesState, delta: f32) -> Vec<RuleOutcome> {
+    if state.phase != MatchPhase::Regular || config.time_limit.is_none() {
+        return Vec::new();
+    }
+
+    state.remaining -= delta;
+    if state.remaining > 0. {
+        return Vec::new();
+    }
+    state.remaining = 0.;
+
+    match state.left.cmp(&state.right) {
+        Ordering::Greater => vec![RuleOutcome::EndMatch(Player::Left, EndReason::TimeUp)],
+        Ordering::Less => vec![RuleOutcome::EndMatch(Player::Right, EndReason::TimeUp)],
+        Ordering::Equal => vec![RuleOutcome::StartOvertime],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RuleOutcome::*;
+    use super::*;
+
+    const LEFT: Player = Player::Left;
+    const RIGHT: Player = Player::Right;
+
+    fn config(score_limit: Option<u32>, time_limit: Option<f32>, mercy: bool) -> RulesConfig {
+        RulesConfig {
+            score_limit,
+            time_limit,
+            mercy_lead: if mercy { Some(MERCY_LEAD) } else { None },
+        }
+    }
+
+    fn state(left: u32, right: u32, phase: MatchPhase) -> RulesState {
+        RulesState {
+            left,
+            right,
+            phase,
+            remaining: 10.,
+        }
+    }
+
+    #[test]
+    fn goals() {
+        let first_to_5 = config(Some(5), None, false);
+        let open = config(None, None, false);
+        let mercy = config(None, None, true);
+        let cases = [
+            (
+                first_to_5,
+                state(2, 1, MatchPhase::Regular),
+                LEFT,
+                vec![AwardPoint(LEFT)],
+            ),
+            (
+                first_to_5,
+                state(4, 2, MatchPhase::Regular),
+                LEFT,
+                vec![AwardPoint(LEFT), EndMatch(LEFT, EndReason::ScoreLimit)],
+            ),
+            (
+                first_to_5,
+                state(3, 4, MatchPhase::Regular),
+                RIGHT,
+                vec![AwardPoint(RIGHT), EndMatch(RIGHT, EndReason::ScoreLimit)],
+            ),
+            (
+                first_to_5,
+                state(4, 5, MatchPhase::Regular),
+                LEFT,
+                vec![AwardPoint(LEFT), StartOvertime],
+            ),
+            (
+                first_to_5,
+                state(5, 5, MatchPhase::Overtime),
+                RIGHT,
+                vec![AwardPoint(RIGHT), EndMatch(RIGHT, EndReason::GoldenGoal)],
+            ),
+            (
+                open,
+                state(3, 3, MatchPhase::Overtime),
+                LEFT,
+                vec![AwardPoint(LEFT), EndMatch(LEFT, EndReason::GoldenGoal)],
+            ),
+            (
+                open,
+                state(6, 0, MatchPhase::Regular),
+                LEFT,
+                vec![AwardPoint(LEFT)],
+            ),
+            (
+                mercy,
+                state(6, 0, MatchPhase::Regular),
+                LEFT,
+                vec![AwardPoint(LEFT), EndMatch(LEFT, EndReason::Mercy)],
+            ),
+            (
+                mercy,
+                state(1, 7, MatchPhase::Regular),
+                RIGHT,
+                vec![AwardPoint(RIGHT), EndMatch(RIGHT, EndReason::Mercy)],
+            ),
+            // Only the leader's own goal ends it, catching up by one leaves the lead short
+            (
+                mercy,
+                state(0, 8, MatchPhase::Regular),
+                LEFT,
+                vec![AwardPoint(LEFT)],
+            ),
+            (
+                mercy,
+                state(5, 0, MatchPhase::Regular),
+                LEFT,
+                vec![AwardPoint(LEFT)],
+            ),
+        ];
+
+        for (config, state, scorer, expected) in cases.iter() {
+            assert_eq!(
+                on_goal(config, state, *scorer),
+                *expected,
+                "{:?} scoring at {:?}",
+                scorer,
+                state
+            );
+        }
+    }
+
+    #[test]
+    fn goals_on_the_same_frame() {
+        let first_to_5 = config(Some(5), None, false);
+        let cases = [
+            (first_to_5, state(2, 2, MatchPhase::Regular), vec![]),
+            (
+                first_to_5,
+                state(4, 4, MatchPhase::Regular),
+                vec![StartOvertime],
+            ),
+            (
+                first_to_5,
+                state(4, 3, MatchPhase::Regular),
+                vec![EndMatch(LEFT, EndReason::ScoreLimit)],
+            ),
+            (first_to_5, state(5, 5, MatchPhase::Overtime), vec![]),
+            (
+                config(None, None, true),
+                state(7, 0, MatchPhase::Regular),
+                vec![EndMatch(LEFT, EndReason::Mercy)],
+            ),
+        ];
+
+        for (config, state, expected) in cases.iter() {
+            for scorers in [[LEFT, RIGHT], [RIGHT, LEFT]].iter() {
+                let outcomes = on_goals(config, state, scorers);
+                let awarded: Vec<RuleOutcome> = scorers.iter().map(|p| AwardPoint(*p)).collect();
+                assert_eq!(outcomes[..2], awarded[..], "{:?} at {:?}", scorers, state);
+                assert_eq!(outcomes[2..], expected[..], "{:?} at {:?}", scorers, state);
+            }
+        }
+    }
+
+    #[test]
+    fn clock() {
+        let timed = config(None, Some(60.), false);
+        let cases = [
+            (state(3, 1, MatchPhase::Regular), 5., vec![]),
+            (
+                state(3, 1, MatchPhase::Regular),
+                10.,
+                vec![EndMatch(LEFT, EndReason::TimeUp)],
+            ),
+            (
+                state(0, 2, MatchPhase::Regular),
+                12.,
+                vec![EndMatch(RIGHT, EndReason::TimeUp)],
+            ),
+            (state(2, 2, MatchPhase::Regular), 10., vec![StartOvertime]),
+            (state(2, 2, MatchPhase::Overtime), 10., vec![]),
+        ];
+
+        for (state, delta, expected) in cases.iter() {
+            let mut ticked = *state;
+            assert_eq!(
+                on_tick(&timed, &mut ticked, *delta),
+                *expected,
+                "{:?} after {}",
+                state,
+                delta
+            );
+        }
+
+        let mut untimed = state(3, 1, MatchPhase::Regular);
+        assert!(on_tick(&config(None, None, false), &mut untimed, 20.).is_empty());
+        assert_eq!(untimed.remaining, 10.);
+    }
+
+    #[test]
+    fn comeback_assist_holds_until_the_gap_closes() {
+        let regular = MatchPhase::Regular;
+        let cases = [
+            // left, right, current leader, expected
+            (0, 0, None, None),
+            (4, 0, None, None),
+            (5, 0, None, Some(LEFT)),
+            (1, 7, None, Some(RIGHT)),
+            (5, 2, Some(LEFT), Some(LEFT)),
+            (5, 3, Some(LEFT), None),
+            (5, 2, None, None),
+            // A new leader has to open the full gap
+            (0, 3, Some(LEFT), None),
+            (3, 3, Some(LEFT), None),
+        ];
+        for (left, right, current, expected) in cases.iter() {
+            let state = state(*left, *right, regular);
+            assert_eq!(
+                comeback_assist(&state, *current),
+                *expected,
+                "{} to {} with {:?}",
+                left,
+                right,
+                current
+            );
+        }
+    }
+}
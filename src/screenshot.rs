@@ -0,0 +1,539 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::ecs::query::{ReadOnlyFetch, WorldQuery};
+use bevy::prelude::*;
+use bevy::render::pass::{
+    LoadOp, Operations, PassDescriptor, RenderPassDepthStencilAttachmentDescriptor,
+    TextureAttachment,
+};
+use bevy::render::render_graph::base::{self, MainPass};
+use bevy::render::render_graph::{
+    Node, PassNode, RenderGraph, ResourceSlotInfo, ResourceSlots, WindowTextureNode,
+};
+use bevy::render::renderer::{
+    BufferId, BufferInfo, BufferMapMode, BufferUsage, RenderContext, RenderResourceContext,
+    RenderResourceType,
+};
+use bevy::render::texture::{
+    Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsage,
+};
+use bevy::ui;
+use bevy::window::WindowId;
+use image::RgbaImage;
+
+use crate::ball_visuals::{BallGlow, BallShadow};
+use crate::callout::Callout;
+use crate::persistence::SaveData;
+use crate::raster::{Picture, Shape};
+use crate::{Ball, GameLabel, GoalEvent};
+
+pub struct ScreenshotPlugin;
+
+impl Plugin for ScreenshotPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        add_capture_graph(app.world_mut());
+        app.add_event::<CaptureFrame>()
+            .init_resource::<FrameCapture>()
+            .add_system(screenshot_keys.system().label("screenshot_keys"))
+            .add_system(capture_goals.system().after(GameLabel::GoalApply))
+            .add_system(
+                capture_frames
+                    .system()
+                    .after("screenshot_keys")
+                    .after(GameLabel::GoalPresent),
+            );
+    }
+}
+
+pub const SCREENSHOT_DIR: &str = "screenshots";
+
+/// Asks for the current frame to be written to the screenshot directory.
+pub struct CaptureFrame {
+    pub prefix: &'static str,
+}
+
+fn screenshot_keys(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut save: ResMut<SaveData>,
+    mut captures: EventWriter<CaptureFrame>,
+    mut callouts: EventWriter<Callout>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F12) {
+        return;
+    }
+
+    let shift = keyboard_input.pressed(KeyCode::LShift) || keyboard_input.pressed(KeyCode::RShift);
+    if shift {
+        toggle_goal_screenshots(&mut save, &mut callouts);
+    } else {
+        captures.send(CaptureFrame {
+            prefix: "screenshot",
+        });
+    }
+}
+
+/// Flips the goal snapshot setting, saves it and announces the new state.
+pub fn toggle_goal_screenshots(save: &mut SaveData, callouts: &mut EventWriter<Callout>) {
+    let enabled = !save.settings.goal_screenshots;
+    save.settings.goal_screenshots = enabled;
+    save.save();
+
+    let state = if enabled { "on" } else { "off" };
+    callouts.send(Callout(format!("Goal snapshots {}", state)));
+}
+
+fn capture_goals(
+    save: Res<SaveData>,
+    mut goals: EventReader<GoalEvent>,
+    mut captures: EventWriter<CaptureFrame>,
+) {
+    if goals.iter().next().is_some() && save.settings.goal_screenshots {
+        captures.send(CaptureFrame { prefix: "goal" });
+    }
+}
+
+/// Asks the render graph for the frame being drawn, the latest request of the frame wins.
+fn capture_frames(mut captures: EventReader<CaptureFrame>, mut capture: ResMut<FrameCapture>) {
+    capture.0 = captures
+        .iter()
+        .last()
+        .map(|capture| screenshot_path(capture.prefix));
+}
+
+/// Where the frame being drawn is saved to, only set on frames that are captured.
+#[derive(Default)]
+struct FrameCapture(Option<PathBuf>);
+
+const CAPTURE_COLOR: &str = "screenshot_color";
+const CAPTURE_SAMPLED_COLOR: &str = "screenshot_sampled_color";
+const CAPTURE_DEPTH: &str = "screenshot_depth";
+const CAPTURE_MAIN_PASS: &str = "screenshot_main_pass";
+const CAPTURE_UI_PASS: &str = "screenshot_ui_pass";
+const CAPTURE_READBACK: &str = "screenshot_readback";
+
+/// The window's swap chain texture can only be rendered to, not copied from. So a captured frame
+/// is drawn a second time by copies of the main and UI passes, into a window sized texture that
+/// can be read back. Both passes replay the same draw commands as the window's own, they only
+/// run on frames with a capture.
+fn add_capture_graph(world: &mut World) {
+    let samples = match world.get_resource::<Msaa>() {
+        Some(msaa) => msaa.samples,
+        None => return,
+    };
+    let mut graph = match world.get_resource_mut::<RenderGraph>() {
+        Some(graph) => graph,
+        None => return,
+    };
+
+    let mut main_pass =
+        PassNode::<&MainPass>::new(capture_pass(LoadOp::Clear(Color::BLACK), samples));
+    main_pass.use_default_clear_color(0);
+    main_pass.add_camera(base::camera::CAMERA_2D);
+    let mut ui_pass = PassNode::<&ui::Node>::new(capture_pass(LoadOp::Load, samples));
+    ui_pass.add_camera(ui::camera::CAMERA_UI);
+
+    graph.add_node(
+        CAPTURE_COLOR,
+        window_texture(
+            TextureFormat::default(),
+            1,
+            TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::COPY_SRC,
+        ),
+    );
+    graph.add_node(
+        CAPTURE_DEPTH,
+        window_texture(
+            TextureFormat::Depth32Float,
+            samples,
+            TextureUsage::OUTPUT_ATTACHMENT,
+        ),
+    );
+    graph.add_node(CAPTURE_MAIN_PASS, CapturePass::new(main_pass));
+    graph.add_node(CAPTURE_UI_PASS, CapturePass::new(ui_pass));
+    graph.add_node(CAPTURE_READBACK, ReadbackNode::default());
+    if samples > 1 {
+        graph.add_node(
+            CAPTURE_SAMPLED_COLOR,
+            window_texture(
+                TextureFormat::default(),
+                samples,
+                TextureUsage::OUTPUT_ATTACHMENT,
+            ),
+        );
+    }
+
+    for pass in [CAPTURE_MAIN_PASS, CAPTURE_UI_PASS].iter() {
+        if samples > 1 {
+            graph
+                .add_slot_edge(
+                    CAPTURE_SAMPLED_COLOR,
+                    WindowTextureNode::OUT_TEXTURE,
+                    *pass,
+                    "color_attachment",
+                )
+                .unwrap();
+        }
+        graph
+            .add_slot_edge(
+                CAPTURE_COLOR,
+                WindowTextureNode::OUT_TEXTURE,
+                *pass,
+                if samples > 1 {
+                    "color_resolve_target"
+                } else {
+                    "color_attachment"
+                },
+            )
+            .unwrap();
+        graph
+            .add_slot_edge(
+                CAPTURE_DEPTH,
+                WindowTextureNode::OUT_TEXTURE,
+                *pass,
+                "depth",
+            )
+            .unwrap();
+    }
+    graph
+        .add_slot_edge(
+            CAPTURE_COLOR,
+            WindowTextureNode::OUT_TEXTURE,
+            CAPTURE_READBACK,
+            ReadbackNode::IN_TEXTURE,
+        )
+        .unwrap();
+
+    // The same inputs as the window's own passes
+    for node in [
+        base::node::TEXTURE_COPY,
+        base::node::SHARED_BUFFERS,
+        base::node::CAMERA_2D,
+    ]
+    .iter()
+    {
+        graph.add_node_edge(*node, CAPTURE_MAIN_PASS).unwrap();
+    }
+    for node in [CAPTURE_MAIN_PASS, ui::node::CAMERA_UI, ui::node::NODE].iter() {
+        graph.add_node_edge(*node, CAPTURE_UI_PASS).unwrap();
+    }
+    graph
+        .add_node_edge(CAPTURE_UI_PASS, CAPTURE_READBACK)
+        .unwrap();
+}
+
+fn window_texture(format: TextureFormat, samples: u32, usage: TextureUsage) -> WindowTextureNode {
+    WindowTextureNode::new(
+        WindowId::primary(),
+        TextureDescriptor {
+            size: Extent3d {
+                width: 1,
+                height: 1,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: samples,
+            dimension: TextureDimension::D2,
+            format,
+            usage,
+        },
+    )
+}
+
+/// Attachments of a capture pass, laid out like the window's passes so their pipelines fit.
+fn capture_pass(load: LoadOp<Color>, samples: u32) -> PassDescriptor {
+    PassDescriptor {
+        color_attachments: vec![Msaa { samples }.color_attachment_descriptor(
+            TextureAttachment::Input("color_attachment".to_string()),
+            TextureAttachment::Input("color_resolve_target".to_string()),
+            Operations { load, store: true },
+        )],
+        depth_stencil_attachment: Some(RenderPassDepthStencilAttachmentDescriptor {
+            attachment: TextureAttachment::Input("depth".to_string()),
+            depth_ops: Some(Operations {
+                load: LoadOp::Clear(1.0),
+                store: true,
+            }),
+            stencil_ops: None,
+        }),
+        sample_count: samples,
+    }
+}
+
+/// A pass that only draws on frames with a capture.
+struct CapturePass<Q: WorldQuery> {
+    pass: PassNode<Q>,
+    active: bool,
+}
+
+impl<Q: WorldQuery> CapturePass<Q> {
+    fn new(pass: PassNode<Q>) -> Self {
+        CapturePass {
+            pass,
+            active: false,
+        }
+    }
+}
+
+impl<Q: WorldQuery + Send + Sync + 'static> Node for CapturePass<Q>
+where
+    Q::Fetch: ReadOnlyFetch,
+{
+    fn input(&self) -> &[ResourceSlotInfo] {
+        self.pass.input()
+    }
+
+    fn prepare(&mut self, world: &mut World) {
+        self.active = world
+            .get_resource::<FrameCapture>()
+            .map_or(false, |capture| capture.0.is_some());
+        if self.active {
+            self.pass.prepare(world);
+        }
+    }
+
+    fn update(
+        &mut self,
+        world: &World,
+        render_context: &mut dyn RenderContext,
+        input: &ResourceSlots,
+        output: &mut ResourceSlots,
+    ) {
+        if self.active {
+            self.pass.update(world, render_context, input, output);
+        }
+    }
+}
+
+/// A captured frame copied into a buffer, waiting for the GPU to be done with it.
+struct Readback {
+    buffer: BufferId,
+    width: u32,
+    height: u32,
+    bytes_per_row: usize,
+    path: PathBuf,
+}
+
+/// Copies the captured frame into a buffer and maps it on the next frame, by when the copy has
+/// run. Encoding and writing the image happen off the main thread.
+#[derive(Default)]
+struct ReadbackNode {
+    requested: Option<PathBuf>,
+    pending: Option<Readback>,
+}
+
+impl ReadbackNode {
+    const IN_TEXTURE: &'static str = "texture";
+}
+
+impl Node for ReadbackNode {
+    fn input(&self) -> &[ResourceSlotInfo] {
+        static INPUT: &[ResourceSlotInfo] = &[ResourceSlotInfo {
+            name: Cow::Borrowed(ReadbackNode::IN_TEXTURE),
+            resource_type: RenderResourceType::Texture,
+        }];
+        INPUT
+    }
+
+    fn prepare(&mut self, world: &mut World) {
+        if let Some(readback) = self.pending.take() {
+            let resources = world
+                .get_resource::<Box<dyn RenderResourceContext>>()
+                .unwrap();
+            let size = readback.bytes_per_row * readback.height as usize;
+            let bytes = RefCell::new(Vec::with_capacity(size));
+            resources.map_buffer(readback.buffer, BufferMapMode::Read);
+            resources.read_mapped_buffer(readback.buffer, 0..size as u64, &|data, _| {
+                bytes.borrow_mut().extend_from_slice(data)
+            });
+            resources.unmap_buffer(readback.buffer);
+            resources.remove_buffer(readback.buffer);
+
+            let bytes = bytes.into_inner();
+            thread::spawn(move || save_frame(&bytes, &readback));
+        }
+
+        self.requested = world
+            .get_resource::<FrameCapture>()
+            .and_then(|capture| capture.0.clone());
+    }
+
+    fn update(
+        &mut self,
+        world: &World,
+        render_context: &mut dyn RenderContext,
+        input: &ResourceSlots,
+        _output: &mut ResourceSlots,
+    ) {
+        let path = match self.requested.take() {
+            Some(path) => path,
+            None => return,
+        };
+        let texture = match input.get(0).and_then(|texture| texture.get_texture()) {
+            Some(texture) => texture,
+            None => return,
+        };
+        let (width, height) = match world
+            .get_resource::<Windows>()
+            .and_then(|windows| windows.get_primary())
+        {
+            Some(window) => (window.physical_width(), window.physical_height()),
+            None => return,
+        };
+        if width == 0 || height == 0 {
+            warn!(
+                "Could not save screenshot {}: the window is minimized",
+                path.display()
+            );
+            return;
+        }
+
+        // Rows of a texture copy are padded to the GPU's alignment
+        let bytes_per_row = render_context
+            .resources()
+            .get_aligned_texture_size(width as usize * 4);
+        let buffer = render_context.resources().create_buffer(BufferInfo {
+            size: bytes_per_row * height as usize,
+            buffer_usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        render_context.copy_texture_to_buffer(
+            texture,
+            [0, 0, 0],
+            0,
+            buffer,
+            0,
+            bytes_per_row as u32,
+            Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+        );
+        self.pending = Some(Readback {
+            buffer,
+            width,
+            height,
+            bytes_per_row,
+            path,
+        });
+    }
+}
+
+fn save_frame(bytes: &[u8], readback: &Readback) {
+    let path = &readback.path;
+    let result = frame_image(
+        bytes,
+        readback.width,
+        readback.height,
+        readback.bytes_per_row,
+    )
+    .ok_or_else(|| "the copied frame is incomplete".to_string())
+    .and_then(|image| {
+        fs::create_dir_all(SCREENSHOT_DIR)
+            .map_err(|err| err.to_string())
+            .and_then(|_| image.save(path).map_err(|err| err.to_string()))
+    });
+
+    match result {
+        Ok(()) => info!("Saved screenshot to {}", path.display()),
+        Err(err) => warn!("Could not save screenshot {}: {}", path.display(), err),
+    }
+}
+
+/// Opaque image from rows of BGRA texels, as the window's format stores them, each row padded
+/// to `bytes_per_row`.
+fn frame_image(bytes: &[u8], width: u32, height: u32, bytes_per_row: usize) -> Option<RgbaImage> {
+    let row_bytes = width as usize * 4;
+    let pixels: Vec<u8> = bytes
+        .chunks(bytes_per_row)
+        .take(height as usize)
+        .flat_map(|row| row[..row_bytes.min(row.len())].chunks(4))
+        .flat_map(|texel| match texel {
+            [blue, green, red, _] => vec![*red, *green, *blue, u8::MAX],
+            _ => Vec::new(),
+        })
+        .collect();
+    RgbaImage::from_raw(width, height, pixels)
+}
+
+pub fn sprite_shapes<'a>(
+    materials: &Assets<ColorMaterial>,
+    textures: &Assets<Texture>,
+    sprites: impl Iterator<
+        Item = (
+            &'a GlobalTransform,
+            &'a Sprite,
+            &'a Handle<ColorMaterial>,
+            &'a Visible,
+            Option<&'a Ball>,
+            Option<&'a BallGlow>,
+            Option<&'a BallShadow>,
+        ),
+    >,
+) -> Vec<Shape> {
+    sprites
+        .filter(|(_, _, _, visible, _, _, _)| visible.is_visible)
+        .filter_map(|(transform, sprite, material, _, ball, glow, shadow)| {
+            let material = materials.get(material)?;
+            let direction = transform.rotation * Vec3::X;
+
+            Some(Shape {
+                center: transform.translation.truncate(),
+                size: sprite.size * transform.scale.truncate(),
+                angle: direction.y.atan2(direction.x),
+                color: material.color,
+                round: ball.is_some() || glow.is_some() || shadow.is_some(),
+                z: transform.translation.z,
+                picture: material_picture(material, textures),
+            })
+        })
+        .collect()
+}
+
+/// Picture of a textured material, `None` draws it flat in its color.
+pub fn material_picture(material: &ColorMaterial, textures: &Assets<Texture>) -> Option<Picture> {
+    material
+        .texture
+        .as_ref()
+        .and_then(|texture| textures.get(texture))
+        .and_then(Picture::from_texture)
+}
+
+pub fn screenshot_path(prefix: &str) -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or(0);
+
+    PathBuf::from(SCREENSHOT_DIR).join(format!("{}_{}.png", prefix, millis))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_lose_their_row_padding() {
+        // Two by two texels, each row padded to 12 bytes
+        let bytes = [
+            1, 2, 3, 0, 4, 5, 6, 0, 9, 9, 9, 9, //
+            7, 8, 9, 0, 10, 11, 12, 0, 9, 9, 9, 9,
+        ];
+        let image = frame_image(&bytes, 2, 2, 12).unwrap();
+
+        assert_eq!(image.get_pixel(0, 0).0, [3, 2, 1, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [6, 5, 4, 255]);
+        assert_eq!(image.get_pixel(0, 1).0, [9, 8, 7, 255]);
+        assert_eq!(image.get_pixel(1, 1).0, [12, 11, 10, 255]);
+    }
+
+    #[test]
+    fn short_frames_are_refused() {
+        assert!(frame_image(&[1, 2, 3, 4], 2, 2, 8).is_none());
+    }
+}
@@ -0,0 +1,224 @@
+use bevy::prelude::*;
+use bevy_rapier2d::physics::{RapierConfiguration, RigidBodyHandleComponent};
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::KeyBindings;
+use crate::callout::Callout;
+use crate::match_state::{MatchPhase, MatchRules};
+use crate::rng::GameRng;
+use crate::tuning::Tuning;
+use crate::{
+    reset_ball, serve_velocity, AppState, Ball, GameLabel, GoalEvent, OutOfPlay, Paused, Player,
+    Score, ServeEvent, ServeKind, UiFont, ARENA_MIDDLE, ARENA_WIDTH,
+};
+
+pub struct ShotClockPlugin;
+
+impl Plugin for ShotClockPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ShotClock>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame).with_system(spawn_warning.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(
+                        run_shot_clock
+                            .system()
+                            .label(GameLabel::GoalDetect)
+                            .after(GameLabel::Contacts),
+                    )
+                    .with_system(
+                        reset_shot_clock
+                            .system()
+                            .after(GameLabel::GoalApply)
+                            .before(GameLabel::GoalPresent),
+                    )
+                    .with_system(
+                        render_warning
+                            .system()
+                            .label(GameLabel::GoalPresent)
+                            .after(GameLabel::GoalApply),
+                    ),
+            );
+    }
+}
+
+/// Seconds the ball may stay in one half before the shot clock runs out.
+const SHOT_CLOCK_SECONDS: f32 = 10.;
+/// The countdown is shown for the last seconds.
+const WARNING_SECONDS: f32 = 3.;
+
+/// What happens when the ball is kept in one half for too long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShotClockRule {
+    Off,
+    /// The other player scores.
+    AwardPoint,
+    /// The ball is served again toward the player who held on to it.
+    ResetBall,
+}
+
+impl Default for ShotClockRule {
+    fn default() -> Self {
+        ShotClockRule::AwardPoint
+    }
+}
+
+/// How long the ball has been in the half of `side` without crossing the center line.
+#[derive(Debug, Default)]
+struct ShotClock {
+    side: Option<Player>,
+    seconds: f32,
+}
+
+impl ShotClock {
+    /// Seconds left for the player holding the ball, once the warning is due.
+    fn warning(&self) -> Option<(Player, f32)> {
+        let left = SHOT_CLOCK_SECONDS - self.seconds;
+        match self.side {
+            Some(side) if left <= WARNING_SECONDS => Some((side, left.max(0.))),
+            _ => None,
+        }
+    }
+}
+
+struct ShotClockWarning;
+
+fn run_shot_clock(
+    time: Res<Time>,
+    paused: Res<Paused>,
+    phase: Res<MatchPhase>,
+    rules: Res<MatchRules>,
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    tuning: Res<Tuning>,
+    score: Res<Score>,
+    rapier_config: Res<RapierConfiguration>,
+    mut clock: ResMut<ShotClock>,
+    mut rng: ResMut<GameRng>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    mut goals: EventWriter<GoalEvent>,
+    mut serves: EventWriter<ServeEvent>,
+    mut callouts: EventWriter<Callout>,
+    mut balls: Query<
+        (Entity, &mut Transform, &RigidBodyHandleComponent),
+        (With<Ball>, Without<OutOfPlay>),
+    >,
+) {
+    if paused.0 || phase.is_finished() || rules.shot_clock == ShotClockRule::Off {
+        return;
+    }
+
+    let (ball, mut transform, rigid_body_component) = match balls.iter_mut().next() {
+        Some(ball) => ball,
+        None => return,
+    };
+
+    let side = if transform.translation.x < ARENA_MIDDLE {
+        Player::Left
+    } else {
+        Player::Right
+    };
+    if clock.side != Some(side) {
+        clock.side = Some(side);
+        clock.seconds = 0.;
+        return;
+    }
+
+    clock.seconds += time.delta_seconds();
+    if clock.seconds < SHOT_CLOCK_SECONDS {
+        return;
+    }
+
+    *clock = ShotClock::default();
+    callouts.send(Callout("Shot clock!".to_string()));
+
+    match rules.shot_clock {
+        ShotClockRule::AwardPoint => {
+            goals.send(GoalEvent {
+                scorer: side.opponent(),
+                ball,
+                crossing_y: None,
+            });
+        }
+        ShotClockRule::ResetBall => {
+            if let Some(rb) = rigid_bodies.get_mut(rigid_body_component.handle()) {
+                let kind = ServeKind::held(&keyboard_input, bindings.for_player(&side.opponent()));
+                let velocity = serve_velocity(
+                    &mut rng,
+                    &rules,
+                    &tuning,
+                    Some(side),
+                    kind,
+                    tuning.serve_speed,
+                );
+                let velocity = score.with_streak_bonus(velocity, &tuning);
+                reset_ball(rb, &mut transform, &rapier_config, velocity);
+                serves.send(ServeEvent {
+                    receiver: side,
+                    kind,
+                });
+            }
+        }
+        ShotClockRule::Off => {}
+    }
+}
+
+/// Goals and serves start the clock over from the center spot.
+fn reset_shot_clock(
+    mut clock: ResMut<ShotClock>,
+    mut goals: EventReader<GoalEvent>,
+    mut serves: EventReader<ServeEvent>,
+) {
+    if goals.iter().next().is_some() | serves.iter().next().is_some() {
+        *clock = ShotClock::default();
+    }
+}
+
+fn spawn_warning(mut commands: Commands, ui_font: Res<UiFont>) {
+    commands
+        .spawn_bundle(TextBundle {
+            text: Text::with_section(
+                "".to_string(),
+                TextStyle {
+                    font: ui_font.0.clone(),
+                    font_size: 40.0,
+                    color: Color::rgb(1.0, 0.4, 0.2),
+                },
+                Default::default(),
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(80.),
+                    left: Val::Px(ARENA_MIDDLE),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(ShotClockWarning);
+}
+
+/// Counts down over the half the ball is held in.
+fn render_warning(
+    clock: Res<ShotClock>,
+    mut warnings: Query<(&mut Text, &mut Style), With<ShotClockWarning>>,
+) {
+    for (mut text, mut style) in warnings.iter_mut() {
+        match clock.warning() {
+            Some((side, left)) => {
+                text.sections[0].value = format!("{}", left.ceil() as u32);
+                let x = match side {
+                    Player::Left => ARENA_WIDTH / 4.,
+                    Player::Right => ARENA_WIDTH * 3. / 4.,
+                };
+                style.position.left = Val::Px(x);
+            }
+            None => text.sections[0].value.clear(),
+        }
+    }
+}
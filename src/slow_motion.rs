@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+use bevy_rapier2d::physics::RigidBodyHandleComponent;
+use bevy_rapier2d::rapier::dynamics::{IntegrationParameters, RigidBodySet};
+
+use crate::accessibility::Accessibility;
+use crate::match_state::{MatchClock, MatchPhase, MatchRules};
+use crate::rules::{goal_wins, RulesConfig, RulesState};
+use crate::{AppState, Ball, GameLabel, OutOfPlay, Paused, Player, Score, ARENA_WIDTH};
+
+/// Slows the game down while a ball heads into the last stretch before a goal that would win the
+/// match, until it crosses or gets saved.
+pub struct SlowMotionPlugin;
+
+impl Plugin for SlowMotionPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<TimeScale>()
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(
+                        slow_match_point
+                            .system()
+                            .label("slow_motion")
+                            .after(GameLabel::GoalApply),
+                    )
+                    .with_system(apply_time_scale.system().after("slow_motion")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::InGame).with_system(reset_time_scale.system()),
+            );
+    }
+}
+
+/// Pixels before the goal line where a winning ball starts slowing down.
+const SLOW_MOTION_DISTANCE: f32 = 150.;
+const SLOW_MOTION_SCALE: f32 = 0.2;
+
+/// Speed of the simulation relative to real time, applied to the physics step. Menus, the clock
+/// and other timers keep running in real time.
+#[derive(Debug)]
+pub struct TimeScale(pub f32);
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        TimeScale(1.)
+    }
+}
+
+impl TimeScale {
+    pub fn is_slowed(&self) -> bool {
+        self.0 < 1.
+    }
+}
+
+/// Recomputed every frame, so a ball that is saved, or turns back, at the last instant drops the
+/// game straight back to full speed.
+fn slow_match_point(
+    paused: Res<Paused>,
+    accessibility: Res<Accessibility>,
+    rules: Res<MatchRules>,
+    score: Res<Score>,
+    phase: Res<MatchPhase>,
+    clock: Res<MatchClock>,
+    rigid_bodies: Res<RigidBodySet>,
+    mut time_scale: ResMut<TimeScale>,
+    balls: Query<(&Transform, &RigidBodyHandleComponent), (With<Ball>, Without<OutOfPlay>)>,
+) {
+    // The physics doesn't move while paused, neither does the decision
+    if paused.0 {
+        return;
+    }
+    if accessibility.reduced_motion || phase.is_finished() {
+        time_scale.0 = 1.;
+        return;
+    }
+
+    let config = RulesConfig::from_rules(&rules);
+    let state = RulesState::new(&score, *phase, &clock);
+    let closing_in = balls.iter().any(|(transform, handle)| {
+        let rb = match rigid_bodies.get(handle.handle()) {
+            Some(rb) => rb,
+            None => return false,
+        };
+        let x = transform.translation.x;
+        let scorer = if rb.linvel().x < 0. && x < SLOW_MOTION_DISTANCE {
+            Player::Right
+        } else if rb.linvel().x > 0. && x > ARENA_WIDTH - SLOW_MOTION_DISTANCE {
+            Player::Left
+        } else {
+            return false;
+        };
+        goal_wins(&config, &state, scorer)
+    });
+
+    time_scale.0 = if closing_in { SLOW_MOTION_SCALE } else { 1. };
+}
+
+fn apply_time_scale(
+    time_scale: Res<TimeScale>,
+    integration_parameters: Option<ResMut<IntegrationParameters>>,
+) {
+    if let Some(mut integration_parameters) = integration_parameters {
+        let dt = IntegrationParameters::default().dt() * time_scale.0;
+        integration_parameters.set_dt(dt);
+    }
+}
+
+fn reset_time_scale(
+    mut time_scale: ResMut<TimeScale>,
+    integration_parameters: Option<ResMut<IntegrationParameters>>,
+) {
+    time_scale.0 = 1.;
+    if let Some(mut integration_parameters) = integration_parameters {
+        integration_parameters.set_dt(IntegrationParameters::default().dt());
+    }
+}
@@ -0,0 +1,261 @@
+use std::fs;
+
+use bevy::prelude::*;
+use bevy_rapier2d::na::Isometry2;
+use bevy_rapier2d::physics::{RapierConfiguration, RigidBodyHandleComponent};
+use bevy_rapier2d::rapier::dynamics::{RigidBody, RigidBodyBuilder, RigidBodySet};
+use bevy_rapier2d::rapier::na::Vector2;
+use serde::{Deserialize, Serialize};
+
+use crate::callout::Callout;
+use crate::loading::GameAssets;
+use crate::match_state::{MatchClock, MatchPhase};
+use crate::physics::teleport_body;
+use crate::rng::GameRng;
+use crate::theme::Theme;
+use crate::tuning::Tuning;
+use crate::{spawn_ball_body, AppState, Ball, Paddle, PaddleRole, Player, Score, SubPaddle};
+
+pub struct SnapshotPlugin;
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<SnapshotStore>().add_system_set(
+            SystemSet::on_update(AppState::InGame).with_system(snapshot_keys.system()),
+        );
+    }
+}
+
+/// Bumped whenever `Snapshot` changes shape, older files are refused.
+const SNAPSHOT_VERSION: u32 = 1;
+const SNAPSHOT_PATH: &str = "pingis_pong_snapshot.ron";
+
+const CAPTURE_KEY: KeyCode = KeyCode::F5;
+const RESTORE_KEY: KeyCode = KeyCode::F9;
+
+/// Gameplay state frozen for debugging, positions and velocities are in physics units.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    balls: Vec<BodyState>,
+    paddles: Vec<PaddleState>,
+    score: Score,
+    phase: MatchPhase,
+    clock_remaining: f32,
+    rng: GameRng,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct BodyState {
+    x: f32,
+    y: f32,
+    angle: f32,
+    linvel: (f32, f32),
+    angvel: f32,
+}
+
+impl BodyState {
+    fn capture(rb: &RigidBody) -> Self {
+        let position = rb.position();
+        BodyState {
+            x: position.translation.x,
+            y: position.translation.y,
+            angle: position.rotation.angle(),
+            linvel: (rb.linvel().x, rb.linvel().y),
+            angvel: rb.angvel(),
+        }
+    }
+
+    fn apply(&self, rb: &mut RigidBody, transform: &mut Transform, scale: f32) {
+        teleport_body(
+            rb,
+            transform,
+            Isometry2::new(Vector2::new(self.x, self.y), self.angle),
+            scale,
+        );
+        rb.set_linvel(Vector2::new(self.linvel.0, self.linvel.1), true);
+        rb.set_angvel(self.angvel, true);
+    }
+}
+
+/// Paddles are matched by who plays them rather than by entity, which may not survive.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct PaddleState {
+    player: Player,
+    role: PaddleRole,
+    sub_paddle: Option<SubPaddle>,
+    body: BodyState,
+}
+
+#[derive(Debug, Default)]
+struct SnapshotStore(Option<Snapshot>);
+
+/// F5 captures and F9 restores, holding shift also writes or reads the snapshot file.
+fn snapshot_keys(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    rapier_config: Res<RapierConfiguration>,
+    tuning: Res<Tuning>,
+    theme: Res<Theme>,
+    game_assets: Res<GameAssets>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut store: ResMut<SnapshotStore>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    mut score: ResMut<Score>,
+    mut phase: ResMut<MatchPhase>,
+    mut clock: ResMut<MatchClock>,
+    mut rng: ResMut<GameRng>,
+    mut callouts: EventWriter<Callout>,
+    mut balls: Query<(Entity, &RigidBodyHandleComponent, &mut Transform), With<Ball>>,
+    mut paddles: Query<
+        (
+            &RigidBodyHandleComponent,
+            &mut Transform,
+            &Player,
+            &PaddleRole,
+            Option<&SubPaddle>,
+        ),
+        (With<Paddle>, Without<Ball>),
+    >,
+) {
+    let shift = keyboard_input.pressed(KeyCode::LShift) || keyboard_input.pressed(KeyCode::RShift);
+
+    if keyboard_input.just_pressed(CAPTURE_KEY) {
+        let snapshot = Snapshot {
+            version: SNAPSHOT_VERSION,
+            balls: balls
+                .iter()
+                .filter_map(|(_, handle, _)| rigid_bodies.get(handle.handle()))
+                .map(BodyState::capture)
+                .collect(),
+            paddles: paddles
+                .iter()
+                .filter_map(|(handle, _, player, role, sub_paddle)| {
+                    let rb = rigid_bodies.get(handle.handle())?;
+                    Some(PaddleState {
+                        player: *player,
+                        role: *role,
+                        sub_paddle: sub_paddle.copied(),
+                        body: BodyState::capture(rb),
+                    })
+                })
+                .collect(),
+            score: score.clone(),
+            phase: *phase,
+            clock_remaining: clock.remaining,
+            rng: rng.clone(),
+        };
+
+        if shift {
+            write_snapshot(&snapshot);
+        }
+        store.0 = Some(snapshot);
+        callouts.send(Callout("Snapshot taken".to_string()));
+        return;
+    }
+
+    if !keyboard_input.just_pressed(RESTORE_KEY) {
+        return;
+    }
+
+    let snapshot = if shift {
+        read_snapshot()
+    } else {
+        store.0.clone()
+    };
+    let snapshot = match snapshot {
+        Some(snapshot) if snapshot.version == SNAPSHOT_VERSION => snapshot,
+        Some(snapshot) => {
+            warn!(
+                "Snapshot version {} is not supported, expected {}",
+                snapshot.version, SNAPSHOT_VERSION
+            );
+            return;
+        }
+        None => {
+            callouts.send(Callout("No snapshot".to_string()));
+            return;
+        }
+    };
+
+    // Balls are interchangeable, restore them in order and make up for any that are gone
+    let mut existing = balls.iter_mut();
+    for body in snapshot.balls.iter() {
+        let ball = existing.next().and_then(|(_, handle, transform)| {
+            rigid_bodies
+                .get_mut(handle.handle())
+                .map(|rb| (rb, transform))
+        });
+        match ball {
+            Some((rb, mut transform)) => body.apply(rb, &mut transform, rapier_config.scale),
+            None => {
+                let builder = RigidBodyBuilder::new_dynamic()
+                    .position(Isometry2::new(Vector2::new(body.x, body.y), body.angle))
+                    .linvel(body.linvel.0, body.linvel.1)
+                    .angvel(body.angvel);
+                spawn_ball_body(
+                    &mut commands,
+                    &mut materials,
+                    &rapier_config,
+                    &tuning,
+                    &theme,
+                    &game_assets,
+                    builder,
+                );
+            }
+        }
+    }
+    for (entity, _, _) in existing {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    for (handle, mut transform, player, role, sub_paddle) in paddles.iter_mut() {
+        let state = snapshot.paddles.iter().find(|state| {
+            state.player == *player
+                && state.role == *role
+                && state.sub_paddle == sub_paddle.copied()
+        });
+
+        if let (Some(state), Some(rb)) = (state, rigid_bodies.get_mut(handle.handle())) {
+            state.body.apply(rb, &mut transform, rapier_config.scale);
+        }
+    }
+
+    *score = snapshot.score;
+    *phase = snapshot.phase;
+    clock.remaining = snapshot.clock_remaining;
+    *rng = snapshot.rng;
+    callouts.send(Callout("Snapshot restored".to_string()));
+}
+
+fn write_snapshot(snapshot: &Snapshot) {
+    let content = match ron::ser::to_string_pretty(snapshot, Default::default()) {
+        Ok(content) => content,
+        Err(err) => {
+            warn!("Failed to serialize snapshot: {}", err);
+            return;
+        }
+    };
+
+    if let Err(err) = fs::write(SNAPSHOT_PATH, content) {
+        warn!("Failed to write {}: {}", SNAPSHOT_PATH, err);
+    }
+}
+
+fn read_snapshot() -> Option<Snapshot> {
+    let content = match fs::read_to_string(SNAPSHOT_PATH) {
+        Ok(content) => content,
+        Err(err) => {
+            warn!("Failed to read {}: {}", SNAPSHOT_PATH, err);
+            return None;
+        }
+    };
+
+    match ron::from_str(&content) {
+        Ok(snapshot) => Some(snapshot),
+        Err(err) => {
+            warn!("Failed to parse {}: {}", SNAPSHOT_PATH, err);
+            None
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy_rapier2d::physics::RigidBodyHandleComponent;
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+
+use crate::match_state::MatchRules;
+use crate::quality::QualitySettings;
+use crate::tuning::Tuning;
+use crate::{AppState, Ball, GameLabel};
+
+/// Makes the ball's spin readable. A stripe on the ball turns with it, and an optional arc of
+/// pips beside it grows with the spin in the direction it turns.
+pub struct SpinPlugin;
+
+impl Plugin for SpinPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(mark_new_balls.system())
+                .with_system(render_spin.system().after(GameLabel::GoalApply)),
+        );
+    }
+}
+
+/// Stripe size and offset from the ball's center, as fractions of the ball size.
+const STRIPE_WIDTH: f32 = 0.15;
+const STRIPE_LENGTH: f32 = 0.4;
+const STRIPE_OFFSET: f32 = 0.2;
+/// In front of the ball sprite, its glow is at -0.5.
+const STRIPE_Z: f32 = 0.1;
+
+const METER_PIPS: usize = 8;
+const PIP_SIZE: f32 = 3.;
+/// Distance of the pips from the ball's center, as a fraction of the ball size.
+const METER_RADIUS: f32 = 0.85;
+/// Angle the arc sweeps at full spin, starting above the ball.
+const METER_SPAN: f32 = PI;
+/// Radians per second lighting every pip.
+const FULL_SPIN: f32 = 20.;
+
+/// Stripe child of a ball, the ball's rotation turns it.
+struct SpinMarker;
+
+/// One pip of the meter of `ball`, `1` being the pip next to the top.
+struct SpinPip {
+    ball: Entity,
+    index: usize,
+}
+
+/// A plain circle or a missing ball sprite looks the same at every angle, the stripe is drawn on
+/// top of whatever the ball shows. Invisible balls get neither, the stripe would give them away.
+fn mark_new_balls(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    rules: Res<MatchRules>,
+    tuning: Res<Tuning>,
+    balls: Query<Entity, Added<Ball>>,
+) {
+    if rules.invisible_ball {
+        return;
+    }
+
+    for ball in balls.iter() {
+        let size = tuning.ball_size;
+        commands.entity(ball).with_children(|parent| {
+            parent
+                .spawn_bundle(SpriteBundle {
+                    material: materials.add(Color::rgba(0., 0., 0., 0.6).into()),
+                    sprite: Sprite::new(Vec2::new(size * STRIPE_WIDTH, size * STRIPE_LENGTH)),
+                    transform: Transform::from_xyz(0., size * STRIPE_OFFSET, STRIPE_Z),
+                    ..Default::default()
+                })
+                .insert(SpinMarker);
+        });
+
+        let pip = materials.add(Color::rgba(1., 1., 1., 0.8).into());
+        for index in 1..=METER_PIPS {
+            commands
+                .spawn_bundle(SpriteBundle {
+                    material: pip.clone(),
+                    sprite: Sprite::new(Vec2::splat(PIP_SIZE)),
+                    visible: Visible {
+                        is_visible: false,
+                        is_transparent: true,
+                    },
+                    ..Default::default()
+                })
+                .insert(SpinPip { ball, index });
+        }
+    }
+}
+
+/// The stripe is off in performance mode, the meter also needs its setting. Counter-clockwise
+/// spin lights the arc to the left of the top, clockwise to the right.
+fn render_spin(
+    mut commands: Commands,
+    quality: Res<QualitySettings>,
+    tuning: Res<Tuning>,
+    rigid_bodies: Res<RigidBodySet>,
+    balls: Query<(&Transform, &RigidBodyHandleComponent), With<Ball>>,
+    mut markers: Query<&mut Visible, (With<SpinMarker>, Without<SpinPip>)>,
+    mut pips: Query<(Entity, &SpinPip, &mut Transform, &mut Visible), Without<Ball>>,
+) {
+    for mut visible in markers.iter_mut() {
+        if visible.is_visible != quality.spin_marker {
+            visible.is_visible = quality.spin_marker;
+        }
+    }
+
+    for (entity, pip, mut transform, mut visible) in pips.iter_mut() {
+        let (ball_transform, handle) = match balls.get(pip.ball) {
+            Ok(ball) => ball,
+            Err(_) => {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        };
+        let spin = rigid_bodies
+            .get(handle.handle())
+            .map_or(0., |rb| rb.angvel());
+
+        let lit = ((spin.abs() / FULL_SPIN).min(1.) * METER_PIPS as f32).round() as usize;
+        let shown = quality.spin_meter && pip.index <= lit;
+        if visible.is_visible != shown {
+            visible.is_visible = shown;
+        }
+        if !shown {
+            continue;
+        }
+
+        let step = METER_SPAN / METER_PIPS as f32 * pip.index as f32;
+        let angle = PI / 2. + step * spin.signum();
+        let radius = tuning.ball_size * METER_RADIUS;
+        transform.translation = ball_transform.translation
+            + Vec3::new(angle.cos() * radius, angle.sin() * radius, STRIPE_Z);
+    }
+}
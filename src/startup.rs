@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+
+use crate::callout::Callout;
+use crate::AppState;
+
+pub struct StartupPlugin;
+
+impl Plugin for StartupPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_enter(AppState::InGame).with_system(announce_warnings.system()),
+        );
+    }
+}
+
+/// Everything that went wrong while starting up and fell back to a default: an unreadable save
+/// file, a clamped config value, an asset that didn't load. Problems the player asked for
+/// explicitly, like a config file that doesn't exist, stop the game in `main` instead.
+/// The pause menu lists the warnings until they are dismissed.
+#[derive(Debug, Default, Clone)]
+pub struct StartupReport {
+    warnings: Vec<String>,
+    dismissed: bool,
+}
+
+impl StartupReport {
+    /// Logs the problem right away. `eprintln` rather than the log macros, as most of these
+    /// are found before the app sets up its logger.
+    pub fn warn(&mut self, message: String) {
+        eprintln!("{}", message);
+        self.warnings.push(message);
+    }
+
+    /// Warnings still to be shown, empty once dismissed.
+    pub fn pending(&self) -> &[String] {
+        if self.dismissed {
+            &[]
+        } else {
+            &self.warnings
+        }
+    }
+
+    pub fn dismiss(&mut self) {
+        self.dismissed = true;
+    }
+}
+
+/// Points the players at the menu, a match could otherwise be played start to end without
+/// anyone noticing the settings aren't theirs.
+fn announce_warnings(report: Res<StartupReport>, mut callouts: EventWriter<Callout>) {
+    let count = report.pending().len();
+    if count > 0 {
+        callouts.send(Callout(format!(
+            "{} startup {}, see the pause menu",
+            count,
+            if count == 1 { "problem" } else { "problems" }
+        )));
+    }
+}
@@ -0,0 +1,521 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_rapier2d::physics::{RapierConfiguration, RigidBodyHandleComponent};
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+
+use crate::contacts::{BallContact, Surface};
+use crate::hill::Queue;
+use crate::match_state::MatchPhase;
+use crate::persistence::SaveData;
+use crate::profiles;
+use crate::tournament::Tournament;
+use crate::{
+    AppState, GameLabel, GoalEvent, Paddle, PaddleRole, Player, Score, ServeEvent, ServeKind,
+};
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<MatchStats>().add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(
+                    record_hits
+                        .system()
+                        .label("record_hits")
+                        .after(GameLabel::Contacts),
+                )
+                .with_system(record_missed_by.system().after(GameLabel::GoalDetect))
+                .with_system(record_serves.system().after(GameLabel::GoalApply))
+                .with_system(
+                    end_rally
+                        .system()
+                        .label(GameLabel::GoalApply)
+                        .after(GameLabel::GoalDetect)
+                        .after("record_hits"),
+                )
+                .with_system(
+                    record_lifetime
+                        .system()
+                        .label(GameLabel::GoalPresent)
+                        .after(GameLabel::GoalApply),
+                ),
+        );
+    }
+}
+
+/// Identifies a single paddle, in doubles each side has two.
+pub type PaddleId = (Player, PaddleRole);
+
+/// Numbers gathered during the current match.
+#[derive(Debug, Default)]
+pub struct MatchStats {
+    pub hits: HashMap<PaddleId, u32>,
+    /// Paddle that touched each ball last in its current rally.
+    pub last_touch: HashMap<Entity, PaddleId>,
+    /// Paddle hits in the current rally of each ball, every ball in play has its own.
+    pub rally: HashMap<Entity, u32>,
+    pub longest_rally: u32,
+    /// Ball speed in pixels per second right after a paddle hit.
+    pub fastest_shot: f32,
+    /// Fastest shot of each player this match, in pixels per second.
+    pub fastest_shots: HashMap<Player, f32>,
+    /// Goals scored straight from a serve, before the receiver touched the ball.
+    pub aces: HashMap<Player, u32>,
+    /// Pixels between the closest defending paddle and the ball for every goal a player
+    /// conceded.
+    pub missed_by: HashMap<Player, Vec<f32>>,
+    /// Serves of each kind by the serving player.
+    pub serves: HashMap<(Player, ServeKind), u32>,
+    /// Seconds of play the ball spent in each player's half.
+    pub territory: HashMap<Player, f32>,
+    /// Hits that carried a good part of the paddle's movement into the ball.
+    pub pushed_shots: HashMap<Player, u32>,
+    /// Hits with the paddle held about still.
+    pub blocks: HashMap<Player, u32>,
+    /// Winner of every point so far, in order.
+    pub point_winners: Vec<Player>,
+    /// Who is on a roll, see `momentum_after`.
+    pub momentum: f32,
+    /// Player last announced as having the momentum.
+    pub momentum_leader: Option<Player>,
+}
+
+impl MatchStats {
+    pub fn average_missed_by(&self, player: Player) -> Option<f32> {
+        let misses = self.missed_by.get(&player)?;
+        if misses.is_empty() {
+            return None;
+        }
+        Some(misses.iter().sum::<f32>() / misses.len() as f32)
+    }
+
+    /// Fraction of the play so far the ball spent in `player`'s half.
+    pub fn territory_share(&self, player: Player) -> Option<f32> {
+        let total: f32 = self.territory.values().sum();
+        if total <= 0. {
+            return None;
+        }
+        Some(self.territory.get(&player).copied().unwrap_or(0.) / total)
+    }
+
+    pub fn player_hits(&self, player: Player) -> u32 {
+        self.hits
+            .iter()
+            .filter(|((hitter, _), _)| *hitter == player)
+            .map(|(_, hits)| hits)
+            .sum()
+    }
+}
+
+/// Points looked back on for the form of the players.
+const RECENT_POINTS: usize = 10;
+/// Points each player is credited with up front, so a couple of early points don't swing the form
+/// to either extreme.
+const FORM_PRIOR: f32 = 2.;
+/// Extra chance of winning a point for the player serving it.
+const SERVE_EDGE: f32 = 0.03;
+/// How much a goal of difference and a point of form count without a score limit to race to.
+const OPEN_GOAL_WEIGHT: f32 = 0.6;
+const OPEN_FORM_WEIGHT: f32 = 4.;
+
+/// Chance the left player wins the match, just for fun. Each point is taken to be a coin flip
+/// weighted by who won the last `RECENT_POINTS` and who serves. With a score limit the chance is
+/// that of reaching it first, golden goal is a single point and an open match weighs the score
+/// difference against the form.
+pub fn win_probability(
+    left: u32,
+    right: u32,
+    score_limit: Option<u32>,
+    overtime: bool,
+    recent: &[Player],
+    server: Option<Player>,
+) -> f32 {
+    let recent = &recent[recent.len().saturating_sub(RECENT_POINTS)..];
+    let left_won = recent
+        .iter()
+        .filter(|winner| **winner == Player::Left)
+        .count() as f32;
+    let form = (left_won + FORM_PRIOR) / (recent.len() as f32 + 2. * FORM_PRIOR);
+    let point = match server {
+        Some(Player::Left) => form + SERVE_EDGE,
+        Some(Player::Right) => form - SERVE_EDGE,
+        None => form,
+    }
+    .max(0.01)
+    .min(0.99);
+
+    if overtime {
+        return point;
+    }
+    match score_limit {
+        Some(limit) => race(
+            limit.saturating_sub(left) as usize,
+            limit.saturating_sub(right) as usize,
+            point,
+        ),
+        None => {
+            let difference = left as f32 - right as f32;
+            let odds = difference * OPEN_GOAL_WEIGHT + (point - 0.5) * OPEN_FORM_WEIGHT;
+            1. / (1. + (-odds).exp())
+        }
+    }
+}
+
+/// Chance of winning `left` points before the other side wins `right`, each point won with
+/// chance `point`.
+fn race(left: usize, right: usize, point: f32) -> f32 {
+    if left == 0 {
+        return 1.;
+    }
+    if right == 0 {
+        return 0.;
+    }
+    // chances[j] is the chance of winning from needing i more points against j more
+    let mut chances = vec![0.; right + 1];
+    for chance in chances.iter_mut().skip(1) {
+        *chance = 1.;
+    }
+    for _ in 1..=left {
+        chances[0] = 0.;
+        for j in 1..=right {
+            chances[j] = point * chances[j] + (1. - point) * chances[j - 1];
+        }
+    }
+    chances[right]
+}
+
+/// What nudges the momentum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Swing {
+    Point { winner: Player, ace: bool },
+    Hit(Player),
+}
+
+/// Part of the way to their end of the scale a point pulls the momentum toward its winner, an
+/// ace pulls further.
+const POINT_PULL: f32 = 0.3;
+const ACE_PULL: f32 = 0.45;
+/// Every paddle hit nudges it toward the hitter, so the player with more of the rally's hits
+/// gains a little.
+const HIT_PULL: f32 = 0.02;
+/// How far from even the momentum has to be for a player to have it.
+const MOMENTUM_MARGIN: f32 = 0.35;
+
+/// Momentum after `swing`, from -1 with the right player on a roll to 1 with the left. Each swing
+/// pulls it part of the way toward its player's end, so older ones fade out exponentially.
+pub fn momentum_after(momentum: f32, swing: Swing) -> f32 {
+    let (player, pull) = match swing {
+        Swing::Point { winner, ace: false } => (winner, POINT_PULL),
+        Swing::Point { winner, ace: true } => (winner, ACE_PULL),
+        Swing::Hit(player) => (player, HIT_PULL),
+    };
+    let end = match player {
+        Player::Left => 1.,
+        Player::Right => -1.,
+    };
+    momentum + (end - momentum) * pull
+}
+
+/// Player clearly having the momentum, `None` while it is about even.
+pub fn momentum_leader(momentum: f32) -> Option<Player> {
+    if momentum >= MOMENTUM_MARGIN {
+        Some(Player::Left)
+    } else if momentum <= -MOMENTUM_MARGIN {
+        Some(Player::Right)
+    } else {
+        None
+    }
+}
+
+fn record_hits(
+    rapier_config: Res<RapierConfiguration>,
+    rigid_bodies: Res<RigidBodySet>,
+    mut stats: ResMut<MatchStats>,
+    mut contacts: EventReader<BallContact>,
+    roles: Query<&PaddleRole>,
+    balls: Query<&RigidBodyHandleComponent>,
+) {
+    for contact in contacts.iter() {
+        let player = match contact.surface {
+            Surface::Paddle(player) if contact.started => player,
+            _ => continue,
+        };
+
+        let role = roles
+            .get(contact.other)
+            .copied()
+            .unwrap_or(PaddleRole::Solo);
+        let paddle = (player, role);
+
+        *stats.hits.entry(paddle).or_insert(0) += 1;
+        stats.last_touch.insert(contact.ball, paddle);
+        stats.momentum = momentum_after(stats.momentum, Swing::Hit(player));
+        let rally = stats.rally.entry(contact.ball).or_insert(0);
+        *rally += 1;
+        let rally = *rally;
+        stats.longest_rally = stats.longest_rally.max(rally);
+
+        let rb = balls
+            .get(contact.ball)
+            .ok()
+            .and_then(|rigid_body_component| rigid_bodies.get(rigid_body_component.handle()));
+        if let Some(rb) = rb {
+            let speed = rb.linvel().magnitude() * rapier_config.scale;
+            stats.fastest_shot = stats.fastest_shot.max(speed);
+            let fastest = stats.fastest_shots.entry(player).or_insert(0.);
+            *fastest = fastest.max(speed);
+        }
+    }
+}
+
+/// How far the defender was from saving each goal, measured along the goal line from the edge of
+/// the closest paddle of the defending side to where the ball crossed.
+fn record_missed_by(
+    mut stats: ResMut<MatchStats>,
+    mut goals: EventReader<GoalEvent>,
+    paddles: Query<(&Player, &Transform, &Sprite), With<Paddle>>,
+) {
+    for goal in goals.iter() {
+        let crossing_y = match goal.crossing_y {
+            Some(crossing_y) => crossing_y,
+            None => continue,
+        };
+        let defender = goal.scorer.opponent();
+
+        let distance = paddles
+            .iter()
+            .filter(|(player, _, _)| **player == defender)
+            .map(|(_, transform, sprite)| {
+                ((crossing_y - transform.translation.y).abs() - sprite.size.y / 2.).max(0.)
+            })
+            .fold(None, |closest: Option<f32>, distance| {
+                Some(closest.map_or(distance, |closest| closest.min(distance)))
+            });
+
+        if let Some(distance) = distance {
+            info!("{:?} missed by {:.0} px", defender, distance);
+            stats.missed_by.entry(defender).or_default().push(distance);
+        }
+    }
+}
+
+/// The server is the player the serve is heading away from.
+fn record_serves(mut stats: ResMut<MatchStats>, mut serves: EventReader<ServeEvent>) {
+    for serve in serves.iter() {
+        let server = serve.receiver.opponent();
+        if serve.kind != ServeKind::Standard {
+            info!("{:?} served a {:?} serve", server, serve.kind);
+        }
+        *stats.serves.entry((server, serve.kind)).or_insert(0) += 1;
+    }
+}
+
+/// A goal through the goal line with no paddle hit on that ball since its serve is an ace. Only
+/// the rally of the ball that went in ends, the point swings the momentum toward its scorer.
+fn end_rally(mut stats: ResMut<MatchStats>, mut goals: EventReader<GoalEvent>) {
+    for goal in goals.iter() {
+        let rally = stats.rally.remove(&goal.ball).unwrap_or(0);
+        let ace = rally == 0 && goal.crossing_y.is_some();
+        if ace {
+            *stats.aces.entry(goal.scorer).or_insert(0) += 1;
+        }
+        stats.momentum = momentum_after(
+            stats.momentum,
+            Swing::Point {
+                winner: goal.scorer,
+                ace,
+            },
+        );
+        stats.last_touch.remove(&goal.ball);
+        stats.point_winners.push(goal.scorer);
+    }
+}
+
+/// Adds a finished match to the lifetime totals in the save file, and to the profiles on the
+/// paddles unless a tournament or king of the hill has its own players on them.
+fn record_lifetime(
+    phase: Res<MatchPhase>,
+    score: Res<Score>,
+    stats: Res<MatchStats>,
+    tournament: Res<Tournament>,
+    queue: Res<Queue>,
+    mut save: ResMut<SaveData>,
+) {
+    let winner = match *phase {
+        MatchPhase::Finished(winner) if phase.is_changed() => winner,
+        _ => return,
+    };
+
+    let lifetime = &mut save.stats;
+    lifetime.matches += 1;
+    match winner {
+        Player::Left => lifetime.left_wins += 1,
+        Player::Right => lifetime.right_wins += 1,
+    }
+    lifetime.goals += score.left + score.right;
+    lifetime.paddle_hits += stats.hits.values().sum::<u32>();
+    lifetime.longest_rally = lifetime.longest_rally.max(stats.longest_rally);
+    lifetime.fastest_shot = lifetime.fastest_shot.max(stats.fastest_shot);
+    if !tournament.is_active() && !queue.is_active() {
+        profiles::record_match(&mut save, winner, &score, &stats);
+    }
+
+    save.save();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: [Option<u32>; 3] = [None, Some(5), Some(11)];
+    const SERVERS: [Option<Player>; 3] = [None, Some(Player::Left), Some(Player::Right)];
+
+    fn recents() -> Vec<Vec<Player>> {
+        vec![
+            vec![],
+            vec![Player::Left, Player::Left, Player::Right],
+            vec![Player::Right; 12],
+        ]
+    }
+
+    #[test]
+    fn level_score_is_a_coin_flip() {
+        for limit in LIMITS.iter() {
+            for score in 0..5 {
+                let chance = win_probability(score, score, *limit, false, &[], None);
+                assert!((chance - 0.5).abs() < 1e-5, "{} all to {:?}", score, limit);
+            }
+        }
+        assert!((win_probability(5, 5, Some(5), true, &[], None) - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn stays_within_bounds() {
+        for limit in LIMITS.iter() {
+            for recent in recents().iter() {
+                for server in SERVERS.iter() {
+                    for left in 0..=12 {
+                        for right in 0..=12 {
+                            let chance =
+                                win_probability(left, right, *limit, false, recent, *server);
+                            assert!((0. ..=1.).contains(&chance), "{} to {}", left, right);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn grows_with_the_lead() {
+        for limit in LIMITS.iter() {
+            for recent in recents().iter() {
+                let mut previous = 0.;
+                for left in 0..5 {
+                    let chance = win_probability(left, 2, *limit, false, recent, None);
+                    assert!(chance > previous, "{} to 2 first to {:?}", left, limit);
+                    previous = chance;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sides_complement_each_other() {
+        for limit in LIMITS.iter() {
+            for recent in recents().iter() {
+                let mirrored: Vec<Player> = recent.iter().map(|player| player.opponent()).collect();
+                for server in SERVERS.iter() {
+                    for (left, right) in [(0, 0), (3, 1), (1, 4), (4, 4), (2, 9)].iter() {
+                        for overtime in [false, true].iter() {
+                            let chance =
+                                win_probability(*left, *right, *limit, *overtime, recent, *server);
+                            let other = win_probability(
+                                *right,
+                                *left,
+                                *limit,
+                                *overtime,
+                                &mirrored,
+                                server.map(|player| player.opponent()),
+                            );
+                            assert!(
+                                (chance - (1. - other)).abs() < 1e-4,
+                                "{} to {} first to {:?}: {} against {}",
+                                left,
+                                right,
+                                limit,
+                                chance,
+                                other
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn point(winner: Player) -> Swing {
+        Swing::Point { winner, ace: false }
+    }
+
+    #[test]
+    fn old_momentum_fades() {
+        // Each point pulls 0.3 of the way to its winner's end: 1 -> 0.4 -> -0.02 -> -0.314
+        let mut momentum = 1.;
+        for expected in [0.4, -0.02, -0.314].iter() {
+            momentum = momentum_after(momentum, point(Player::Right));
+            assert!((momentum - expected).abs() < 1e-5, "{}", momentum);
+        }
+        assert_eq!(momentum_leader(momentum), None);
+
+        // A hit only nudges it
+        assert!((momentum_after(0., Swing::Hit(Player::Left)) - 0.02).abs() < 1e-6);
+        assert!((momentum_after(0., Swing::Hit(Player::Right)) + 0.02).abs() < 1e-6);
+    }
+
+    #[test]
+    fn streak_then_swing() {
+        // Three points: 0.3, 0.51, 0.657
+        let mut momentum = 0.;
+        for _ in 0..3 {
+            momentum = momentum_after(momentum, point(Player::Left));
+        }
+        assert!((momentum - 0.657).abs() < 1e-5, "{}", momentum);
+        assert_eq!(momentum_leader(momentum), Some(Player::Left));
+
+        // An ace pulls 0.45 of the way: 0.657 - 1.657 * 0.45 = -0.08865, even again
+        momentum = momentum_after(
+            momentum,
+            Swing::Point {
+                winner: Player::Right,
+                ace: true,
+            },
+        );
+        assert!((momentum + 0.08865).abs() < 1e-5, "{}", momentum);
+        assert_eq!(momentum_leader(momentum), None);
+
+        // -0.08865 - 0.91135 * 0.3 = -0.362055, over to the right
+        momentum = momentum_after(momentum, point(Player::Right));
+        assert!((momentum + 0.362055).abs() < 1e-5, "{}", momentum);
+        assert_eq!(momentum_leader(momentum), Some(Player::Right));
+    }
+
+    #[test]
+    fn even_momentum_has_no_leader() {
+        assert_eq!(momentum_leader(0.), None);
+        assert_eq!(momentum_leader(0.34), None);
+        assert_eq!(momentum_leader(-0.34), None);
+        assert_eq!(momentum_leader(0.35), Some(Player::Left));
+        assert_eq!(momentum_leader(-0.35), Some(Player::Right));
+
+        // A point each from even: 0.3, then 0.3 - 1.3 * 0.3 = -0.09
+        let momentum = momentum_after(
+            momentum_after(0., point(Player::Left)),
+            point(Player::Right),
+        );
+        assert!((momentum + 0.09).abs() < 1e-5, "{}", momentum);
+        assert_eq!(momentum_leader(momentum), None);
+    }
+}
@@ -0,0 +1,168 @@
+use bevy::prelude::*;
+
+use crate::hud;
+use crate::intro::Intro;
+use crate::persistence::SaveData;
+use crate::replay::Replay;
+use crate::stats::MatchStats;
+use crate::theme::Theme;
+use crate::{AppState, GameLabel, Player, Score, ServeEvent, UiFont};
+
+/// Each player's numbers of the match so far beside their score, shown with `OVERLAY_KEY`.
+pub struct StatsOverlayPlugin;
+
+impl Plugin for StatsOverlayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<StatsOverlay>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame).with_system(spawn_stats_overlay.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(overlay_key.system().label("overlay_key"))
+                    .with_system(
+                        render_stats_overlay
+                            .system()
+                            .after("overlay_key")
+                            .after(GameLabel::GoalApply),
+                    ),
+            );
+    }
+}
+
+const OVERLAY_KEY: KeyCode = KeyCode::Tab;
+/// Seconds between refreshes of the numbers.
+const REFRESH_SECONDS: f32 = 0.5;
+/// Seconds the overlay stays hidden after a serve.
+const SERVE_HIDE_SECONDS: f32 = 1.;
+/// Centered on the outer quarter of each half, level with the scores.
+const OVERLAY_TOP: f32 = 0.42;
+const FONT_SIZE: f32 = 18.;
+
+struct StatsOverlay {
+    shown: bool,
+    refresh: Timer,
+    /// Runs from the latest serve.
+    since_serve: Timer,
+}
+
+impl Default for StatsOverlay {
+    fn default() -> Self {
+        let mut since_serve = Timer::from_seconds(SERVE_HIDE_SECONDS, false);
+        since_serve.tick(since_serve.duration());
+        StatsOverlay {
+            shown: false,
+            refresh: Timer::from_seconds(REFRESH_SECONDS, true),
+            since_serve,
+        }
+    }
+}
+
+struct StatsOverlayText(Player);
+
+fn spawn_stats_overlay(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    ui_font: Res<UiFont>,
+    theme: Res<Theme>,
+) {
+    for player in [Player::Left, Player::Right].iter() {
+        let left = match player {
+            Player::Left => 0.,
+            Player::Right => 0.75,
+        };
+
+        commands
+            .spawn_bundle(NodeBundle {
+                style: hud::row(left, 0.25, OVERLAY_TOP),
+                material: materials.add(Color::NONE.into()),
+                ..Default::default()
+            })
+            .with_children(|parent| {
+                parent
+                    .spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            "",
+                            TextStyle {
+                                font: ui_font.0.clone(),
+                                font_size: FONT_SIZE,
+                                color: theme.player_color(*player),
+                            },
+                            TextAlignment {
+                                horizontal: HorizontalAlign::Center,
+                                ..Default::default()
+                            },
+                        ),
+                        visible: Visible {
+                            is_visible: false,
+                            is_transparent: true,
+                        },
+                        ..Default::default()
+                    })
+                    .insert(StatsOverlayText(*player));
+            });
+    }
+}
+
+/// Toggles the overlay on each press, or shows it while the key is held when the settings ask
+/// for that.
+fn overlay_key(
+    keyboard_input: Res<Input<KeyCode>>,
+    save: Res<SaveData>,
+    mut overlay: ResMut<StatsOverlay>,
+) {
+    if save.settings.hold_stats_overlay {
+        overlay.shown = keyboard_input.pressed(OVERLAY_KEY);
+    } else if keyboard_input.just_pressed(OVERLAY_KEY) {
+        overlay.shown = !overlay.shown;
+    }
+}
+
+fn overlay_lines(player: Player, stats: &MatchStats, score: &Score) -> String {
+    let streak = match score.streak {
+        Some((streaker, goals)) if streaker == player => goals,
+        _ => 0,
+    };
+    format!(
+        "Hits {}\nAces {}\nFastest {:.0} px/s\nStreak {}",
+        stats.player_hits(player),
+        stats.aces.get(&player).copied().unwrap_or(0),
+        stats.fastest_shots.get(&player).copied().unwrap_or(0.),
+        streak
+    )
+}
+
+/// Refreshes the numbers at most twice a second. The overlay is hidden right after a serve, so
+/// the ball can be followed off the center spot, and during the intro and replays.
+fn render_stats_overlay(
+    time: Res<Time>,
+    stats: Res<MatchStats>,
+    score: Res<Score>,
+    intro: Res<Intro>,
+    replay: Res<Replay>,
+    mut overlay: ResMut<StatsOverlay>,
+    mut serves: EventReader<ServeEvent>,
+    mut texts: Query<(&StatsOverlayText, &mut Text, &mut Visible)>,
+) {
+    if serves.iter().next().is_some() {
+        overlay.since_serve.reset();
+    }
+    overlay.since_serve.tick(time.delta());
+    let refresh = overlay.refresh.tick(time.delta()).just_finished();
+
+    let visible = overlay.shown
+        && overlay.since_serve.finished()
+        && !intro.is_playing()
+        && !replay.is_playing();
+
+    for (overlay_text, mut text, mut shown) in texts.iter_mut() {
+        // Fill in the numbers as soon as it shows, then on the refresh ticks
+        let appeared = visible && !shown.is_visible;
+        if shown.is_visible != visible {
+            shown.is_visible = visible;
+        }
+        if appeared || (visible && refresh) {
+            text.sections[0].value = overlay_lines(overlay_text.0, &stats, &score);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy_rapier2d::physics::{RapierConfiguration, RigidBodyHandleComponent};
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+
+use crate::ai::AiState;
+use crate::contacts::BallContact;
+use crate::input::Controller;
+use crate::physics_guard::PhysicsResets;
+use crate::quality::QualitySettings;
+use crate::stats::MatchStats;
+use crate::tuning::Tuning;
+use crate::{AppState, Ball, GameLabel, PaddleRole, Paused, Player, UiFont};
+
+pub struct StepPlugin;
+
+impl Plugin for StepPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<StepControl>()
+            .add_system_to_stage(CoreStage::PreUpdate, drive_step.system().after(InputSystem))
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(render_step_overlay.system().after(GameLabel::Contacts)),
+            );
+    }
+}
+
+const TOGGLE_KEY: KeyCode = KeyCode::F6;
+const STEP_KEY: KeyCode = KeyCode::F7;
+
+/// Debug step mode. While on, the game stays paused except for single frames let through by the
+/// step key, physics advances by exactly one step in those frames and paddle input is read in
+/// them too. Rendering and UI keep running every frame.
+#[derive(Debug, Default)]
+struct StepControl {
+    stepping: bool,
+    /// Let the gameplay run this frame.
+    step_now: bool,
+    /// Frames run since step mode was switched on.
+    frame: u64,
+    /// Ball contacts reported since the last step, they arrive the frame after it.
+    contacts: usize,
+    paused_game: bool,
+}
+
+struct StepOverlay;
+
+fn drive_step(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    font: Option<Res<UiFont>>,
+    mut step: ResMut<StepControl>,
+    mut paused: ResMut<Paused>,
+    overlay: Query<Entity, With<StepOverlay>>,
+) {
+    if keyboard_input.just_pressed(TOGGLE_KEY) {
+        if step.stepping {
+            // Pick up at normal speed, physics only ever runs one step per frame so there is
+            // nothing to catch up on
+            for entity in overlay.iter() {
+                commands.entity(entity).despawn();
+            }
+            if step.paused_game {
+                paused.0 = false;
+            }
+            *step = StepControl::default();
+            return;
+        }
+
+        let font = match font {
+            Some(font) => font.0.clone(),
+            None => return,
+        };
+
+        step.stepping = true;
+        step.paused_game = !paused.0;
+        paused.0 = true;
+        spawn_overlay(&mut commands, font);
+        return;
+    }
+
+    if !step.stepping {
+        return;
+    }
+
+    step.step_now = keyboard_input.just_pressed(STEP_KEY);
+    paused.0 = !step.step_now;
+    if step.step_now {
+        step.frame += 1;
+        step.contacts = 0;
+    }
+}
+
+fn spawn_overlay(commands: &mut Commands, font: Handle<Font>) {
+    commands
+        .spawn_bundle(TextBundle {
+            text: Text::with_section(
+                "".to_string(),
+                TextStyle {
+                    font,
+                    font_size: 20.0,
+                    color: Color::rgb(0.6, 1.0, 0.6),
+                },
+                Default::default(),
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(20.),
+                    right: Val::Px(20.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(StepOverlay);
+}
+
+fn render_step_overlay(
+    mut step: ResMut<StepControl>,
+    quality: Res<QualitySettings>,
+    resets: Res<PhysicsResets>,
+    rapier_config: Res<RapierConfiguration>,
+    rigid_bodies: Res<RigidBodySet>,
+    tuning: Res<Tuning>,
+    stats: Res<MatchStats>,
+    mut contacts: EventReader<BallContact>,
+    balls: Query<(Entity, &RigidBodyHandleComponent), With<Ball>>,
+    computers: Query<(&Player, &PaddleRole, &Controller, &AiState)>,
+    mut overlay: Query<&mut Text, With<StepOverlay>>,
+) {
+    let contact_count = contacts.iter().count();
+    if !step.stepping {
+        return;
+    }
+    step.contacts += contact_count;
+
+    let mut lines = vec![format!("STEP  frame {}", step.frame)];
+    for (ball, rigid_body_component) in balls.iter() {
+        if let Some(rb) = rigid_bodies.get(rigid_body_component.handle()) {
+            let velocity = *rb.linvel() * rapier_config.scale;
+            let rally = stats.rally.get(&ball).copied().unwrap_or(0);
+            lines.push(format!(
+                "ball {:.0}, {:.0} px/s, rally {}, wall bounce x{:.2}",
+                velocity.x,
+                velocity.y,
+                rally,
+                tuning.long_rally_bounce(rally)
+            ));
+        }
+    }
+    lines.push(format!("contacts {}", step.contacts));
+    for (player, role, controller, state) in computers.iter() {
+        if controller.is_ai() {
+            lines.push(format!(
+                "ai {:?} {:?} reaction {:.2} s, miss {:.0}%",
+                player,
+                role,
+                state.skill.reaction_seconds,
+                state.skill.miss_chance * 100.
+            ));
+        }
+    }
+    lines.push(format!("quality {:?}", quality.level));
+    lines.push(format!("physics resets {}", resets.0));
+    lines.push(format!("{:?} toggle, {:?} step", TOGGLE_KEY, STEP_KEY));
+
+    for mut text in overlay.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
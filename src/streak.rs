@@ -0,0 +1,131 @@
+use bevy::prelude::*;
+
+use crate::callout::Callout;
+use crate::loading::GameAssets;
+use crate::match_state::MatchPhase;
+use crate::theme::Theme;
+use crate::{AppState, GameLabel, Player};
+
+pub struct StreakPlugin;
+
+impl Plugin for StreakPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<SessionStreaks>()
+            .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(spawn_crown.system()))
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(
+                        record_winner
+                            .system()
+                            .label(GameLabel::GoalPresent)
+                            .after(GameLabel::GoalApply),
+                    )
+                    .with_system(place_crown.system()),
+            );
+    }
+}
+
+/// Streak lengths announced with a callout.
+const MILESTONES: [u32; 3] = [3, 5, 10];
+const CROWN_WIDTH: f32 = 32.;
+const CROWN_HEIGHT: f32 = 24.;
+/// Space between the score text and the crown.
+const CROWN_GAP: f32 = 12.;
+
+/// Matches won in a row by the same player during this session, rematches included.
+#[derive(Debug, Default)]
+pub struct SessionStreaks {
+    holder: Option<(Player, u32)>,
+}
+
+impl SessionStreaks {
+    /// Counts a match win, returns the length of the winner's streak.
+    fn record(&mut self, winner: Player) -> u32 {
+        let wins = match self.holder {
+            Some((holder, wins)) if holder == winner => wins + 1,
+            _ => 1,
+        };
+        self.holder = Some((winner, wins));
+        wins
+    }
+}
+
+struct Crown;
+
+fn record_winner(
+    phase: Res<MatchPhase>,
+    mut streaks: ResMut<SessionStreaks>,
+    mut callouts: EventWriter<Callout>,
+) {
+    let winner = match *phase {
+        MatchPhase::Finished(winner) if phase.is_changed() => winner,
+        _ => return,
+    };
+
+    let wins = streaks.record(winner);
+    if MILESTONES.contains(&wins) {
+        callouts.send(Callout(format!("{} in a row!", wins)));
+    }
+}
+
+fn spawn_crown(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    game_assets: Res<GameAssets>,
+    theme: Res<Theme>,
+) {
+    let material = match &game_assets.crown_texture {
+        Some(texture) => texture.clone().into(),
+        None => theme.crown.into(),
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Px(CROWN_WIDTH), Val::Px(CROWN_HEIGHT)),
+                ..Default::default()
+            },
+            material: materials.add(material),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(Crown);
+}
+
+/// Sits right of the holder's score, following the text as the scoreboard style changes its
+/// length.
+fn place_crown(
+    streaks: Res<SessionStreaks>,
+    scores: Query<(&Player, &Node, &Style), (With<Text>, Without<Crown>)>,
+    mut crowns: Query<(&mut Style, &mut Visible), With<Crown>>,
+) {
+    let score = streaks.holder.and_then(|(holder, _)| {
+        scores
+            .iter()
+            .find(|(player, _, _)| **player == holder)
+            .map(|(_, node, style)| (node, style))
+    });
+
+    for (mut style, mut visible) in crowns.iter_mut() {
+        let (node, score_style) = match score {
+            Some(score) => score,
+            None => {
+                visible.is_visible = false;
+                continue;
+            }
+        };
+
+        let (left, top) = match (score_style.position.left, score_style.position.top) {
+            (Val::Px(left), Val::Px(top)) => (left, top),
+            _ => continue,
+        };
+
+        visible.is_visible = true;
+        style.position.left = Val::Px(left + node.size.x + CROWN_GAP);
+        style.position.top = Val::Px(top + (node.size.y - CROWN_HEIGHT) / 2.);
+    }
+}
@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+
+use crate::hud;
+use crate::persistence::SaveData;
+use crate::theme::Theme;
+use crate::{AppState, GameLabel, GoalEvent, Player, UiFont};
+
+pub struct TauntPlugin;
+
+impl Plugin for TauntPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(
+                    show_taunt
+                        .system()
+                        .label(GameLabel::GoalPresent)
+                        .after(GameLabel::GoalApply),
+                )
+                .with_system(wobble_taunts.system()),
+        );
+    }
+}
+
+const TAUNT_SECONDS: f32 = 1.2;
+/// Below the scoreboard and well clear of the callouts in the upper middle, as a fraction of the
+/// window height.
+const TAUNT_TOP: f32 = 0.7;
+const WOBBLE_PIXELS: f32 = 6.;
+/// Wobbles per second.
+const WOBBLE_RATE: f32 = 3.;
+
+/// The scorer's taunt, shown in front of the goal they just scored in. Holds the text, which
+/// wobbles about its place.
+struct Taunt {
+    timer: Timer,
+}
+
+struct TauntText;
+
+fn show_taunt(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    save: Res<SaveData>,
+    theme: Res<Theme>,
+    ui_font: Res<UiFont>,
+    mut goals: EventReader<GoalEvent>,
+    current: Query<Entity, With<Taunt>>,
+) {
+    let goal = match goals.iter().last() {
+        Some(goal) => goal,
+        None => return,
+    };
+
+    let text = save.settings.taunt(goal.scorer);
+    if !save.settings.taunts || text.is_empty() {
+        return;
+    }
+
+    for entity in current.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    // Centered on the outer quarter of the window on the side of the goal
+    let left = match goal.scorer.opponent() {
+        Player::Left => 0.,
+        Player::Right => 0.75,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: hud::row(left, 0.25, TAUNT_TOP),
+            material: materials.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .insert(Taunt {
+            timer: Timer::from_seconds(TAUNT_SECONDS, false),
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        text.to_string(),
+                        TextStyle {
+                            font: ui_font.0.clone(),
+                            font_size: 40.0,
+                            color: theme.player_color(goal.scorer),
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(TauntText);
+        });
+}
+
+/// Shakes each taunt about its spot.
+fn wobble_taunts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut taunts: Query<(Entity, &mut Taunt)>,
+    mut texts: Query<(&Parent, &mut Style), With<TauntText>>,
+) {
+    for (entity, mut taunt) in taunts.iter_mut() {
+        taunt.timer.tick(time.delta());
+        if taunt.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    for (parent, mut style) in texts.iter_mut() {
+        let phase = match taunts.get_mut(parent.0) {
+            Ok((_, taunt)) => taunt.timer.elapsed_secs() * WOBBLE_RATE * 2. * std::f32::consts::PI,
+            Err(_) => continue,
+        };
+        style.position.left = Val::Px(phase.sin() * WOBBLE_PIXELS);
+        style.position.top = Val::Px((phase * 2.).sin() * WOBBLE_PIXELS / 2.);
+    }
+}
@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+use bevy_rapier2d::physics::{RapierConfiguration, RigidBodyHandleComponent};
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+
+use crate::hud;
+use crate::match_state::MatchPhase;
+use crate::stats::MatchStats;
+use crate::theme::Theme;
+use crate::{AppState, Ball, GameLabel, Paused, Player, ARENA_MIDDLE};
+
+pub struct TerritoryPlugin;
+
+impl Plugin for TerritoryPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_enter(AppState::InGame).with_system(spawn_territory_bar.system()),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(record_territory.system().before(GameLabel::GoalDetect))
+                .with_system(render_territory_bar.system()),
+        );
+    }
+}
+
+const BAR_WIDTH: f32 = 200.;
+const BAR_HEIGHT: f32 = 6.;
+/// Below the scores, above the power pips, as a fraction of the window height.
+const BAR_TOP: f32 = 0.62;
+
+/// One player's part of the territory bar, it grows from the middle of the bar toward their side.
+struct TerritoryBar(Player);
+
+/// Time each side of the center line held the ball while it was in play. With several balls each
+/// one counts for its share of the frame, so the totals stay in seconds of play.
+fn record_territory(
+    time: Res<Time>,
+    paused: Res<Paused>,
+    phase: Res<MatchPhase>,
+    rigid_bodies: Res<RigidBodySet>,
+    rapier_config: Res<RapierConfiguration>,
+    mut stats: ResMut<MatchStats>,
+    balls: Query<&RigidBodyHandleComponent, With<Ball>>,
+) {
+    if paused.0 || phase.is_finished() {
+        return;
+    }
+
+    // A ball at rest is waiting to be served
+    let positions: Vec<f32> = balls
+        .iter()
+        .filter_map(|rigid_body_component| rigid_bodies.get(rigid_body_component.handle()))
+        .filter(|rb| rb.linvel().magnitude_squared() > 0.)
+        .map(|rb| rb.position().translation.x * rapier_config.scale)
+        .collect();
+    if positions.is_empty() {
+        return;
+    }
+
+    let share = time.delta_seconds() / positions.len() as f32;
+    for x in positions {
+        let side = if x < ARENA_MIDDLE {
+            Player::Left
+        } else {
+            Player::Right
+        };
+        *stats.territory.entry(side).or_insert(0.) += share;
+    }
+}
+
+fn spawn_territory_bar(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    theme: Res<Theme>,
+) {
+    let none = materials.add(Color::NONE.into());
+    commands
+        .spawn_bundle(NodeBundle {
+            style: hud::full_row(BAR_TOP),
+            material: none.clone(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(BAR_WIDTH), Val::Px(BAR_HEIGHT)),
+                        ..Default::default()
+                    },
+                    material: none,
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    // Left first, the two parts fill the bar from the left edge
+                    for player in [Player::Left, Player::Right].iter() {
+                        parent
+                            .spawn_bundle(NodeBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(bar_width(0.5)), Val::Px(BAR_HEIGHT)),
+                                    ..Default::default()
+                                },
+                                material: materials.add(theme.player_color(*player).into()),
+                                ..Default::default()
+                            })
+                            .insert(TerritoryBar(*player));
+                    }
+                });
+        });
+}
+
+/// Width of a player's part of the bar when they held the ball `share` of the time.
+fn bar_width(share: f32) -> f32 {
+    BAR_WIDTH * share
+}
+
+/// The widths only change once they are a whole pixel off, so the UI isn't laid out again every
+/// frame for a bar that barely moves.
+fn render_territory_bar(
+    stats: Res<MatchStats>,
+    theme: Res<Theme>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut bars: Query<(&TerritoryBar, &mut Style, &Handle<ColorMaterial>)>,
+) {
+    if theme.is_changed() {
+        for (bar, _, material) in bars.iter_mut() {
+            if let Some(material) = materials.get_mut(material) {
+                material.color = theme.player_color(bar.0);
+            }
+        }
+    }
+
+    if !stats.is_changed() {
+        return;
+    }
+
+    for (bar, mut style, _) in bars.iter_mut() {
+        let width = bar_width(stats.territory_share(bar.0).unwrap_or(0.5));
+        let moved = match style.size.width {
+            Val::Px(current) => (current - width).abs() >= 1.,
+            _ => true,
+        };
+        if moved {
+            style.size.width = Val::Px(width);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::Player;
+
+/// Colors players can pick for themselves in the menu, they replace the theme's player colors.
+pub const PALETTE: [Color; 12] = [
+    Color::rgb(0.25, 0.6, 1.0),
+    Color::rgb(0.3, 0.85, 0.95),
+    Color::rgb(0.3, 0.85, 0.45),
+    Color::rgb(0.65, 0.95, 0.3),
+    Color::rgb(1.0, 0.9, 0.3),
+    Color::rgb(1.0, 0.6, 0.2),
+    Color::rgb(1.0, 0.35, 0.3),
+    Color::rgb(0.95, 0.4, 0.7),
+    Color::rgb(0.7, 0.45, 1.0),
+    Color::rgb(0.45, 0.35, 0.9),
+    Color::rgb(0.95, 0.95, 0.95),
+    Color::rgb(0.55, 0.4, 0.3),
+];
+
+/// Colors shared by every visual feature, so a palette change restyles the whole game.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub wall: Color,
+    pub left: Color,
+    pub right: Color,
+    /// Ball color from serve speed through to the speed cap.
+    pub ball_ramp: [Color; 3],
+    /// Glow behind a fast ball, alpha is the value at the speed cap.
+    pub ball_glow: Color,
+    /// Shadow under the ball, a dark theme can make it a light halo instead.
+    pub ball_shadow: Color,
+    /// Ball flash right after a power shot.
+    pub power_shot: Color,
+    /// Charged and empty pips of the power meter.
+    pub power_pip: Color,
+    pub power_pip_empty: Color,
+    pub hazard: Color,
+    /// Hazard color right after the ball bounces off it.
+    pub hazard_flash: Color,
+    /// Teleport gates, alpha is the value when fully shown.
+    pub gate: Color,
+    /// Particles of a ball bouncing off a wall and off a paddle.
+    pub spark: Color,
+    pub puff: Color,
+    /// Crown of the win streak holder when its sprite is missing.
+    pub crown: Color,
+    /// Dashes in place of the top and bottom walls in wrap-around matches.
+    pub wrap_marker: Color,
+    /// Wall bounces left and used in the half the ball is in, when bounces are limited.
+    pub bounce_pip: Color,
+    pub bounce_pip_used: Color,
+    pub button: Color,
+    pub button_hovered: Color,
+    pub button_pressed: Color,
+    pub button_text: Color,
+}
+
+impl Theme {
+    /// White on black whatever the chosen colors, for players who need the strongest contrast.
+    /// Effects that only decorate are left out.
+    pub fn high_contrast() -> Self {
+        let white = Color::rgb(1.0, 1.0, 1.0);
+        let grey = Color::rgb(0.6, 0.6, 0.6);
+        Theme {
+            wall: white,
+            left: white,
+            right: white,
+            ball_ramp: [white; 3],
+            ball_glow: Color::NONE,
+            ball_shadow: Color::NONE,
+            power_shot: grey,
+            power_pip: white,
+            power_pip_empty: Color::rgb(0.3, 0.3, 0.3),
+            hazard: white,
+            hazard_flash: grey,
+            gate: grey,
+            spark: white,
+            puff: white,
+            crown: white,
+            wrap_marker: white,
+            bounce_pip: white,
+            bounce_pip_used: Color::rgb(0.3, 0.3, 0.3),
+            ..Theme::default()
+        }
+    }
+
+    pub fn player_color(&self, player: Player) -> Color {
+        match player {
+            Player::Left => self.left,
+            Player::Right => self.right,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            wall: Color::rgb(0.0, 0.0, 0.0),
+            left: Color::rgb(0.25, 0.6, 1.0),
+            right: Color::rgb(1.0, 0.35, 0.3),
+            ball_ramp: [
+                Color::rgb(1.0, 1.0, 1.0),
+                Color::rgb(1.0, 0.9, 0.3),
+                Color::rgb(1.0, 0.3, 0.15),
+            ],
+            ball_glow: Color::rgba(1.0, 0.6, 0.2, 0.5),
+            ball_shadow: Color::rgba(0.0, 0.0, 0.0, 0.35),
+            power_shot: Color::rgb(1.0, 1.0, 1.0),
+            power_pip: Color::rgb(1.0, 0.9, 0.3),
+            power_pip_empty: Color::rgba(1.0, 1.0, 1.0, 0.2),
+            hazard: Color::rgb(0.3, 0.3, 0.35),
+            hazard_flash: Color::rgb(1.0, 1.0, 0.6),
+            gate: Color::rgba(0.6, 0.4, 1.0, 0.8),
+            spark: Color::rgb(1.0, 0.85, 0.4),
+            puff: Color::rgba(0.9, 0.9, 0.9, 0.8),
+            crown: Color::rgb(1.0, 0.85, 0.25),
+            wrap_marker: Color::rgba(1.0, 1.0, 1.0, 0.35),
+            bounce_pip: Color::rgb(0.5, 1.0, 0.7),
+            bounce_pip_used: Color::rgba(1.0, 1.0, 1.0, 0.15),
+            button: Color::rgb(0.15, 0.15, 0.15),
+            button_hovered: Color::rgb(0.25, 0.25, 0.25),
+            button_pressed: Color::rgb(0.25, 0.6, 1.0),
+            button_text: Color::rgb(0.9, 0.9, 0.9),
+        }
+    }
+}
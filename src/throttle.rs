@@ -0,0 +1,186 @@
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+
+use crate::Paused;
+
+/// Throttles the game while its window is minimized. The gameplay is paused and frames come a
+/// few times a second instead of as fast as they can, enough to notice the window coming back.
+pub struct ThrottlePlugin;
+
+impl Plugin for ThrottlePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Throttle>()
+            .add_system_to_stage(CoreStage::PreUpdate, detect_minimized.system())
+            .add_system_to_stage(CoreStage::Last, limit_frame_rate.system());
+    }
+}
+
+/// Frames per second while throttled.
+const THROTTLED_RATE: f64 = 5.;
+/// Full rate frames after the window comes back before the game is unpaused. The first can carry
+/// all the time spent minimized as its delta, the gameplay must not see it.
+const SETTLE_FRAMES: u32 = 2;
+
+#[derive(Debug)]
+pub struct Throttle {
+    active: bool,
+    /// The game was running when the window went away, unpause it when it comes back.
+    paused_game: bool,
+    settle: u32,
+    frame_start: Instant,
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Throttle {
+            active: false,
+            paused_game: false,
+            settle: 0,
+            frame_start: Instant::now(),
+        }
+    }
+}
+
+/// Winit has no minimized state to ask for, a minimized window shows up as one of zero size.
+/// Pausing rather than slowing the game keeps every gameplay timer still, they all stop on
+/// `Paused`, so nothing elapses while throttled and there is nothing to catch up on.
+fn detect_minimized(
+    windows: Res<Windows>,
+    mut throttle: ResMut<Throttle>,
+    mut paused: ResMut<Paused>,
+) {
+    let minimized = windows.get_primary().map_or(false, |window| {
+        window.physical_width() == 0 || window.physical_height() == 0
+    });
+
+    if minimized && !throttle.active {
+        throttle.active = true;
+        throttle.settle = 0;
+        throttle.paused_game = throttle.paused_game || !paused.0;
+        paused.0 = true;
+        return;
+    }
+    if !minimized && throttle.active {
+        throttle.active = false;
+        throttle.settle = SETTLE_FRAMES;
+        return;
+    }
+
+    if throttle.settle > 0 {
+        throttle.settle -= 1;
+        if throttle.settle == 0 && throttle.paused_game {
+            throttle.paused_game = false;
+            paused.0 = false;
+        }
+    }
+}
+
+/// Sleeps away the rest of each throttled frame, the menus, saving and the window keep being
+/// served at the lower rate.
+fn limit_frame_rate(mut throttle: ResMut<Throttle>) {
+    if throttle.active {
+        let frame = Duration::from_secs_f64(1. / THROTTLED_RATE);
+        if let Some(rest) = frame.checked_sub(throttle.frame_start.elapsed()) {
+            std::thread::sleep(rest);
+        }
+    }
+    throttle.frame_start = Instant::now();
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::window::WindowId;
+
+    use super::*;
+
+    fn throttled_world(paused: bool) -> (World, SystemStage) {
+        let mut world = World::default();
+        let mut windows = Windows::default();
+        windows.add(Window::new(
+            WindowId::primary(),
+            &WindowDescriptor::default(),
+            1280,
+            720,
+            1.,
+        ));
+        world.insert_resource(windows);
+        world.insert_resource(Throttle::default());
+        world.insert_resource(Paused(paused));
+        (world, SystemStage::single(detect_minimized.system()))
+    }
+
+    fn resize(world: &mut World, width: u32, height: u32) {
+        world
+            .get_resource_mut::<Windows>()
+            .unwrap()
+            .get_primary_mut()
+            .unwrap()
+            .update_actual_size_from_backend(width, height);
+    }
+
+    fn paused(world: &World) -> bool {
+        world.get_resource::<Paused>().unwrap().0
+    }
+
+    #[test]
+    fn minimizing_pauses_until_settled() {
+        let (mut world, mut stage) = throttled_world(false);
+        stage.run(&mut world);
+        assert!(!paused(&world));
+
+        resize(&mut world, 0, 0);
+        for _ in 0..3 {
+            stage.run(&mut world);
+            assert!(paused(&world));
+            assert!(world.get_resource::<Throttle>().unwrap().active);
+        }
+
+        resize(&mut world, 1280, 720);
+        stage.run(&mut world);
+        assert!(!world.get_resource::<Throttle>().unwrap().active);
+        for _ in 1..SETTLE_FRAMES {
+            assert!(paused(&world));
+            stage.run(&mut world);
+        }
+        assert!(paused(&world));
+        stage.run(&mut world);
+        assert!(!paused(&world));
+
+        // Nothing left over for the next frames
+        stage.run(&mut world);
+        assert!(!paused(&world));
+    }
+
+    #[test]
+    fn paused_game_stays_paused() {
+        let (mut world, mut stage) = throttled_world(true);
+        resize(&mut world, 1280, 0);
+        stage.run(&mut world);
+        assert!(paused(&world));
+
+        resize(&mut world, 1280, 720);
+        for _ in 0..=SETTLE_FRAMES + 1 {
+            stage.run(&mut world);
+            assert!(paused(&world));
+        }
+    }
+
+    #[test]
+    fn minimizing_again_while_settling_keeps_the_game_to_unpause() {
+        let (mut world, mut stage) = throttled_world(false);
+        resize(&mut world, 0, 0);
+        stage.run(&mut world);
+        resize(&mut world, 1280, 720);
+        stage.run(&mut world);
+        resize(&mut world, 0, 0);
+        stage.run(&mut world);
+        assert!(paused(&world));
+
+        resize(&mut world, 1280, 720);
+        for _ in 0..=SETTLE_FRAMES {
+            stage.run(&mut world);
+        }
+        assert!(!paused(&world));
+    }
+}
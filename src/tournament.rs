@@ -0,0 +1,738 @@
+use std::fs;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::bracket::{Bracket, BracketMatch, Entrant, Outcome};
+use crate::callout::Callout;
+use crate::hud;
+use crate::match_state::{MatchPhase, RestartMatch};
+use crate::persistence::SaveData;
+use crate::ready_check::ReadyCheck;
+use crate::theme::Theme;
+use crate::{AppState, GameLabel, Player, Score, UiFont};
+
+pub struct TournamentPlugin;
+
+impl Plugin for TournamentPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<Withdraw>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame)
+                    .with_system(spawn_names.system())
+                    .with_system(announce_saved_tournament.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(
+                        record_result
+                            .system()
+                            .label(GameLabel::GoalPresent)
+                            .after(GameLabel::GoalApply),
+                    )
+                    .with_system(next_match.system())
+                    .with_system(withdraw_player.system())
+                    .with_system(render_names.system()),
+            );
+    }
+}
+
+/// Every tournament match is first to this many goals.
+pub const TOURNAMENT_SCORE_LIMIT: u32 = 5;
+pub const MIN_PLAYERS: usize = 3;
+pub const MAX_PLAYERS: usize = 6;
+
+/// Where the results of the last finished tournament are written.
+const RESULTS_PATH: &str = "tournament_results.txt";
+
+/// How the host starts a tournament.
+#[derive(Debug, Clone)]
+pub enum TournamentEntry {
+    /// Round-robin between `MIN_PLAYERS` to `MAX_PLAYERS` names.
+    New(Vec<String>),
+    /// Single elimination between `MIN_PLAYERS` to `MAX_BRACKET_PLAYERS` names.
+    Elimination(Vec<String>),
+    /// Picks up the unfinished tournament kept in the save file.
+    Resume,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MatchResult {
+    left: usize,
+    right: usize,
+    left_goals: u32,
+    right_goals: u32,
+}
+
+/// Round-robin between named players sharing the keyboard. Empty when no tournament is played,
+/// matches are then regular rematches.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tournament {
+    players: Vec<String>,
+    /// Pairings in playing order as indices into `players`, the first one plays on the left.
+    schedule: Vec<(usize, usize)>,
+    results: Vec<MatchResult>,
+    /// Single elimination instead of the round-robin `schedule`, players are in seed order.
+    bracket: Option<Bracket>,
+}
+
+/// One row of the standings table.
+#[derive(Debug)]
+pub struct Standing<'a> {
+    pub name: &'a str,
+    pub played: u32,
+    pub wins: u32,
+    pub goal_difference: i32,
+}
+
+impl Tournament {
+    /// Schedules every player against every other once with the circle method, so nobody plays
+    /// two matches in a row while others wait a whole round. Sides alternate between rounds.
+    pub fn round_robin(players: Vec<String>) -> Self {
+        let mut seats: Vec<Option<usize>> = (0..players.len()).map(Some).collect();
+        if seats.len() % 2 == 1 {
+            seats.push(None);
+        }
+
+        let mut schedule = Vec::new();
+        let count = seats.len();
+        for round in 0..count - 1 {
+            for index in 0..count / 2 {
+                let pairing = (seats[index], seats[count - 1 - index]);
+                if let (Some(a), Some(b)) = pairing {
+                    schedule.push(if round % 2 == 0 { (a, b) } else { (b, a) });
+                }
+            }
+            // Everyone but the first seat moves on by one
+            let last = seats.pop().unwrap();
+            seats.insert(1, last);
+        }
+
+        Tournament {
+            players,
+            schedule,
+            results: Vec::new(),
+            bracket: None,
+        }
+    }
+
+    /// Single elimination with `players` given in seed order.
+    pub fn elimination(players: Vec<String>) -> Self {
+        Tournament {
+            bracket: Some(Bracket::new(players.len())),
+            players,
+            schedule: Vec::new(),
+            results: Vec::new(),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.players.is_empty()
+    }
+
+    pub fn is_elimination(&self) -> bool {
+        self.bracket.is_some()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match &self.bracket {
+            Some(bracket) => bracket.is_finished(),
+            None => self.is_active() && self.results.len() >= self.schedule.len(),
+        }
+    }
+
+    /// Names on the left and right paddle in the match being played.
+    pub fn pairing(&self) -> Option<(&str, &str)> {
+        let (left, right) = match &self.bracket {
+            Some(bracket) => bracket.pairing()?,
+            None => *self.schedule.get(self.results.len())?,
+        };
+        Some((&self.players[left], &self.players[right]))
+    }
+
+    pub fn name(&self, player: Player) -> Option<&str> {
+        self.pairing().map(|(left, right)| match player {
+            Player::Left => left,
+            Player::Right => right,
+        })
+    }
+
+    fn record(&mut self, left_goals: u32, right_goals: u32) {
+        if let Some(bracket) = &mut self.bracket {
+            bracket.record(left_goals, right_goals);
+            return;
+        }
+        if let Some(&(left, right)) = self.schedule.get(self.results.len()) {
+            self.results.push(MatchResult {
+                left,
+                right,
+                left_goals,
+                right_goals,
+            });
+        }
+    }
+
+    /// Takes the player on `player`'s paddle out of an elimination tournament, returns their
+    /// name. A round-robin can't do without anyone.
+    fn withdraw(&mut self, player: Player) -> Option<String> {
+        let (left, right) = self.bracket.as_ref()?.pairing()?;
+        let index = match player {
+            Player::Left => left,
+            Player::Right => right,
+        };
+        self.bracket.as_mut()?.withdraw(index);
+        Some(self.players[index].clone())
+    }
+
+    /// Plays the same tournament again from the first match.
+    fn restart(&mut self) {
+        self.results.clear();
+        if self.bracket.is_some() {
+            self.bracket = Some(Bracket::new(self.players.len()));
+        }
+    }
+
+    pub fn champion(&self) -> Option<&str> {
+        match &self.bracket {
+            Some(bracket) => bracket.champion().map(|index| self.players[index].as_str()),
+            None => self.standings().first().map(|standing| standing.name),
+        }
+    }
+
+    /// Sorted by wins, then goal difference.
+    pub fn standings(&self) -> Vec<Standing> {
+        let mut standings: Vec<Standing> = self
+            .players
+            .iter()
+            .map(|name| Standing {
+                name: name.as_str(),
+                played: 0,
+                wins: 0,
+                goal_difference: 0,
+            })
+            .collect();
+
+        for result in self.results.iter() {
+            let difference = result.left_goals as i32 - result.right_goals as i32;
+            for (index, own_difference) in [(result.left, difference), (result.right, -difference)]
+                .iter()
+                .copied()
+            {
+                let standing = &mut standings[index];
+                standing.played += 1;
+                standing.goal_difference += own_difference;
+                if own_difference > 0 {
+                    standing.wins += 1;
+                }
+            }
+        }
+
+        standings.sort_by(|a, b| {
+            b.wins
+                .cmp(&a.wins)
+                .then(b.goal_difference.cmp(&a.goal_difference))
+        });
+        standings
+    }
+
+    fn standings_lines(&self) -> Vec<String> {
+        self.standings()
+            .iter()
+            .enumerate()
+            .map(|(rank, standing)| {
+                format!(
+                    "{}. {}  played {}  wins {}  goals {:+}",
+                    rank + 1,
+                    standing.name,
+                    standing.played,
+                    standing.wins,
+                    standing.goal_difference
+                )
+            })
+            .collect()
+    }
+
+    fn entrant_name(&self, entrant: Entrant) -> &str {
+        match entrant {
+            Entrant::Player(index) => &self.players[index],
+            Entrant::Bye => "bye",
+            Entrant::Pending => "?",
+        }
+    }
+
+    /// The two lines of a bracket match, with the goals or how it was decided once it is.
+    fn bracket_match_lines(&self, bracket_match: &BracketMatch) -> (String, String) {
+        let left = self.entrant_name(bracket_match.left);
+        let right = self.entrant_name(bracket_match.right);
+        match bracket_match.decided.map(|decided| decided.outcome) {
+            Some(Outcome::Played {
+                left_goals,
+                right_goals,
+            }) => (
+                format!("{}  {}", left, left_goals),
+                format!("{}  {}", right, right_goals),
+            ),
+            Some(Outcome::Walkover) => {
+                let winner = bracket_match
+                    .decided
+                    .map(|decided| Entrant::Player(decided.winner));
+                let mark = |entrant: Entrant| {
+                    if Some(entrant) == winner {
+                        "w/o"
+                    } else {
+                        "withdrew"
+                    }
+                };
+                (
+                    format!("{}  {}", left, mark(bracket_match.left)),
+                    format!("{}  {}", right, mark(bracket_match.right)),
+                )
+            }
+            Some(Outcome::Bye) | None => (left.to_string(), right.to_string()),
+        }
+    }
+
+    /// Every match and the final table or champion, as written to `RESULTS_PATH`.
+    fn results_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        match &self.bracket {
+            Some(bracket) => {
+                lines.push("Single elimination".to_string());
+                let rounds = bracket.matches();
+                for (round, matches) in rounds.iter().enumerate() {
+                    lines.push(String::new());
+                    lines.push(round_name(round, rounds.len()));
+                    for bracket_match in matches.iter() {
+                        let (left, right) = self.bracket_match_lines(bracket_match);
+                        lines.push(format!("  {}  vs  {}", left, right));
+                    }
+                }
+            }
+            None => {
+                lines.push("Round-robin".to_string());
+                lines.push(String::new());
+                for result in self.results.iter() {
+                    lines.push(format!(
+                        "  {} {} - {} {}",
+                        self.players[result.left],
+                        result.left_goals,
+                        result.right_goals,
+                        self.players[result.right]
+                    ));
+                }
+                lines.push(String::new());
+                lines.extend(self.standings_lines());
+            }
+        }
+        if let Some(champion) = self.champion() {
+            lines.push(String::new());
+            lines.push(format!("Champion: {}", champion));
+        }
+        lines
+    }
+
+    fn export_results(&self) -> Result<(), String> {
+        let mut content = self.results_lines().join("\n");
+        content.push('\n');
+        fs::write(RESULTS_PATH, content)
+            .map_err(|err| format!("Failed to write {}: {}", RESULTS_PATH, err))
+    }
+}
+
+/// Final, semifinal and quarterfinal counted from the last round.
+fn round_name(round: usize, rounds: usize) -> String {
+    match rounds - round {
+        1 => "Final".to_string(),
+        2 => "Semifinals".to_string(),
+        3 => "Quarterfinals".to_string(),
+        _ => format!("Round {}", round + 1),
+    }
+}
+
+/// Orders `players` by the win rate of the profile with their name, best first. Players without
+/// a profile or a finished match come last, in the order given.
+pub fn seed(mut players: Vec<String>, save: &SaveData) -> Vec<String> {
+    let win_rate = |name: &String| {
+        save.profiles
+            .iter()
+            .find(|profile| profile.name == *name && profile.stats.matches > 0)
+            .map_or(-1., |profile| {
+                profile.stats.wins as f32 / profile.stats.matches as f32
+            })
+    };
+    players.sort_by(|a, b| {
+        win_rate(b)
+            .partial_cmp(&win_rate(a))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    players
+}
+
+/// Sent by the pause menu when the player on a paddle quits an elimination tournament.
+pub struct Withdraw(pub Player);
+
+/// Name of the player on each paddle, above their score.
+struct NameLabel(Player);
+
+/// Top of the names as a fraction of the window height.
+const NAME_TOP: f32 = 1. / 3.;
+
+/// Standings shown between matches.
+struct StandingsText;
+
+fn spawn_names(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    font: Res<UiFont>,
+    theme: Res<Theme>,
+    tournament: Res<Tournament>,
+) {
+    if !tournament.is_active() {
+        return;
+    }
+
+    for player in [Player::Left, Player::Right].iter() {
+        commands
+            .spawn_bundle(NodeBundle {
+                style: hud::half_row(*player, NAME_TOP),
+                material: materials.add(Color::NONE.into()),
+                ..Default::default()
+            })
+            .with_children(|parent| {
+                parent
+                    .spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            tournament.name(*player).unwrap_or_default().to_string(),
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 32.0,
+                                color: theme.player_color(*player),
+                            },
+                            Default::default(),
+                        ),
+                        ..Default::default()
+                    })
+                    .insert(NameLabel(*player));
+            });
+    }
+}
+
+fn render_names(
+    tournament: Res<Tournament>,
+    theme: Res<Theme>,
+    mut labels: Query<(&NameLabel, &mut Text)>,
+) {
+    if !tournament.is_changed() && !theme.is_changed() {
+        return;
+    }
+
+    for (label, mut text) in labels.iter_mut() {
+        let section = &mut text.sections[0];
+        // The names of the last match stay up with the final standings
+        if let Some(name) = tournament.name(label.0) {
+            section.value = name.to_string();
+        }
+        section.style.color = theme.player_color(label.0);
+    }
+}
+
+/// The intro splash names the players of the first match, only point out a tournament left
+/// unfinished in the save file.
+fn announce_saved_tournament(
+    tournament: Res<Tournament>,
+    save: Res<SaveData>,
+    mut callouts: EventWriter<Callout>,
+) {
+    if !tournament.is_active() && save.tournament.is_some() {
+        callouts.send(Callout(
+            "Unfinished tournament saved, resume it with --resume-tournament".to_string(),
+        ));
+    }
+}
+
+/// Books the finished match, keeps the tournament in the save file so it can be resumed after
+/// quitting, and puts the standings or the bracket up until the next match starts.
+fn record_result(
+    mut commands: Commands,
+    phase: Res<MatchPhase>,
+    score: Res<Score>,
+    font: Res<UiFont>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut tournament: ResMut<Tournament>,
+    mut save: ResMut<SaveData>,
+    mut callouts: EventWriter<Callout>,
+) {
+    if !phase.is_changed() || !phase.is_finished() || !tournament.is_active() {
+        return;
+    }
+
+    tournament.record(score.left, score.right);
+    let mut lines = if tournament.is_elimination() {
+        Vec::new()
+    } else {
+        tournament.standings_lines()
+    };
+    for line in lines.iter() {
+        info!("Standings: {}", line);
+    }
+
+    let footer = match tournament.pairing() {
+        Some((left, right)) => format!("Next: {} vs {}, press Enter", left, right),
+        None => {
+            finish(&tournament, &mut callouts);
+            "Press Enter to play the tournament again".to_string()
+        }
+    };
+
+    save.tournament = if tournament.is_finished() {
+        None
+    } else {
+        Some(tournament.clone())
+    };
+    save.save();
+
+    if tournament.is_elimination() {
+        spawn_bracket(&mut commands, &mut materials, &font, &tournament, footer);
+        return;
+    }
+
+    lines.push(String::new());
+    lines.push(footer);
+    commands
+        .spawn_bundle(TextBundle {
+            text: Text::with_section(
+                lines.join("\n"),
+                TextStyle {
+                    font: font.0.clone(),
+                    font_size: 24.0,
+                    color: Color::rgb(1.0, 1.0, 1.0),
+                },
+                Default::default(),
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: hud::percent(1. / 6.),
+                    left: hud::percent(0.3),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(StandingsText);
+}
+
+/// Names the champion and writes the results out.
+fn finish(tournament: &Tournament, callouts: &mut EventWriter<Callout>) {
+    for line in tournament.results_lines() {
+        info!("Tournament: {}", line);
+    }
+    if let Some(champion) = tournament.champion() {
+        callouts.send(Callout(format!("{} wins the tournament!", champion)));
+    }
+    match tournament.export_results() {
+        Ok(()) => callouts.send(Callout(format!("Results saved to {}", RESULTS_PATH))),
+        Err(err) => {
+            warn!("{}", err);
+            callouts.send(Callout(err));
+        }
+    }
+}
+
+/// The bracket as a tree, a column per round from the first to the final. Each column spreads
+/// its matches evenly, so every match sits between the two it is fed by.
+fn spawn_bracket(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    font: &UiFont,
+    tournament: &Tournament,
+    footer: String,
+) {
+    let bracket = match &tournament.bracket {
+        Some(bracket) => bracket,
+        None => return,
+    };
+    let rounds = bracket.matches();
+    let none = materials.add(Color::NONE.into());
+    let style = |size: f32| TextStyle {
+        font: font.0.clone(),
+        font_size: size,
+        color: Color::rgb(1.0, 1.0, 1.0),
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: hud::percent(0.1),
+                    top: hud::percent(0.1),
+                    ..Default::default()
+                },
+                size: Size::new(hud::percent(0.8), hud::percent(0.8)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.7).into()),
+            ..Default::default()
+        })
+        .insert(StandingsText)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.), Val::Percent(85.)),
+                        flex_direction: FlexDirection::Row,
+                        ..Default::default()
+                    },
+                    material: none.clone(),
+                    ..Default::default()
+                })
+                .with_children(|row| {
+                    for (round, matches) in rounds.iter().enumerate() {
+                        row.spawn_bundle(NodeBundle {
+                            style: Style {
+                                size: Size::new(
+                                    Val::Percent(100. / rounds.len() as f32),
+                                    Val::Percent(100.),
+                                ),
+                                flex_direction: FlexDirection::ColumnReverse,
+                                justify_content: JustifyContent::SpaceAround,
+                                align_items: AlignItems::Center,
+                                ..Default::default()
+                            },
+                            material: none.clone(),
+                            ..Default::default()
+                        })
+                        .with_children(|column| {
+                            column.spawn_bundle(TextBundle {
+                                text: Text::with_section(
+                                    round_name(round, rounds.len()),
+                                    style(24.0),
+                                    Default::default(),
+                                ),
+                                ..Default::default()
+                            });
+                            for bracket_match in matches.iter() {
+                                let (left, right) = tournament.bracket_match_lines(bracket_match);
+                                column.spawn_bundle(TextBundle {
+                                    text: Text::with_section(
+                                        format!("{}\n{}", left, right),
+                                        style(20.0),
+                                        Default::default(),
+                                    ),
+                                    ..Default::default()
+                                });
+                            }
+                        });
+                    }
+                });
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(footer, style(24.0), Default::default()),
+                ..Default::default()
+            });
+        });
+}
+
+/// The rematch key starts the next pairing, after the last one it starts over. Standings are
+/// only up between matches, so a match starting while they are shown is the next one. The new
+/// players ready up before its first serve.
+fn next_match(
+    mut commands: Commands,
+    phase: Res<MatchPhase>,
+    mut tournament: ResMut<Tournament>,
+    mut save: ResMut<SaveData>,
+    mut ready_check: ResMut<ReadyCheck>,
+    mut callouts: EventWriter<Callout>,
+    texts: Query<Entity, With<StandingsText>>,
+) {
+    if phase.is_finished() || texts.iter().next().is_none() {
+        return;
+    }
+
+    for entity in texts.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    if tournament.is_finished() {
+        tournament.restart();
+        save.tournament = Some(tournament.clone());
+        save.save();
+    }
+
+    if let Some((left, right)) = tournament.pairing() {
+        callouts.send(Callout(format!("{} vs {}", left, right)));
+    }
+    ready_check.request();
+}
+
+/// A withdrawal decides the match being played, the next one starts over from the serve. A
+/// final decided that way finishes the tournament, which starts again.
+fn withdraw_player(
+    mut tournament: ResMut<Tournament>,
+    mut save: ResMut<SaveData>,
+    mut ready_check: ResMut<ReadyCheck>,
+    mut withdrawals: EventReader<Withdraw>,
+    mut restarts: EventWriter<RestartMatch>,
+    mut callouts: EventWriter<Callout>,
+) {
+    for withdrawal in withdrawals.iter() {
+        let name = match tournament.withdraw(withdrawal.0) {
+            Some(name) => name,
+            None => continue,
+        };
+        callouts.send(Callout(format!("{} withdraws", name)));
+
+        if tournament.is_finished() {
+            finish(&tournament, &mut callouts);
+            tournament.restart();
+        }
+        save.tournament = Some(tournament.clone());
+        save.save();
+
+        if let Some((left, right)) = tournament.pairing() {
+            callouts.send(Callout(format!("{} vs {}", left, right)));
+        }
+        restarts.send(RestartMatch);
+        ready_check.request();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::startup::StartupReport;
+
+    fn players(count: usize) -> Vec<String> {
+        (0..count).map(|index| format!("P{}", index)).collect()
+    }
+
+    #[test]
+    fn bracket_survives_save_and_load() {
+        // Three players give the top seed a bye, then the other two play
+        let mut tournament = Tournament::elimination(players(3));
+        assert_eq!(tournament.pairing(), Some(("P1", "P2")));
+        tournament.record(2, 5);
+        assert_eq!(tournament.pairing(), Some(("P0", "P2")));
+
+        let save = SaveData {
+            tournament: Some(tournament.clone()),
+            ..Default::default()
+        };
+        let content = ron::ser::to_string_pretty(&save, Default::default()).unwrap();
+        let mut report = StartupReport::default();
+        let loaded = SaveData::parse(&content, &mut report)
+            .tournament
+            .expect("tournament dropped on load");
+
+        assert!(report.pending().is_empty(), "{:?}", report.pending());
+        assert_eq!(loaded.pairing(), tournament.pairing());
+        assert_eq!(
+            loaded.bracket.as_ref().map(Bracket::matches),
+            tournament.bracket.as_ref().map(Bracket::matches)
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{ARENA_HEIGHT, PADDLE_HEIGHT, PADDLE_WIDTH};
+
+/// Named bundle of tuning values picked before launch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Preset {
+    Standard,
+    /// Bigger and slower ball, larger paddles and a narrower goal.
+    Kid,
+}
+
+impl Default for Preset {
+    fn default() -> Self {
+        Preset::Standard
+    }
+}
+
+/// Gameplay numbers in physics units, kept in one place so modes can adjust them.
+/// Sizes are in pixels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tuning {
+    pub serve_speed: f32,
+    pub max_ball_speed: f32,
+    /// Serve speed multiplier on the serves to the opponent of a player on a scoring streak.
+    pub streak_serve_bonus: f32,
+    pub ball_size: f32,
+    pub paddle_width: f32,
+    pub paddle_height: f32,
+    pub paddle_rotation: bool,
+    /// Paddle speed multiplier for the receiver right after a serve.
+    pub receiver_boost: f32,
+    pub receiver_boost_seconds: f32,
+    /// Cap on the combined paddle speed multipliers.
+    pub max_paddle_speed_factor: f32,
+    /// Fraction of the paddle's velocity added to the ball when it hits it.
+    pub paddle_carry: f32,
+    /// Velocity carried into a hit from which it counts as a pushed shot.
+    pub pushed_shot_threshold: f32,
+    /// Paddle speed under which a hit is a clean block that keeps the ball's speed.
+    pub block_speed: f32,
+    /// Paddle speed over `block_speed` across which a block turns into a push.
+    pub block_blend: f32,
+    /// Ball speed after a clean block, as a multiple of its speed coming in.
+    pub block_rebound: f32,
+    /// Seconds after a paddle hits the top or bottom limit in which reversing wall-jumps.
+    pub wall_jump_window: f32,
+    /// Paddle speed multiplier of a wall-jump.
+    pub wall_jump_boost: f32,
+    pub wall_jump_seconds: f32,
+    /// Restitution of the top and bottom walls right next to the goals, full in the middle.
+    pub wall_edge_restitution: f32,
+    /// Part of the top and bottom walls at each end over which their bounce falls off.
+    pub wall_edge_fraction: f32,
+    /// Downward pull on the ball in the left half when gravity zones are on.
+    pub zone_gravity_left: f32,
+    /// Upward pull on the ball in the right half when gravity zones are on.
+    pub zone_gravity_right: f32,
+    /// Height of the goal opening, `None` leaves the whole arena edge open.
+    pub goal_mouth: Option<f32>,
+    /// How well a computer player plays.
+    pub ai_skill: AiSkill,
+    /// How an adaptive computer player changes its skill with the score.
+    pub ai_adaptive: AdaptiveCurve,
+    /// Seconds without input before the computer takes over a human paddle.
+    pub idle_takeover_seconds: f32,
+    /// Paddle hits in a rally after which wall bounces start losing bounce.
+    pub long_rally_hits: u32,
+    /// Bounce lost off the walls for every hit past `long_rally_hits`, 0 turns it off.
+    pub long_rally_decay: f32,
+}
+
+impl Tuning {
+    pub fn for_preset(preset: Preset) -> Self {
+        let standard = Tuning {
+            serve_speed: 20.0,
+            max_ball_speed: 60.0,
+            streak_serve_bonus: 1.15,
+            ball_size: 40.0,
+            paddle_width: PADDLE_WIDTH,
+            paddle_height: PADDLE_HEIGHT,
+            paddle_rotation: true,
+            receiver_boost: 1.3,
+            receiver_boost_seconds: 1.5,
+            max_paddle_speed_factor: 1.5,
+            paddle_carry: 0.4,
+            pushed_shot_threshold: 5.0,
+            block_speed: 2.0,
+            block_blend: 4.0,
+            block_rebound: 1.1,
+            wall_jump_window: 0.1,
+            wall_jump_boost: 1.5,
+            wall_jump_seconds: 0.25,
+            wall_edge_restitution: 0.6,
+            wall_edge_fraction: 0.2,
+            zone_gravity_left: 4.0,
+            zone_gravity_right: 4.0,
+            goal_mouth: None,
+            ai_skill: AiSkill {
+                reaction_seconds: 0.0,
+                miss_chance: 0.0,
+            },
+            ai_adaptive: AdaptiveCurve {
+                reaction_per_goal: 0.05,
+                miss_chance_per_goal: 0.05,
+                max_goals: 5,
+            },
+            idle_takeover_seconds: 10.0,
+            long_rally_hits: 15,
+            long_rally_decay: 0.0,
+        };
+
+        match preset {
+            Preset::Standard => standard,
+            Preset::Kid => Tuning {
+                serve_speed: standard.serve_speed / 2.,
+                max_ball_speed: standard.max_ball_speed / 2.,
+                ball_size: 70.0,
+                paddle_width: 25.0,
+                paddle_height: 160.0,
+                paddle_rotation: false,
+                goal_mouth: Some(ARENA_HEIGHT * 0.5),
+                ai_skill: AiSkill {
+                    miss_chance: 0.3,
+                    ..standard.ai_skill
+                },
+                ..standard
+            },
+        }
+    }
+}
+
+/// Colliders each end of the top and bottom walls is split into, a single collider only has one
+/// restitution.
+const WALL_EDGE_SEGMENTS: usize = 4;
+/// Least bounce a long rally wears wall bounces down to.
+const LONG_RALLY_FLOOR: f32 = 0.5;
+
+impl Tuning {
+    /// Restitution of the top and bottom walls `along` their length, from 0 at the left goal to
+    /// 1 at the right one. Full in the middle, falling off linearly toward each goal so balls
+    /// hugging the wall there lose speed.
+    fn wall_restitution(&self, along: f32) -> f32 {
+        let from_goal = along.min(1. - along);
+        if from_goal >= self.wall_edge_fraction {
+            return 1.;
+        }
+        let t = from_goal / self.wall_edge_fraction;
+        self.wall_edge_restitution + (1. - self.wall_edge_restitution) * t
+    }
+
+    /// Share of its bounce the ball keeps off a wall in a rally of `rally` paddle hits, 1 until
+    /// the rally gets long. Never below `LONG_RALLY_FLOOR`, so the ball keeps moving.
+    pub fn long_rally_bounce(&self, rally: u32) -> f32 {
+        let extra = rally.saturating_sub(self.long_rally_hits) as f32;
+        (1. - extra * self.long_rally_decay).max(LONG_RALLY_FLOOR)
+    }
+
+    /// Segments of the top and bottom walls as `(start, end, restitution)`, in fractions of their
+    /// length. Each takes the restitution at its middle, the middle segment has the full bounce.
+    pub fn wall_segments(&self) -> Vec<(f32, f32, f32)> {
+        let edge = self.wall_edge_fraction;
+        if edge <= 0. || self.wall_edge_restitution == 1. {
+            return vec![(0., 1., 1.)];
+        }
+
+        let step = edge / WALL_EDGE_SEGMENTS as f32;
+        let mut bounds: Vec<f32> = (0..=WALL_EDGE_SEGMENTS)
+            .map(|index| index as f32 * step)
+            .collect();
+        bounds.extend(
+            (0..=WALL_EDGE_SEGMENTS)
+                .rev()
+                .map(|index| 1. - index as f32 * step),
+        );
+
+        bounds
+            .windows(2)
+            .map(|span| {
+                let (start, end) = (span[0], span[1]);
+                (start, end, self.wall_restitution((start + end) / 2.))
+            })
+            .collect()
+    }
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Tuning::for_preset(Preset::Standard)
+    }
+}
+
+/// How well a computer player plays.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct AiSkill {
+    /// Seconds between the ball turning toward the paddle and the paddle going after it.
+    pub reaction_seconds: f32,
+    /// Fraction of returns deliberately missed.
+    pub miss_chance: f32,
+}
+
+/// Handicap of the adaptive computer player. For every goal it leads by it reacts slower and
+/// misses more often, for every goal it trails by it plays that much better.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AdaptiveCurve {
+    pub reaction_per_goal: f32,
+    pub miss_chance_per_goal: f32,
+    /// Leads and deficits beyond this many goals change nothing more.
+    pub max_goals: u32,
+}
+
+impl AiSkill {
+    /// Skill of an adaptive computer player leading by `lead` goals, negative when behind.
+    pub fn adapted(self, curve: &AdaptiveCurve, lead: i32) -> Self {
+        let max_goals = curve.max_goals as i32;
+        let goals = lead.max(-max_goals).min(max_goals) as f32;
+        AiSkill {
+            reaction_seconds: (self.reaction_seconds + curve.reaction_per_goal * goals).max(0.),
+            miss_chance: (self.miss_chance + curve.miss_chance_per_goal * goals)
+                .max(0.)
+                .min(1.),
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use bevy::prelude::*;
+
+use crate::bindings::{key_name, KeyBindings, PlayerKeys};
+use crate::match_state::MatchRules;
+use crate::persistence::SaveData;
+use crate::tuning::Tuning;
+use crate::{Paused, UiFont, ARENA_HEIGHT, ARENA_WIDTH, PADDLE_WALL_OFFSET};
+
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Tutorial>()
+            .add_startup_system(request_on_first_run.system())
+            .add_system(tutorial_input.system().label("tutorial_input"))
+            .add_system(spawn_tutorial.system().after("tutorial_input"));
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Tutorial {
+    requested: bool,
+    open: bool,
+    paused_game: bool,
+}
+
+impl Tutorial {
+    pub fn is_open(&self) -> bool {
+        self.open || self.requested
+    }
+
+    /// Opens the overlay on the next frame.
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+}
+
+/// Root of the overlay, despawned recursively on dismiss.
+struct TutorialOverlay;
+
+const OPEN_KEY: KeyCode = KeyCode::F1;
+
+fn request_on_first_run(save: Res<SaveData>, mut tutorial: ResMut<Tutorial>) {
+    if !save.tutorial_seen {
+        tutorial.requested = true;
+    }
+}
+
+fn tutorial_input(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut tutorial: ResMut<Tutorial>,
+    mut paused: ResMut<Paused>,
+    mut save: ResMut<SaveData>,
+    overlay: Query<Entity, With<TutorialOverlay>>,
+) {
+    if tutorial.open {
+        if keyboard_input.get_just_pressed().next().is_none() {
+            return;
+        }
+
+        for entity in overlay.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+
+        if tutorial.paused_game {
+            paused.0 = false;
+        }
+        tutorial.open = false;
+        tutorial.paused_game = false;
+
+        if !save.tutorial_seen {
+            save.tutorial_seen = true;
+            save.save();
+        }
+    } else if keyboard_input.just_pressed(OPEN_KEY) {
+        tutorial.requested = true;
+    }
+}
+
+fn spawn_tutorial(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut tutorial: ResMut<Tutorial>,
+    mut paused: ResMut<Paused>,
+    bindings: Res<KeyBindings>,
+    tuning: Res<Tuning>,
+    rules: Res<MatchRules>,
+    font: Option<Res<UiFont>>,
+) {
+    if !tutorial.requested || tutorial.open {
+        return;
+    }
+
+    // The font resource is inserted by a startup system, wait for it
+    let font = match font {
+        Some(font) => font.0.clone(),
+        None => return,
+    };
+
+    tutorial.requested = false;
+    tutorial.open = true;
+    tutorial.paused_game = !paused.0;
+    paused.0 = true;
+
+    let backdrop = materials.add(Color::rgba(0.0, 0.0, 0.0, 0.85).into());
+    let arena = materials.add(Color::rgb(0.4, 0.4, 0.4).into());
+    let paddle = materials.add(Color::rgb(0.0, 0.0, 0.0).into());
+    let transparent = materials.add(Color::NONE.into());
+
+    let white = Color::rgb(1.0, 1.0, 1.0);
+    let title_size = ARENA_HEIGHT / 12.;
+    let text_size = ARENA_HEIGHT / 24.;
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(0.),
+                    top: Val::Px(0.),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: backdrop,
+            ..Default::default()
+        })
+        .insert(TutorialOverlay)
+        .with_children(|parent| {
+            parent.spawn_bundle(text_line(&font, "How to play", title_size, white));
+
+            // Miniature arena, everything positioned relative to the real arena
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(40.), Val::Percent(40.)),
+                        margin: Rect::all(Val::Percent(2.)),
+                        ..Default::default()
+                    },
+                    material: arena,
+                    ..Default::default()
+                })
+                .with_children(|arena| {
+                    let (paddle_width, paddle_height) = (tuning.paddle_width, tuning.paddle_height);
+                    let width = Val::Percent(paddle_width / ARENA_WIDTH * 100.);
+                    let height = Val::Percent(paddle_height / ARENA_HEIGHT * 100.);
+                    let offset =
+                        Val::Percent((PADDLE_WALL_OFFSET - paddle_width / 2.) / ARENA_WIDTH * 100.);
+                    let top =
+                        Val::Percent((ARENA_HEIGHT - paddle_height) / 2. / ARENA_HEIGHT * 100.);
+
+                    for left_side in [true, false].iter() {
+                        let position = if *left_side {
+                            Rect {
+                                left: offset,
+                                top,
+                                ..Default::default()
+                            }
+                        } else {
+                            Rect {
+                                right: offset,
+                                top,
+                                ..Default::default()
+                            }
+                        };
+
+                        arena.spawn_bundle(NodeBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                position,
+                                size: Size::new(width, height),
+                                ..Default::default()
+                            },
+                            material: paddle.clone(),
+                            ..Default::default()
+                        });
+                    }
+                });
+
+            // Key hints, one column per player
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(80.), Val::Auto),
+                        justify_content: JustifyContent::SpaceAround,
+                        ..Default::default()
+                    },
+                    material: transparent.clone(),
+                    ..Default::default()
+                })
+                .with_children(|columns| {
+                    for (name, keys) in [
+                        ("Left player", &bindings.left),
+                        ("Right player", &bindings.right),
+                    ]
+                    .iter()
+                    {
+                        columns
+                            .spawn_bundle(NodeBundle {
+                                style: Style {
+                                    flex_direction: FlexDirection::ColumnReverse,
+                                    align_items: AlignItems::Center,
+                                    ..Default::default()
+                                },
+                                material: transparent.clone(),
+                                ..Default::default()
+                            })
+                            .with_children(|column| {
+                                for line in hint_lines(
+                                    name,
+                                    keys,
+                                    tuning.paddle_rotation,
+                                    rules.catch_and_throw,
+                                )
+                                .iter()
+                                {
+                                    column.spawn_bundle(text_line(&font, line, text_size, white));
+                                }
+                            });
+                    }
+                });
+
+            parent.spawn_bundle(text_line(
+                &font,
+                "Press any key to continue",
+                text_size,
+                Color::rgb(0.8, 0.8, 0.8),
+            ));
+        });
+}
+
+fn hint_lines(name: &str, keys: &PlayerKeys, rotation: bool, catch: bool) -> Vec<String> {
+    let mut lines = vec![
+        name.to_string(),
+        format!(
+            "Move: {} {} {} {}",
+            key_name(keys.up),
+            key_name(keys.left),
+            key_name(keys.down),
+            key_name(keys.right)
+        ),
+    ];
+
+    if rotation {
+        lines.push(format!(
+            "Rotate: {} {}",
+            key_name(keys.rotate_left),
+            key_name(keys.rotate_right)
+        ));
+    }
+
+    lines.push(format!("Power shot: {}", key_name(keys.power)));
+    if catch {
+        lines.push(format!(
+            "Catch: hold {} as the ball hits, let go to throw",
+            key_name(keys.catch)
+        ));
+    }
+    lines.push(format!(
+        "Serving after conceding: hold {} to lob, {} to serve flat",
+        key_name(keys.up),
+        key_name(keys.down)
+    ));
+    lines.push("Paddles move freely within their own half".to_string());
+    lines
+}
+
+fn text_line(font: &Handle<Font>, value: &str, font_size: f32, color: Color) -> TextBundle {
+    TextBundle {
+        text: Text::with_section(
+            value.to_string(),
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color,
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,150 @@
+use bevy::prelude::*;
+
+use crate::input::PaddleIntent;
+use crate::theme::Theme;
+use crate::tuning::Tuning;
+use crate::{AppState, GameLabel, LimitAxis, PaddleHitLimit, Paused, Player};
+
+pub struct WallJumpPlugin;
+
+impl Plugin for WallJumpPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(
+                    record_wall_hits
+                        .system()
+                        .label("record_wall_hits")
+                        .after(GameLabel::Input),
+                )
+                .with_system(
+                    wall_jump
+                        .system()
+                        .label("wall_jump")
+                        .after("record_wall_hits"),
+                )
+                .with_system(flash_wall_jump.system().after("wall_jump")),
+        );
+    }
+}
+
+/// How long the confirmation flash takes to fade.
+const FLASH_SECONDS: f32 = 0.15;
+/// Flash size relative to the paddle.
+const FLASH_SCALE: f32 = 1.4;
+
+/// A paddle that just hit its top or bottom limit, and which way it was driven.
+struct WallHit {
+    direction: f32,
+    elapsed: f32,
+}
+
+/// Speeds a paddle away from the wall it bounced off, combined with the other paddle speed
+/// multipliers under the same cap.
+pub struct WallJump {
+    pub multiplier: f32,
+    direction: f32,
+    timer: Timer,
+}
+
+/// Short flash child confirming a wall-jump.
+struct WallJumpFlash {
+    elapsed: f32,
+}
+
+fn record_wall_hits(
+    mut commands: Commands,
+    mut limits: EventReader<PaddleHitLimit>,
+    intents: Query<&PaddleIntent>,
+) {
+    for limit in limits.iter() {
+        if limit.axis != LimitAxis::Y {
+            continue;
+        }
+
+        if let Ok(intent) = intents.get(limit.paddle) {
+            if intent.movement.y != 0. {
+                commands.entity(limit.paddle).insert(WallHit {
+                    direction: intent.movement.y.signum(),
+                    elapsed: 0.,
+                });
+            }
+        }
+    }
+}
+
+/// Starts a wall-jump when the player reverses within the window after hitting the wall, and
+/// ends it when the time is up or the paddle stops moving away from the wall.
+fn wall_jump(
+    mut commands: Commands,
+    time: Res<Time>,
+    paused: Res<Paused>,
+    tuning: Res<Tuning>,
+    mut hits: Query<(Entity, &PaddleIntent, &mut WallHit)>,
+    mut jumps: Query<(Entity, &PaddleIntent, &mut WallJump)>,
+) {
+    if paused.0 {
+        return;
+    }
+
+    for (entity, intent, mut hit) in hits.iter_mut() {
+        hit.elapsed += time.delta_seconds();
+        if intent.movement.y * hit.direction < 0. {
+            commands
+                .entity(entity)
+                .remove::<WallHit>()
+                .insert(WallJump {
+                    multiplier: tuning.wall_jump_boost,
+                    direction: -hit.direction,
+                    timer: Timer::from_seconds(tuning.wall_jump_seconds, false),
+                });
+        } else if hit.elapsed > tuning.wall_jump_window {
+            commands.entity(entity).remove::<WallHit>();
+        }
+    }
+
+    for (entity, intent, mut jump) in jumps.iter_mut() {
+        jump.timer.tick(time.delta());
+        if jump.timer.finished() || intent.movement.y * jump.direction <= 0. {
+            commands.entity(entity).remove::<WallJump>();
+        }
+    }
+}
+
+fn flash_wall_jump(
+    mut commands: Commands,
+    time: Res<Time>,
+    tuning: Res<Tuning>,
+    theme: Res<Theme>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    jumped: Query<(Entity, &Player, &Sprite), Added<WallJump>>,
+    mut flashes: Query<(Entity, &mut WallJumpFlash, &Handle<ColorMaterial>)>,
+) {
+    for (paddle, player, sprite) in jumped.iter() {
+        let mut color = theme.player_color(*player);
+        color.set_a(0.6);
+        let size = Vec2::new(tuning.paddle_width, sprite.size.y) * FLASH_SCALE;
+
+        commands.entity(paddle).with_children(|parent| {
+            parent
+                .spawn_bundle(SpriteBundle {
+                    material: materials.add(color.into()),
+                    sprite: Sprite::new(size),
+                    transform: Transform::from_xyz(0., 0., -0.05),
+                    ..Default::default()
+                })
+                .insert(WallJumpFlash { elapsed: 0. });
+        });
+    }
+
+    for (entity, mut flash, material) in flashes.iter_mut() {
+        flash.elapsed += time.delta_seconds();
+        if flash.elapsed >= FLASH_SECONDS {
+            commands.entity(entity).despawn();
+        } else if let Some(material) = materials.get_mut(material) {
+            material
+                .color
+                .set_a(0.6 * (1. - flash.elapsed / FLASH_SECONDS));
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+
+use crate::accessibility::Accessibility;
+use crate::daily::DailyChallenge;
+use crate::hud;
+use crate::match_state::{MatchPhase, MatchRules};
+use crate::stats::{win_probability, MatchStats};
+use crate::theme::Theme;
+use crate::tournament::Tournament;
+use crate::{AppState, GameLabel, Player, Score};
+
+/// A bar under the scores with each player's chance of winning the match, just for fun.
+pub struct WinMeterPlugin;
+
+impl Plugin for WinMeterPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<WinMeter>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame).with_system(spawn_win_meter.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(
+                        estimate_win_chance
+                            .system()
+                            .label("estimate_win_chance")
+                            .after(GameLabel::GoalApply),
+                    )
+                    .with_system(render_win_meter.system().after("estimate_win_chance")),
+            );
+    }
+}
+
+const BAR_WIDTH: f32 = 200.;
+const BAR_HEIGHT: f32 = 6.;
+/// Right under the scores, above the territory bar, as a fraction of the window height.
+const BAR_TOP: f32 = 0.6;
+/// How quickly the bar closes in on a new estimate, per second.
+const EASE_RATE: f32 = 3.;
+
+#[derive(Debug)]
+struct WinMeter {
+    /// Chance the left player wins, as estimated after the latest point.
+    target: f32,
+    /// Chance the bar shows, easing toward `target`.
+    shown: f32,
+}
+
+impl Default for WinMeter {
+    fn default() -> Self {
+        WinMeter {
+            target: 0.5,
+            shown: 0.5,
+        }
+    }
+}
+
+struct WinMeterRow;
+
+/// One player's part of the bar, left first so the two fill it from the left edge.
+struct WinBar(Player);
+
+fn spawn_win_meter(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    theme: Res<Theme>,
+    mut meter: ResMut<WinMeter>,
+) {
+    *meter = WinMeter::default();
+
+    let none = materials.add(Color::NONE.into());
+    commands
+        .spawn_bundle(NodeBundle {
+            style: hud::full_row(BAR_TOP),
+            material: none.clone(),
+            ..Default::default()
+        })
+        .insert(WinMeterRow)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(BAR_WIDTH), Val::Px(BAR_HEIGHT)),
+                        ..Default::default()
+                    },
+                    material: none,
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for player in [Player::Left, Player::Right].iter() {
+                        parent
+                            .spawn_bundle(NodeBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(BAR_WIDTH / 2.), Val::Px(BAR_HEIGHT)),
+                                    ..Default::default()
+                                },
+                                material: materials.add(theme.player_color(*player).into()),
+                                ..Default::default()
+                            })
+                            .insert(WinBar(*player));
+                    }
+                });
+        });
+}
+
+/// Only after a point, or a rematch, the bar shouldn't drift while the ball is in play. The
+/// player who conceded the latest point serves the next one.
+fn estimate_win_chance(
+    score: Res<Score>,
+    phase: Res<MatchPhase>,
+    rules: Res<MatchRules>,
+    stats: Res<MatchStats>,
+    mut meter: ResMut<WinMeter>,
+) {
+    if !score.is_changed() && !phase.is_changed() {
+        return;
+    }
+
+    meter.target = match *phase {
+        MatchPhase::Finished(Player::Left) => 1.,
+        MatchPhase::Finished(Player::Right) => 0.,
+        _ => win_probability(
+            score.left,
+            score.right,
+            rules.score_limit,
+            *phase == MatchPhase::Overtime,
+            &stats.point_winners,
+            stats.point_winners.last().map(|winner| winner.opponent()),
+        ),
+    };
+}
+
+/// Hidden in tournaments and the daily challenge, and with reduced clutter.
+fn render_win_meter(
+    time: Res<Time>,
+    tournament: Res<Tournament>,
+    daily: Res<DailyChallenge>,
+    accessibility: Res<Accessibility>,
+    theme: Res<Theme>,
+    mut meter: ResMut<WinMeter>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut rows: Query<&mut Style, (With<WinMeterRow>, Without<WinBar>)>,
+    mut bars: Query<(&WinBar, &mut Style, &Handle<ColorMaterial>)>,
+) {
+    let hidden = tournament.is_active() || daily.is_active() || accessibility.reduced_clutter;
+    let display = if hidden { Display::None } else { Display::Flex };
+    for mut style in rows.iter_mut() {
+        if style.display != display {
+            style.display = display;
+        }
+    }
+    if hidden {
+        return;
+    }
+
+    if theme.is_changed() {
+        for (bar, _, material) in bars.iter_mut() {
+            if let Some(material) = materials.get_mut(material) {
+                material.color = theme.player_color(bar.0);
+            }
+        }
+    }
+
+    if (meter.target - meter.shown).abs() < f32::EPSILON {
+        return;
+    }
+    let step = 1. - (-EASE_RATE * time.delta_seconds()).exp();
+    meter.shown += (meter.target - meter.shown) * step;
+    if (meter.target - meter.shown).abs() < 0.001 {
+        meter.shown = meter.target;
+    }
+
+    for (bar, mut style, _) in bars.iter_mut() {
+        let share = match bar.0 {
+            Player::Left => meter.shown,
+            Player::Right => 1. - meter.shown,
+        };
+        style.size.width = Val::Px(BAR_WIDTH * share);
+    }
+}
@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+use bevy_rapier2d::physics::{RapierConfiguration, RigidBodyHandleComponent};
+use bevy_rapier2d::rapier::dynamics::RigidBodySet;
+
+use crate::match_state::MatchRules;
+use crate::physics::teleport_body;
+use crate::{AppState, Ball, GameLabel, ARENA_HEIGHT, ARENA_WIDTH, WALL_THICKNESS};
+
+pub struct WrapPlugin;
+
+impl Plugin for WrapPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(wrap_balls.system().after(GameLabel::GoalApply)),
+        );
+    }
+}
+
+const DASH_LENGTH: f32 = 24.;
+const DASH_GAP: f32 = 16.;
+
+/// Moves a ball that left through the top or bottom edge to the opposite edge. Only the
+/// position changes, so the ball keeps its velocity and spin.
+fn wrap_balls(
+    rules: Res<MatchRules>,
+    rapier_config: Res<RapierConfiguration>,
+    mut rigid_bodies: ResMut<RigidBodySet>,
+    mut balls: Query<(&RigidBodyHandleComponent, &mut Transform), With<Ball>>,
+) {
+    if !rules.wrap_around {
+        return;
+    }
+
+    for (rigid_body_component, mut transform) in balls.iter_mut() {
+        let rb = match rigid_bodies.get_mut(rigid_body_component.handle()) {
+            Some(rb) => rb,
+            None => continue,
+        };
+
+        let mut position = *rb.position();
+        let y = position.translation.y * rapier_config.scale;
+        let wrapped = if y > ARENA_HEIGHT {
+            y - ARENA_HEIGHT
+        } else if y < 0. {
+            y + ARENA_HEIGHT
+        } else {
+            continue;
+        };
+
+        position.translation.y = wrapped / rapier_config.scale;
+        teleport_body(rb, &mut transform, position, rapier_config.scale);
+    }
+}
+
+/// Dashed line along a top or bottom wall, showing the ball passes through it. Spawned as
+/// children of the hidden wall sprite so it slides in with the intro.
+pub fn spawn_wrap_markers(parent: &mut ChildBuilder, material: Handle<ColorMaterial>) {
+    let step = DASH_LENGTH + DASH_GAP;
+    let count = (ARENA_WIDTH / step) as usize;
+    let start = -(count as f32 * step - DASH_GAP) / 2. + DASH_LENGTH / 2.;
+
+    for index in 0..count {
+        parent.spawn_bundle(SpriteBundle {
+            material: material.clone(),
+            sprite: Sprite::new(Vec2::new(DASH_LENGTH, WALL_THICKNESS / 4.)),
+            transform: Transform::from_xyz(start + index as f32 * step, 0., 0.),
+            ..Default::default()
+        });
+    }
+}